@@ -0,0 +1,71 @@
+//! Compare sequential and parallel ticks on scenarios of independent pump pairs.
+//!
+//! Run with `cargo bench --features parallel --bench parallel_update`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use ic10_emulator_lib::atmospherics::GasType;
+use ic10_emulator_lib::devices::{DeviceAtmosphericNetworkType, VolumePump};
+use ic10_emulator_lib::{LogicType, SimulationManager};
+
+const TICKS: u32 = 200;
+
+/// A manager with `pairs` isolated input/output networks, each joined by a running pump
+fn build(pairs: usize, parallel: bool) -> SimulationManager {
+    let mut manager = SimulationManager::new();
+    manager.set_parallel_updates(parallel);
+    for _ in 0..pairs {
+        let input = manager.create_atmospheric_network(100.0).unwrap();
+        let output = manager.create_atmospheric_network(100.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 1.0e6, 300.0)
+            .unwrap();
+        let pump = manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .unwrap();
+        {
+            let mut device = pump.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input))
+                .unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(output))
+                .unwrap();
+        }
+        pump.borrow().write(LogicType::Setting, 1.0).unwrap();
+        pump.borrow().write(LogicType::On, 1.0).unwrap();
+    }
+    manager
+}
+
+fn time_per_tick(pairs: usize, parallel: bool) -> Duration {
+    let mut manager = build(pairs, parallel);
+    for _ in 0..10 {
+        manager.update().unwrap();
+    }
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        black_box(manager.update().unwrap());
+    }
+    start.elapsed() / TICKS
+}
+
+fn main() {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!("{cores} core(s) available, {TICKS} ticks per run");
+    println!(
+        "{:>6} {:>14} {:>14} {:>8}",
+        "pairs", "sequential", "parallel", "speedup"
+    );
+    for pairs in [8, 32, 64, 128, 512, 2048] {
+        let sequential = time_per_tick(pairs, false);
+        let parallel = time_per_tick(pairs, true);
+        println!(
+            "{pairs:>6} {:>14?} {:>14?} {:>7.2}x",
+            sequential,
+            parallel,
+            sequential.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
@@ -1,5 +1,9 @@
 //! Chemistry constants for atmospheric simulation
 
+use std::collections::HashMap;
+
+use super::GasType;
+
 /// Ideal gas constant R in J/(mol·K)
 pub const IDEAL_GAS_CONSTANT: f64 = 8.31446261815324;
 
@@ -54,6 +58,10 @@ pub const EVAP_INTERPOLATION_TEMP_DELTA: f64 = 10.0;
 /// Temperature margin used near freezing for special handling (Kelvin)
 pub const NEAR_FREEZING_MARGIN: f64 = 1.0;
 
+/// Half-width of the band around the evaporation temperature in which no phase change starts (Kelvin).
+/// Keeps mixtures sitting on a phase boundary from flipping between states every tick.
+pub const PHASE_CHANGE_HYSTERESIS: f64 = 0.5;
+
 /// Factor for halving freezing temperature
 pub const HALF_FREEZING_FACTOR: f64 = 0.5;
 
@@ -139,3 +147,93 @@ pub fn map_to_scale(min: f64, max: f64, out_min: f64, out_max: f64, value: f64)
     }
     (value - min) * range_out / range_in + out_min
 }
+
+/// Physical properties of a single gas or liquid type
+///
+/// Defaults for each `GasType` match the game values; see `GasProperties::of`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasProperties {
+    /// Molar specific heat (J/(mol·K))
+    pub specific_heat: f64,
+    /// Latent heat of vaporization (J/mol)
+    pub latent_heat_of_vaporization: f64,
+    /// Molar mass (g/mol)
+    pub molar_mass: f64,
+    /// Molar volume for liquids (L/mol); 0.0 for gases
+    pub molar_volume: f64,
+    /// Freezing/triple point temperature (K)
+    pub freezing_temperature: f64,
+    /// Maximum liquid temperature (critical temperature, K)
+    pub max_liquid_temperature: f64,
+    /// Minimum pressure for liquid phase (kPa)
+    pub min_liquid_pressure: f64,
+    /// Critical pressure (kPa)
+    pub critical_pressure: f64,
+    /// Evaporation coefficient A for the vapor pressure formula P = A * T^B
+    pub evaporation_coefficient_a: f64,
+    /// Evaporation coefficient B for the vapor pressure formula P = A * T^B
+    pub evaporation_coefficient_b: f64,
+}
+
+impl GasProperties {
+    /// Default (game) properties for `gas_type`
+    pub fn of(gas_type: GasType) -> Self {
+        Self {
+            specific_heat: gas_type.specific_heat(),
+            latent_heat_of_vaporization: gas_type.latent_heat_of_vaporization(),
+            molar_mass: gas_type.molar_mass(),
+            molar_volume: gas_type.molar_volume(),
+            freezing_temperature: gas_type.freezing_temperature(),
+            max_liquid_temperature: gas_type.max_liquid_temperature(),
+            min_liquid_pressure: gas_type.min_liquid_pressure(),
+            critical_pressure: gas_type.critical_pressure(),
+            evaporation_coefficient_a: gas_type.evaporation_coefficient_a(),
+            evaporation_coefficient_b: gas_type.evaporation_coefficient_b(),
+        }
+    }
+
+    /// Latent heat of fusion (J/mol)
+    pub fn latent_heat_of_fusion(&self) -> f64 {
+        self.latent_heat_of_vaporization / FUSION_TO_VAPORIZATION_DENOMINATOR
+    }
+
+    /// Boiling point at one atmosphere (K), derived from the vapor pressure curve
+    pub fn boiling_point(&self) -> f64 {
+        (ONE_ATMOSPHERE / self.evaporation_coefficient_a).powf(1.0 / self.evaporation_coefficient_b)
+    }
+
+    /// Return a copy whose vapor pressure curve passes through one atmosphere at `temperature` (K)
+    pub fn with_boiling_point(mut self, temperature: f64) -> Self {
+        self.evaporation_coefficient_a =
+            ONE_ATMOSPHERE / temperature.powf(self.evaporation_coefficient_b);
+        self
+    }
+}
+
+/// Table of `GasProperties` for every `GasType`, defaulting to the game values
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasPropertiesTable {
+    properties: HashMap<GasType, GasProperties>,
+}
+
+impl GasPropertiesTable {
+    /// Get the properties for `gas_type`
+    pub fn get(&self, gas_type: GasType) -> GasProperties {
+        self.properties[&gas_type]
+    }
+
+    /// Override the properties for `gas_type`
+    pub fn set(&mut self, gas_type: GasType, properties: GasProperties) {
+        self.properties.insert(gas_type, properties);
+    }
+}
+
+impl Default for GasPropertiesTable {
+    fn default() -> Self {
+        Self {
+            properties: GasType::all()
+                .map(|gas_type| (gas_type, GasProperties::of(gas_type)))
+                .collect(),
+        }
+    }
+}
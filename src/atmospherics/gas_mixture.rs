@@ -6,16 +6,69 @@
 
 use crate::{
     atmospherics::{
-        DEFAULT_STATE_CHANGE_RATIO, MINIMUM_GAS_VOLUME, MINIMUM_QUANTITY_MOLES,
-        MINIMUM_VALID_TOTAL_MOLES, PRESSURE_EQUALIZATION_EPSILON, calculate_pressure,
-        kelvin_to_celsius,
+        DEFAULT_STATE_CHANGE_RATIO, GasPropertiesTable, MINIMUM_GAS_VOLUME, MINIMUM_QUANTITY_MOLES,
+        MINIMUM_VALID_TOTAL_MOLES, ONE_ATMOSPHERE, PRESSURE_EQUALIZATION_EPSILON,
+        calculate_pressure, kelvin_to_celsius,
     },
     conversions::fmt_trim,
+    error::{SimulationError, SimulationResult},
 };
 
 use super::{GasType, MatterState, Mole};
 use std::fmt::{self, Debug, Display};
 
+/// Amount of a mixture to move with `GasMixture::transfer_moles_to`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferAmount {
+    /// Fraction of the contents matching the transferred state, clamped to `[0, 1]`
+    Fraction(f64),
+    /// Absolute moles, capped at the contents matching the transferred state
+    Moles(f64),
+}
+
+/// Pressure unit used by `GasMixture::format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PressureUnit {
+    /// Kilopascals
+    #[default]
+    Kilopascals,
+    /// Standard atmospheres (`ONE_ATMOSPHERE` kPa)
+    Atmospheres,
+}
+
+/// Temperature unit used by `GasMixture::format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    /// Degrees Celsius, followed by kelvin in brackets
+    #[default]
+    Celsius,
+    /// Kelvin only
+    Kelvin,
+}
+
+/// What `GasMixture::format` lists for each gas and liquid present
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContentsDisplay {
+    /// Moles
+    #[default]
+    Moles,
+    /// Percentage of the total moles
+    Ratios,
+    /// Partial pressure, in the selected pressure unit (zero for liquids)
+    PartialPressures,
+}
+
+/// Options for `GasMixture::format`; the default matches the `Display` output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Unit for the total and partial pressures
+    pub pressure: PressureUnit,
+    /// Unit for the temperature
+    pub temperature: TemperatureUnit,
+    /// Value listed for each gas and liquid present
+    pub contents: ContentsDisplay,
+}
+
 /// A mixture of gases and liquids with their associated energies
 #[derive(Clone)]
 pub struct GasMixture {
@@ -73,6 +126,21 @@ impl GasMixture {
         }
     }
 
+    /// Create an empty `GasMixture` whose gases use the given property table
+    pub fn with_properties(volume: f64, properties: &GasPropertiesTable) -> Self {
+        let mut mixture = Self::new(volume);
+        mixture.set_properties(properties);
+        mixture
+    }
+
+    /// Apply a property table to every gas and liquid, preserving temperatures
+    pub fn set_properties(&mut self, properties: &GasPropertiesTable) {
+        for gas_type in GasType::all() {
+            self.get_gas_mut(gas_type)
+                .set_properties(properties.get(gas_type));
+        }
+    }
+
     /// Volume in litres
     pub fn volume(&self) -> f64 {
         self.volume
@@ -129,17 +197,55 @@ impl GasMixture {
         }
     }
 
+    /// Get the `Mole` for `gas_type`, or `None` when the mixture holds none of it
+    pub fn get(&self, gas_type: GasType) -> Option<&Mole> {
+        let mole = self.get_gas(gas_type);
+        (!mole.is_empty()).then_some(mole)
+    }
+
+    /// Gases and liquids present in the mixture, ordered by `GasType` discriminant
+    pub fn gases(&self) -> Vec<(GasType, Mole)> {
+        let mut present: Vec<(GasType, Mole)> = GasType::all()
+            .filter_map(|gas_type| self.get(gas_type).map(|mole| (gas_type, *mole)))
+            .collect();
+        present.sort_by_key(|(gas_type, _)| *gas_type as u32);
+        present
+    }
+
     /// Get moles of `gas_type`
     pub fn get_moles(&self, gas_type: GasType) -> f64 {
         self.get_gas(gas_type).quantity()
     }
 
     /// Add gas/liquid to the mixture and equalize temperature
-    pub fn add_gas(&mut self, gas_type: GasType, moles: f64, temperature: f64) {
-        let new_mole = Mole::new(gas_type, moles, temperature);
+    ///
+    /// Errors without touching the mixture if `moles` is negative or not finite, or if
+    /// `temperature` is not a finite value above absolute zero.
+    pub fn add_gas(
+        &mut self,
+        gas_type: GasType,
+        moles: f64,
+        temperature: f64,
+    ) -> SimulationResult<()> {
+        if !moles.is_finite() || moles < 0.0 {
+            return Err(SimulationError::RuntimeError {
+                message: format!("Cannot add {moles} moles of {gas_type:?}"),
+                line: 0,
+            });
+        }
+        if !temperature.is_finite() || temperature <= 0.0 {
+            return Err(SimulationError::RuntimeError {
+                message: format!("Cannot add {gas_type:?} at {temperature} K"),
+                line: 0,
+            });
+        }
+
+        let mut new_mole = Mole::new(gas_type, moles, temperature);
+        new_mole.set_properties(*self.get_gas(gas_type).properties());
         self.get_gas_mut(gas_type).add(&new_mole);
         self.equalize_internal_energy();
         self.cleanup();
+        Ok(())
     }
 
     /// Add a `Mole` and equalize temperature
@@ -322,7 +428,13 @@ impl GasMixture {
         self.pressure_gases()
     }
 
+    /// Total pressure (kPa); the sum of the partial pressures of all gases
+    pub fn total_pressure(&self) -> f64 {
+        self.pressure_gases()
+    }
+
     /// Calculate partial pressure for a specific gas (kPa)
+    /// Returns 0 for liquids and for empty mixtures
     pub fn partial_pressure(&self, gas_type: GasType) -> f64 {
         if gas_type.is_liquid() {
             return 0.0; // Liquids don't contribute to partial pressure
@@ -331,6 +443,54 @@ impl GasMixture {
         calculate_pressure(moles, self.temperature(), self.gas_volume())
     }
 
+    /// Plan the signed mole deltas needed to reach the target gas ratios
+    ///
+    /// `targets` lists mole fractions of the gas phase. Total gas moles are preserved: listed
+    /// gases are set to `ratio * total`, and unlisted gases are scaled proportionally to fill
+    /// the remaining fraction (removed entirely when the targets sum to 1). Liquids are ignored.
+    /// Only non-zero deltas are returned; positive values must be added, negative removed.
+    pub fn plan_to_ratio(&self, targets: &[(GasType, f64)]) -> Vec<(GasType, f64)> {
+        let total = self.total_moles_gases();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+
+        let targets: Vec<(GasType, f64)> = targets
+            .iter()
+            .filter(|(gas_type, _)| gas_type.is_gas())
+            .map(|&(gas_type, ratio)| (gas_type, ratio.max(0.0)))
+            .collect();
+        let target_sum: f64 = targets.iter().map(|(_, ratio)| ratio).sum();
+        let scale = if target_sum > 1.0 {
+            1.0 / target_sum
+        } else {
+            1.0
+        };
+
+        let is_target = |gas_type: GasType| targets.iter().any(|(g, _)| *g == gas_type);
+        let unlisted_total: f64 = GasType::all_gases()
+            .filter(|g| !is_target(*g))
+            .map(|g| self.get_moles(g))
+            .sum();
+        let unlisted_scale = if unlisted_total > 0.0 {
+            (1.0 - target_sum * scale) * total / unlisted_total
+        } else {
+            0.0
+        };
+
+        GasType::all_gases()
+            .filter_map(|gas_type| {
+                let current = self.get_moles(gas_type);
+                let desired = match targets.iter().find(|(g, _)| *g == gas_type) {
+                    Some((_, ratio)) => ratio * scale * total,
+                    None => current * unlisted_scale,
+                };
+                let delta = desired - current;
+                (delta != 0.0).then_some((gas_type, delta))
+            })
+            .collect()
+    }
+
     /// Get the ratio of a specific gas/liquid (0.0 to 1.0) relative to all content
     pub fn gas_ratio(&self, gas_type: GasType) -> f64 {
         let total = self.total_moles();
@@ -340,6 +500,16 @@ impl GasMixture {
         self.get_moles(gas_type) / total
     }
 
+    /// Get the fraction (0.0 to 1.0) of all content that is in `state`
+    /// The gas and liquid fractions of a mixed mixture sum to 1
+    pub fn state_ratio(&self, state: MatterState) -> f64 {
+        let total = self.total_moles();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.total_moles_by_state(state) / total
+    }
+
     /// Add energy to the mixture (distributed by heat capacity)
     pub fn add_energy(&mut self, joules: f64) {
         let total_capacity = self.total_heat_capacity();
@@ -452,6 +622,81 @@ impl GasMixture {
         transferred
     }
 
+    /// Conduct heat to another mixture without moving any moles
+    /// Transfers `conductivity` joules per kelvin of temperature difference, capped at the
+    /// equilibrium transfer so temperatures never overshoot. Returns the joules moved from
+    /// `self` to `other` (negative when heat flows the other way).
+    pub fn conduct_heat_with(&mut self, other: &mut GasMixture, conductivity: f64) -> f64 {
+        let self_heat_capacity = self.total_heat_capacity();
+        let other_heat_capacity = other.total_heat_capacity();
+        if self_heat_capacity <= 0.0 || other_heat_capacity <= 0.0 || conductivity <= 0.0 {
+            return 0.0;
+        }
+
+        let delta_temperature = self.temperature() - other.temperature();
+        let equilibrium_energy = delta_temperature * self_heat_capacity * other_heat_capacity
+            / (self_heat_capacity + other_heat_capacity);
+        let energy = conductivity * delta_temperature;
+        let energy = if energy.abs() > equilibrium_energy.abs() {
+            equilibrium_energy
+        } else {
+            energy
+        };
+
+        if energy > 0.0 {
+            let removed = self.remove_energy(energy);
+            other.add_energy(removed);
+            removed
+        } else if energy < 0.0 {
+            let removed = other.remove_energy(-energy);
+            self.add_energy(removed);
+            -removed
+        } else {
+            0.0
+        }
+    }
+
+    /// Move the mixture toward `target` kelvin using at most `max_energy` joules
+    ///
+    /// Heats or cools as needed and stops at the target or when the budget runs out.
+    /// Returns the energy added to the mixture (negative when energy was removed).
+    pub fn move_toward_temperature(&mut self, target: f64, max_energy: f64) -> f64 {
+        let heat_capacity = self.total_heat_capacity();
+        if heat_capacity <= 0.0 || max_energy <= 0.0 {
+            return 0.0;
+        }
+
+        let needed = (target.max(0.0) - self.temperature()) * heat_capacity;
+        let energy = needed.clamp(-max_energy, max_energy);
+
+        if energy > 0.0 {
+            self.add_energy(energy);
+            energy
+        } else if energy < 0.0 {
+            -self.remove_energy(-energy)
+        } else {
+            0.0
+        }
+    }
+
+    /// Heat the mixture toward `target` kelvin using at most `max_energy` joules
+    /// Does nothing if the mixture is already at or above the target. Returns the joules added.
+    pub fn heat_to(&mut self, target: f64, max_energy: f64) -> f64 {
+        if self.temperature() >= target {
+            return 0.0;
+        }
+        self.move_toward_temperature(target, max_energy)
+    }
+
+    /// Cool the mixture toward `target` kelvin using at most `max_energy` joules
+    /// Does nothing if the mixture is already at or below the target. Returns the joules removed.
+    pub fn cool_to(&mut self, target: f64, max_energy: f64) -> f64 {
+        if self.temperature() <= target {
+            return 0.0;
+        }
+        -self.move_toward_temperature(target, max_energy)
+    }
+
     /// Transfer gas by pressure difference
     /// Moves gas from higher to lower pressure until equilibrium
     pub fn equalize_with(&mut self, other: &mut GasMixture) {
@@ -526,6 +771,42 @@ impl GasMixture {
         removed
     }
 
+    /// Move `amount` of the contents matching `state` straight into `other`, each gas taking
+    /// its share of energy along.
+    ///
+    /// Unlike `remove_moles` followed by `merge` no intermediate mixture is built, and trace
+    /// quantities of each gas are kept rather than cleaned up. Returns the moles transferred.
+    pub fn transfer_moles_to(
+        &mut self,
+        other: &mut GasMixture,
+        amount: TransferAmount,
+        state: MatterState,
+    ) -> f64 {
+        let ratio = match amount {
+            TransferAmount::Fraction(fraction) => fraction,
+            TransferAmount::Moles(moles) => {
+                let total = self.total_moles_by_state(state);
+                if total <= 0.0 { 0.0 } else { moles / total }
+            }
+        }
+        .clamp(0.0, 1.0);
+        if ratio <= 0.0 {
+            return 0.0;
+        }
+
+        let mut transferred = 0.0;
+        for gas_type in GasType::all() {
+            if gas_type.matches_state(state) {
+                let source = self.get_gas_mut(gas_type);
+                let amount = source.quantity() * ratio;
+                transferred += source.transfer_to(other.get_gas_mut(gas_type), amount);
+            }
+        }
+
+        self.cleanup();
+        transferred
+    }
+
     /// Merge another gas mixture into this one
     pub fn merge(&mut self, other: &GasMixture) {
         for gas_type in GasType::all() {
@@ -586,26 +867,32 @@ impl GasMixture {
         }
         self.cleanup();
     }
-}
 
-impl Debug for GasMixture {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Describe the mixture over several lines, with the units and per-gas values chosen in
+    /// `options`. `FormatOptions::default()` gives the same text as `Display`.
+    pub fn format(&self, options: FormatOptions) -> String {
+        let pressure = |kpa: f64| match options.pressure {
+            PressureUnit::Kilopascals => format!("{} kPa", fmt_trim(kpa, 3)),
+            PressureUnit::Atmospheres => format!("{} atm", fmt_trim(kpa / ONE_ATMOSPHERE, 3)),
+        };
+
         let mut lines: Vec<String> = Vec::new();
-        // Temperature display: Celsius first, Kelvin in brackets
         let temp_k = self.temperature();
-        let temp_c = kelvin_to_celsius(temp_k);
 
         lines.push(format!(
-            "Mixture (total: {}L, liquid volume: {}L)",
+            "Mixture (total: {} L, liquid volume: {} L)",
             fmt_trim(self.volume, 3),
             fmt_trim(self.total_volume_liquids(), 3)
         ));
-        lines.push(format!(
-            "  Temperature: {}°C ({}°K)",
-            fmt_trim(temp_c, 2),
-            fmt_trim(temp_k, 2)
-        ));
-        lines.push(format!("  Pressure: {}kPa", fmt_trim(self.pressure(), 3)));
+        lines.push(match options.temperature {
+            TemperatureUnit::Celsius => format!(
+                "  Temperature: {} °C ({} K)",
+                fmt_trim(kelvin_to_celsius(temp_k), 2),
+                fmt_trim(temp_k, 2)
+            ),
+            TemperatureUnit::Kelvin => format!("  Temperature: {} K", fmt_trim(temp_k, 2)),
+        });
+        lines.push(format!("  Pressure: {}", pressure(self.pressure())));
         lines.push(format!(
             "  Moles: {} (gases: {}, liquids: {})",
             fmt_trim(self.total_moles(), 3),
@@ -613,64 +900,69 @@ impl Debug for GasMixture {
             fmt_trim(self.total_moles_liquids(), 3)
         ));
 
-        // Gases
-        let mut gas_lines: Vec<String> = Vec::new();
-        for gt in GasType::all_gases() {
+        // Compute latent energy (J) that will be moved on the next tick
+        let mut total_latent_energy_j: f64 = 0.0;
+        let gas_vol = self.gas_volume();
+        let total_pressure = self.pressure();
+        for gt in GasType::all_liquids() {
             let mole = self.get_gas(gt);
-            if mole.is_empty() {
-                continue;
+            let energy = mole.latent_energy_next_tick(
+                total_pressure,
+                gas_vol,
+                0.0,
+                true,
+                DEFAULT_STATE_CHANGE_RATIO,
+            );
+
+            if energy.abs() > 0.0 {
+                total_latent_energy_j += energy;
             }
-            gas_lines.push(format!(
-                "    {}: {} mol",
-                gt.symbol(),
-                fmt_trim(mole.quantity(), 3)
-            ));
-        }
-        if !gas_lines.is_empty() {
-            lines.push("  Gases:".to_string());
-            lines.extend(gas_lines);
         }
 
-        // Liquids
-        let mut liquid_lines: Vec<String> = Vec::new();
-        for gt in GasType::all_liquids() {
+        lines.push(format!(
+            "  Latent: {} J",
+            // Subtracting from zero avoids printing "-0" when nothing changes phase
+            fmt_trim(0.0 - total_latent_energy_j, 3)
+        ));
+
+        lines.push("  Contents:".to_string());
+
+        // Gases, then liquids
+        for gt in GasType::all_gases().chain(GasType::all_liquids()) {
             let mole = self.get_gas(gt);
             if mole.is_empty() {
                 continue;
             }
-            liquid_lines.push(format!(
-                "    {}: {} mol",
-                gt.symbol(),
-                fmt_trim(mole.quantity(), 3)
-            ));
-        }
-        if !liquid_lines.is_empty() {
-            lines.push("  Liquids:".to_string());
-            lines.extend(liquid_lines);
+            let value = match options.contents {
+                ContentsDisplay::Moles => format!("{} mol", fmt_trim(mole.quantity(), 3)),
+                ContentsDisplay::Ratios => format!("{} %", fmt_trim(self.gas_ratio(gt) * 100.0, 3)),
+                ContentsDisplay::PartialPressures => pressure(self.partial_pressure(gt)),
+            };
+            lines.push(format!("    {}: {}", gt.symbol(), value));
         }
 
-        // Write joined lines without a trailing newline
-        write!(f, "{}", lines.join("\n"))
+        lines.join("\n")
     }
 }
 
-impl Display for GasMixture {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Debug for GasMixture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut lines: Vec<String> = Vec::new();
+        // Temperature display: Celsius first, Kelvin in brackets
         let temp_k = self.temperature();
         let temp_c = kelvin_to_celsius(temp_k);
 
         lines.push(format!(
-            "Mixture (total: {} L, liquid volume: {} L)",
+            "Mixture (total: {}L, liquid volume: {}L)",
             fmt_trim(self.volume, 3),
             fmt_trim(self.total_volume_liquids(), 3)
         ));
         lines.push(format!(
-            "  Temperature: {} °C ({} K)",
+            "  Temperature: {}°C ({}°K)",
             fmt_trim(temp_c, 2),
             fmt_trim(temp_k, 2)
         ));
-        lines.push(format!("  Pressure: {} kPa", fmt_trim(self.pressure(), 3)));
+        lines.push(format!("  Pressure: {}kPa", fmt_trim(self.pressure(), 3)));
         lines.push(format!(
             "  Moles: {} (gases: {}, liquids: {})",
             fmt_trim(self.total_moles(), 3),
@@ -678,58 +970,49 @@ impl Display for GasMixture {
             fmt_trim(self.total_moles_liquids(), 3)
         ));
 
-        // Compute latent energy (J) that will be moved on the next tick
-        let mut total_latent_energy_j: f64 = 0.0;
-        let gas_vol = self.gas_volume();
-        let pressure = self.pressure();
-        for gt in GasType::all_liquids() {
-            let mole = self.get_gas(gt);
-            let energy = mole.latent_energy_next_tick(
-                pressure,
-                gas_vol,
-                0.0,
-                true,
-                DEFAULT_STATE_CHANGE_RATIO,
-            );
-
-            if energy.abs() > 0.0 {
-                total_latent_energy_j += energy;
-            }
-        }
-
-        lines.push(format!(
-            "  Latent: {} J",
-            fmt_trim(-total_latent_energy_j, 3)
-        ));
-
-        lines.push("  Contents:".to_string());
-
         // Gases
+        let mut gas_lines: Vec<String> = Vec::new();
         for gt in GasType::all_gases() {
             let mole = self.get_gas(gt);
             if mole.is_empty() {
                 continue;
             }
-            lines.push(format!(
+            gas_lines.push(format!(
                 "    {}: {} mol",
                 gt.symbol(),
                 fmt_trim(mole.quantity(), 3)
             ));
         }
+        if !gas_lines.is_empty() {
+            lines.push("  Gases:".to_string());
+            lines.extend(gas_lines);
+        }
 
         // Liquids
+        let mut liquid_lines: Vec<String> = Vec::new();
         for gt in GasType::all_liquids() {
             let mole = self.get_gas(gt);
             if mole.is_empty() {
                 continue;
             }
-            lines.push(format!(
+            liquid_lines.push(format!(
                 "    {}: {} mol",
                 gt.symbol(),
                 fmt_trim(mole.quantity(), 3)
             ));
         }
+        if !liquid_lines.is_empty() {
+            lines.push("  Liquids:".to_string());
+            lines.extend(liquid_lines);
+        }
 
+        // Write joined lines without a trailing newline
         write!(f, "{}", lines.join("\n"))
     }
 }
+
+impl Display for GasMixture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format(FormatOptions::default()))
+    }
+}
@@ -8,6 +8,7 @@ use std::fmt::Display;
 use wasm_bindgen::prelude::*;
 
 use crate::atmospherics::FUSION_TO_VAPORIZATION_DENOMINATOR;
+use crate::parser::string_to_hash;
 
 /// Represents the state of matter for a substance
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -126,6 +127,11 @@ impl GasType {
         }
     }
 
+    /// Hash of the display name, used to identify this gas when read as a reagent
+    pub fn reagent_hash(&self) -> i32 {
+        string_to_hash(self.display_name())
+    }
+
     /// Prefab name for gas filters
     pub fn filter_name(&self) -> &'static str {
         match self {
@@ -391,6 +397,12 @@ impl GasType {
         }
     }
 
+    /// Breathable gases, for use with `AtmosphericNetwork::pressure_of_group`
+    pub const BREATHABLE: &'static [GasType] = &[GasType::Oxygen, GasType::Nitrogen];
+
+    /// Combustible gases, for use with `AtmosphericNetwork::pressure_of_group`
+    pub const COMBUSTIBLE: &'static [GasType] = &[GasType::Volatiles];
+
     /// Iterator over gas types
     pub fn all_gases() -> impl Iterator<Item = GasType> {
         [
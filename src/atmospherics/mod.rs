@@ -10,6 +10,8 @@ mod gas_type;
 mod mole;
 
 pub use chemistry::*;
-pub use gas_mixture::GasMixture;
+pub use gas_mixture::{
+    ContentsDisplay, FormatOptions, GasMixture, PressureUnit, TemperatureUnit, TransferAmount,
+};
 pub use gas_type::{GasType, MatterState};
 pub use mole::{Mole, PhaseChangeResult};
@@ -7,7 +7,7 @@
 
 use crate::conversions::{fmt_trim, lerp};
 
-use super::{GasType, MatterState, chemistry};
+use super::{GasProperties, GasType, MatterState, chemistry};
 use std::fmt;
 
 /// Result of a phase change operation
@@ -46,6 +46,8 @@ pub struct Mole {
     quantity: f64,
     /// Thermal energy in Joules
     energy: f64,
+    /// Physical properties used for temperature and phase-change calculations
+    properties: GasProperties,
 }
 
 impl Mole {
@@ -58,12 +60,14 @@ impl Mole {
     pub fn new(gas_type: GasType, quantity: f64, temperature: f64) -> Self {
         let quantity = quantity.max(0.0);
         let temperature = temperature.max(0.0);
-        let energy = quantity * gas_type.specific_heat() * temperature;
+        let properties = GasProperties::of(gas_type);
+        let energy = quantity * properties.specific_heat * temperature;
 
         Self {
             gas_type,
             quantity,
             energy,
+            properties,
         }
     }
 
@@ -73,6 +77,7 @@ impl Mole {
             gas_type,
             quantity: 0.0,
             energy: 0.0,
+            properties: GasProperties::of(gas_type),
         }
     }
 
@@ -82,9 +87,22 @@ impl Mole {
             gas_type,
             quantity: quantity.max(0.0),
             energy: energy.max(0.0),
+            properties: GasProperties::of(gas_type),
         }
     }
 
+    /// Physical properties used by this `Mole`
+    pub fn properties(&self) -> &GasProperties {
+        &self.properties
+    }
+
+    /// Replace the physical properties, preserving temperature
+    pub fn set_properties(&mut self, properties: GasProperties) {
+        let temp = self.temperature();
+        self.properties = properties;
+        self.energy = self.quantity * self.properties.specific_heat * temp;
+    }
+
     /// Return the `GasType`
     pub fn gas_type(&self) -> GasType {
         self.gas_type
@@ -105,13 +123,13 @@ impl Mole {
         if self.quantity <= chemistry::MINIMUM_QUANTITY_MOLES {
             return 0.0;
         }
-        let temp = self.energy / (self.quantity * self.gas_type.specific_heat());
+        let temp = self.energy / (self.quantity * self.properties.specific_heat);
         temp.max(0.0)
     }
 
     /// Heat capacity (J/K)
     pub fn heat_capacity(&self) -> f64 {
-        self.quantity * self.gas_type.specific_heat()
+        self.quantity * self.properties.specific_heat
     }
 
     /// Returns true if this `Mole` is effectively empty
@@ -123,14 +141,14 @@ impl Mole {
     pub fn set_quantity(&mut self, new_quantity: f64) {
         let temp = self.temperature();
         self.quantity = new_quantity.max(0.0);
-        self.energy = self.quantity * self.gas_type.specific_heat() * temp;
+        self.energy = self.quantity * self.properties.specific_heat * temp;
         self.cleanup();
     }
 
     /// Set temperature (K), adjusting energy
     pub fn set_temperature(&mut self, temperature: f64) {
         let temp = temperature.max(0.0);
-        self.energy = self.quantity * self.gas_type.specific_heat() * temp;
+        self.energy = self.quantity * self.properties.specific_heat * temp;
     }
 
     /// Add thermal energy (J)
@@ -159,10 +177,10 @@ impl Mole {
         self.cleanup();
     }
 
-    /// Remove up to `amount` moles and return them
+    /// Remove up to `amount` moles and return them; a negative or NaN amount removes nothing
     /// Energy is removed proportionally
     pub fn remove(&mut self, amount: f64) -> Mole {
-        let amount = amount.min(self.quantity).max(0.0);
+        let amount = amount.max(0.0).min(self.quantity);
         if amount <= 0.0 || self.quantity <= 0.0 {
             return Mole::zero(self.gas_type);
         }
@@ -173,7 +191,9 @@ impl Mole {
         self.energy -= removed_energy;
 
         self.cleanup();
-        Mole::with_energy(self.gas_type, amount, removed_energy)
+        let mut removed = Mole::with_energy(self.gas_type, amount, removed_energy);
+        removed.properties = self.properties;
+        removed
     }
 
     /// Remove a ratio of the gas (0.0 to 1.0)
@@ -182,13 +202,34 @@ impl Mole {
         self.remove(self.quantity * ratio.clamp(0.0, 1.0))
     }
 
-    /// Transfer gas to another Mole of the same type
-    /// Returns the amount actually transferred
+    /// Transfer up to `amount` moles, with their share of energy, to another Mole of the
+    /// same type. Returns the amount actually transferred.
+    ///
+    /// Moles are conserved exactly: unlike `remove` followed by `add`, trace quantities left
+    /// behind or arriving in `target` are kept rather than cleaned up.
+    ///
+    /// # Panics
+    /// Panics if gas types differ
     pub fn transfer_to(&mut self, target: &mut Mole, amount: f64) -> f64 {
-        let removed = self.remove(amount);
-        let transferred = removed.quantity;
-        target.add(&removed);
-        transferred
+        assert_eq!(
+            self.gas_type, target.gas_type,
+            "Cannot transfer moles between different gas types"
+        );
+        let amount = amount.max(0.0).min(self.quantity);
+        if amount <= 0.0 {
+            return 0.0;
+        }
+
+        let energy = if amount == self.quantity {
+            self.energy
+        } else {
+            self.energy * amount / self.quantity
+        };
+        self.quantity -= amount;
+        self.energy -= energy;
+        target.quantity += amount;
+        target.energy += energy;
+        amount
     }
 
     /// Calculate the energy required to reach a target temperature
@@ -221,32 +262,32 @@ impl Mole {
         if self.quantity < chemistry::MINIMUM_QUANTITY_MOLES {
             return false;
         }
-        self.temperature() <= self.gas_type.freezing_temperature()
+        self.temperature() <= self.properties.freezing_temperature
     }
 
     /// Get the volume of this liquid in litres
     /// Returns 0 for gases
     pub fn volume(&self) -> f64 {
-        self.gas_type.molar_volume() * self.quantity
+        self.properties.molar_volume * self.quantity
     }
 
     /// Get the mass of this gas/liquid in grams
     pub fn mass(&self) -> f64 {
-        self.gas_type.molar_mass() * self.quantity
+        self.properties.molar_mass * self.quantity
     }
 
     /// Calculate the evaporation temperature for a given pressure
     /// This is clamped between the freezing and max liquid temperatures
     pub fn evaporation_temperature_clamped(&self, pressure: f64) -> f64 {
         let clamped_pressure = pressure.clamp(
-            self.gas_type.min_liquid_pressure(),
-            self.gas_type.critical_pressure(),
+            self.properties.min_liquid_pressure,
+            self.properties.critical_pressure,
         );
 
         self.calculate_evaporation_temperature(clamped_pressure)
             .clamp(
-                self.gas_type.freezing_temperature(),
-                self.gas_type.max_liquid_temperature(),
+                self.properties.freezing_temperature,
+                self.properties.max_liquid_temperature,
             )
     }
 
@@ -254,29 +295,29 @@ impl Mole {
     /// This is clamped between min liquid pressure and critical pressure
     pub fn evaporation_pressure_clamped(&self, temperature: f64) -> f64 {
         let clamped_temp = temperature.clamp(
-            self.gas_type.freezing_temperature(),
-            self.gas_type.max_liquid_temperature(),
+            self.properties.freezing_temperature,
+            self.properties.max_liquid_temperature,
         );
 
         self.calculate_evaporation_pressure(clamped_temp).clamp(
-            self.gas_type.min_liquid_pressure(),
-            self.gas_type.critical_pressure(),
+            self.properties.min_liquid_pressure,
+            self.properties.critical_pressure,
         )
     }
 
     /// Calculate evaporation temperature from pressure using power law formula
     /// T = (P / A)^(1/B) where P is pressure in kPa
     fn calculate_evaporation_temperature(&self, pressure: f64) -> f64 {
-        let a = self.gas_type.evaporation_coefficient_a();
-        let b = self.gas_type.evaporation_coefficient_b();
+        let a = self.properties.evaporation_coefficient_a;
+        let b = self.properties.evaporation_coefficient_b;
         (pressure / a).powf(1.0 / b)
     }
 
     /// Calculate evaporation pressure from temperature using power law formula
     /// P = A * T^B where T is temperature in K
     fn calculate_evaporation_pressure(&self, temperature: f64) -> f64 {
-        let a = self.gas_type.evaporation_coefficient_a();
-        let b = self.gas_type.evaporation_coefficient_b();
+        let a = self.properties.evaporation_coefficient_a;
+        let b = self.properties.evaporation_coefficient_b;
         a * temperature.powf(b)
     }
 
@@ -298,18 +339,18 @@ impl Mole {
         let mut evap_pressure = self.evaporation_pressure_clamped(self.temperature());
 
         // Calculate minimum evaporation temperature (half of freezing point)
-        let half_freezing = self.gas_type.freezing_temperature() * chemistry::HALF_FREEZING_FACTOR;
+        let half_freezing = self.properties.freezing_temperature * chemistry::HALF_FREEZING_FACTOR;
 
         // Adjust evaporation pressure for sub-freezing temperatures
-        if self.temperature() < self.gas_type.freezing_temperature()
+        if self.temperature() < self.properties.freezing_temperature
             && prevent_absolute_zero_evaporation
         {
             let effective_temp = self.temperature().max(half_freezing);
             evap_pressure = chemistry::map_to_scale(
                 half_freezing,
-                self.gas_type.freezing_temperature(),
+                self.properties.freezing_temperature,
                 chemistry::ARMSTRONG_LIMIT,
-                self.gas_type.min_liquid_pressure(),
+                self.properties.min_liquid_pressure,
                 effective_temp,
             );
         }
@@ -322,7 +363,7 @@ impl Mole {
                 return None;
             }
             // Interpolate evaporation temperature based on pressure
-            let t = (pressure_delta / self.gas_type.min_liquid_pressure()).clamp(0.0, 1.0);
+            let t = (pressure_delta / self.properties.min_liquid_pressure).clamp(0.0, 1.0);
             lerp(
                 self.temperature(),
                 self.temperature() - chemistry::EVAP_INTERPOLATION_TEMP_DELTA,
@@ -347,27 +388,27 @@ impl Mole {
         // Calculate the energy available for state change
         let energy_for_change = chemistry::calculate_energy_for_temperature_change(
             self.quantity,
-            self.gas_type.specific_heat(),
+            self.properties.specific_heat,
             self.temperature() - effective_evap_temp,
         );
 
         // Handle near-freezing conditions with rate limiting (time-limited energy)
         let adjusted_energy = if self.temperature()
-            < self.gas_type.freezing_temperature() + chemistry::NEAR_FREEZING_MARGIN
+            < self.properties.freezing_temperature + chemistry::NEAR_FREEZING_MARGIN
             && self.temperature() > half_freezing + chemistry::NEAR_FREEZING_MARGIN
             && prevent_absolute_zero_evaporation
         {
-            chemistry::SMALL_STATE_CHANGE_RATE * self.gas_type.specific_heat() * self.quantity
+            chemistry::SMALL_STATE_CHANGE_RATE * self.properties.specific_heat * self.quantity
         } else {
             energy_for_change
         };
 
         // Force full evaporation if above critical temperature
-        let final_energy = if self.temperature() > self.gas_type.max_liquid_temperature() {
+        let final_energy = if self.temperature() > self.properties.max_liquid_temperature {
             let min_energy = chemistry::calculate_energy_for_temperature_change(
                 self.quantity,
-                self.gas_type.specific_heat(),
-                self.temperature() - self.gas_type.max_liquid_temperature(),
+                self.properties.specific_heat,
+                self.temperature() - self.properties.max_liquid_temperature,
             );
             adjusted_energy.max(min_energy)
         } else {
@@ -386,6 +427,10 @@ impl Mole {
         prevent_absolute_zero_evaporation: bool,
         ratio: f64,
     ) -> f64 {
+        if self.within_phase_hysteresis(pressure, temperature_offset) {
+            return 0.0;
+        }
+
         let base = match self.compute_evaporation_base(
             pressure,
             volume,
@@ -399,7 +444,7 @@ impl Mole {
         let (final_energy, max_quantity) = base;
 
         // Minimum energy for a meaningful state change
-        let latent_heat = self.gas_type.latent_heat_of_vaporization();
+        let latent_heat = self.properties.latent_heat_of_vaporization;
         let min_change_energy = chemistry::MINIMUM_QUANTITY_MOLES * latent_heat;
 
         // If not enough energy for a meaningful change, only allow it for very small quantities
@@ -429,6 +474,23 @@ impl Mole {
         (final_energy / scale) * effective_ratio
     }
 
+    /// Whether the temperature is within `PHASE_CHANGE_HYSTERESIS` of the evaporation
+    /// temperature at `pressure`, where phase changes are suppressed.
+    ///
+    /// The band only applies where the boundary is a real liquid/gas equilibrium: between
+    /// the minimum liquid and critical pressures and below the maximum liquid temperature.
+    fn within_phase_hysteresis(&self, pressure: f64, temperature_offset: f64) -> bool {
+        if pressure < self.properties.min_liquid_pressure
+            || pressure > self.properties.critical_pressure
+            || self.temperature() > self.properties.max_liquid_temperature
+        {
+            return false;
+        }
+
+        let boundary = self.evaporation_temperature_clamped(pressure) + temperature_offset;
+        (self.temperature() - boundary).abs() < chemistry::PHASE_CHANGE_HYSTERESIS
+    }
+
     /// Perform phase change based on current conditions
     pub fn change_state(
         &mut self,
@@ -437,7 +499,9 @@ impl Mole {
         temperature_offset: f64,
         prevent_absolute_zero_evaporation: bool,
     ) -> PhaseChangeResult {
-        if self.quantity < chemistry::MINIMUM_QUANTITY_MOLES {
+        if self.quantity < chemistry::MINIMUM_QUANTITY_MOLES
+            || self.within_phase_hysteresis(pressure, temperature_offset)
+        {
             return PhaseChangeResult::none();
         }
 
@@ -484,7 +548,7 @@ impl Mole {
 
         // Check if there's enough energy for meaningful state change
         let min_change_energy =
-            chemistry::MINIMUM_QUANTITY_MOLES * self.gas_type.latent_heat_of_vaporization();
+            chemistry::MINIMUM_QUANTITY_MOLES * self.properties.latent_heat_of_vaporization;
 
         if final_energy < min_change_energy {
             if self.quantity < chemistry::MINIMUM_WORLD_VALID_TOTAL_MOLES {
@@ -512,7 +576,7 @@ impl Mole {
             return PhaseChangeResult::none();
         }
 
-        if pressure < self.gas_type.min_liquid_pressure() {
+        if pressure < self.properties.min_liquid_pressure {
             return PhaseChangeResult::none();
         }
 
@@ -530,12 +594,12 @@ impl Mole {
         // Calculate energy deficit (how much energy needs to be added to reach condensation temp)
         let deficit_energy = chemistry::calculate_energy_for_temperature_change(
             self.quantity,
-            self.gas_type.specific_heat(),
+            self.properties.specific_heat,
             condensation_temp - self.temperature(),
         );
 
         let min_change_energy =
-            chemistry::MINIMUM_QUANTITY_MOLES * self.gas_type.latent_heat_of_vaporization();
+            chemistry::MINIMUM_QUANTITY_MOLES * self.properties.latent_heat_of_vaporization;
 
         if deficit_energy < min_change_energy {
             if self.quantity <= chemistry::MINIMUM_WORLD_VALID_TOTAL_MOLES {
@@ -568,7 +632,7 @@ impl Mole {
         }
 
         let max_quantity = max_quantity.min(self.quantity);
-        let latent_heat = self.gas_type.latent_heat_of_vaporization();
+        let latent_heat = self.properties.latent_heat_of_vaporization;
 
         // Calculate how many moles can change state
         let moles_to_change =
@@ -636,7 +700,7 @@ impl Mole {
         ratio: f64,
         target_type: GasType,
     ) -> PhaseChangeResult {
-        let latent_heat = self.gas_type.latent_heat_of_vaporization();
+        let latent_heat = self.properties.latent_heat_of_vaporization;
 
         // Limit energy to what's available
         let max_energy = chemistry::calculate_energy_for_state_change(self.quantity, latent_heat);
@@ -723,11 +787,7 @@ impl Mole {
 
 impl Default for Mole {
     fn default() -> Self {
-        Self {
-            gas_type: GasType::Nitrogen,
-            quantity: 0.0,
-            energy: 0.0,
-        }
+        Mole::zero(GasType::Nitrogen)
     }
 }
 
@@ -16,9 +16,27 @@ pub const STACK_POINTER_INDEX: usize = 16;
 /// Return address register index (r17/ra)
 pub const RETURN_ADDRESS_INDEX: usize = 17;
 
+/// Largest integer magnitude (2^53) that registers hold exactly.
+///
+/// Registers are `f64`, so hashes and other integers survive a `move` unchanged up to this
+/// magnitude. Hex and binary literals beyond it are rejected rather than silently rounded.
+pub const MAX_EXACT_INTEGER: i64 = 1 << 53;
+
+/// Maximum number of lines in an IC10 program, counting blank and comment lines
+pub const MAX_PROGRAM_LINES: usize = 128;
+
+/// Maximum number of characters on a single IC10 program line, including comments
+pub const MAX_LINE_LENGTH: usize = 52;
+
 /// Default maximum instructions per tick for ic10 execution
 pub const DEFAULT_MAX_INSTRUCTIONS_PER_TICK: usize = 128;
 
+/// Default number of simulation ticks per second of game time, used to convert `sleep` durations
+pub const DEFAULT_TICKS_PER_SECOND: f64 = 2.0;
+
+/// Default number of decimal places used when displaying logic values
+pub const DEFAULT_DISPLAY_PRECISION: usize = 3;
+
 /// Default number of ticks per day cycle
 pub const DEFAULT_TICKS_PER_DAY: f64 = 2400.0;
 
@@ -3,6 +3,11 @@
 //! These functions match the C# implementations in ProgrammableChip.cs
 //! The IC10 uses 64-bit IEEE 754 doubles but operates on integers within
 //! the 53-bit mantissa range for bitwise operations.
+//!
+//! The gas helpers at the end use the default `GasProperties` so tooling can show masses and
+//! heat loads without repeating the atmospherics math.
+
+use crate::atmospherics::{GasProperties, GasType};
 
 /// Mask for the 53-bit mantissa (2^53 - 1 = 9,007,199,254,740,991)
 pub const MANTISSA_MASK: i64 = 0x1FFFFFFFFFFFFF; // 9007199254740991
@@ -70,6 +75,16 @@ pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
     a + (b - a) * t.clamp(0.0, 1.0)
 }
 
+/// Round a float to `prec` decimal places, half away from zero. Non-finite values are
+/// returned unchanged.
+pub fn round_to(v: f64, prec: usize) -> f64 {
+    if !v.is_finite() {
+        return v;
+    }
+    let factor = 10f64.powi(prec as i32);
+    (v * factor).round() / factor
+}
+
 /// Format a float with up to `prec` decimal places, trimming trailing zeros
 /// and removing the decimal point when not needed. This is useful for
 /// printing where `3.0` should print as "3" and `3.50` -> "3.5".
@@ -85,3 +100,30 @@ pub fn fmt_trim(v: f64, prec: usize) -> String {
     }
     s
 }
+
+/// Mass in grams of `moles` of `gas_type`, from its molar mass.
+pub fn moles_to_grams(gas_type: GasType, moles: f64) -> f64 {
+    moles * GasProperties::of(gas_type).molar_mass
+}
+
+/// Moles of `gas_type` in `grams`, from its molar mass.
+pub fn grams_to_moles(gas_type: GasType, grams: f64) -> f64 {
+    grams / GasProperties::of(gas_type).molar_mass
+}
+
+/// Energy in joules needed to change the temperature of `moles` of `gas_type` by `delta_k`
+/// (negative when cooling), from its specific heat. Phase changes are not included.
+pub fn energy_for_temperature_change(gas_type: GasType, moles: f64, delta_k: f64) -> f64 {
+    moles * GasProperties::of(gas_type).specific_heat * delta_k
+}
+
+/// Temperature change in kelvin from adding `joules` to `moles` of `gas_type`, the inverse of
+/// `energy_for_temperature_change`. No moles means no temperature change.
+pub fn temperature_change_for_energy(gas_type: GasType, moles: f64, joules: f64) -> f64 {
+    let heat_capacity = moles * GasProperties::of(gas_type).specific_heat;
+    if heat_capacity > 0.0 {
+        joules / heat_capacity
+    } else {
+        0.0
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::{
     CableNetwork,
-    atmospherics::{MatterState, ONE_ATMOSPHERE, calculate_moles},
+    atmospherics::{MatterState, ONE_ATMOSPHERE, TransferAmount, calculate_moles},
     devices::{
         AtmosphericDevice, Device, DeviceAtmosphericNetworkType, LogicType,
         SimulationDeviceSettings,
@@ -11,14 +11,13 @@ use crate::{
     error::{SimulationError, SimulationResult},
     networks::AtmosphericNetwork,
     parser::string_to_hash,
-    prop_ro, prop_rw_bool, prop_rw_clamped,
+    prop_ro, prop_rw_bool, prop_rw_clamped, prop_rw_name_hash,
     types::{OptShared, OptWeakShared, Shared, shared},
 };
 
 use std::{
     cell::RefCell,
     fmt::{Debug, Display},
-    rc::Rc,
     sync::OnceLock,
 };
 
@@ -28,6 +27,8 @@ const PRESSURE_PER_TICK: f64 = 10.0;
 /// Active vent device
 pub struct ActiveVent {
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     network: OptWeakShared<CableNetwork>,
 
     reference_id: i32,
@@ -47,6 +48,12 @@ pub struct ActiveVent {
 }
 
 impl ActiveVent {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+    ];
+
     pub const PREFAB_HASH: i32 = string_to_hash("StructureActiveVent");
 
     pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
@@ -59,6 +66,7 @@ impl ActiveVent {
         // Default to outward mode with external = 1 atm, internal = 0
         shared(ActiveVent {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             reference_id: settings.id.unwrap(),
             on: RefCell::new(0.0),
@@ -89,7 +97,8 @@ impl ActiveVent {
             const DESCRIPTORS: &[PropertyDescriptor<ActiveVent>] = &[
                 prop_ro!(ReferenceId, |device: &ActiveVent, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device: &ActiveVent, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device: &ActiveVent, _| Ok(device.get_name_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device: &ActiveVent, _| Ok(if device.any_network_over_pressure() { 1.0 } else { 0.0 })),
                 prop_rw_bool!(On, on),
                 PropertyDescriptor::read_write(
                     Mode,
@@ -113,7 +122,7 @@ impl ActiveVent {
                 // Read-only properties exposing the connected pipe (Output) atmospheric state
                 prop_ro!(PressureOutput, |device, _| device.read_network_prop(Output, |net| net.pressure())),
                 prop_ro!(TemperatureOutput, |device, _| device.read_network_prop(Output, |net| net.temperature())),
-                prop_ro!(TotalMolesOutput, |device, _| device.read_network_prop(Output, |net| net.total_moles())),
+                prop_ro!(TotalMolesOutput, |device, _| device.network_total_moles(Output)),
                 prop_ro!(RatioOxygenOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Nitrogen))),
@@ -121,7 +130,7 @@ impl ActiveVent {
                 prop_ro!(RatioVolatilesOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Volatiles))),
                 prop_ro!(RatioWaterOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Water))),
                 prop_ro!(RatioNitrousOxideOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(NitrousOxide))),
-                prop_ro!(CombustionOutput, |_, _| Err(SimulationError::RuntimeError { message: "CombustionOutput not implemented".to_string(), line: 0 })),
+                prop_ro!(CombustionOutput, |device, _| device.read_network_prop(Output, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
 
                 // Read-write external/internal pressure targets
                 prop_rw_clamped!(PressureExternal, external_pressure, 0.0, f64::INFINITY),
@@ -232,7 +241,11 @@ impl ActiveVent {
         let transfer_moles = candidate_moles.min(pipe_available_moles);
 
         if transfer_moles > 0.0 {
-            world.add_mixture(&pipe.remove_moles(transfer_moles, MatterState::All));
+            pipe.transfer_moles_to(
+                world,
+                TransferAmount::Moles(transfer_moles),
+                MatterState::All,
+            );
             return true;
         }
 
@@ -271,7 +284,11 @@ impl ActiveVent {
 
         if transfer_moles > 0.0 {
             // Only transfer gas (not liquids) into pipe
-            pipe.add_mixture(&world.remove_moles(transfer_moles, MatterState::Gas));
+            world.transfer_moles_to(
+                pipe,
+                TransferAmount::Moles(transfer_moles),
+                MatterState::Gas,
+            );
             // Return change in world pressure (how much was removed)
             return true;
         }
@@ -290,7 +307,9 @@ impl Device for ActiveVent {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(self.name.as_str())
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
     }
 
     fn get_name(&self) -> &str {
@@ -309,6 +328,7 @@ impl Device for ActiveVent {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
                 self.reference_id,
@@ -347,8 +367,7 @@ impl Device for ActiveVent {
     }
 
     fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
-        use DeviceAtmosphericNetworkType::*;
-        vec![Input, Output]
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
     }
 
     fn as_atmospheric_device(&self) -> Option<&dyn AtmosphericDevice> {
@@ -427,28 +446,19 @@ impl Display for ActiveVent {
 }
 
 impl AtmosphericDevice for ActiveVent {
-    fn set_atmospheric_network(
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
         &mut self,
         connection: DeviceAtmosphericNetworkType,
-        network: OptShared<AtmosphericNetwork>,
-    ) -> SimulationResult<()> {
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
         use DeviceAtmosphericNetworkType::*;
         match connection {
-            Input => {
-                self.input_network = network.as_ref().map(Rc::downgrade);
-                Ok(())
-            }
-            Output => {
-                self.output_network = network.as_ref().map(Rc::downgrade);
-                Ok(())
-            }
-            _ => Err(SimulationError::RuntimeError {
-                message: format!(
-                    "ActiveVent does not support atmospheric connection {:?}",
-                    connection
-                ),
-                line: 0,
-            }),
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.output_network),
+            _ => None,
         }
     }
 
@@ -2,17 +2,19 @@
 
 use crate::{
     CableNetwork,
-    atmospherics::{CELSIUS_TO_KELVIN, GasType, MatterState, ONE_ATMOSPHERE, calculate_moles},
+    atmospherics::{
+        CELSIUS_TO_KELVIN, GasType, MatterState, ONE_ATMOSPHERE, TransferAmount, calculate_moles,
+    },
     constants::DEFAULT_MAX_INSTRUCTIONS_PER_TICK,
     devices::{
-        AtmosphericDevice, ChipSlot, Device, DeviceAtmosphericNetworkType, ICHostDevice,
-        ICHostDeviceMemoryOverride, LogicType, SimulationDeviceSettings,
+        AtmosphericDevice, ChipSlot, Device, DeviceAtmosphericNetworkType, IC_HOST_LOGIC_TYPES,
+        ICHostDevice, ICHostDeviceMemoryOverride, LogicType, SimulationDeviceSettings,
         property_descriptor::{PropertyDescriptor, PropertyRegistry},
     },
     error::{SimulationError, SimulationResult},
     networks::AtmosphericNetwork,
     parser::string_to_hash,
-    prop_ro, prop_rw_bool, prop_rw_clamped,
+    prop_ro, prop_rw_bool, prop_rw_clamped, prop_rw_name_hash,
     types::{OptShared, OptWeakShared, Shared, shared},
 };
 
@@ -21,7 +23,6 @@ use crate::conversions::fmt_trim;
 use std::{
     cell::RefCell,
     fmt::{Debug, Display},
-    rc::Rc,
     sync::{Arc, OnceLock},
 };
 
@@ -38,6 +39,8 @@ const ENERGY_COEFFICIENT: f64 = 14000.0;
 pub struct AirConditioner {
     /// Device name
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     /// Connected network
     network: OptWeakShared<CableNetwork>,
 
@@ -84,6 +87,13 @@ pub struct AirConditioner {
 
 /// Constructors and helpers
 impl AirConditioner {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+        DeviceAtmosphericNetworkType::Output2,
+    ];
+
     /// Compile-time prefab hash constant for this device
     pub const PREFAB_HASH: i32 = string_to_hash("StructureAirConditioner");
 
@@ -129,6 +139,7 @@ impl AirConditioner {
 
         let s = shared(Self {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             setting: RefCell::new(20.0),
             on: RefCell::new(1.0),
@@ -214,15 +225,16 @@ impl AirConditioner {
             const DESCRIPTORS: &[PropertyDescriptor<AirConditioner>] = &[
                 prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device, _| Ok(device.get_name_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.has_connection_error(&AirConditioner::required_atmospheric_connections()) { 1.0 } else { 0.0 })),
                 prop_rw_bool!(On, on),
                 prop_rw_bool!(Mode, mode),
                 prop_rw_clamped!(Setting, setting, 0.0, 999.0 + CELSIUS_TO_KELVIN),
 
                 prop_ro!(PressureInput, |device, _| device.read_network_prop(Input, |net| net.pressure())),
                 prop_ro!(TemperatureInput, |device, _| device.read_network_prop(Input, |net| net.temperature())),
-                prop_ro!(TotalMolesInput, |device, _| device.read_network_prop(Input, |net| net.total_moles())),
-                prop_ro!(CombustionInput, |_, _| Err(SimulationError::RuntimeError { message: "CombustionInput not implemented".to_string(), line: 0 })),
+                prop_ro!(TotalMolesInput, |device, _| device.network_total_moles(Input)),
+                prop_ro!(CombustionInput, |device, _| device.read_network_prop(Input, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
                 prop_ro!(RatioOxygenInput, |device, _| device.read_network_prop(Input, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideInput, |device, _| device.read_network_prop(Input, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenInput, |device, _| device.read_network_prop(Input, |net| net.gas_ratio(Nitrogen))),
@@ -240,8 +252,8 @@ impl AirConditioner {
 
                 prop_ro!(PressureOutput, |device, _| device.read_network_prop(Output, |net| net.pressure())),
                 prop_ro!(TemperatureOutput, |device, _| device.read_network_prop(Output, |net| net.temperature())),
-                prop_ro!(TotalMolesOutput, |device, _| device.read_network_prop(Output, |net| net.total_moles())),
-                prop_ro!(CombustionOutput, |_, _| Err(SimulationError::RuntimeError { message: "CombustionOutput not implemented".to_string(), line: 0 })),
+                prop_ro!(TotalMolesOutput, |device, _| device.network_total_moles(Output)),
+                prop_ro!(CombustionOutput, |device, _| device.read_network_prop(Output, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
                 prop_ro!(RatioOxygenOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Nitrogen))),
@@ -259,8 +271,8 @@ impl AirConditioner {
 
                 prop_ro!(PressureOutput2, |device, _| device.read_network_prop(Output2, |net| net.pressure())),
                 prop_ro!(TemperatureOutput2, |device, _| device.read_network_prop(Output2, |net| net.temperature())),
-                prop_ro!(TotalMolesOutput2, |device, _| device.read_network_prop(Output2, |net| net.total_moles())),
-                prop_ro!(CombustionOutput2, |_, _| Err(SimulationError::RuntimeError { message: "CombustionOutput2 not implemented".to_string(), line: 0 })),
+                prop_ro!(TotalMolesOutput2, |device, _| device.network_total_moles(Output2)),
+                prop_ro!(CombustionOutput2, |device, _| device.read_network_prop(Output2, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
                 prop_ro!(RatioOxygenOutput2, |device, _| device.read_network_prop(Output2, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideOutput2, |device, _| device.read_network_prop(Output2, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenOutput2, |device, _| device.read_network_prop(Output2, |net| net.gas_ratio(Nitrogen))),
@@ -335,26 +347,6 @@ impl AirConditioner {
         let net = self.require_network(connection)?;
         Ok(f(&net.borrow()))
     }
-
-    /// Helper to get a mutable reference to an atmospheric network slot
-    fn network_slot_mut(
-        &mut self,
-        connection: DeviceAtmosphericNetworkType,
-    ) -> Result<&mut OptWeakShared<AtmosphericNetwork>, SimulationError> {
-        use DeviceAtmosphericNetworkType::*;
-        match connection {
-            Input => Ok(&mut self.input_network),
-            Output => Ok(&mut self.output_network),
-            Output2 => Ok(&mut self.waste_network),
-            _ => Err(SimulationError::RuntimeError {
-                message: format!(
-                    "AirConditioner does not support atmospheric connection type {:?}",
-                    connection
-                ),
-                line: 0,
-            }),
-        }
-    }
 }
 
 /// `Device` trait implementation for `AirConditioner` providing logic access, naming, and update behavior.
@@ -368,7 +360,9 @@ impl Device for AirConditioner {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(self.name.as_str())
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
     }
 
     fn get_name(&self) -> &str {
@@ -387,6 +381,7 @@ impl Device for AirConditioner {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
 
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
@@ -398,7 +393,7 @@ impl Device for AirConditioner {
     }
 
     fn can_read(&self, logic_type: LogicType) -> bool {
-        Self::properties().can_read(logic_type)
+        IC_HOST_LOGIC_TYPES.contains(&logic_type) || Self::properties().can_read(logic_type)
     }
 
     fn can_write(&self, logic_type: LogicType) -> bool {
@@ -406,7 +401,10 @@ impl Device for AirConditioner {
     }
 
     fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
-        Self::properties().read(self, logic_type)
+        match self.read_host_logic(logic_type) {
+            Some(value) => Ok(value),
+            None => Self::properties().read(self, logic_type),
+        }
     }
 
     fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
@@ -458,16 +456,14 @@ impl Device for AirConditioner {
             return Ok(false);
         }
 
-        // remove that many moles from the input network
-        let transferred_mixture = input_rc
-            .borrow_mut()
-            .remove_moles(transfer_moles, MatterState::All);
+        // move that many moles from the input network into the internal buffer
+        input_rc.borrow_mut().transfer_moles_to(
+            &mut self.internal.borrow_mut(),
+            TransferAmount::Moles(transfer_moles),
+            MatterState::All,
+        );
 
-        // add to internal buffer
         {
-            // Add transferred gas to internal
-            self.internal.borrow_mut().add_mixture(&transferred_mixture);
-
             // temperature gap evaluation (between internal and waste depending on direction)
             let temperature_gap = if target_temperature > self.internal.borrow().temperature() {
                 waste_rc.borrow().temperature() - self.internal.borrow().temperature()
@@ -501,9 +497,11 @@ impl Device for AirConditioner {
             }
 
             // move internal gas to primary output and reset internal buffer
-            output_rc
-                .borrow_mut()
-                .add_mixture(&self.internal.borrow_mut().consume());
+            self.internal.borrow_mut().transfer_moles_to(
+                &mut output_rc.borrow_mut(),
+                TransferAmount::Fraction(1.0),
+                MatterState::All,
+            );
 
             // store metrics
             *self.temperature_differential_efficiency.borrow_mut() =
@@ -530,7 +528,9 @@ impl Device for AirConditioner {
     }
 
     fn supported_types(&self) -> Vec<LogicType> {
-        Self::properties().supported_types()
+        let mut types = Self::properties().supported_types();
+        types.extend_from_slice(IC_HOST_LOGIC_TYPES);
+        types
     }
 
     fn get_memory(&self, index: usize) -> SimulationResult<f64> {
@@ -554,8 +554,11 @@ impl Device for AirConditioner {
     }
 
     fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
-        use DeviceAtmosphericNetworkType::*;
-        vec![Internal, Input, Output, Output2]
+        [
+            &[DeviceAtmosphericNetworkType::Internal],
+            Self::ATMOSPHERIC_CONNECTIONS,
+        ]
+        .concat()
     }
 
     fn as_ic_host_device(&self) -> Option<&dyn ICHostDevice> {
@@ -598,14 +601,21 @@ impl ICHostDeviceMemoryOverride for AirConditioner {}
 
 /// `AtmosphericDevice` implementation for `AirConditioner` that manages input/output/waste networks.
 impl AtmosphericDevice for AirConditioner {
-    fn set_atmospheric_network(
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
         &mut self,
         connection: DeviceAtmosphericNetworkType,
-        network: OptShared<AtmosphericNetwork>,
-    ) -> SimulationResult<()> {
-        let slot = self.network_slot_mut(connection)?;
-        *slot = network.as_ref().map(Rc::downgrade);
-        Ok(())
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.output_network),
+            Output2 => Some(&mut self.waste_network),
+            _ => None,
+        }
     }
 
     fn get_atmospheric_network(
@@ -0,0 +1,304 @@
+//! Airlock: a door that joins two atmospheres while open and seals them while closed.
+
+use crate::{
+    CableNetwork,
+    atmospherics::PRESSURE_EQUALIZATION_EPSILON,
+    devices::{
+        AtmosphericDevice, Device, DeviceAtmosphericNetworkType, LogicType,
+        SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::{SimulationError, SimulationResult},
+    networks::AtmosphericNetwork,
+    parser::string_to_hash,
+    prop_ro, prop_rw_bool, prop_rw_name_hash,
+    types::{OptShared, OptWeakShared, Shared, shared},
+};
+
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    sync::OnceLock,
+};
+
+/// Airlock door between an input and an output atmosphere
+pub struct Airlock {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// The device reference ID
+    reference_id: i32,
+    /// The Open state (0 = sealed, 1 = open)
+    open: RefCell<f64>,
+
+    /// The input network
+    input_network: OptWeakShared<AtmosphericNetwork>,
+    /// The output network
+    output_network: OptWeakShared<AtmosphericNetwork>,
+}
+
+/// Constructors for `Airlock`.
+impl Airlock {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+    ];
+
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureAirlock");
+
+    /// Create a new, sealed `Airlock`.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            reference_id: settings.id.unwrap(),
+            open: RefCell::new(0.0),
+            input_network: None,
+            output_network: None,
+        })
+    }
+
+    /// Return the prefab hash for `Airlock`.
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    /// Human-readable display name
+    pub fn display_name_static() -> &'static str {
+        "Airlock"
+    }
+
+    /// Whether the airlock currently joins its two atmospheres
+    pub fn is_open(&self) -> bool {
+        *self.open.borrow() != 0.0
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<Airlock>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<Airlock>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.any_network_over_pressure() { 1.0 } else { 0.0 })),
+                prop_rw_bool!(Open, open),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+
+    fn require_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> SimulationResult<Shared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => self.input_network.as_ref().and_then(|w| w.upgrade()).ok_or(
+                SimulationError::RuntimeError {
+                    message: "Airlock device has no input atmospheric network".to_string(),
+                    line: 0,
+                },
+            ),
+            Output => self
+                .output_network
+                .as_ref()
+                .and_then(|w| w.upgrade())
+                .ok_or(SimulationError::RuntimeError {
+                    message: "Airlock device has no output atmospheric network".to_string(),
+                    line: 0,
+                }),
+            _ => Err(SimulationError::RuntimeError {
+                message: format!(
+                    "Airlock does not support atmospheric connection type {:?}",
+                    connection
+                ),
+                line: 0,
+            }),
+        }
+    }
+}
+
+/// `Device` trait implementation for `Airlock`
+impl Device for Airlock {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Airlock::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn update(&self, _tick: u64) -> SimulationResult<bool> {
+        // A sealed airlock keeps both atmospheres apart
+        if !self.is_open() {
+            return Ok(false);
+        }
+
+        let input_rc = self.require_network(DeviceAtmosphericNetworkType::Input)?;
+        let output_rc = self.require_network(DeviceAtmosphericNetworkType::Output)?;
+
+        let pressure_delta = input_rc.borrow().pressure() - output_rc.borrow().pressure();
+        if pressure_delta.abs() < PRESSURE_EQUALIZATION_EPSILON {
+            return Ok(false);
+        }
+
+        input_rc
+            .borrow_mut()
+            .equalize_with(&mut output_rc.borrow_mut());
+
+        Ok(true)
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        Airlock::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        Airlock::display_name_static()
+    }
+
+    fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
+    }
+
+    fn as_atmospheric_device(&self) -> Option<&dyn AtmosphericDevice> {
+        Some(self)
+    }
+
+    fn as_atmospheric_device_mut(&mut self) -> Option<&mut dyn AtmosphericDevice> {
+        Some(self)
+    }
+}
+
+impl Display for Airlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let open_str = if self.is_open() { "Open" } else { "Sealed" };
+
+        write!(
+            f,
+            "Airlock {{ name: \"{}\", id: {}, state: {}",
+            self.name, self.reference_id, open_str
+        )?;
+
+        if let Some(weak) = &self.input_network
+            && let Some(net) = weak.upgrade()
+        {
+            write!(f, ", input: {}", net.borrow())?;
+        }
+        if let Some(weak) = &self.output_network
+            && let Some(net) = weak.upgrade()
+        {
+            write!(f, ", output: {}", net.borrow())?;
+        }
+
+        write!(f, " }}")
+    }
+}
+
+impl Debug for Airlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// `AtmosphericDevice` implementation for `Airlock` that manages the connections on either side of the door.
+impl AtmosphericDevice for Airlock {
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
+        &mut self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.output_network),
+            _ => None,
+        }
+    }
+
+    fn get_atmospheric_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> OptShared<AtmosphericNetwork> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => self.input_network.as_ref().and_then(|w| w.upgrade()),
+            Output => self.output_network.as_ref().and_then(|w| w.upgrade()),
+            _ => None,
+        }
+    }
+}
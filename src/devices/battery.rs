@@ -0,0 +1,280 @@
+//! Battery: stores the cable network's surplus power and releases it to cover demand.
+
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::sync::OnceLock;
+
+use crate::constants::DEFAULT_TICKS_PER_SECOND;
+use crate::conversions::fmt_trim;
+use crate::types::OptWeakShared;
+use crate::{
+    CableNetwork,
+    devices::{
+        Device, LogicType, SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::{SimulationError, SimulationResult},
+    parser::string_to_hash,
+    types::{OptShared, Shared, shared},
+};
+use crate::{prop_ro, prop_rw_name_hash};
+
+/// Energy a Battery holds when full (J)
+pub const BATTERY_CAPACITY: f64 = 3_600_000.0;
+
+/// Station battery.
+///
+/// Each tick the battery takes its share of the power surplus of the other devices on its
+/// network (split evenly between the batteries on the network) and integrates it over the
+/// tick: a surplus charges it, a deficit discharges it, and the charge is clamped to
+/// `[0, Maximum]`. A tick lasts `1 / ticks_per_second` seconds, from the device settings
+/// (`DEFAULT_TICKS_PER_SECOND` when unset). `Ratio` reports the charge ratio.
+///
+/// The power moved in the last tick goes back to the network: discharging is reported as
+/// `power_generation`, so it covers the demand it was drawn for, and charging as
+/// `power_demand`.
+pub struct Battery {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// Device reference ID
+    reference_id: i32,
+    /// Stored energy (J)
+    charge: RefCell<f64>,
+    /// Capacity (J)
+    maximum: f64,
+    /// Power supplied to the network in the last tick (W); negative while charging
+    output: RefCell<f64>,
+    /// Simulation ticks per second of game time, used to integrate power over a tick
+    ticks_per_second: f64,
+}
+
+/// Constructors and helpers
+impl Battery {
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureBattery");
+
+    /// Create a new, empty `Battery`.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            charge: RefCell::new(0.0),
+            maximum: BATTERY_CAPACITY,
+            output: RefCell::new(0.0),
+            ticks_per_second: settings
+                .ticks_per_second
+                .filter(|&rate| rate > 0.0)
+                .unwrap_or(DEFAULT_TICKS_PER_SECOND),
+            reference_id: settings.id.unwrap(),
+        })
+    }
+
+    /// Prefab hash for `Battery`
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    pub fn display_name_static() -> &'static str {
+        "Station Battery"
+    }
+
+    /// Simulation ticks per second of game time the battery integrates power over
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks_per_second
+    }
+
+    /// Stored energy (J)
+    pub fn charge(&self) -> f64 {
+        *self.charge.borrow()
+    }
+
+    /// Set the stored energy (J), clamped to the capacity
+    pub fn set_charge(&self, charge: f64) {
+        *self.charge.borrow_mut() = charge.clamp(0.0, self.maximum);
+    }
+
+    /// Capacity (J)
+    pub fn maximum(&self) -> f64 {
+        self.maximum
+    }
+
+    /// Power supplied to the network in the last tick (W); negative while charging
+    pub fn output(&self) -> f64 {
+        *self.output.borrow()
+    }
+
+    /// Stored energy as a fraction of the capacity, in `[0, 1]`
+    pub fn charge_ratio(&self) -> f64 {
+        self.charge() / self.maximum
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<Battery>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<Battery>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Charge, |device, _| Ok(device.charge())),
+                prop_ro!(Maximum, |device, _| Ok(device.maximum())),
+                prop_ro!(Ratio, |device, _| Ok(device.charge_ratio())),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+}
+
+/// `Device` trait implementation for `Battery`
+impl Device for Battery {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Battery::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn update(&self, _tick: u64) -> SimulationResult<bool> {
+        let Some(network) = self.get_network() else {
+            *self.output.borrow_mut() = 0.0;
+            return Ok(false);
+        };
+
+        // Batteries only balance the rest of the network, so their own flows are left out
+        let (surplus, batteries) = {
+            let net = network
+                .try_borrow()
+                .map_err(|_| SimulationError::RuntimeError {
+                    message: format!(
+                        "Cable network of battery {} is busy and cannot be accessed",
+                        self.reference_id
+                    ),
+                    line: 0,
+                })?;
+            let mut surplus = 0.0;
+            let mut batteries = 0_usize;
+            for id in net.all_device_ids() {
+                let Some(device) = net.try_get_device(id)? else {
+                    continue;
+                };
+                if device.get_prefab_hash() == Self::PREFAB_HASH {
+                    batteries += 1;
+                } else {
+                    surplus += device.power_generation() - device.power_demand();
+                }
+            }
+            (surplus, batteries.max(1))
+        };
+
+        let before = self.charge();
+        self.set_charge(before + surplus / batteries as f64 / self.ticks_per_second);
+        *self.output.borrow_mut() = (before - self.charge()) * self.ticks_per_second;
+
+        Ok(self.charge() != before)
+    }
+
+    fn power_generation(&self) -> f64 {
+        self.output().max(0.0)
+    }
+
+    fn power_demand(&self) -> f64 {
+        (-self.output()).max(0.0)
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        Battery::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        Battery::display_name_static()
+    }
+}
+
+impl Display for Battery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Battery {{ name: \"{}\", id: {}, charge: {} J / {} J }}",
+            self.name,
+            self.reference_id,
+            fmt_trim(self.charge(), 0),
+            fmt_trim(self.maximum, 0)
+        )
+    }
+}
+
+impl Debug for Battery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
@@ -2,15 +2,15 @@
 
 use crate::{
     CableNetwork, Device, Item,
+    constants::{STACK_POINTER_INDEX, STACK_SIZE},
     devices::LogicType,
     error::{SimulationError, SimulationResult},
     items::{ItemIntegratedCircuit10, ItemType, Slot},
-    types::{OptShared, OptWeakShared, Shared, shared},
+    types::{OptShared, OptWeakShared, Shared, SharedPtr, SharedRef, SharedRefMut, shared},
 };
 use std::{
-    cell::{Ref, RefCell, RefMut},
+    cell::RefCell,
     fmt::{Debug, Display},
-    rc::Rc,
 };
 
 /// Chip slot for IC; manages chip, pins, and execution state
@@ -29,6 +29,9 @@ pub struct ChipSlot {
 
     /// Last executed instruction count
     last_executed_instructions: RefCell<usize>,
+
+    /// Cap on the instructions the next `run` may execute, below the host's own limit
+    instruction_budget: RefCell<Option<usize>>,
 }
 
 impl ChipSlot {
@@ -40,16 +43,17 @@ impl ChipSlot {
             slot: Slot::new(Some(ItemType::ItemIntegratedCircuit10)),
             chip_ref: None,
             last_executed_instructions: RefCell::new(0),
+            instruction_budget: RefCell::new(None),
         })
     }
 
     /// Set the host device reference
     pub fn set_host_device(&mut self, device: OptShared<dyn Device>) {
-        self.host_device = device.map(|d| Rc::downgrade(&d));
+        self.host_device = device.map(|d| SharedPtr::downgrade(&d));
     }
 
     /// Get the hosted chip (if any)
-    pub fn get_chip(&self) -> Option<Ref<'_, ItemIntegratedCircuit10>> {
+    pub fn get_chip(&self) -> Option<SharedRef<'_, ItemIntegratedCircuit10>> {
         self.slot.borrow_item()
     }
 
@@ -58,8 +62,21 @@ impl ChipSlot {
         self.chip_ref.clone()
     }
 
+    /// Stack capacity of a chip in this slot, answered for the host's `StackSize`
+    pub fn stack_size(&self) -> usize {
+        STACK_SIZE
+    }
+
+    /// Number of values on the hosted chip's stack (its `sp`, clamped to the capacity), or 0
+    /// without a chip
+    pub fn stack_depth(&self) -> usize {
+        self.get_chip()
+            .and_then(|chip| chip.get_register(STACK_POINTER_INDEX).ok())
+            .map_or(0, |sp| sp.clamp(0.0, STACK_SIZE as f64) as usize)
+    }
+
     /// Borrow the item in the slot as type T mutably, if it matches
-    pub fn get_chip_mut(&self) -> Option<RefMut<'_, ItemIntegratedCircuit10>> {
+    pub fn get_chip_mut(&self) -> Option<SharedRefMut<'_, ItemIntegratedCircuit10>> {
         self.slot.borrow_item_mut()
     }
 
@@ -87,7 +104,9 @@ impl ChipSlot {
         self.slot.remove()
     }
 
-    /// Set device pin `pin` to `device_ref_id`
+    /// Set device pin `pin` (`d{pin}` in IC10 code) to `device_ref_id`, or clear it with
+    /// `None`. The ID is resolved on the host's cable network when the chip accesses the pin.
+    /// Pins at or above `device_pin_count()` are ignored.
     pub fn set_device_pin(&mut self, pin: usize, device_ref_id: Option<i32>) {
         if pin < self.device_pins.len() {
             self.device_pins[pin] = device_ref_id;
@@ -108,10 +127,24 @@ impl ChipSlot {
         self.device_pins.len()
     }
 
-    /// Run the hosted chip up to `max_instructions_per_tick`
+    /// Run the hosted chip up to `max_instructions_per_tick`, or fewer when an instruction
+    /// budget is set. The chip does not run at all once the budget is used up.
     pub fn run(&self, max_instructions_per_tick: usize) -> SimulationResult<()> {
+        let max_steps = self
+            .instruction_budget
+            .borrow()
+            .map_or(max_instructions_per_tick, |budget| {
+                budget.min(max_instructions_per_tick)
+            });
+
+        // A used-up budget skips the chip, leaving its tick and sleep counters untouched
+        if *self.instruction_budget.borrow() == Some(0) {
+            *self.last_executed_instructions.borrow_mut() = 0;
+            return Ok(());
+        }
+
         if let Some(chip) = self.get_chip() {
-            let instructions = chip.run(max_instructions_per_tick)?;
+            let instructions = chip.run(max_steps)?;
             *self.last_executed_instructions.borrow_mut() = instructions;
         } else {
             *self.last_executed_instructions.borrow_mut() = 0;
@@ -120,57 +153,79 @@ impl ChipSlot {
         Ok(())
     }
 
+    /// Cap the instructions later runs may execute; `None` leaves only the host's own limit
+    pub fn set_instruction_budget(&self, budget: Option<usize>) {
+        *self.instruction_budget.borrow_mut() = budget;
+    }
+
     /// Get last executed instruction count
     pub fn get_last_executed_instructions(&self) -> usize {
         *self.last_executed_instructions.borrow()
     }
 
     /// Get host device's network
+    ///
+    /// `None` when there is no host, or when the host is mutably borrowed elsewhere (see
+    /// `try_get_network` to tell the two apart)
     pub fn get_network(&self) -> OptShared<CableNetwork> {
-        if let Some(host_weak) = &self.host_device
-            && let Some(host) = host_weak.upgrade()
+        self.try_get_network().ok().flatten()
+    }
+
+    /// Get host device's network, `None` when there is no host or it is not on a network.
+    /// Fails when the host is mutably borrowed elsewhere.
+    pub fn try_get_network(&self) -> SimulationResult<OptShared<CableNetwork>> {
+        if self
+            .host_device
+            .as_ref()
+            .and_then(|w| w.upgrade())
+            .is_none()
         {
-            host.borrow().get_network()
-        } else {
-            None
+            return Ok(None);
         }
+        self.borrow_host(|host| Ok(host.get_network()))
     }
 
     /// Host device reference ID, if any
+    ///
+    /// `None` when there is no host, or when the host is mutably borrowed elsewhere
     pub fn id(&self) -> Option<i32> {
         if let Some(host_weak) = &self.host_device {
-            host_weak.upgrade().map(|host| host.borrow().get_id())
+            let host = host_weak.upgrade()?;
+            let id = host.try_borrow().ok()?.get_id();
+            Some(id)
         } else {
             None
         }
     }
 
-    /// Read a logic value from the host device
-    pub fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
-        if let Some(host_weak) = &self.host_device
-            && let Some(host) = host_weak.upgrade()
-        {
-            host.borrow().read(logic_type)
-        } else {
-            Err(SimulationError::RuntimeError {
+    /// Borrow the host device, failing instead of panicking when it is mutably borrowed
+    fn borrow_host<T>(
+        &self,
+        f: impl FnOnce(&dyn Device) -> SimulationResult<T>,
+    ) -> SimulationResult<T> {
+        let Some(host) = self.host_device.as_ref().and_then(|w| w.upgrade()) else {
+            return Err(SimulationError::RuntimeError {
                 message: "No host device".to_string(),
                 line: 0,
-            })
-        }
+            });
+        };
+        let host = host
+            .try_borrow()
+            .map_err(|_| SimulationError::RuntimeError {
+                message: "Host device is busy and cannot be accessed".to_string(),
+                line: 0,
+            })?;
+        f(&*host)
+    }
+
+    /// Read a logic value from the host device
+    pub fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        self.borrow_host(|host| host.read(logic_type))
     }
 
     /// Write a logic value to the host device
     pub fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
-        if let Some(host_weak) = &self.host_device
-            && let Some(host) = host_weak.upgrade()
-        {
-            host.borrow().write(logic_type, value)
-        } else {
-            Err(SimulationError::RuntimeError {
-                message: "No host device".to_string(),
-                line: 0,
-            })
-        }
+        self.borrow_host(|host| host.write(logic_type, value))
     }
 }
 
@@ -0,0 +1,239 @@
+//! Console device: shows the number, or with `Mode` 1 the text, a chip writes to its `Setting`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::sync::OnceLock;
+
+use crate::conversions::fmt_trim;
+use crate::types::OptWeakShared;
+use crate::{
+    CableNetwork,
+    devices::{
+        Device, LogicType, SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::SimulationResult,
+    parser::{string_to_hash, unpack_ascii6},
+    types::{OptShared, Shared, shared},
+};
+use crate::{prop_ro, prop_rw_bool, prop_rw_name_hash};
+
+/// Number of written values a Console keeps for `history`
+pub const CONSOLE_HISTORY_SIZE: usize = 32;
+
+/// Console displaying the values a chip writes to `Setting`
+pub struct Console {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// Device reference ID
+    reference_id: i32,
+    /// Last value written to `Setting`
+    setting: RefCell<f64>,
+    /// The Mode state (0 = number, 1 = text packed with `STR(...)`)
+    mode: RefCell<f64>,
+    /// Most recent `Setting` writes with whether they were shown as text, oldest first
+    history: RefCell<VecDeque<(f64, bool)>>,
+}
+
+/// Constructors and helpers
+impl Console {
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureConsole");
+
+    /// Create a new, blank `Console`.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            setting: RefCell::new(0.0),
+            mode: RefCell::new(0.0),
+            history: RefCell::new(VecDeque::with_capacity(CONSOLE_HISTORY_SIZE)),
+            reference_id: settings.id.unwrap(),
+        })
+    }
+
+    /// Prefab hash for `Console`
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    pub fn display_name_static() -> &'static str {
+        "Console"
+    }
+
+    /// Whether `Setting` is shown as `STR(...)` packed text rather than a number
+    pub fn is_text_mode(&self) -> bool {
+        *self.mode.borrow() != 0.0
+    }
+
+    /// Render a written value: in text mode the `STR(...)` packed text when it decodes,
+    /// otherwise the number
+    fn render(value: f64, as_text: bool) -> String {
+        as_text
+            .then(|| unpack_ascii6(value))
+            .flatten()
+            .unwrap_or_else(|| fmt_trim(value, 3))
+    }
+
+    /// The currently displayed text
+    pub fn text(&self) -> String {
+        Self::render(*self.setting.borrow(), self.is_text_mode())
+    }
+
+    /// The last `CONSOLE_HISTORY_SIZE` written values as displayed when written, oldest first
+    pub fn history(&self) -> Vec<String> {
+        self.history
+            .borrow()
+            .iter()
+            .map(|&(value, as_text)| Self::render(value, as_text))
+            .collect()
+    }
+
+    /// The last `CONSOLE_HISTORY_SIZE` written raw values, oldest first
+    pub fn history_values(&self) -> Vec<f64> {
+        self.history
+            .borrow()
+            .iter()
+            .map(|&(value, _)| value)
+            .collect()
+    }
+
+    /// Display `value` and record it in the history
+    fn show(&self, value: f64) {
+        *self.setting.borrow_mut() = value;
+        let mut history = self.history.borrow_mut();
+        if history.len() == CONSOLE_HISTORY_SIZE {
+            history.pop_front();
+        }
+        history.push_back((value, self.is_text_mode()));
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<Console>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<Console>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_rw_bool!(Mode, mode),
+                PropertyDescriptor::read_write(
+                    Setting,
+                    |device, _| Ok(*device.setting.borrow()),
+                    |device, _, value| {
+                        device.show(value);
+                        Ok(())
+                    },
+                ),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+}
+
+/// `Device` trait implementation for `Console`
+impl Device for Console {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Console::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        Console::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        Console::display_name_static()
+    }
+}
+
+impl Display for Console {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Console {{ name: \"{}\", id: {}, text: \"{}\" }}",
+            self.name,
+            self.reference_id,
+            self.text()
+        )
+    }
+}
+
+impl Debug for Console {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
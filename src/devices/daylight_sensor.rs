@@ -17,12 +17,40 @@ use crate::{
     parser::string_to_hash,
     types::{OptShared, Shared, shared},
 };
-use crate::{prop_ro, prop_rw_bool};
+use crate::{prop_ro, prop_rw_bool, prop_rw_name_hash};
+
+/// Sun position `(horizontal, vertical)` in degrees at `tick` of a `ticks_per_day` day cycle.
+///
+/// Vertical is measured from the zenith: 0 at noon, 90 on the horizon and 180 at midnight.
+pub fn sun_angles(tick: u64, ticks_per_day: f64) -> (f64, f64) {
+    // Calculate position within the day cycle [0.0, 1.0)
+    let day_progress = ((tick % ticks_per_day as u64) as f64) / ticks_per_day;
+
+    // Horizontal angle: simple rotation around the compass
+    // 0 degrees at tick 0, 360 degrees at tick 2400
+    let horizontal = day_progress * 360.0;
+
+    // Vertical angle: uses cosine to create smooth oscillation
+    // At tick 0 (midnight): vertical = 180 (nadir)
+    // At tick 600 (sunrise): vertical = 90 (horizon)
+    // At tick 1200 (noon): vertical = 0 (zenith)
+    // At tick 1800 (sunset): vertical = 90 (horizon)
+    // At tick 2400 (midnight): vertical = 180 (nadir)
+    //
+    // Formula: vertical = 90 + 90 * cos(2π * progress)
+    // This gives: 180 at progress=0, 0 at progress=0.5, 180 at progress=1
+    let angle_radians = 2.0 * f64::consts::PI * day_progress;
+    let vertical = 90.0 + 90.0 * angle_radians.cos();
+
+    (horizontal, vertical)
+}
 
 /// Daylight sensor: tracks sun position
 pub struct DaylightSensor {
     /// Device name
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     /// Connected network
     network: OptWeakShared<CableNetwork>,
 
@@ -56,6 +84,7 @@ impl DaylightSensor {
 
         shared(Self {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             reference_id: settings.id.unwrap(),
             on: RefCell::new(1.0),
@@ -94,7 +123,7 @@ impl DaylightSensor {
             const DESCRIPTORS: &[PropertyDescriptor<DaylightSensor>] = &[
                 prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device, _| Ok(device.get_name_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
                 prop_ro!(Horizontal, |device, _| Ok(*device.horizontal.borrow())),
                 prop_ro!(Vertical, |device, _| Ok(*device.vertical.borrow())),
                 prop_rw_bool!(On, on),
@@ -116,7 +145,9 @@ impl Device for DaylightSensor {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(&self.name)
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
     }
 
     fn get_name(&self) -> &str {
@@ -135,6 +166,7 @@ impl Device for DaylightSensor {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
 
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
@@ -173,24 +205,7 @@ impl Device for DaylightSensor {
             return Ok(false);
         }
 
-        // Calculate position within the day cycle [0.0, 1.0)
-        let day_progress = ((tick % self.ticks_per_day as u64) as f64) / self.ticks_per_day;
-
-        // Horizontal angle: simple rotation around the compass
-        // 0 degrees at tick 0, 360 degrees at tick 2400
-        let horizontal = day_progress * 360.0;
-
-        // Vertical angle: uses cosine to create smooth oscillation
-        // At tick 0 (midnight): vertical = 180 (nadir)
-        // At tick 600 (sunrise): vertical = 90 (horizon)
-        // At tick 1200 (noon): vertical = 0 (zenith)
-        // At tick 1800 (sunset): vertical = 90 (horizon)
-        // At tick 2400 (midnight): vertical = 180 (nadir)
-        //
-        // Formula: vertical = 90 + 90 * cos(2π * progress)
-        // This gives: 180 at progress=0, 0 at progress=0.5, 180 at progress=1
-        let angle_radians = 2.0 * f64::consts::PI * day_progress;
-        let vertical = 90.0 + 90.0 * angle_radians.cos();
+        let (horizontal, vertical) = sun_angles(tick, self.ticks_per_day);
 
         // Update the logic fields with the new angles
         *self.horizontal.borrow_mut() = horizontal;
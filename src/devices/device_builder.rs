@@ -0,0 +1,171 @@
+//! Fluent builder for creating, wiring and registering devices through a `SimulationManager`.
+
+use crate::{
+    CableNetwork, LogicType, SimulationManager,
+    devices::{Device, DeviceAtmosphericNetworkType, SimulationDeviceSettings},
+    error::{SimulationError, SimulationResult},
+    networks::AtmosphericNetwork,
+    types::Shared,
+};
+
+/// Builder for a device of type `T`, started from `SimulationManager::build_device` or one of
+/// its per-device shorthands such as `build_air_conditioner`.
+///
+/// `build()` creates the device with a manager-assigned ID, attaches its atmospheric
+/// connections, applies the queued logic writes in order, tracks it in the manager and adds
+/// it to a cable network (a new one unless `cable_network` was given).
+pub struct DeviceBuilder<'a, T: Device + 'static> {
+    manager: &'a mut SimulationManager,
+    new: fn(SimulationDeviceSettings) -> Shared<T>,
+    settings: SimulationDeviceSettings,
+    connections: Vec<(DeviceAtmosphericNetworkType, Shared<AtmosphericNetwork>)>,
+    writes: Vec<(LogicType, f64)>,
+    cable_network: Option<Shared<CableNetwork>>,
+}
+
+impl<'a, T: Device + 'static> DeviceBuilder<'a, T> {
+    /// Create a builder for the device constructed by `new`
+    pub fn new(
+        manager: &'a mut SimulationManager,
+        new: fn(SimulationDeviceSettings) -> Shared<T>,
+    ) -> Self {
+        Self {
+            manager,
+            new,
+            settings: SimulationDeviceSettings::default(),
+            connections: Vec::new(),
+            writes: Vec::new(),
+            cable_network: None,
+        }
+    }
+
+    /// Use a specific reference ID instead of the manager's next free one
+    pub fn id(mut self, id: i32) -> Self {
+        self.settings.id = Some(id);
+        self
+    }
+
+    /// Override the device name
+    pub fn name(mut self, name: &str) -> Self {
+        self.settings.name = Some(name.to_string());
+        self
+    }
+
+    /// Replace the simulation settings; the reference ID is still assigned by the manager
+    /// unless the settings carry one
+    pub fn settings(mut self, settings: SimulationDeviceSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Attach an atmospheric network to the given connection
+    pub fn connect(
+        mut self,
+        connection: DeviceAtmosphericNetworkType,
+        network: Shared<AtmosphericNetwork>,
+    ) -> Self {
+        self.connections.push((connection, network));
+        self
+    }
+
+    /// Attach the input atmospheric network
+    pub fn input(self, network: Shared<AtmosphericNetwork>) -> Self {
+        self.connect(DeviceAtmosphericNetworkType::Input, network)
+    }
+
+    /// Attach the output atmospheric network
+    pub fn output(self, network: Shared<AtmosphericNetwork>) -> Self {
+        self.connect(DeviceAtmosphericNetworkType::Output, network)
+    }
+
+    /// Attach the waste (second output) atmospheric network
+    pub fn waste(self, network: Shared<AtmosphericNetwork>) -> Self {
+        self.connect(DeviceAtmosphericNetworkType::Output2, network)
+    }
+
+    /// Register the device on an existing cable network instead of a new one
+    pub fn cable_network(mut self, network: Shared<CableNetwork>) -> Self {
+        self.cable_network = Some(network);
+        self
+    }
+
+    /// Queue a logic write applied once the device is wired
+    pub fn write(mut self, logic_type: LogicType, value: f64) -> Self {
+        self.writes.push((logic_type, value));
+        self
+    }
+
+    /// Queue `On = 1`
+    pub fn on(self) -> Self {
+        self.write(LogicType::On, 1.0)
+    }
+
+    /// Queue `On = 0`
+    pub fn off(self) -> Self {
+        self.write(LogicType::On, 0.0)
+    }
+
+    /// Queue a `Mode` write
+    pub fn mode(self, mode: f64) -> Self {
+        self.write(LogicType::Mode, mode)
+    }
+
+    /// Queue a `Setting` write
+    pub fn setting(self, setting: f64) -> Self {
+        self.write(LogicType::Setting, setting)
+    }
+
+    /// Create the device, wire it and register it with the manager and a cable network.
+    /// Nothing is tracked when wiring, a logic write or joining the cable network fails.
+    pub fn build(self) -> SimulationResult<Shared<T>> {
+        let settings = self.manager.prepare_device_settings(Some(self.settings))?;
+        let id = settings.id.unwrap();
+        let device = (self.new)(settings);
+
+        let attached = Self::wire(&device, self.connections, self.writes)
+            .and_then(|_| match self.cable_network {
+                Some(network) => Ok(network),
+                None => self.manager.create_cable_network(),
+            })
+            .and_then(|network| {
+                network
+                    .borrow_mut()
+                    .add_device(device.clone(), network.clone())
+            });
+        if let Err(e) = attached {
+            self.manager.release_id(id);
+            return Err(e);
+        }
+
+        self.manager.track_device(device.clone());
+        Ok(device)
+    }
+
+    /// Attach the atmospheric connections and apply the queued logic writes
+    fn wire(
+        device: &Shared<T>,
+        connections: Vec<(DeviceAtmosphericNetworkType, Shared<AtmosphericNetwork>)>,
+        writes: Vec<(LogicType, f64)>,
+    ) -> SimulationResult<()> {
+        if !connections.is_empty() {
+            let mut d = device.borrow_mut();
+            let name = d.get_name().to_string();
+            let atmo = d
+                .as_atmospheric_device_mut()
+                .ok_or(SimulationError::RuntimeError {
+                    message: format!("{name} has no atmospheric connections"),
+                    line: 0,
+                })?;
+            for (connection, network) in connections {
+                atmo.set_atmospheric_network(connection, Some(network))?;
+            }
+        }
+
+        let d = device.borrow();
+        for (logic_type, value) in writes {
+            d.write(logic_type, value)?;
+        }
+
+        Ok(())
+    }
+}
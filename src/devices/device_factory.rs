@@ -2,7 +2,9 @@
 
 use crate::{
     LogicSlotType, LogicType,
-    devices::{Device, DeviceAtmosphericNetworkType, SimulationDeviceSettings},
+    devices::{
+        Device, DeviceAtmosphericNetworkType, IC_HOST_LOGIC_TYPES, SimulationDeviceSettings,
+    },
     types::Shared,
 };
 use std::collections::HashMap;
@@ -35,6 +37,19 @@ pub struct DeviceProps {
     pub supports_cable_network: bool,
 }
 
+/// Summary of a registered prefab, as returned by [`list_prefabs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefabInfo {
+    /// Prefab hash of the device type
+    pub prefab_hash: i32,
+    /// Static display name of the device type
+    pub display_name: &'static str,
+    /// Logic types the device type supports
+    pub logic_types: Vec<LogicType>,
+    /// Slot logic types the device type supports
+    pub slot_logic_types: Vec<LogicSlotType>,
+}
+
 /// Function type that returns device metadata: `(display_name, DeviceProps)`.
 pub type DeviceMetaFn = fn() -> (&'static str, DeviceProps);
 
@@ -86,6 +101,30 @@ impl DeviceFactoryRegistry {
     pub fn registered_prefabs(&self) -> Vec<i32> {
         self.factories.keys().copied().collect()
     }
+
+    /// Get a summary of every prefab with registered metadata, sorted by display name
+    pub fn prefab_infos(&self) -> Vec<PrefabInfo> {
+        let mut infos: Vec<PrefabInfo> = self
+            .metas
+            .iter()
+            .map(|(&prefab_hash, meta)| {
+                let (display_name, props) = meta();
+                PrefabInfo {
+                    prefab_hash,
+                    display_name,
+                    logic_types: props.properties.iter().map(|(lt, _, _)| *lt).collect(),
+                    slot_logic_types: props.slot_properties.iter().map(|(st, _, _)| *st).collect(),
+                }
+            })
+            .collect();
+
+        infos.sort_by(|a, b| {
+            a.display_name
+                .cmp(b.display_name)
+                .then(a.prefab_hash.cmp(&b.prefab_hash))
+        });
+        infos
+    }
 }
 
 /// Create a device by prefab hash using the global registry
@@ -112,6 +151,18 @@ pub fn get_registered_device_prefabs() -> Vec<i32> {
         .unwrap()
 }
 
+/// List every registered device prefab with its display name and supported logic types,
+/// sorted by display name
+pub fn list_prefabs() -> Vec<PrefabInfo> {
+    initialize_device_factory();
+
+    let registry_guard = get_registry().lock().unwrap();
+    registry_guard
+        .as_ref()
+        .map(|registry| registry.prefab_infos())
+        .unwrap()
+}
+
 /// Get or initialize the global device factory registry
 fn get_registry() -> &'static Mutex<Option<DeviceFactoryRegistry>> {
     &DEVICE_FACTORY
@@ -170,7 +221,7 @@ fn meta_from_type<T>() -> (&'static str, DeviceProps)
 where
     T: Device + 'static,
 {
-    let props: Vec<(LogicType, bool, bool)> = T::properties()
+    let mut props: Vec<(LogicType, bool, bool)> = T::properties()
         .supported_types()
         .into_iter()
         .map(|lt| {
@@ -182,6 +233,11 @@ where
         })
         .collect();
 
+    // Chip hosts also answer the read-only types shared by every host
+    if T::is_ic_host() {
+        props.extend(IC_HOST_LOGIC_TYPES.iter().map(|&lt| (lt, true, false)));
+    }
+
     // Collect slot property descriptors including the per-slot indices
     let slot_props: Vec<(LogicSlotType, bool, Vec<usize>)> = T::slot_properties()
         .descriptors()
@@ -218,5 +274,14 @@ fn initialize_device_factory() {
         register_device!(LogicMemory);
         register_device!(PassiveVent);
         register_device!(ActiveVent);
+        register_device!(HeatExchanger);
+        register_device!(Airlock);
+        register_device!(Locker);
+        register_device!(Console);
+        register_device!(Switch);
+        register_device!(Battery);
+        register_device!(SolarPanel);
+        register_device!(Hydroponics);
+        register_device!(GasTank);
     }
 }
@@ -3,18 +3,21 @@
 use std::{
     cell::RefCell,
     fmt::{Debug, Display},
-    rc::Rc,
     sync::OnceLock,
 };
 
 use crate::{
     CableNetwork, Filter, Item, ItemType, LogicSlotType, Slot,
-    atmospherics::{GasType, MAX_PRESSURE_GAS_PIPE, MatterState, PIPE_VOLUME, calculate_moles},
+    atmospherics::{
+        GasMixture, GasType, MAX_PRESSURE_GAS_PIPE, MINIMUM_VALID_TOTAL_MOLES, MatterState,
+        PIPE_VOLUME, TransferAmount, calculate_moles,
+    },
     constants::DEFAULT_MAX_INSTRUCTIONS_PER_TICK,
     conversions::lerp,
     devices::{
-        AtmosphericDevice, ChipSlot, Device, DeviceAtmosphericNetworkType, ICHostDevice,
-        ICHostDeviceMemoryOverride, LogicType, SimulationDeviceSettings, SlotHostDevice,
+        AtmosphericDevice, ChipSlot, Device, DeviceAtmosphericNetworkType, IC_HOST_LOGIC_TYPES,
+        ICHostDevice, ICHostDeviceMemoryOverride, LogicType, ReagentMode, RejectedItem,
+        SimulationDeviceSettings, SlotHostDevice,
         property_descriptor::{
             PropertyDescriptor, PropertyRegistry, SlotPropertyDescriptor, SlotPropertyRegistry,
         },
@@ -22,12 +25,15 @@ use crate::{
     error::{SimulationError, SimulationResult},
     networks::AtmosphericNetwork,
     parser::string_to_hash,
-    prop_ro, prop_rw_bool, prop_slot_ro,
+    prop_ro, prop_rw_bool, prop_rw_name_hash, prop_slot_ro,
     types::{OptShared, OptWeakShared, Shared, shared},
 };
 
+/// Slot indices holding filters on a Filtration device
+const FILTER_SLOTS: &[usize] = &[0, 1];
+
 /// Maximum number of filter slots on a Filtration device
-const MAX_FILTERS: usize = 2;
+const MAX_FILTERS: usize = FILTER_SLOTS.len();
 
 const PRESSURE_PER_TICK: f64 = 1000.0;
 
@@ -35,6 +41,8 @@ const PRESSURE_PER_TICK: f64 = 1000.0;
 pub struct Filtration {
     /// Device name
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     /// Connected network
     network: OptWeakShared<CableNetwork>,
 
@@ -65,8 +73,23 @@ pub struct Filtration {
 /// Minimum mole fraction threshold to also siphon remaining gas from the input atmosphere
 const MIN_RATIO_TO_FILTER_ALL: f64 = 1.0 / 1000.0;
 
+/// Move all of `gas_type` from one in-flight mixture to another, returning the moles moved.
+/// Trace amounts move too, so nothing is lost to cleanup on the way through the filter.
+fn move_gas(from: &mut GasMixture, to: &mut GasMixture, gas_type: GasType) -> f64 {
+    let quantity = from.get_moles(gas_type);
+    from.get_gas_mut(gas_type)
+        .transfer_to(to.get_gas_mut(gas_type), quantity)
+}
+
 /// Constructors and helper methods
 impl Filtration {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+        DeviceAtmosphericNetworkType::Output2,
+    ];
+
     /// Compile-time prefab hash constant for this device
     pub const PREFAB_HASH: i32 = string_to_hash("StructureFiltration");
 
@@ -84,6 +107,7 @@ impl Filtration {
 
         let s = shared(Self {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             on: RefCell::new(1.0),
             mode: RefCell::new(0.0),
@@ -92,13 +116,15 @@ impl Filtration {
             input_network: None,
             waste_network: None,
             filtered_network: None,
-            slots: {
-                let mut s = Vec::with_capacity(MAX_FILTERS);
-                for _ in 0..MAX_FILTERS {
-                    s.push(Slot::new(Some(ItemType::Filter)));
-                }
-                s
-            },
+            slots: (0..MAX_FILTERS)
+                .map(|i| {
+                    Slot::with_allowed_types(
+                        Self::slot_properties()
+                            .allowed_item_types(i)
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect(),
             chip_host: ChipSlot::new(2),
         });
 
@@ -121,6 +147,11 @@ impl Filtration {
         self.slots.get_mut(index)
     }
 
+    /// Number of filter slots on this device
+    pub fn filter_slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
     /// Get the currently active filters from inserted physical filter items (quantity > 0)
     pub fn active_filters(&self) -> Vec<GasType> {
         let mut out = Vec::new();
@@ -130,6 +161,7 @@ impl Filtration {
                 let item_ref = item.borrow();
                 if item_ref.item_type() == ItemType::Filter
                     && let Some(filter_item) = item_ref.as_any().downcast_ref::<Filter>()
+                    && !filter_item.is_spent()
                 {
                     out.push(filter_item.gas_type());
                 }
@@ -139,6 +171,23 @@ impl Filtration {
         out
     }
 
+    /// Consume life from the first non-spent filter for `gas_type`
+    fn degrade_filter(&self, gas_type: GasType, moles: f64) {
+        if moles <= 0.0 {
+            return;
+        }
+
+        for slot in &self.slots {
+            if let Some(mut filter_item) = slot.borrow_item_mut::<Filter>()
+                && filter_item.gas_type() == gas_type
+                && !filter_item.is_spent()
+            {
+                filter_item.degrade(moles);
+                return;
+            }
+        }
+    }
+
     /// Return the prefab hash for `Filtration`.
     pub fn prefab_hash() -> i32 {
         Self::PREFAB_HASH
@@ -160,14 +209,15 @@ impl Filtration {
             const DESCRIPTORS: &[PropertyDescriptor<Filtration>] = &[
                 prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device, _| Ok(device.get_name_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.has_connection_error(&Filtration::required_atmospheric_connections()) { 1.0 } else { 0.0 })),
                 prop_rw_bool!(On, on),
                 prop_rw_bool!(Mode, mode),
 
                 prop_ro!(PressureInput, |device, _| device.read_network_prop(Input, |net| net.pressure())),
                 prop_ro!(TemperatureInput, |device, _| device.read_network_prop(Input, |net| net.temperature())),
-                prop_ro!(TotalMolesInput, |device, _| device.read_network_prop(Input, |net| net.total_moles())),
-                prop_ro!(CombustionInput, |_, _| Err(SimulationError::RuntimeError { message: "CombustionInput not implemented for Filtration".to_string(), line: 0 })),
+                prop_ro!(TotalMolesInput, |device, _| device.network_total_moles(Input)),
+                prop_ro!(CombustionInput, |device, _| device.read_network_prop(Input, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
                 prop_ro!(RatioOxygenInput, |device, _| device.read_network_prop(Input, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideInput, |device, _| device.read_network_prop(Input, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenInput, |device, _| device.read_network_prop(Input, |net| net.gas_ratio(Nitrogen))),
@@ -185,8 +235,8 @@ impl Filtration {
 
                 prop_ro!(PressureOutput, |device, _| device.read_network_prop(Output, |net| net.pressure())),
                 prop_ro!(TemperatureOutput, |device, _| device.read_network_prop(Output, |net| net.temperature())),
-                prop_ro!(TotalMolesOutput, |device, _| device.read_network_prop(Output, |net| net.total_moles())),
-                prop_ro!(CombustionOutput, |_, _| Err(SimulationError::RuntimeError { message: "CombustionOutput not implemented for Filtration".to_string(), line: 0 })),
+                prop_ro!(TotalMolesOutput, |device, _| device.network_total_moles(Output)),
+                prop_ro!(CombustionOutput, |device, _| device.read_network_prop(Output, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
                 prop_ro!(RatioOxygenOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenOutput, |device, _| device.read_network_prop(Output, |net| net.gas_ratio(Nitrogen))),
@@ -204,8 +254,8 @@ impl Filtration {
 
                 prop_ro!(PressureOutput2, |device, _| device.read_network_prop(Output2, |net| net.pressure())),
                 prop_ro!(TemperatureOutput2, |device, _| device.read_network_prop(Output2, |net| net.temperature())),
-                prop_ro!(TotalMolesOutput2, |device, _| device.read_network_prop(Output2, |net| net.total_moles())),
-                prop_ro!(CombustionOutput2, |_, _| Err(SimulationError::RuntimeError { message: "CombustionOutput2 not implemented for Filtration".to_string(), line: 0 })),
+                prop_ro!(TotalMolesOutput2, |device, _| device.network_total_moles(Output2)),
+                prop_ro!(CombustionOutput2, |device, _| device.read_network_prop(Output2, |net| if net.is_combusting() { 1.0 } else { 0.0 })),
                 prop_ro!(RatioOxygenOutput2, |device, _| device.read_network_prop(Output2, |net| net.gas_ratio(Oxygen))),
                 prop_ro!(RatioCarbonDioxideOutput2, |device, _| device.read_network_prop(Output2, |net| net.gas_ratio(CarbonDioxide))),
                 prop_ro!(RatioNitrogenOutput2, |device, _| device.read_network_prop(Output2, |net| net.gas_ratio(Nitrogen))),
@@ -233,14 +283,14 @@ impl Filtration {
 
         SLOT_REGISTRY.get_or_init(|| {
             const DESCRIPTORS: &[SlotPropertyDescriptor<Filtration>] = &[
-                prop_slot_ro!(Occupied, &[0, 1], |device: &Filtration, idx, _| Ok(
+                prop_slot_ro!(Occupied, FILTER_SLOTS, |device: &Filtration, idx, _| Ok(
                     if device.get_slot(idx).unwrap().is_empty() {
                         0.0
                     } else {
                         1.0
                     }
                 )),
-                prop_slot_ro!(OccupantHash, &[0, 1], |device: &Filtration, idx, _| {
+                prop_slot_ro!(OccupantHash, FILTER_SLOTS, |device: &Filtration, idx, _| {
                     let item = device.get_slot(idx).unwrap().get_item();
                     if let Some(i) = item {
                         Ok(i.borrow().get_prefab_hash() as f64)
@@ -248,7 +298,7 @@ impl Filtration {
                         Ok(0.0)
                     }
                 }),
-                prop_slot_ro!(Quantity, &[0, 1], |device: &Filtration, idx, _| {
+                prop_slot_ro!(Quantity, FILTER_SLOTS, |device: &Filtration, idx, _| {
                     let item = device.get_slot(idx).unwrap().get_item();
                     if let Some(i) = item {
                         Ok(i.borrow().quantity() as f64)
@@ -256,15 +306,10 @@ impl Filtration {
                         Ok(0.0)
                     }
                 }),
-                prop_slot_ro!(MaxQuantity, &[0, 1], |device: &Filtration, idx, _| {
-                    let item = device.get_slot(idx).unwrap().get_item();
-                    if let Some(i) = item {
-                        Ok(i.borrow().max_quantity() as f64)
-                    } else {
-                        Ok(0.0)
-                    }
-                }),
-                prop_slot_ro!(FilterType, &[0, 1], |device: &Filtration, idx, _| {
+                prop_slot_ro!(MaxQuantity, FILTER_SLOTS, |device: &Filtration, idx, _| Ok(
+                    device.get_slot(idx).unwrap().max_quantity() as f64
+                )),
+                prop_slot_ro!(FilterType, FILTER_SLOTS, |device: &Filtration, idx, _| {
                     let item_opt = device.get_slot(idx).unwrap().get_item();
                     if let Some(item) = item_opt {
                         let item_ref = item.borrow();
@@ -276,7 +321,7 @@ impl Filtration {
                     }
                     Ok(0.0)
                 }),
-                prop_slot_ro!(ReferenceId, &[0, 1], |device: &Filtration, idx, _| {
+                prop_slot_ro!(ReferenceId, FILTER_SLOTS, |device: &Filtration, idx, _| {
                     let item = device.get_slot(idx).unwrap().get_item();
                     if let Some(i) = item {
                         Ok(i.borrow().get_id() as f64)
@@ -284,11 +329,16 @@ impl Filtration {
                         Ok(0.0)
                     }
                 }),
-                prop_slot_ro!(FreeSlots, &[0, 1], |_device: &Filtration, _idx, _| Ok(0.0)),
-                prop_slot_ro!(TotalSlots, &[0, 1], |_device: &Filtration, _idx, _| Ok(0.0)),
+                prop_slot_ro!(FreeSlots, FILTER_SLOTS, |device: &Filtration, _idx, _| Ok(
+                    SlotHostDevice::free_slots(device) as f64
+                )),
+                prop_slot_ro!(TotalSlots, FILTER_SLOTS, |device: &Filtration, _idx, _| Ok(
+                    SlotHostDevice::total_slots(device) as f64
+                )),
             ];
 
             SlotPropertyRegistry::new(DESCRIPTORS)
+                .with_allowed_types(FILTER_SLOTS, &[ItemType::Filter])
         })
     }
 
@@ -341,26 +391,6 @@ impl Filtration {
         let net = self.require_network(connection)?;
         Ok(f(&net.borrow()))
     }
-
-    /// Helper to get a mutable reference to an atmospheric network slot
-    fn network_slot_mut(
-        &mut self,
-        connection: DeviceAtmosphericNetworkType,
-    ) -> Result<&mut OptWeakShared<AtmosphericNetwork>, SimulationError> {
-        use DeviceAtmosphericNetworkType::*;
-        match connection {
-            Input => Ok(&mut self.input_network),
-            Output => Ok(&mut self.filtered_network),
-            Output2 => Ok(&mut self.waste_network),
-            _ => Err(SimulationError::RuntimeError {
-                message: format!(
-                    "Filtration device does not support atmospheric connection type {:?}",
-                    connection
-                ),
-                line: 0,
-            }),
-        }
-    }
 }
 
 /// `Device` trait implementation for `Filtration` providing logic access and network handling.
@@ -374,7 +404,9 @@ impl Device for Filtration {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(&self.name)
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
     }
 
     fn get_name(&self) -> &str {
@@ -393,6 +425,7 @@ impl Device for Filtration {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
 
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
@@ -404,7 +437,7 @@ impl Device for Filtration {
     }
 
     fn can_read(&self, logic_type: LogicType) -> bool {
-        Self::properties().can_read(logic_type)
+        IC_HOST_LOGIC_TYPES.contains(&logic_type) || Self::properties().can_read(logic_type)
     }
 
     fn can_write(&self, logic_type: LogicType) -> bool {
@@ -412,7 +445,10 @@ impl Device for Filtration {
     }
 
     fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
-        Self::properties().read(self, logic_type)
+        match self.read_host_logic(logic_type) {
+            Some(value) => Ok(value),
+            None => Self::properties().read(self, logic_type),
+        }
     }
 
     fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
@@ -420,7 +456,9 @@ impl Device for Filtration {
     }
 
     fn supported_types(&self) -> Vec<LogicType> {
-        Self::properties().supported_types()
+        let mut types = Self::properties().supported_types();
+        types.extend_from_slice(IC_HOST_LOGIC_TYPES);
+        types
     }
 
     fn supported_slot_types(&self) -> Vec<LogicSlotType> {
@@ -462,6 +500,33 @@ impl Device for Filtration {
         })
     }
 
+    /// Reagents are the gases in the input atmosphere, identified by `GasType::reagent_hash`.
+    /// `Required` reports the moles of a gas still to be separated by an active filter.
+    /// Filtration has no recipe, so `Recipe` reads are rejected.
+    fn read_reagent(&self, mode: ReagentMode, reagent_hash: i32) -> SimulationResult<f64> {
+        if mode == ReagentMode::Recipe {
+            return Err(SimulationError::RuntimeError {
+                message: "Filtration has no recipe to read reagents from".to_string(),
+                line: 0,
+            });
+        }
+
+        let Ok(input) = self.require_network(DeviceAtmosphericNetworkType::Input) else {
+            return Ok(0.0);
+        };
+        let input = input.borrow();
+        let gas = GasType::all().find(|g| g.reagent_hash() == reagent_hash);
+
+        Ok(match (mode, gas) {
+            (ReagentMode::Contents, Some(gas)) => input.get_moles(gas),
+            (ReagentMode::Required, Some(gas)) if self.active_filters().contains(&gas) => {
+                input.get_moles(gas)
+            }
+            (ReagentMode::TotalContents, _) => input.total_moles(),
+            _ => 0.0,
+        })
+    }
+
     fn update(&self, _tick: u64) -> SimulationResult<bool> {
         // Only run filtration when device is On and Mode is enabled
         if *self.on.borrow() == 0.0 || *self.mode.borrow() == 0.0 {
@@ -478,6 +543,12 @@ impl Device for Filtration {
             return Ok(false);
         }
 
+        // Determine the filters to apply; spent filters are skipped and filtration halts without any
+        let filters_to_apply = self.active_filters();
+        if filters_to_apply.is_empty() {
+            return Ok(false);
+        }
+
         let input_pressure = input_rc.borrow().pressure();
         let filtered_pressure = filtered_rc.borrow().pressure();
         let waste_pressure = waste_rc.borrow().pressure();
@@ -492,82 +563,76 @@ impl Device for Filtration {
         );
 
         // transfer moles using ideal gas law for pipe volume
-        let transfer_moles_amount =
+        let mut transfer_moles_amount =
             calculate_moles(scale_pressure, PIPE_VOLUME, input_rc.borrow().temperature());
 
         if transfer_moles_amount <= 0.0 {
             return Ok(false);
         }
 
-        // Remove that many moles from the input network
-        let mut transfer_mixture = input_rc
-            .borrow_mut()
-            .remove_moles(transfer_moles_amount, MatterState::All);
+        // Take the whole input once the remainder would fall below a valid mixture,
+        // rather than leaving it to be discarded by the input's cleanup
+        let input_moles = input_rc.borrow().total_moles();
+        if input_moles - transfer_moles_amount < MINIMUM_VALID_TOTAL_MOLES {
+            transfer_moles_amount = input_moles;
+        }
 
-        // Determine the filters to apply (physical slots with quantity > 0 take precedence)
-        let filters_to_apply = self.active_filters();
+        // Move that many moles from the input network into the working mixture
+        let mut transfer_mixture = GasMixture::new(0.0);
+        input_rc.borrow_mut().transfer_moles_into(
+            &mut transfer_mixture,
+            TransferAmount::Moles(transfer_moles_amount),
+            MatterState::All,
+        );
 
-        // For each configured filter, remove that gas from the transfer mixture and add to filtered output
-        // Also remove its counterpart (liquid <-> gas) so a single filter handles both forms
+        // For each configured filter, move that gas from the transfer mixture to the filtered mixture
+        // Also move its counterpart (liquid <-> gas) so a single filter handles both forms
         // Then, if the remaining input atmosphere has that gas (or counterpart) below the min ratio, siphon all of it too
+        let mut filtered_mixture = GasMixture::new(0.0);
         for filter_type in &filters_to_apply {
-            // Remove the configured type from the transfer mixture
-            let mol = transfer_mixture.remove_all_gas(*filter_type);
-            if !mol.is_empty() {
-                filtered_rc.borrow_mut().add_mole(&mol);
-            }
-
-            // Remove counterpart form (condensation or evaporation type) if present
-            if let Some(counter_type) = filter_type.condensation_type() {
-                let mol2 = transfer_mixture.remove_all_gas(counter_type);
-                if !mol2.is_empty() {
-                    filtered_rc.borrow_mut().add_mole(&mol2);
-                }
-            } else if let Some(counter_type) = filter_type.evaporation_type() {
-                let mol2 = transfer_mixture.remove_all_gas(counter_type);
-                if !mol2.is_empty() {
-                    filtered_rc.borrow_mut().add_mole(&mol2);
-                }
+            // Counterpart form (condensation or evaporation type), if any
+            let counter_type = filter_type
+                .condensation_type()
+                .or_else(|| filter_type.evaporation_type());
+
+            // Moles separated by this filter, used to consume its life
+            let mut filtered_moles = 0.0;
+            for gas_type in std::iter::once(*filter_type).chain(counter_type) {
+                filtered_moles += move_gas(&mut transfer_mixture, &mut filtered_mixture, gas_type);
             }
 
             // Check remaining input atmosphere mole fraction and optionally remove all of that gas/counterpart
             let atm_total = input_rc.borrow().total_moles();
             if atm_total > 0.0 {
-                let atm_gas_moles = input_rc.borrow().get_moles(*filter_type);
-                if atm_gas_moles / atm_total < MIN_RATIO_TO_FILTER_ALL {
-                    let extra = input_rc.borrow_mut().remove_all_gas(*filter_type);
-                    if !extra.is_empty() {
-                        filtered_rc.borrow_mut().add_mole(&extra);
-                    }
-                }
-
-                if let Some(counter_type) = filter_type.condensation_type() {
-                    let atm_counter_moles = input_rc.borrow().get_moles(counter_type);
-                    if atm_counter_moles / atm_total >= MIN_RATIO_TO_FILTER_ALL {
-                        continue;
-                    } else {
-                        let extra2 = input_rc.borrow_mut().remove_all_gas(counter_type);
-                        if !extra2.is_empty() {
-                            filtered_rc.borrow_mut().add_mole(&extra2);
-                        }
-                    }
-                } else if let Some(counter_type) = filter_type.evaporation_type() {
-                    let atm_counter_moles = input_rc.borrow().get_moles(counter_type);
-                    if atm_counter_moles / atm_total >= MIN_RATIO_TO_FILTER_ALL {
-                        continue;
-                    } else {
-                        let extra2 = input_rc.borrow_mut().remove_all_gas(counter_type);
-                        if !extra2.is_empty() {
-                            filtered_rc.borrow_mut().add_mole(&extra2);
-                        }
+                for gas_type in std::iter::once(*filter_type).chain(counter_type) {
+                    let atm_gas_moles = input_rc.borrow().get_moles(gas_type);
+                    if atm_gas_moles / atm_total < MIN_RATIO_TO_FILTER_ALL {
+                        let mut extra = input_rc.borrow_mut().remove_all_gas(gas_type);
+                        let quantity = extra.quantity();
+                        filtered_moles +=
+                            extra.transfer_to(filtered_mixture.get_gas_mut(gas_type), quantity);
                     }
                 }
             }
+
+            self.degrade_filter(*filter_type, filtered_moles);
         }
 
-        // Remaining transfer mixture goes to the waste output
-        let mut waste_mut = waste_rc.borrow_mut();
-        waste_mut.add_mixture(&transfer_mixture);
+        // Separated gases go to the filtered output at one temperature, the rest to the waste output
+        {
+            let mut filtered_mut = filtered_rc.borrow_mut();
+            filtered_mut.transfer_moles_from(
+                &mut filtered_mixture,
+                TransferAmount::Fraction(1.0),
+                MatterState::All,
+            );
+            filtered_mut.equalize_internal_energy();
+        }
+        waste_rc.borrow_mut().transfer_moles_from(
+            &mut transfer_mixture,
+            TransferAmount::Fraction(1.0),
+            MatterState::All,
+        );
 
         Ok(true)
     }
@@ -609,8 +674,7 @@ impl Device for Filtration {
     }
 
     fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
-        use crate::devices::DeviceAtmosphericNetworkType::*;
-        vec![Input, Output, Output2]
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
     }
 
     fn as_ic_host_device(&self) -> Option<&dyn ICHostDevice> {
@@ -663,13 +727,18 @@ impl SlotHostDevice for Filtration {
         &mut self,
         index: usize,
         incoming: Shared<dyn Item>,
-    ) -> Result<(), Shared<dyn Item>> {
-        if index >= self.slots.len() {
-            return Err(incoming);
+    ) -> Result<(), RejectedItem> {
+        let checked = self.check_insert(index, &*incoming.borrow());
+        if let Err(reason) = checked {
+            return Err(RejectedItem {
+                item: incoming,
+                reason,
+            });
         }
 
-        let slot = &mut self.slots[index];
-        slot.try_insert(incoming)
+        self.slots[index]
+            .try_insert(incoming)
+            .map_err(|item| RejectedItem::leftover(index, item))
     }
 
     fn remove_item(&mut self, index: usize) -> OptShared<dyn Item> {
@@ -691,17 +760,28 @@ impl SlotHostDevice for Filtration {
     fn slot_count(&self) -> usize {
         self.slots.len()
     }
+
+    fn allowed_item_types(&self, index: usize) -> Option<&'static [ItemType]> {
+        Self::slot_properties().allowed_item_types(index)
+    }
 }
 
 impl AtmosphericDevice for Filtration {
-    fn set_atmospheric_network(
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
         &mut self,
         connection: DeviceAtmosphericNetworkType,
-        network: OptShared<AtmosphericNetwork>,
-    ) -> SimulationResult<()> {
-        let slot = self.network_slot_mut(connection)?;
-        *slot = network.as_ref().map(Rc::downgrade);
-        Ok(())
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.filtered_network),
+            Output2 => Some(&mut self.waste_network),
+            _ => None,
+        }
     }
 
     fn get_atmospheric_network(
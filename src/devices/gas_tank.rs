@@ -0,0 +1,395 @@
+//! Gas tank device: stages gas from its input network in an internal buffer and releases it
+//! into its output network. Emulator-only: Stationeers has no matching prefab.
+
+use crate::{
+    CableNetwork,
+    atmospherics::{
+        MAX_PRESSURE_GAS_PIPE, MatterState, PRESSURE_EQUALIZATION_EPSILON, TransferAmount,
+        calculate_moles,
+    },
+    devices::{
+        AtmosphericDevice, Device, DeviceAtmosphericNetworkType, LogicType,
+        SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::{SimulationError, SimulationResult},
+    networks::AtmosphericNetwork,
+    parser::string_to_hash,
+    prop_ro, prop_rw_bool, prop_rw_clamped, prop_rw_name_hash,
+    types::{OptShared, OptWeakShared, Shared, shared},
+};
+
+use crate::conversions::fmt_trim;
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    sync::OnceLock,
+};
+
+/// Internal buffer volume in litres
+const INTERNAL_VOLUME_LITRES: f64 = 100.0;
+
+/// Volume (litres) moved through each side of the tank per tick
+const TRANSFER_VOLUME_PER_TICK: f64 = 10.0;
+
+/// Gas tank.
+///
+/// Emulator-only device for exercising `Internal` buffer networks. It does not model a
+/// Stationeers prefab, so `PREFAB_HASH` matches no in-game device and IC10 code written for
+/// the game never refers to it.
+///
+/// Each tick, while On, the tank draws up to `TRANSFER_VOLUME_PER_TICK` litres of its input
+/// network into the internal buffer (never filling it past `MAX_PRESSURE_GAS_PIPE`), then
+/// releases up to the same volume of the buffer into its output network until the output
+/// reaches the `Setting` pressure. The tank starts off, and the default `Setting` of 0 only
+/// stores gas.
+///
+/// The buffer is the `Internal` atmospheric connection: `Pressure`, `Temperature` and
+/// `TotalMoles` read it, and `get_atmospheric_network(Internal)` returns it.
+pub struct GasTank {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// The device reference ID
+    reference_id: i32,
+    /// The On state
+    on: RefCell<f64>,
+    /// Output pressure (kPa) the tank releases gas up to
+    setting: RefCell<f64>,
+
+    /// Internal buffer holding the staged gas
+    internal: Shared<AtmosphericNetwork>,
+    /// The input network
+    input_network: OptWeakShared<AtmosphericNetwork>,
+    /// The output network
+    output_network: OptWeakShared<AtmosphericNetwork>,
+}
+
+/// Constructors for `GasTank`.
+impl GasTank {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+    ];
+
+    /// Compile-time prefab hash constant for this device (emulator-only prefab name)
+    pub const PREFAB_HASH: i32 = string_to_hash("EmulatorGasTank");
+
+    /// Create a new `GasTank`, using `settings.internal_atmospheric_network` as its buffer when set.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let internal = if let Some(net) = settings.internal_atmospheric_network.as_ref() {
+            net.clone()
+        } else {
+            AtmosphericNetwork::new(INTERNAL_VOLUME_LITRES)
+        };
+
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            on: RefCell::new(0.0),
+            setting: RefCell::new(0.0),
+            reference_id: settings.id.unwrap(),
+            internal,
+            input_network: None,
+            output_network: None,
+        })
+    }
+
+    /// Return the prefab hash for `GasTank`.
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    /// Human-readable display name
+    pub fn display_name_static() -> &'static str {
+        "Gas Tank (Emulator)"
+    }
+
+    /// Get the internal buffer network
+    pub fn internal_network(&self) -> Shared<AtmosphericNetwork> {
+        self.internal.clone()
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        use DeviceAtmosphericNetworkType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<GasTank>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<GasTank>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.has_connection_error(&GasTank::required_atmospheric_connections()) { 1.0 } else { 0.0 })),
+                prop_rw_bool!(On, on),
+                prop_rw_clamped!(Setting, setting, 0.0, MAX_PRESSURE_GAS_PIPE),
+                prop_ro!(Pressure, |device, _| Ok(device.internal.borrow().pressure())),
+                prop_ro!(Temperature, |device, _| Ok(device.internal.borrow().temperature())),
+                prop_ro!(TotalMoles, |device, _| device.network_total_moles(Internal)),
+                prop_ro!(PressureInput, |device, _| device.read_network_prop(Input, |net| net.pressure())),
+                prop_ro!(TemperatureInput, |device, _| device.read_network_prop(Input, |net| net.temperature())),
+                prop_ro!(TotalMolesInput, |device, _| device.network_total_moles(Input)),
+                prop_ro!(PressureOutput, |device, _| device.read_network_prop(Output, |net| net.pressure())),
+                prop_ro!(TemperatureOutput, |device, _| device.read_network_prop(Output, |net| net.temperature())),
+                prop_ro!(TotalMolesOutput, |device, _| device.network_total_moles(Output)),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+
+    // Helper methods to get atmospheric networks
+    fn require_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> SimulationResult<Shared<AtmosphericNetwork>> {
+        self.get_atmospheric_network(connection)
+            .ok_or(SimulationError::RuntimeError {
+                message: format!("GasTank device has no {} atmospheric network", connection),
+                line: 0,
+            })
+    }
+
+    /// Helper to read a property from an atmospheric network
+    fn read_network_prop<T, F>(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+        f: F,
+    ) -> SimulationResult<T>
+    where
+        F: FnOnce(&AtmosphericNetwork) -> T,
+    {
+        let net = self.require_network(connection)?;
+        Ok(f(&net.borrow()))
+    }
+
+    /// Move up to `TRANSFER_VOLUME_PER_TICK` litres of `source` into `target`, without pushing
+    /// `target` past `max_pressure`. Returns true if any gas moved.
+    fn transfer(
+        source: &mut AtmosphericNetwork,
+        target: &mut AtmosphericNetwork,
+        max_pressure: f64,
+    ) -> bool {
+        let pressure_headroom = max_pressure - target.pressure();
+        let source_volume = source.total_volume();
+        if pressure_headroom <= PRESSURE_EQUALIZATION_EPSILON || source_volume <= 0.0 {
+            return false;
+        }
+
+        let ratio = (TRANSFER_VOLUME_PER_TICK / source_volume).clamp(0.0, 1.0);
+        let moles_to_move = (source.total_moles() * ratio).min(calculate_moles(
+            pressure_headroom,
+            target.gas_volume(),
+            source.temperature(),
+        ));
+        if moles_to_move <= 0.0 {
+            return false;
+        }
+
+        source.transfer_moles_to(
+            target,
+            TransferAmount::Moles(moles_to_move),
+            MatterState::All,
+        );
+        true
+    }
+}
+
+/// `Device` trait implementation for `GasTank` providing logic access, naming, and update behavior.
+impl Device for GasTank {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        GasTank::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn update(&self, _tick: u64) -> SimulationResult<bool> {
+        use DeviceAtmosphericNetworkType::*;
+
+        if *self.on.borrow() == 0.0 {
+            return Ok(false);
+        }
+
+        let input_rc = self.require_network(Input)?;
+        let output_rc = self.require_network(Output)?;
+
+        // Stage gas from the input into the buffer, then release it toward the Setting pressure
+        let filled = GasTank::transfer(
+            &mut input_rc.borrow_mut(),
+            &mut self.internal.borrow_mut(),
+            MAX_PRESSURE_GAS_PIPE,
+        );
+        let setting = *self.setting.borrow();
+        let drained = GasTank::transfer(
+            &mut self.internal.borrow_mut(),
+            &mut output_rc.borrow_mut(),
+            setting,
+        );
+
+        Ok(filled || drained)
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        GasTank::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        GasTank::display_name_static()
+    }
+
+    fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
+        [
+            &[DeviceAtmosphericNetworkType::Internal],
+            Self::ATMOSPHERIC_CONNECTIONS,
+        ]
+        .concat()
+    }
+
+    fn as_atmospheric_device(&self) -> Option<&dyn AtmosphericDevice> {
+        Some(self)
+    }
+
+    fn as_atmospheric_device_mut(&mut self) -> Option<&mut dyn AtmosphericDevice> {
+        Some(self)
+    }
+}
+
+impl Display for GasTank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on_str = if *self.on.borrow() == 0.0 {
+            "Off"
+        } else {
+            "On"
+        };
+
+        write!(
+            f,
+            "GasTank {{ name: \"{}\", id: {}, on: {}, setting: {}, internal: {}",
+            self.name,
+            self.reference_id,
+            on_str,
+            fmt_trim(*self.setting.borrow(), 3),
+            self.internal.borrow()
+        )?;
+
+        if let Some(weak) = &self.input_network
+            && let Some(net) = weak.upgrade()
+        {
+            write!(f, ", input: {}", net.borrow())?;
+        }
+        if let Some(weak) = &self.output_network
+            && let Some(net) = weak.upgrade()
+        {
+            write!(f, ", output: {}", net.borrow())?;
+        }
+
+        write!(f, " }}")
+    }
+}
+
+impl Debug for GasTank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// `AtmosphericDevice` implementation for `GasTank`; the internal buffer is fixed at construction.
+impl AtmosphericDevice for GasTank {
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
+        &mut self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.output_network),
+            _ => None,
+        }
+    }
+
+    fn get_atmospheric_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> OptShared<AtmosphericNetwork> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Internal => Some(self.internal.clone()),
+            Input => self.input_network.as_ref().and_then(|w| w.upgrade()),
+            Output => self.output_network.as_ref().and_then(|w| w.upgrade()),
+            _ => None,
+        }
+    }
+}
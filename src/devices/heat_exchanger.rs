@@ -0,0 +1,278 @@
+//! Heat exchanger: conducts heat between two atmospheres without mixing gas
+
+use crate::{
+    CableNetwork,
+    devices::{
+        AtmosphericDevice, Device, DeviceAtmosphericNetworkType, LogicType,
+        SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::{SimulationError, SimulationResult},
+    networks::AtmosphericNetwork,
+    parser::string_to_hash,
+    prop_ro, prop_rw_clamped, prop_rw_name_hash,
+    types::{OptShared, OptWeakShared, Shared, SharedPtr, shared},
+};
+
+use crate::conversions::fmt_trim;
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    sync::OnceLock,
+};
+
+/// Default conductance in joules per kelvin of temperature difference per tick
+const DEFAULT_CONDUCTANCE: f64 = 100.0;
+
+/// Maximum conductance in joules per kelvin of temperature difference per tick
+const MAX_CONDUCTANCE: f64 = 10_000.0;
+
+/// Heat exchanger device
+pub struct HeatExchanger {
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+
+    reference_id: i32,
+
+    /// The Setting state (conductance, J/K per tick)
+    conductance: RefCell<f64>,
+
+    input_network: OptWeakShared<AtmosphericNetwork>,
+    input2_network: OptWeakShared<AtmosphericNetwork>,
+}
+
+impl HeatExchanger {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Input2,
+    ];
+
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructurePipeHeatExchanger");
+
+    /// Create a new `HeatExchanger`
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = settings
+            .name
+            .clone()
+            .unwrap_or_else(|| Self::display_name_static().to_string());
+        shared(HeatExchanger {
+            name,
+            name_hash_override: RefCell::new(None),
+            reference_id: settings.id.unwrap(),
+            conductance: RefCell::new(DEFAULT_CONDUCTANCE),
+            input_network: None,
+            input2_network: None,
+        })
+    }
+
+    /// Return the prefab hash for `HeatExchanger`
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    /// Human-readable display name
+    pub fn display_name_static() -> &'static str {
+        "Heat Exchanger"
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<HeatExchanger>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<HeatExchanger>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.any_network_over_pressure() { 1.0 } else { 0.0 })),
+                prop_rw_clamped!(Setting, conductance, 0.0, MAX_CONDUCTANCE),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+
+    fn require_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> SimulationResult<Shared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => self.input_network.as_ref().and_then(|w| w.upgrade()).ok_or(
+                SimulationError::RuntimeError {
+                    message: "HeatExchanger device has no input atmospheric network".to_string(),
+                    line: 0,
+                },
+            ),
+            Input2 => self
+                .input2_network
+                .as_ref()
+                .and_then(|w| w.upgrade())
+                .ok_or(SimulationError::RuntimeError {
+                    message: "HeatExchanger device has no input2 atmospheric network".to_string(),
+                    line: 0,
+                }),
+            _ => Err(SimulationError::RuntimeError {
+                message: format!(
+                    "HeatExchanger does not support atmospheric connection type {:?}",
+                    connection
+                ),
+                line: 0,
+            }),
+        }
+    }
+}
+
+/// `Device` trait implementation for `HeatExchanger`
+impl Device for HeatExchanger {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        HeatExchanger::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        None
+    }
+
+    fn set_network(&mut self, _network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        Err(SimulationError::RuntimeError {
+            message: "HeatExchanger cannot be connected to a cable network".to_string(),
+            line: 0,
+        })
+    }
+
+    fn rename(&mut self, name: &str) {
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn update(&self, _tick: u64) -> SimulationResult<bool> {
+        let input_rc = self.require_network(DeviceAtmosphericNetworkType::Input)?;
+        let input2_rc = self.require_network(DeviceAtmosphericNetworkType::Input2)?;
+
+        // Both connections on the same network have nothing to exchange
+        if SharedPtr::ptr_eq(&input_rc, &input2_rc) {
+            return Ok(false);
+        }
+
+        // Move heat from the hotter to the colder network; no moles are transferred
+        let energy = input_rc
+            .borrow_mut()
+            .conduct_heat_with(&mut input2_rc.borrow_mut(), *self.conductance.borrow());
+
+        Ok(energy != 0.0)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        HeatExchanger::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        HeatExchanger::display_name_static()
+    }
+
+    fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
+    }
+
+    fn supports_cable_network() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+
+    fn as_atmospheric_device(&self) -> Option<&dyn AtmosphericDevice> {
+        Some(self)
+    }
+
+    fn as_atmospheric_device_mut(&mut self) -> Option<&mut dyn AtmosphericDevice> {
+        Some(self)
+    }
+}
+
+impl Display for HeatExchanger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HeatExchanger {{ name: \"{}\", id: {}, conductance: {} }}",
+            self.name,
+            self.reference_id,
+            fmt_trim(*self.conductance.borrow(), 3),
+        )
+    }
+}
+
+impl Debug for HeatExchanger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl AtmosphericDevice for HeatExchanger {
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
+        &mut self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => Some(&mut self.input_network),
+            Input2 => Some(&mut self.input2_network),
+            _ => None,
+        }
+    }
+
+    fn get_atmospheric_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> OptShared<AtmosphericNetwork> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => self.input_network.as_ref().and_then(|w| w.upgrade()),
+            Input2 => self.input2_network.as_ref().and_then(|w| w.upgrade()),
+            _ => None,
+        }
+    }
+}
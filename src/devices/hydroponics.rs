@@ -0,0 +1,457 @@
+//! Hydroponics device: grows a planted seed using light and carbon dioxide.
+
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    sync::OnceLock,
+};
+
+use crate::{
+    CableNetwork, Item, ItemType, LogicSlotType, Slot,
+    atmospherics::GasType,
+    constants::DEFAULT_TICKS_PER_DAY,
+    devices::{
+        AtmosphericDevice, Device, DeviceAtmosphericNetworkType, LogicType, RejectedItem,
+        SimulationDeviceSettings, SlotHostDevice,
+        daylight_sensor::sun_angles,
+        property_descriptor::{
+            PropertyDescriptor, PropertyRegistry, SlotPropertyDescriptor, SlotPropertyRegistry,
+        },
+    },
+    error::{SimulationError, SimulationResult},
+    items::Seed,
+    networks::AtmosphericNetwork,
+    parser::string_to_hash,
+    prop_ro, prop_rw_name_hash, prop_slot_ro,
+    types::{OptShared, OptWeakShared, Shared, shared},
+};
+
+/// Slot index holding the plant on a Hydroponics device
+const PLANT_SLOTS: &[usize] = &[0];
+
+/// Minimum carbon dioxide partial pressure a plant needs to grow (kPa)
+pub const HYDROPONICS_MIN_CO2_PRESSURE: f64 = 0.1;
+
+/// Carbon dioxide a growing plant converts to oxygen each tick (mol)
+pub const HYDROPONICS_CO2_PER_TICK: f64 = 0.001;
+
+/// Hydroponics device.
+///
+/// The plant in slot 0 grows one tick for every tick the sun is above the horizon and the
+/// atmosphere on `Input` holds at least `HYDROPONICS_MIN_CO2_PRESSURE` of carbon dioxide.
+/// While growing it converts `HYDROPONICS_CO2_PER_TICK` moles of carbon dioxide to oxygen.
+pub struct Hydroponics {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// The device reference ID
+    reference_id: i32,
+
+    /// The surrounding atmosphere
+    input_network: OptWeakShared<AtmosphericNetwork>,
+
+    /// Device slots (1x Seed)
+    slots: Vec<Slot>,
+
+    /// Number of ticks in a day cycle used to determine sun position
+    ticks_per_day: f64,
+}
+
+/// Constructors and helpers for `Hydroponics`.
+impl Hydroponics {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] =
+        &[DeviceAtmosphericNetworkType::Input];
+
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureHydroponicsDevice");
+
+    /// Create a new, empty `Hydroponics` device.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        let ticks_per_day = settings.ticks_per_day.unwrap_or(DEFAULT_TICKS_PER_DAY);
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            reference_id: settings.id.unwrap(),
+            input_network: None,
+            slots: PLANT_SLOTS
+                .iter()
+                .map(|&i| {
+                    Slot::with_allowed_types(
+                        Self::slot_properties()
+                            .allowed_item_types(i)
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect(),
+            ticks_per_day,
+        })
+    }
+
+    /// Return the prefab hash for `Hydroponics`.
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    /// Human-readable display name
+    pub fn display_name_static() -> &'static str {
+        "Hydroponics Device"
+    }
+
+    /// Get a reference to a slot by index
+    pub fn get_slot(&self, index: usize) -> Option<&Slot> {
+        self.slots.get(index)
+    }
+
+    /// Get a mutable reference to a slot by index
+    pub fn get_slot_mut(&mut self, index: usize) -> Option<&mut Slot> {
+        self.slots.get_mut(index)
+    }
+
+    /// Read a value from the seed in slot `index`, or 0 when the slot holds no seed
+    fn read_seed(&self, index: usize, read: fn(&Seed) -> f64) -> SimulationResult<f64> {
+        Ok(self
+            .get_slot(index)
+            .and_then(|slot| slot.borrow_item::<Seed>())
+            .map_or(0.0, |seed| read(&seed)))
+    }
+
+    /// Whether the atmosphere has enough carbon dioxide for a plant to grow
+    fn has_enough_co2(network: &AtmosphericNetwork) -> bool {
+        network.partial_pressure(GasType::CarbonDioxide) >= HYDROPONICS_MIN_CO2_PRESSURE
+            && network.get_moles(GasType::CarbonDioxide) >= HYDROPONICS_CO2_PER_TICK
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<Hydroponics>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<Hydroponics>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.any_network_over_pressure() { 1.0 } else { 0.0 })),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+
+    /// Get the slot property registry for this device type
+    pub fn slot_properties() -> &'static SlotPropertyRegistry<Hydroponics> {
+        use LogicSlotType::*;
+        static SLOT_REGISTRY: OnceLock<SlotPropertyRegistry<Hydroponics>> = OnceLock::new();
+
+        SLOT_REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[SlotPropertyDescriptor<Hydroponics>] = &[
+                prop_slot_ro!(Occupied, PLANT_SLOTS, |device: &Hydroponics, idx, _| Ok(
+                    if device.get_slot(idx).unwrap().is_empty() {
+                        0.0
+                    } else {
+                        1.0
+                    }
+                )),
+                prop_slot_ro!(OccupantHash, PLANT_SLOTS, |device: &Hydroponics, idx, _| {
+                    let item = device.get_slot(idx).unwrap().get_item();
+                    if let Some(i) = item {
+                        Ok(i.borrow().get_prefab_hash() as f64)
+                    } else {
+                        Ok(0.0)
+                    }
+                }),
+                prop_slot_ro!(Quantity, PLANT_SLOTS, |device: &Hydroponics, idx, _| {
+                    let item = device.get_slot(idx).unwrap().get_item();
+                    if let Some(i) = item {
+                        Ok(i.borrow().quantity() as f64)
+                    } else {
+                        Ok(0.0)
+                    }
+                }),
+                prop_slot_ro!(MaxQuantity, PLANT_SLOTS, |device: &Hydroponics, idx, _| Ok(
+                    device.get_slot(idx).unwrap().max_quantity() as f64
+                )),
+                prop_slot_ro!(ReferenceId, PLANT_SLOTS, |device: &Hydroponics, idx, _| {
+                    let item = device.get_slot(idx).unwrap().get_item();
+                    if let Some(i) = item {
+                        Ok(i.borrow().get_id() as f64)
+                    } else {
+                        Ok(0.0)
+                    }
+                }),
+                prop_slot_ro!(Growth, PLANT_SLOTS, |device: &Hydroponics, idx, _| device
+                    .read_seed(idx, |seed| seed.growth_stage() as f64)),
+                prop_slot_ro!(Mature, PLANT_SLOTS, |device: &Hydroponics, idx, _| device
+                    .read_seed(idx, |seed| if seed.is_mature() { 1.0 } else { 0.0 })),
+                prop_slot_ro!(
+                    MaturityRatio,
+                    PLANT_SLOTS,
+                    |device: &Hydroponics, idx, _| device.read_seed(idx, Seed::maturity_ratio)
+                ),
+                prop_slot_ro!(Seeding, PLANT_SLOTS, |device: &Hydroponics, idx, _| device
+                    .read_seed(idx, |seed| if seed.is_seeding() { 1.0 } else { 0.0 })),
+                prop_slot_ro!(SeedingRatio, PLANT_SLOTS, |device: &Hydroponics, idx, _| {
+                    device.read_seed(idx, Seed::seeding_ratio)
+                }),
+                prop_slot_ro!(FreeSlots, PLANT_SLOTS, |device: &Hydroponics, _idx, _| Ok(
+                    SlotHostDevice::free_slots(device) as f64
+                )),
+                prop_slot_ro!(TotalSlots, PLANT_SLOTS, |device: &Hydroponics, _idx, _| Ok(
+                    SlotHostDevice::total_slots(device) as f64
+                )),
+            ];
+
+            SlotPropertyRegistry::new(DESCRIPTORS)
+                .with_allowed_types(PLANT_SLOTS, &[ItemType::Seed])
+        })
+    }
+}
+
+/// `Device` trait implementation for `Hydroponics`
+impl Device for Hydroponics {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Hydroponics::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn supported_slot_types(&self) -> Vec<LogicSlotType> {
+        Self::slot_properties().supported_types()
+    }
+
+    fn read_slot(&self, index: usize, slot_logic_type: LogicSlotType) -> SimulationResult<f64> {
+        if index >= self.slots.len() {
+            return Err(SimulationError::RuntimeError {
+                message: format!("Slot index out of range: {index}"),
+                line: 0,
+            });
+        }
+
+        Self::slot_properties()
+            .read(self, index, slot_logic_type)
+            .map_err(|_| SimulationError::RuntimeError {
+                message: format!(
+                    "Hydroponics does not support reading slot logic type {slot_logic_type:?}"
+                ),
+                line: 0,
+            })
+    }
+
+    fn update(&self, tick: u64) -> SimulationResult<bool> {
+        let Some(item) = self.slots[0].get_item() else {
+            return Ok(false);
+        };
+        let Some(network_rc) = self.input_network.as_ref().and_then(|w| w.upgrade()) else {
+            return Ok(false);
+        };
+
+        let (_, sun_vertical) = sun_angles(tick, self.ticks_per_day);
+        if sun_vertical >= 90.0 || !Self::has_enough_co2(&network_rc.borrow()) {
+            return Ok(false);
+        }
+
+        let mut item = item.borrow_mut();
+        let Some(seed) = item.as_any_mut().downcast_mut::<Seed>() else {
+            return Ok(false);
+        };
+        seed.grow(1.0);
+
+        let mut network = network_rc.borrow_mut();
+        let temperature = network.temperature();
+        let consumed = network.remove_gas(GasType::CarbonDioxide, HYDROPONICS_CO2_PER_TICK);
+        network.add_gas(GasType::Oxygen, consumed, temperature)?;
+
+        Ok(true)
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        Hydroponics::properties()
+    }
+
+    fn slot_properties() -> &'static SlotPropertyRegistry<Self> {
+        Hydroponics::slot_properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        Hydroponics::display_name_static()
+    }
+
+    fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
+    }
+
+    fn as_slot_host_device(&self) -> Option<&dyn SlotHostDevice> {
+        Some(self)
+    }
+
+    fn as_slot_host_device_mut(&mut self) -> Option<&mut dyn SlotHostDevice> {
+        Some(self)
+    }
+
+    fn as_atmospheric_device(&self) -> Option<&dyn AtmosphericDevice> {
+        Some(self)
+    }
+
+    fn as_atmospheric_device_mut(&mut self) -> Option<&mut dyn AtmosphericDevice> {
+        Some(self)
+    }
+}
+
+impl SlotHostDevice for Hydroponics {
+    fn try_insert_item(
+        &mut self,
+        index: usize,
+        incoming: Shared<dyn Item>,
+    ) -> Result<(), RejectedItem> {
+        let checked = self.check_insert(index, &*incoming.borrow());
+        if let Err(reason) = checked {
+            return Err(RejectedItem {
+                item: incoming,
+                reason,
+            });
+        }
+
+        self.slots[index]
+            .try_insert(incoming)
+            .map_err(|item| RejectedItem::leftover(index, item))
+    }
+
+    fn get_slot(&self, index: usize) -> Option<&Slot> {
+        self.slots.get(index)
+    }
+
+    fn get_slot_mut(&mut self, index: usize) -> Option<&mut Slot> {
+        self.slots.get_mut(index)
+    }
+
+    fn remove_item(&mut self, index: usize) -> OptShared<dyn Item> {
+        self.slots.get_mut(index).and_then(Slot::remove)
+    }
+
+    fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn allowed_item_types(&self, index: usize) -> Option<&'static [ItemType]> {
+        Self::slot_properties().allowed_item_types(index)
+    }
+}
+
+impl AtmosphericDevice for Hydroponics {
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
+        &mut self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
+        use DeviceAtmosphericNetworkType::*;
+        match connection {
+            Input => Some(&mut self.input_network),
+            _ => None,
+        }
+    }
+
+    fn get_atmospheric_network(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> OptShared<AtmosphericNetwork> {
+        match connection {
+            DeviceAtmosphericNetworkType::Input => {
+                self.input_network.as_ref().and_then(|w| w.upgrade())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Hydroponics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plant = match self.slots[0].borrow_item::<Seed>() {
+            Some(seed) => format!("{:.0}%", seed.maturity_ratio() * 100.0),
+            None => "empty".to_string(),
+        };
+
+        write!(
+            f,
+            "Hydroponics {{ name: \"{}\", id: {}, plant: {} }}",
+            self.name, self.reference_id, plant
+        )
+    }
+}
+
+impl Debug for Hydroponics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
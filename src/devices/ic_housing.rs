@@ -5,9 +5,8 @@ use crate::conversions::fmt_trim;
 use crate::types::OptWeakShared;
 use crate::{
     CableNetwork,
-    constants::STACK_SIZE,
     devices::{
-        ChipSlot, Device, ICHostDevice, ICHostDeviceMemoryOverride, LogicType,
+        ChipSlot, Device, IC_HOST_LOGIC_TYPES, ICHostDevice, ICHostDeviceMemoryOverride, LogicType,
         SimulationDeviceSettings,
         property_descriptor::{PropertyDescriptor, PropertyRegistry},
     },
@@ -15,7 +14,7 @@ use crate::{
     parser::string_to_hash,
     types::{OptShared, Shared, shared},
 };
-use crate::{prop_ro, prop_rw_bool, prop_rw_clamped};
+use crate::{prop_ro, prop_rw_bool, prop_rw_clamped, prop_rw_name_hash};
 
 use std::cell::RefCell;
 use std::fmt::{Debug, Display};
@@ -25,6 +24,8 @@ use std::sync::OnceLock;
 pub struct ICHousing {
     /// Device name
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     /// Connected network
     network: OptWeakShared<CableNetwork>,
 
@@ -61,6 +62,7 @@ impl ICHousing {
 
         let s = shared(Self {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             setting: RefCell::new(0.0),
             on: RefCell::new(1.0),
@@ -96,10 +98,9 @@ impl ICHousing {
             const DESCRIPTORS: &[PropertyDescriptor<ICHousing>] = &[
                 prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device, _| Ok(device.get_name_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
                 prop_rw_clamped!(Setting, setting, -f64::INFINITY, f64::INFINITY),
                 prop_rw_bool!(On, on),
-                prop_ro!(StackSize, |_, _| Ok(STACK_SIZE as f64)),
                 PropertyDescriptor::read_write(
                     LineNumber,
                     |device, _| {
@@ -148,7 +149,9 @@ impl Device for ICHousing {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(&self.name)
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
     }
 
     fn get_name(&self) -> &str {
@@ -167,6 +170,7 @@ impl Device for ICHousing {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
 
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
@@ -178,7 +182,7 @@ impl Device for ICHousing {
     }
 
     fn can_read(&self, logic_type: LogicType) -> bool {
-        Self::properties().can_read(logic_type)
+        IC_HOST_LOGIC_TYPES.contains(&logic_type) || Self::properties().can_read(logic_type)
     }
 
     fn can_write(&self, logic_type: LogicType) -> bool {
@@ -186,7 +190,10 @@ impl Device for ICHousing {
     }
 
     fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
-        Self::properties().read(self, logic_type)
+        match self.read_host_logic(logic_type) {
+            Some(value) => Ok(value),
+            None => Self::properties().read(self, logic_type),
+        }
     }
 
     fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
@@ -194,7 +201,9 @@ impl Device for ICHousing {
     }
 
     fn supported_types(&self) -> Vec<LogicType> {
-        Self::properties().supported_types()
+        let mut types = Self::properties().supported_types();
+        types.extend_from_slice(IC_HOST_LOGIC_TYPES);
+        types
     }
 
     fn run(&self) -> SimulationResult<bool> {
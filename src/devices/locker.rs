@@ -0,0 +1,348 @@
+//! Locker: a storage device with a bank of general purpose item slots.
+
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    sync::OnceLock,
+};
+
+use crate::{
+    CableNetwork, Item, ItemType, LogicSlotType, Slot,
+    devices::{
+        Device, LogicType, RejectedItem, SimulationDeviceSettings, SlotHostDevice,
+        property_descriptor::{
+            PropertyDescriptor, PropertyRegistry, SlotPropertyDescriptor, SlotPropertyRegistry,
+        },
+    },
+    error::{SimulationError, SimulationResult},
+    parser::string_to_hash,
+    prop_ro, prop_rw_bool, prop_rw_name_hash, prop_slot_ro,
+    types::{OptShared, OptWeakShared, Shared, shared},
+};
+
+/// Number of item slots on a Locker
+const LOCKER_SLOT_COUNT: usize = 30;
+
+/// Slot indices of a Locker (all of them)
+const LOCKER_SLOTS: &[usize] = &{
+    let mut indices = [0; LOCKER_SLOT_COUNT];
+    let mut i = 0;
+    while i < LOCKER_SLOT_COUNT {
+        indices[i] = i;
+        i += 1;
+    }
+    indices
+};
+
+/// Locker storing items of any type
+pub struct Locker {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// The device reference ID
+    reference_id: i32,
+    /// The Open state (0 = closed, 1 = open)
+    open: RefCell<f64>,
+
+    /// Item slots
+    slots: Vec<Slot>,
+}
+
+/// Constructors and helpers for `Locker`.
+impl Locker {
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureStorageLocker");
+
+    /// Create a new, closed and empty `Locker`.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            reference_id: settings.id.unwrap(),
+            open: RefCell::new(0.0),
+            slots: (0..LOCKER_SLOT_COUNT)
+                .map(|i| {
+                    Slot::with_allowed_types(
+                        Self::slot_properties()
+                            .allowed_item_types(i)
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Return the prefab hash for `Locker`.
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    /// Human-readable display name
+    pub fn display_name_static() -> &'static str {
+        "Locker"
+    }
+
+    /// Get a reference to a slot by index
+    pub fn get_slot(&self, index: usize) -> Option<&Slot> {
+        self.slots.get(index)
+    }
+
+    /// Get a mutable reference to a slot by index
+    pub fn get_slot_mut(&mut self, index: usize) -> Option<&mut Slot> {
+        self.slots.get_mut(index)
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<Locker>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<Locker>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_rw_bool!(Open, open),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+
+    /// Get the slot property registry for this device type
+    pub fn slot_properties() -> &'static SlotPropertyRegistry<Locker> {
+        use LogicSlotType::*;
+        static SLOT_REGISTRY: OnceLock<SlotPropertyRegistry<Locker>> = OnceLock::new();
+
+        SLOT_REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[SlotPropertyDescriptor<Locker>] = &[
+                prop_slot_ro!(Occupied, LOCKER_SLOTS, |device: &Locker, idx, _| Ok(
+                    if device.get_slot(idx).unwrap().is_empty() {
+                        0.0
+                    } else {
+                        1.0
+                    }
+                )),
+                prop_slot_ro!(OccupantHash, LOCKER_SLOTS, |device: &Locker, idx, _| {
+                    let item = device.get_slot(idx).unwrap().get_item();
+                    if let Some(i) = item {
+                        Ok(i.borrow().get_prefab_hash() as f64)
+                    } else {
+                        Ok(0.0)
+                    }
+                }),
+                prop_slot_ro!(Quantity, LOCKER_SLOTS, |device: &Locker, idx, _| {
+                    let item = device.get_slot(idx).unwrap().get_item();
+                    if let Some(i) = item {
+                        Ok(i.borrow().quantity() as f64)
+                    } else {
+                        Ok(0.0)
+                    }
+                }),
+                prop_slot_ro!(MaxQuantity, LOCKER_SLOTS, |device: &Locker, idx, _| Ok(
+                    device.get_slot(idx).unwrap().max_quantity() as f64
+                )),
+                prop_slot_ro!(ReferenceId, LOCKER_SLOTS, |device: &Locker, idx, _| {
+                    let item = device.get_slot(idx).unwrap().get_item();
+                    if let Some(i) = item {
+                        Ok(i.borrow().get_id() as f64)
+                    } else {
+                        Ok(0.0)
+                    }
+                }),
+                prop_slot_ro!(FreeSlots, LOCKER_SLOTS, |device: &Locker, _idx, _| Ok(
+                    SlotHostDevice::free_slots(device) as f64
+                )),
+                prop_slot_ro!(TotalSlots, LOCKER_SLOTS, |device: &Locker, _idx, _| Ok(
+                    SlotHostDevice::total_slots(device) as f64
+                )),
+            ];
+
+            SlotPropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+}
+
+/// `Device` trait implementation for `Locker`
+impl Device for Locker {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Locker::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn supported_slot_types(&self) -> Vec<LogicSlotType> {
+        Self::slot_properties().supported_types()
+    }
+
+    fn read_slot(&self, index: usize, slot_logic_type: LogicSlotType) -> SimulationResult<f64> {
+        if index >= self.slots.len() {
+            return Err(SimulationError::RuntimeError {
+                message: format!("Slot index out of range: {index}"),
+                line: 0,
+            });
+        }
+
+        Self::slot_properties()
+            .read(self, index, slot_logic_type)
+            .map_err(|_| SimulationError::RuntimeError {
+                message: format!(
+                    "Locker does not support reading slot logic type {slot_logic_type:?}"
+                ),
+                line: 0,
+            })
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        Locker::properties()
+    }
+
+    fn slot_properties() -> &'static SlotPropertyRegistry<Self> {
+        Locker::slot_properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        Locker::display_name_static()
+    }
+
+    fn as_slot_host_device(&self) -> Option<&dyn SlotHostDevice> {
+        Some(self)
+    }
+
+    fn as_slot_host_device_mut(&mut self) -> Option<&mut dyn SlotHostDevice> {
+        Some(self)
+    }
+}
+
+impl SlotHostDevice for Locker {
+    fn try_insert_item(
+        &mut self,
+        index: usize,
+        incoming: Shared<dyn Item>,
+    ) -> Result<(), RejectedItem> {
+        let checked = self.check_insert(index, &*incoming.borrow());
+        if let Err(reason) = checked {
+            return Err(RejectedItem {
+                item: incoming,
+                reason,
+            });
+        }
+
+        self.slots[index]
+            .try_insert(incoming)
+            .map_err(|item| RejectedItem::leftover(index, item))
+    }
+
+    fn get_slot(&self, index: usize) -> Option<&Slot> {
+        self.slots.get(index)
+    }
+
+    fn get_slot_mut(&mut self, index: usize) -> Option<&mut Slot> {
+        self.slots.get_mut(index)
+    }
+
+    fn remove_item(&mut self, index: usize) -> OptShared<dyn Item> {
+        self.slots.get_mut(index).and_then(Slot::remove)
+    }
+
+    fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn allowed_item_types(&self, index: usize) -> Option<&'static [ItemType]> {
+        Self::slot_properties().allowed_item_types(index)
+    }
+}
+
+impl Display for Locker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let open_str = if *self.open.borrow() != 0.0 {
+            "Open"
+        } else {
+            "Closed"
+        };
+
+        write!(
+            f,
+            "Locker {{ name: \"{}\", id: {}, state: {}, free_slots: {}/{} }}",
+            self.name,
+            self.reference_id,
+            open_str,
+            self.free_slots(),
+            self.total_slots()
+        )
+    }
+}
+
+impl Debug for Locker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
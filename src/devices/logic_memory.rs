@@ -1,9 +1,10 @@
-//! Logic memory device: stores a single numeric value.
+//! Logic memory device: stores a numeric setting and an addressable memory bank.
 
 use std::cell::RefCell;
 use std::fmt::{Debug, Display};
 use std::sync::OnceLock;
 
+use crate::constants::STACK_SIZE;
 use crate::conversions::fmt_trim;
 use crate::types::OptWeakShared;
 use crate::{
@@ -12,15 +13,17 @@ use crate::{
         Device, LogicType, SimulationDeviceSettings,
         property_descriptor::{PropertyDescriptor, PropertyRegistry},
     },
-    error::SimulationResult,
+    error::{SimulationError, SimulationResult},
     parser::string_to_hash,
     types::{OptShared, Shared, shared},
 };
-use crate::{prop_ro, prop_rw_clamped};
+use crate::{prop_ro, prop_rw_clamped, prop_rw_name_hash};
 
 pub struct LogicMemory {
     /// Device name
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     /// Connected network
     network: OptWeakShared<CableNetwork>,
 
@@ -28,6 +31,8 @@ pub struct LogicMemory {
     reference_id: i32,
     /// Stored setting value
     setting: RefCell<f64>,
+    /// Addressable memory bank accessed with get/put and getd/putd
+    memory: RefCell<Vec<f64>>,
 }
 
 /// Constructors and helpers
@@ -36,6 +41,8 @@ impl LogicMemory {
     pub const PREFAB_HASH: i32 = string_to_hash("StructureLogicMemory");
 
     /// Create a new `LogicMemory`.
+    ///
+    /// The memory bank size is taken from `settings.memory_size`, defaulting to `STACK_SIZE`.
     pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
         let name = if let Some(n) = settings.name.as_ref() {
             n.to_string()
@@ -45,12 +52,19 @@ impl LogicMemory {
 
         shared(Self {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             setting: RefCell::new(0.0),
+            memory: RefCell::new(vec![0.0; settings.memory_size.unwrap_or(STACK_SIZE)]),
             reference_id: settings.id.unwrap(),
         })
     }
 
+    /// Number of addressable memory cells
+    pub fn memory_size(&self) -> usize {
+        self.memory.borrow().len()
+    }
+
     /// Prefab hash for `LogicMemory`
     pub fn prefab_hash() -> i32 {
         Self::PREFAB_HASH
@@ -70,7 +84,7 @@ impl LogicMemory {
             const DESCRIPTORS: &[PropertyDescriptor<LogicMemory>] = &[
                 prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device, _| Ok(device.get_name_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
                 prop_rw_clamped!(Setting, setting, -f64::INFINITY, f64::INFINITY),
             ];
 
@@ -90,7 +104,9 @@ impl Device for LogicMemory {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(&self.name)
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
     }
 
     fn get_name(&self) -> &str {
@@ -109,6 +125,7 @@ impl Device for LogicMemory {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
 
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
@@ -139,6 +156,28 @@ impl Device for LogicMemory {
         Self::properties().supported_types()
     }
 
+    fn get_memory(&self, index: usize) -> SimulationResult<f64> {
+        self.memory
+            .borrow()
+            .get(index)
+            .copied()
+            .ok_or(SimulationError::StackOutOfBounds { index, line: None })
+    }
+
+    fn set_memory(&self, index: usize, value: f64) -> SimulationResult<()> {
+        let mut memory = self.memory.borrow_mut();
+        let cell = memory
+            .get_mut(index)
+            .ok_or(SimulationError::StackOutOfBounds { index, line: None })?;
+        *cell = value;
+        Ok(())
+    }
+
+    fn clear(&self) -> SimulationResult<()> {
+        self.memory.borrow_mut().fill(0.0);
+        Ok(())
+    }
+
     fn properties() -> &'static PropertyRegistry<Self> {
         LogicMemory::properties()
     }
@@ -153,8 +192,11 @@ impl Display for LogicMemory {
         let setting = fmt_trim(*self.setting.borrow(), 3);
         write!(
             f,
-            "LogicMemory {{ name: \"{}\", id: {}, setting: {} }}",
-            self.name, self.reference_id, setting
+            "LogicMemory {{ name: \"{}\", id: {}, setting: {}, memory_size: {} }}",
+            self.name,
+            self.reference_id,
+            setting,
+            self.memory_size()
         )
     }
 }
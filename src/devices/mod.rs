@@ -1,38 +1,61 @@
 //! Device implementations for the IC10 emulator
 
+use std::any::Any;
 use std::fmt::{Debug, Display};
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 use crate::{
-    AtmosphericNetwork, CableNetwork, Item, Slot,
+    AtmosphericNetwork, CableNetwork, Item, ItemType, Slot,
+    constants::DEFAULT_DISPLAY_PRECISION,
+    conversions::round_to,
     devices::property_descriptor::{PropertyRegistry, SlotPropertyRegistry, empty_slot_registry},
     error::{SimulationError, SimulationResult},
-    items::ItemIntegratedCircuit10,
-    types::{OptShared, OptWeakShared, Shared},
+    items::{ItemIntegratedCircuit10, ReagentRegistry},
+    types::{MaybeSend, OptShared, OptWeakShared, Shared, SharedPtr},
 };
 
 pub mod active_vent;
 pub mod air_conditioner;
+pub mod airlock;
+pub mod battery;
 pub mod chip_slot;
+pub mod console;
 pub mod daylight_sensor;
+pub mod device_builder;
 pub mod device_factory;
 pub mod filtration;
+pub mod gas_tank;
+pub mod heat_exchanger;
+pub mod hydroponics;
 pub mod ic_housing;
+pub mod locker;
 pub mod logic_memory;
 pub mod passive_vent;
 pub mod property_descriptor;
+pub mod solar_panel;
+pub mod switch;
 pub mod volume_pump;
 
 pub use active_vent::ActiveVent;
 pub use air_conditioner::AirConditioner;
+pub use airlock::Airlock;
+pub use battery::Battery;
 pub use chip_slot::ChipSlot;
+pub use console::Console;
 pub use daylight_sensor::DaylightSensor;
+pub use device_builder::DeviceBuilder;
 pub use filtration::Filtration;
+pub use gas_tank::GasTank;
+pub use heat_exchanger::HeatExchanger;
+pub use hydroponics::Hydroponics;
 pub use ic_housing::ICHousing;
+pub use locker::Locker;
 pub use logic_memory::LogicMemory;
 pub use passive_vent::PassiveVent;
+pub use solar_panel::SolarPanel;
+pub use switch::Switch;
 pub use volume_pump::VolumePump;
 
 /// Simulation settings for devices
@@ -40,22 +63,28 @@ pub use volume_pump::VolumePump;
 pub struct SimulationDeviceSettings {
     /// Number of ticks in a day cycle
     pub ticks_per_day: Option<f64>,
+    /// Simulation ticks per second of game time, for devices that integrate per-second rates
+    pub ticks_per_second: Option<f64>,
     /// Max IC10 instructions per tick
     pub max_instructions_per_tick: Option<usize>,
     /// Device name override
     pub name: Option<String>,
-    /// The reference id of the device, caller is responsible for ensuring uniqueness
+    /// The reference id of the device. Required by device constructors; `SimulationManager`
+    /// fills it in when unset and returns an error when the requested id is already in use
     pub id: Option<i32>,
     /// Internal atmospheric network to use for devices that require an internal buffer, ignored otherwise
     pub internal_atmospheric_network: OptShared<AtmosphericNetwork>,
+    /// Number of addressable memory cells for devices that expose memory, ignored otherwise
+    pub memory_size: Option<usize>,
 }
 
 impl Display for SimulationDeviceSettings {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "SimulationDeviceSettings {{ ticks_per_day: {:?}, max_instructions_per_tick: {:?}, name: {:?}, id: {:?}, internal: {} }}",
+            "SimulationDeviceSettings {{ ticks_per_day: {:?}, ticks_per_second: {:?}, max_instructions_per_tick: {:?}, name: {:?}, id: {:?}, internal: {}, memory_size: {:?} }}",
             self.ticks_per_day,
+            self.ticks_per_second,
             self.max_instructions_per_tick,
             self.name,
             self.id,
@@ -63,7 +92,8 @@ impl Display for SimulationDeviceSettings {
                 "Some"
             } else {
                 "None"
-            }
+            },
+            self.memory_size
         )
     }
 }
@@ -194,19 +224,65 @@ impl LogicSlotType {
     }
 }
 
+/// Reagent modes for `lr` reagent reads
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReagentMode {
+    /// Quantity of the reagent currently held by the device
+    Contents = 0,
+    /// Quantity of the reagent the device still requires
+    Required = 1,
+    /// Quantity of the reagent used by the device's current recipe
+    Recipe = 2,
+    /// Total quantity of all reagents held by the device
+    TotalContents = 3,
+}
+
+impl ReagentMode {
+    /// Parse reagent mode from a numeric value (as used in IC10 instructions)
+    pub fn from_value(value: f64) -> Option<Self> {
+        match value as i32 {
+            0 => Some(ReagentMode::Contents),
+            1 => Some(ReagentMode::Required),
+            2 => Some(ReagentMode::Recipe),
+            3 => Some(ReagentMode::TotalContents),
+            _ => None,
+        }
+    }
+
+    /// Parse ReagentMode from a string name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Contents" => Some(ReagentMode::Contents),
+            "Required" => Some(ReagentMode::Required),
+            "Recipe" => Some(ReagentMode::Recipe),
+            "TotalContents" => Some(ReagentMode::TotalContents),
+            _ => None,
+        }
+    }
+}
+
 /// Logic types for device property access
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[repr(i32)]
 pub enum LogicType {
+    Open = 2,
     Mode = 3,
+    Error = 4,
+    Pressure = 5,
+    Temperature = 6,
     PressureExternal = 7,
     PressureInternal = 8,
+    Charge = 11,
     Setting = 12,
     Horizontal = 20,
     Vertical = 21,
+    Maximum = 23,
     Ratio = 24,
     On = 28,
+    TotalMoles = 66,
     PrefabHash = 84,
 
     // Atmospheric Input 1
@@ -305,14 +381,21 @@ impl LogicType {
     pub fn from_value(value: f64) -> Option<Self> {
         use LogicType::*;
         match value as i32 {
+            2 => Some(Open),
             3 => Some(Mode),
+            4 => Some(Error),
+            5 => Some(Pressure),
+            6 => Some(Temperature),
             7 => Some(PressureExternal),
             8 => Some(PressureInternal),
+            11 => Some(Charge),
             12 => Some(Setting),
             20 => Some(Horizontal),
             21 => Some(Vertical),
+            23 => Some(Maximum),
             24 => Some(Ratio),
             28 => Some(On),
+            66 => Some(TotalMoles),
             84 => Some(PrefabHash),
 
             // Atmospheric Input 1
@@ -413,14 +496,21 @@ impl LogicType {
     pub fn from_name(name: &str) -> Option<Self> {
         use LogicType::*;
         match name {
+            "Open" => Some(Open),
             "Mode" => Some(Mode),
+            "Error" => Some(Error),
+            "Pressure" => Some(Pressure),
+            "Temperature" => Some(Temperature),
             "PressureExternal" => Some(PressureExternal),
             "PressureInternal" => Some(PressureInternal),
+            "Charge" => Some(Charge),
             "Setting" => Some(Setting),
             "Horizontal" => Some(Horizontal),
             "Vertical" => Some(Vertical),
+            "Maximum" => Some(Maximum),
             "Ratio" => Some(Ratio),
             "On" => Some(On),
+            "TotalMoles" => Some(TotalMoles),
             "PrefabHash" => Some(PrefabHash),
 
             // Atmospheric Input 1
@@ -552,6 +642,18 @@ impl DeviceAtmosphericNetworkType {
             _ => None,
         }
     }
+
+    /// Iterator over all connection types
+    pub fn all() -> impl Iterator<Item = DeviceAtmosphericNetworkType> {
+        [
+            DeviceAtmosphericNetworkType::Internal,
+            DeviceAtmosphericNetworkType::Input,
+            DeviceAtmosphericNetworkType::Input2,
+            DeviceAtmosphericNetworkType::Output,
+            DeviceAtmosphericNetworkType::Output2,
+        ]
+        .into_iter()
+    }
 }
 
 impl Display for DeviceAtmosphericNetworkType {
@@ -568,7 +670,7 @@ impl Display for DeviceAtmosphericNetworkType {
 }
 
 /// Trait for devices that can be controlled by IC10
-pub trait Device: Debug {
+pub trait Device: Debug + MaybeSend + AsAny {
     /// Get the device's unique identifier
     fn get_id(&self) -> i32;
 
@@ -590,7 +692,10 @@ pub trait Device: Debug {
     /// Check if the device can write the specified logic type
     fn can_write(&self, logic_type: LogicType) -> bool;
 
-    /// Read a logic value from the device
+    /// Read a logic value from the device.
+    ///
+    /// As in the game, reading a logic type the device does not support is an error rather
+    /// than a 0 or NaN reading: every type `can_read` rejects fails here.
     fn read(&self, logic_type: LogicType) -> SimulationResult<f64>;
 
     /// Write a logic value to the device
@@ -614,30 +719,67 @@ pub trait Device: Debug {
     where
         Self: Sized;
 
-    /// Read from device internal memory at index
+    /// Number of decimal places the game displays for `logic_type`
+    fn display_precision(&self, _logic_type: LogicType) -> usize {
+        DEFAULT_DISPLAY_PRECISION
+    }
+
+    /// Read a logic value rounded as the game displays it. The stored value is unchanged.
+    fn read_display(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Ok(round_to(
+            self.read(logic_type)?,
+            self.display_precision(logic_type),
+        ))
+    }
+
+    /// Read from device internal memory at index (get/getd)
     fn get_memory(&self, _index: usize) -> SimulationResult<f64> {
         Err(SimulationError::RuntimeError {
-            message: "Device does not support memory access".to_string(),
+            message: format!(
+                "Device {} (\"{}\") has no stack memory to read",
+                self.get_id(),
+                self.get_name()
+            ),
             line: 0,
         })
     }
 
-    /// Write to device internal memory at index
+    /// Write to device internal memory at index (put/putd)
     fn set_memory(&self, _index: usize, _value: f64) -> SimulationResult<()> {
         Err(SimulationError::RuntimeError {
-            message: "Device does not support memory access".to_string(),
+            message: format!(
+                "Device {} (\"{}\") has no stack memory to write",
+                self.get_id(),
+                self.get_name()
+            ),
             line: 0,
         })
     }
 
-    /// Clear device stack memory (clr/clrd)
+    /// Clear device stack memory (clr/clrd). Devices without a stack report which device was
+    /// addressed.
     fn clear(&self) -> SimulationResult<()> {
         Err(SimulationError::RuntimeError {
-            message: "Device does not support memory clearing".to_string(),
+            message: format!(
+                "Device {} (\"{}\") has no stack memory to clear",
+                self.get_id(),
+                self.get_name()
+            ),
             line: 0,
         })
     }
 
+    /// Read reagent state for `lr`. Devices without reagents report 0.
+    fn read_reagent(&self, _mode: ReagentMode, _reagent_hash: i32) -> SimulationResult<f64> {
+        Ok(0.0)
+    }
+
+    /// Map a reagent hash to the prefab hash used by this device's recipes (rmap).
+    /// Default looks the reagent up in `reagents`, the executing chip's reagent registry.
+    fn map_reagent(&self, reagent_hash: i32, reagents: &ReagentRegistry) -> Option<i32> {
+        reagents.prefab_hash(reagent_hash)
+    }
+
     /// Set the network reference for the device
     fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()>;
 
@@ -672,6 +814,16 @@ pub trait Device: Debug {
         Ok(false)
     }
 
+    /// Power this device supplies to its cable network (W); default is 0
+    fn power_generation(&self) -> f64 {
+        0.0
+    }
+
+    /// Power this device draws from its cable network (W); default is 0
+    fn power_demand(&self) -> f64 {
+        0.0
+    }
+
     /// Run chip code if applicable for the device.
     /// Returns `Ok(true)` if the device executed any instructions; default is `Ok(false)`.
     fn run(&self) -> SimulationResult<bool> {
@@ -746,6 +898,42 @@ pub trait Device: Debug {
     }
 }
 
+/// `Any` access for downcasting a device to its concrete type, provided for every `Device`
+pub trait AsAny {
+    /// Returns self as Any for downcasting to concrete types
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns self as mutable Any for downcasting to concrete types
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Device + 'static> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl dyn Device {
+    /// Check whether the device is of concrete type `T`
+    pub fn is<T: Device + 'static>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    /// Get the device as concrete type `T`, if it matches
+    pub fn downcast_ref<T: Device + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
+
+    /// Get the device as concrete type `T` mutably, if it matches
+    pub fn downcast_mut<T: Device + 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut::<T>()
+    }
+}
+
 /// Marker trait to ensure implementors of `ICHostDevice` explicitly opt into providing device
 /// memory access methods (`Device::get_memory`, `Device::set_memory`, and `Device::clear`).
 ///
@@ -755,12 +943,17 @@ pub trait Device: Debug {
 /// a compile-time error for any new `ICHostDevice` implementations that forget to opt-in.
 pub trait ICHostDeviceMemoryOverride {}
 
+/// Logic types every chip host answers through `ICHostDevice::read_host_logic`, on top of
+/// its own property registry
+pub const IC_HOST_LOGIC_TYPES: &[LogicType] = &[LogicType::StackSize];
+
 /// Trait for devices that host an IC10 chip and provide common helpers for chip access and execution.
 ///
 /// Implementors of this trait should also override `Device`'s `get_memory`, `set_memory`, and `clear`
 /// methods (or delegate to the `ICHostDevice` default implementations) so calls through a
-/// `dyn Device` object are routed to the hosted chip's memory. Unit tests in `logic_tests`
-/// verify this behavior.
+/// `dyn Device` object are routed to the hosted chip's memory. Likewise `Device`'s `can_read`,
+/// `read` and `supported_types` should include `IC_HOST_LOGIC_TYPES`, answered by
+/// `read_host_logic`. Unit tests in `logic_tests` verify this behavior.
 pub trait ICHostDevice: ICHostDeviceMemoryOverride {
     fn ichost_get_id(&self) -> i32;
 
@@ -823,14 +1016,16 @@ pub trait ICHostDevice: ICHostDeviceMemoryOverride {
         Ok(())
     }
 
-    /// Set a device pin on the housing's chip slot (d0-dN)
+    /// Wire device pin `pin` (d0-dN) to the device with reference ID `device_ref_id`, or clear
+    /// it with `None`. This is how a harness pre-wires pins without IC10 `alias` code; the
+    /// wiring survives chip swaps. Pins the host does not have are ignored.
     fn set_device_pin(&self, pin: usize, device_ref_id: Option<i32>) {
         self.chip_slot()
             .borrow_mut()
             .set_device_pin(pin, device_ref_id);
     }
 
-    /// Get a device pin reference ID from the chip slot (d0-dN)
+    /// Get the device reference ID wired to pin `pin` (d0-dN); `None` when unset or out of range
     fn get_device_pin(&self, pin: usize) -> Option<i32> {
         self.chip_slot().borrow().get_device_pin(pin)
     }
@@ -840,6 +1035,20 @@ pub trait ICHostDevice: ICHostDeviceMemoryOverride {
         self.chip_slot().borrow().get_last_executed_instructions()
     }
 
+    /// Read one of the `IC_HOST_LOGIC_TYPES` every host answers, or `None` for any other type.
+    /// `StackSize` is the capacity of the chip slot's stack, with or without a chip.
+    fn read_host_logic(&self, logic_type: LogicType) -> Option<f64> {
+        match logic_type {
+            LogicType::StackSize => Some(self.chip_slot().borrow().stack_size() as f64),
+            _ => None,
+        }
+    }
+
+    /// Get the number of values on the hosted chip's stack (0 without a chip).
+    fn stack_depth(&self) -> usize {
+        self.chip_slot().borrow().stack_depth()
+    }
+
     /// Get a register value from the hosted chip (if present).
     fn get_register(&self, index: usize) -> SimulationResult<f64> {
         if let Some(chip) = self.chip_slot().borrow().get_chip() {
@@ -861,14 +1070,37 @@ pub trait ICHostDevice: ICHostDeviceMemoryOverride {
     }
 }
 
+/// An item a slot host did not take in full, handed back together with the reason
+#[derive(Debug)]
+pub struct RejectedItem {
+    /// The rejected item, or what is left of it after a partial merge
+    pub item: Shared<dyn Item>,
+    /// Why the item was not inserted
+    pub reason: SimulationError,
+}
+
+impl RejectedItem {
+    /// Reject `item` because the slot at `index` is full or holds a different item
+    pub fn leftover(index: usize, item: Shared<dyn Item>) -> Self {
+        Self {
+            item,
+            reason: SimulationError::RuntimeError {
+                message: format!("Slot {index} is full or holds a different item"),
+                line: 0,
+            },
+        }
+    }
+}
+
 /// Trait for devices that expose normal item slot behaviour (inserting/removing items)
 pub trait SlotHostDevice {
-    /// Try to insert an item into a slot by index. Returns Ok(()) on success or Err(leftover) on failure.
+    /// Try to insert an item into a slot by index. Returns Ok(()) on success or the
+    /// rejected item (or its leftover quantity) with the reason on failure.
     fn try_insert_item(
         &mut self,
         index: usize,
         incoming: Shared<dyn Item>,
-    ) -> Result<(), Shared<dyn Item>>;
+    ) -> Result<(), RejectedItem>;
 
     /// Get a reference to the slot by index.
     fn get_slot(&self, index: usize) -> Option<&Slot>;
@@ -883,20 +1115,141 @@ pub trait SlotHostDevice {
     fn slot_count(&self) -> usize {
         0
     }
+
+    /// Item types accepted by the slot at `index`, or `None` when it accepts any item type
+    fn allowed_item_types(&self, _index: usize) -> Option<&'static [ItemType]> {
+        None
+    }
+
+    /// Check that `item` may be inserted into the slot at `index`, explaining why not.
+    /// Capacity and merging are left to `try_insert_item`.
+    fn check_insert(&self, index: usize, item: &dyn Item) -> SimulationResult<()> {
+        if index >= self.slot_count() {
+            return Err(SimulationError::RuntimeError {
+                message: format!("Slot index out of range: {index}"),
+                line: 0,
+            });
+        }
+
+        if let Some(allowed) = self.allowed_item_types(index)
+            && !allowed.contains(&item.item_type())
+        {
+            let allowed: Vec<&str> = allowed.iter().map(ItemType::as_str).collect();
+            return Err(SimulationError::RuntimeError {
+                message: format!(
+                    "Slot {index} only accepts {} items, not {}",
+                    allowed.join("/"),
+                    item.item_type().as_str()
+                ),
+                line: 0,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Total number of item slots, as read through `TotalSlots`
+    fn total_slots(&self) -> usize {
+        self.slot_count()
+    }
+
+    /// Number of empty item slots, as read through `FreeSlots`
+    fn free_slots(&self) -> usize {
+        (0..self.slot_count())
+            .filter(|&index| self.get_slot(index).is_some_and(Slot::is_empty))
+            .count()
+    }
+}
+
+/// Error returned by `set_atmospheric_network` when `device` has no `connection`
+fn unsupported_connection_error(
+    device: &str,
+    connection: DeviceAtmosphericNetworkType,
+    supported: &[DeviceAtmosphericNetworkType],
+) -> SimulationError {
+    let supported = supported
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    SimulationError::RuntimeError {
+        message: format!(
+            "{device} does not support atmospheric connection {connection}; valid connections: {supported}"
+        ),
+        line: 0,
+    }
 }
 
 /// Trait for devices that connect to atmospheric networks
 pub trait AtmosphericDevice: Debug {
-    /// Set the atmospheric network for a specific connection on this device
+    /// Connections `set_atmospheric_network` accepts on this device. Internal buffers are
+    /// fixed at construction and never listed.
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType];
+
+    /// Storage for the network on `connection`, or `None` when the device has no such port.
+    /// Only called with connections listed in `supported_atmospheric_connections`.
+    fn atmospheric_network_slot(
+        &mut self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>>;
+
+    /// Set the atmospheric network for a specific connection on this device. Connections
+    /// outside `supported_atmospheric_connections` are rejected with an error.
     fn set_atmospheric_network(
         &mut self,
         connection: DeviceAtmosphericNetworkType,
         network: OptShared<AtmosphericNetwork>,
-    ) -> SimulationResult<()>;
+    ) -> SimulationResult<()> {
+        let supported = self.supported_atmospheric_connections();
+        let slot = supported
+            .contains(&connection)
+            .then(|| self.atmospheric_network_slot(connection))
+            .flatten();
+        let Some(slot) = slot else {
+            let device = std::any::type_name::<Self>()
+                .rsplit("::")
+                .next()
+                .unwrap_or_default();
+            return Err(unsupported_connection_error(device, connection, supported));
+        };
+        *slot = network.as_ref().map(SharedPtr::downgrade);
+        Ok(())
+    }
 
     /// Get the atmospheric network for a specific connection on this device
     fn get_atmospheric_network(
         &self,
         connection: DeviceAtmosphericNetworkType,
     ) -> OptShared<AtmosphericNetwork>;
+
+    /// Total moles in the network on `connection`, as read through the `TotalMoles*` logic
+    /// types; errors when nothing is connected there
+    fn network_total_moles(
+        &self,
+        connection: DeviceAtmosphericNetworkType,
+    ) -> SimulationResult<f64> {
+        self.get_atmospheric_network(connection)
+            .map(|net| net.borrow().total_moles())
+            .ok_or_else(|| SimulationError::RuntimeError {
+                message: format!("Device has no {connection:?} atmospheric network"),
+                line: 0,
+            })
+    }
+
+    /// Whether any connected network is over pressure, as read through `Error`
+    fn any_network_over_pressure(&self) -> bool {
+        DeviceAtmosphericNetworkType::all().any(|connection| {
+            self.get_atmospheric_network(connection)
+                .is_some_and(|net| net.borrow().over_pressure())
+        })
+    }
+
+    /// Whether the device is faulted, as read through `Error` on devices that check their
+    /// wiring: a `required` connection has no network, or a connected network is over pressure
+    fn has_connection_error(&self, required: &[DeviceAtmosphericNetworkType]) -> bool {
+        required
+            .iter()
+            .any(|&connection| self.get_atmospheric_network(connection).is_none())
+            || self.any_network_over_pressure()
+    }
 }
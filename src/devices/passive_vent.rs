@@ -14,7 +14,6 @@ use crate::{
 
 use std::{
     fmt::{Debug, Display},
-    rc::Rc,
     sync::OnceLock,
 };
 
@@ -29,6 +28,12 @@ pub struct PassiveVent {
 }
 
 impl PassiveVent {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+    ];
+
     pub const PREFAB_HASH: i32 = string_to_hash("StructurePassiveVent");
 
     pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
@@ -183,8 +188,7 @@ impl Device for PassiveVent {
     }
 
     fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
-        use DeviceAtmosphericNetworkType::*;
-        vec![Input, Output]
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
     }
 
     fn supports_cable_network() -> bool
@@ -220,28 +224,19 @@ impl Debug for PassiveVent {
 }
 
 impl AtmosphericDevice for PassiveVent {
-    fn set_atmospheric_network(
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
         &mut self,
         connection: DeviceAtmosphericNetworkType,
-        network: OptShared<AtmosphericNetwork>,
-    ) -> SimulationResult<()> {
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
         use DeviceAtmosphericNetworkType::*;
         match connection {
-            Input => {
-                self.input_network = network.as_ref().map(Rc::downgrade);
-                Ok(())
-            }
-            Output => {
-                self.output_network = network.as_ref().map(Rc::downgrade);
-                Ok(())
-            }
-            _ => Err(SimulationError::RuntimeError {
-                message: format!(
-                    "PassiveVent does not support atmospheric connection {:?}",
-                    connection
-                ),
-                line: 0,
-            }),
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.output_network),
+            _ => None,
         }
     }
 
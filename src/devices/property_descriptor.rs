@@ -1,10 +1,11 @@
 //! Property descriptor system for extensible device logic types
 
 use crate::{
-    LogicSlotType, LogicType,
+    Device, ItemType, LogicSlotType, LogicType,
     error::{SimulationError, SimulationResult},
 };
 use std::{
+    cell::RefCell,
     collections::{HashMap, HashSet},
     sync::OnceLock,
 };
@@ -53,6 +54,22 @@ macro_rules! prop_rw_clamped {
     };
 }
 
+/// `NameHash` read from `get_name_hash`; a write goes through `write_name_hash`, storing
+/// the hash in `$field`, a `RefCell<Option<i32>>` that `get_name_hash` prefers over the hash
+/// of the name
+#[macro_export]
+macro_rules! prop_rw_name_hash {
+    ($field:ident) => {
+        PropertyDescriptor::read_write(
+            LogicType::NameHash,
+            |device, _| Ok(device.get_name_hash() as f64),
+            |device, _, value| {
+                $crate::devices::property_descriptor::write_name_hash(device, &device.$field, value)
+            },
+        )
+    };
+}
+
 /// Function type for reading a property value from a device
 pub type PropertyReadFn<T> = fn(&T, LogicType) -> SimulationResult<f64>;
 
@@ -156,7 +173,7 @@ impl<T: 'static> PropertyRegistry<T> {
             .unwrap_or(false)
     }
 
-    /// Read a property value
+    /// Read a property value; errors for properties `can_read` rejects
     pub fn read(&self, device: &T, logic_type: LogicType) -> SimulationResult<f64> {
         match self.lookup.get(&logic_type) {
             Some(&idx) => {
@@ -264,6 +281,9 @@ pub struct SlotPropertyRegistry<T: 'static> {
 
     // Sorted list of unique slot indices covered by the descriptors
     registered_slots: Vec<usize>,
+
+    // Item types accepted by specific slots; slots not listed accept any item type
+    allowed_types: Vec<(&'static [usize], &'static [ItemType])>,
 }
 
 impl<T: 'static> SlotPropertyRegistry<T> {
@@ -308,7 +328,29 @@ impl<T: 'static> SlotPropertyRegistry<T> {
             properties,
             lookup,
             registered_slots,
+            allowed_types: Vec::new(),
+        }
+    }
+
+    /// Restrict the given slot indices to the listed item types
+    pub fn with_allowed_types(
+        mut self,
+        slots: &'static [usize],
+        item_types: &'static [ItemType],
+    ) -> Self {
+        if slots.iter().any(|s| self.allowed_item_types(*s).is_some()) {
+            panic!("Duplicate item type restriction for same slot in SlotPropertyRegistry");
         }
+        self.allowed_types.push((slots, item_types));
+        self
+    }
+
+    /// Item types accepted by the slot at `index`, or `None` when it accepts any item type
+    pub fn allowed_item_types(&self, index: usize) -> Option<&'static [ItemType]> {
+        self.allowed_types
+            .iter()
+            .find(|(slots, _)| slots.contains(&index))
+            .map(|(_, item_types)| *item_types)
     }
 
     /// Read a slot property value
@@ -378,6 +420,35 @@ impl<T: 'static> SlotPropertyRegistry<T> {
     }
 }
 
+/// Store a `NameHash` written to `device` in its `name_hash_override` and move the device to
+/// the new name hash in its cable network's name index, so `lbn`/`sbn` and `remove_device`
+/// see the new hash
+pub fn write_name_hash<T: Device>(
+    device: &T,
+    name_hash_override: &RefCell<Option<i32>>,
+    value: f64,
+) -> SimulationResult<()> {
+    let network = device.get_network();
+    let network = network
+        .as_ref()
+        .map(|network| network.try_borrow())
+        .transpose()
+        .map_err(|_| SimulationError::RuntimeError {
+            message: format!(
+                "Cable network of device {} is busy and cannot be accessed",
+                device.get_id()
+            ),
+            line: 0,
+        })?;
+
+    let old_name_hash = device.get_name_hash();
+    *name_hash_override.borrow_mut() = Some(value as i32);
+    if let Some(network) = network {
+        network.update_device_name(device.get_id(), old_name_hash, device.get_name_hash());
+    }
+    Ok(())
+}
+
 /// Return a static empty `SlotPropertyRegistry<T>` instance for types that have no slot properties.
 pub fn empty_slot_registry<T>() -> &'static SlotPropertyRegistry<T> {
     static EMPTY: OnceLock<SlotPropertyRegistry<()>> = OnceLock::new();
@@ -0,0 +1,268 @@
+//! Solar panel: generates power in proportion to how directly it faces the sun.
+
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::sync::OnceLock;
+
+use crate::constants::DEFAULT_TICKS_PER_DAY;
+use crate::conversions::fmt_trim;
+use crate::devices::daylight_sensor::sun_angles;
+use crate::types::OptWeakShared;
+use crate::{
+    CableNetwork,
+    devices::{
+        Device, LogicType, SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::SimulationResult,
+    parser::string_to_hash,
+    types::{OptShared, Shared, shared},
+};
+use crate::{prop_ro, prop_rw_name_hash};
+
+/// Power a Solar Panel generates when facing the sun directly (W)
+pub const SOLAR_PANEL_MAX_POWER: f64 = 500.0;
+
+/// Unit vector for a `(horizontal, vertical)` direction, vertical measured from the zenith
+fn direction(horizontal: f64, vertical: f64) -> [f64; 3] {
+    let (h, v) = (horizontal.to_radians(), vertical.to_radians());
+    [v.sin() * h.cos(), v.sin() * h.sin(), v.cos()]
+}
+
+/// Solar panel.
+///
+/// `Horizontal`/`Vertical` set the direction the panel faces, using the same angles the
+/// DaylightSensor reports for the sun. Each tick `Ratio` becomes the cosine between the panel
+/// and the sun (zero when facing away or when the sun is below the horizon) and `Charge` the
+/// resulting power output, which is supplied to the cable network.
+pub struct SolarPanel {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// Device reference ID
+    reference_id: i32,
+    /// Panel horizontal angle (degrees)
+    horizontal: RefCell<f64>,
+    /// Panel vertical angle (degrees)
+    vertical: RefCell<f64>,
+    /// Current efficiency in `[0, 1]`
+    ratio: RefCell<f64>,
+
+    /// Number of ticks in a day cycle used to determine sun position
+    ticks_per_day: f64,
+}
+
+/// Constructors and helpers
+impl SolarPanel {
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureSolarPanel");
+
+    /// Create a new `SolarPanel` facing the zenith.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        let ticks_per_day = settings.ticks_per_day.unwrap_or(DEFAULT_TICKS_PER_DAY);
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            reference_id: settings.id.unwrap(),
+            horizontal: RefCell::new(0.0),
+            vertical: RefCell::new(0.0),
+            ratio: RefCell::new(0.0),
+            ticks_per_day,
+        })
+    }
+
+    /// Prefab hash for `SolarPanel`
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    pub fn display_name_static() -> &'static str {
+        "Solar Panel"
+    }
+
+    /// Current efficiency in `[0, 1]`
+    pub fn ratio(&self) -> f64 {
+        *self.ratio.borrow()
+    }
+
+    /// Current power output (W)
+    pub fn charge(&self) -> f64 {
+        self.ratio() * SOLAR_PANEL_MAX_POWER
+    }
+
+    /// Efficiency of a panel facing `(horizontal, vertical)` with the sun at
+    /// `(sun_horizontal, sun_vertical)`
+    pub fn efficiency(
+        horizontal: f64,
+        vertical: f64,
+        sun_horizontal: f64,
+        sun_vertical: f64,
+    ) -> f64 {
+        if sun_vertical >= 90.0 {
+            return 0.0;
+        }
+
+        let panel = direction(horizontal, vertical);
+        let sun = direction(sun_horizontal, sun_vertical);
+        let cos = panel[0] * sun[0] + panel[1] * sun[1] + panel[2] * sun[2];
+        cos.clamp(0.0, 1.0)
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<SolarPanel>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<SolarPanel>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                PropertyDescriptor::read_write(
+                    Horizontal,
+                    |device, _| Ok(*device.horizontal.borrow()),
+                    |device, _, value| {
+                        *device.horizontal.borrow_mut() = value;
+                        Ok(())
+                    },
+                ),
+                PropertyDescriptor::read_write(
+                    Vertical,
+                    |device, _| Ok(*device.vertical.borrow()),
+                    |device, _, value| {
+                        *device.vertical.borrow_mut() = value;
+                        Ok(())
+                    },
+                ),
+                prop_ro!(Ratio, |device, _| Ok(device.ratio())),
+                prop_ro!(Charge, |device, _| Ok(device.charge())),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+}
+
+/// `Device` trait implementation for `SolarPanel`
+impl Device for SolarPanel {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        SolarPanel::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn update(&self, tick: u64) -> SimulationResult<bool> {
+        let (sun_horizontal, sun_vertical) = sun_angles(tick, self.ticks_per_day);
+        let ratio = Self::efficiency(
+            *self.horizontal.borrow(),
+            *self.vertical.borrow(),
+            sun_horizontal,
+            sun_vertical,
+        );
+
+        let changed = ratio != self.ratio();
+        *self.ratio.borrow_mut() = ratio;
+
+        Ok(changed)
+    }
+
+    fn power_generation(&self) -> f64 {
+        self.charge()
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        SolarPanel::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        SolarPanel::display_name_static()
+    }
+}
+
+impl Display for SolarPanel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SolarPanel {{ name: \"{}\", id: {}, horiz: {}, vert: {}, output: {} W }}",
+            self.name,
+            self.reference_id,
+            fmt_trim(*self.horizontal.borrow(), 2),
+            fmt_trim(*self.vertical.borrow(), 2),
+            fmt_trim(self.charge(), 1)
+        )
+    }
+}
+
+impl Debug for SolarPanel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
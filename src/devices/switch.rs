@@ -0,0 +1,190 @@
+//! Switch: a lever the host program flips between ticks, read by chips through `On`/`Setting`.
+
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::sync::OnceLock;
+
+use crate::types::OptWeakShared;
+use crate::{
+    CableNetwork,
+    devices::{
+        Device, LogicType, SimulationDeviceSettings,
+        property_descriptor::{PropertyDescriptor, PropertyRegistry},
+    },
+    error::SimulationResult,
+    parser::string_to_hash,
+    types::{OptShared, Shared, shared},
+};
+use crate::{prop_ro, prop_rw_bool, prop_rw_name_hash};
+
+/// Two-state switch; `On` and `Setting` both read and write the same state
+pub struct Switch {
+    /// Device name
+    name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
+    /// Connected network
+    network: OptWeakShared<CableNetwork>,
+
+    /// Device reference ID
+    reference_id: i32,
+    /// Switch state (0 = off, 1 = on)
+    on: RefCell<f64>,
+}
+
+/// Constructors and helpers
+impl Switch {
+    /// Compile-time prefab hash constant for this device
+    pub const PREFAB_HASH: i32 = string_to_hash("StructureLogicSwitch");
+
+    /// Create a new `Switch` in the off position.
+    pub fn new(settings: SimulationDeviceSettings) -> Shared<Self> {
+        let name = if let Some(n) = settings.name.as_ref() {
+            n.to_string()
+        } else {
+            Self::display_name_static().to_string()
+        };
+
+        shared(Self {
+            name,
+            name_hash_override: RefCell::new(None),
+            network: None,
+            on: RefCell::new(0.0),
+            reference_id: settings.id.unwrap(),
+        })
+    }
+
+    /// Prefab hash for `Switch`
+    pub fn prefab_hash() -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    pub fn display_name_static() -> &'static str {
+        "Switch"
+    }
+
+    /// Whether the switch is on
+    pub fn is_on(&self) -> bool {
+        *self.on.borrow() != 0.0
+    }
+
+    /// Put the switch in the given position, as a player would between ticks
+    pub fn set(&self, on: bool) {
+        *self.on.borrow_mut() = if on { 1.0 } else { 0.0 };
+    }
+
+    /// Flip the switch, returning the new position
+    pub fn toggle(&self) -> bool {
+        let on = !self.is_on();
+        self.set(on);
+        on
+    }
+
+    /// Get the property registry for this device type
+    #[rustfmt::skip]
+    pub fn properties() -> &'static PropertyRegistry<Self> {
+        use LogicType::*;
+        static REGISTRY: OnceLock<PropertyRegistry<Switch>> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            const DESCRIPTORS: &[PropertyDescriptor<Switch>] = &[
+                prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
+                prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_rw_bool!(On, on),
+                prop_rw_bool!(Setting, on),
+            ];
+
+            PropertyRegistry::new(DESCRIPTORS)
+        })
+    }
+}
+
+/// `Device` trait implementation for `Switch`
+impl Device for Switch {
+    fn get_id(&self) -> i32 {
+        self.reference_id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Switch::prefab_hash()
+    }
+
+    fn get_name_hash(&self) -> i32 {
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(&self.name))
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_network(&self) -> OptShared<CableNetwork> {
+        self.network.as_ref().and_then(|w| w.upgrade()).clone()
+    }
+
+    fn set_network(&mut self, network: OptWeakShared<CableNetwork>) -> SimulationResult<()> {
+        self.network = network;
+        Ok(())
+    }
+
+    fn rename(&mut self, name: &str) {
+        let old_name_hash = self.get_name_hash();
+        self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
+
+        if let Some(net_rc) = self.get_network() {
+            net_rc.borrow_mut().update_device_name(
+                self.reference_id,
+                old_name_hash,
+                string_to_hash(name),
+            );
+        }
+    }
+
+    fn can_read(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_read(logic_type)
+    }
+
+    fn can_write(&self, logic_type: LogicType) -> bool {
+        Self::properties().can_write(logic_type)
+    }
+
+    fn read(&self, logic_type: LogicType) -> SimulationResult<f64> {
+        Self::properties().read(self, logic_type)
+    }
+
+    fn write(&self, logic_type: LogicType, value: f64) -> SimulationResult<()> {
+        Self::properties().write(self, logic_type, value)
+    }
+
+    fn supported_types(&self) -> Vec<LogicType> {
+        Self::properties().supported_types()
+    }
+
+    fn properties() -> &'static PropertyRegistry<Self> {
+        Switch::properties()
+    }
+
+    fn display_name_static() -> &'static str {
+        Switch::display_name_static()
+    }
+}
+
+impl Display for Switch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on_str = if self.is_on() { "On" } else { "Off" };
+        write!(
+            f,
+            "Switch {{ name: \"{}\", id: {}, state: {} }}",
+            self.name, self.reference_id, on_str
+        )
+    }
+}
+
+impl Debug for Switch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::{
     CableNetwork,
-    atmospherics::MatterState,
+    atmospherics::{MatterState, PRESSURE_EQUALIZATION_EPSILON, TransferAmount, calculate_moles},
     devices::{
         AtmosphericDevice, Device, DeviceAtmosphericNetworkType, LogicType,
         SimulationDeviceSettings,
@@ -11,7 +11,7 @@ use crate::{
     error::{SimulationError, SimulationResult},
     networks::AtmosphericNetwork,
     parser::string_to_hash,
-    prop_ro, prop_rw_bool, prop_rw_clamped,
+    prop_ro, prop_rw_bool, prop_rw_clamped, prop_rw_name_hash,
     types::{OptShared, OptWeakShared, Shared, shared},
 };
 
@@ -19,14 +19,21 @@ use crate::conversions::fmt_trim;
 use std::{
     cell::RefCell,
     fmt::{Debug, Display},
-    rc::Rc,
     sync::OnceLock,
 };
 
+/// Maximum volume (litres) the pump can move per tick
+const MAX_VOLUME_PER_TICK: f64 = 10.0;
+
+/// Power the pump draws from its cable network while On (W)
+pub const VOLUME_PUMP_POWER_DRAW: f64 = 100.0;
+
 /// Volume pump: moves gas between input and output networks
 pub struct VolumePump {
     /// Device name
     name: String,
+    /// Name hash written through `NameHash`, used instead of the hash of `name`
+    name_hash_override: RefCell<Option<i32>>,
     /// Connected network
     network: OptWeakShared<CableNetwork>,
 
@@ -36,6 +43,10 @@ pub struct VolumePump {
     on: RefCell<f64>,
     /// The Setting state (volume)
     setting: RefCell<f64>,
+    /// The Mode state (0 = Input -> Output, 1 = Output -> Input)
+    mode: RefCell<f64>,
+    /// Pressure (kPa) at which the pump stops pushing into the destination network, if any
+    max_output_pressure: RefCell<Option<f64>>,
 
     /// The input network
     input_network: OptWeakShared<AtmosphericNetwork>,
@@ -45,6 +56,12 @@ pub struct VolumePump {
 
 /// Constructors for `VolumePump`.
 impl VolumePump {
+    /// Connections `set_atmospheric_network` accepts
+    pub const ATMOSPHERIC_CONNECTIONS: &'static [DeviceAtmosphericNetworkType] = &[
+        DeviceAtmosphericNetworkType::Input,
+        DeviceAtmosphericNetworkType::Output,
+    ];
+
     /// Compile-time prefab hash constant for this device
     pub const PREFAB_HASH: i32 = string_to_hash("StructureVolumePump");
 
@@ -58,8 +75,11 @@ impl VolumePump {
 
         shared(Self {
             name,
+            name_hash_override: RefCell::new(None),
             network: None,
             setting: RefCell::new(5.0),
+            mode: RefCell::new(0.0),
+            max_output_pressure: RefCell::new(None),
             on: RefCell::new(0.0),
             reference_id: settings.id.unwrap(),
             input_network: None,
@@ -77,20 +97,41 @@ impl VolumePump {
         "Volume Pump"
     }
 
+    /// Whether the pump moves gas from Output to Input
+    pub fn is_reversed(&self) -> bool {
+        *self.mode.borrow() != 0.0
+    }
+
+    /// Pressure (kPa) at which the pump stops pushing into the destination network, if any
+    pub fn max_output_pressure(&self) -> Option<f64> {
+        *self.max_output_pressure.borrow()
+    }
+
+    /// Set the pressure (kPa) at which the pump stops pushing into the destination network.
+    /// `None` (the default) pumps regardless of the destination pressure.
+    pub fn set_max_output_pressure(&self, pressure: Option<f64>) {
+        *self.max_output_pressure.borrow_mut() = pressure.map(|p| p.max(0.0));
+    }
+
     /// Get the property registry for this device type
     #[rustfmt::skip]
     pub fn properties() -> &'static PropertyRegistry<Self> {
         use LogicType::*;
+        use DeviceAtmosphericNetworkType::*;
         static REGISTRY: OnceLock<PropertyRegistry<VolumePump>> = OnceLock::new();
 
         REGISTRY.get_or_init(|| {
             const DESCRIPTORS: &[PropertyDescriptor<VolumePump>] = &[
                 prop_ro!(ReferenceId, |device, _| Ok(device.reference_id as f64)),
                 prop_ro!(PrefabHash, |device, _| Ok(device.get_prefab_hash() as f64)),
-                prop_ro!(NameHash, |device, _| Ok(device.get_name_hash() as f64)),
-                prop_ro!(Ratio, |device, _| Ok(*device.setting.borrow() / 10.0)),
+                prop_rw_name_hash!(name_hash_override),
+                prop_ro!(Error, |device, _| Ok(if device.has_connection_error(&VolumePump::required_atmospheric_connections()) { 1.0 } else { 0.0 })),
+                prop_ro!(Ratio, |device, _| Ok(*device.setting.borrow() / MAX_VOLUME_PER_TICK)),
                 prop_rw_bool!(On, on),
-                prop_rw_clamped!(Setting, setting, 0.0, 10.0),
+                prop_rw_bool!(Mode, mode),
+                prop_rw_clamped!(Setting, setting, 0.0, MAX_VOLUME_PER_TICK),
+                prop_ro!(TotalMolesInput, |device, _| device.network_total_moles(Input)),
+                prop_ro!(TotalMolesOutput, |device, _| device.network_total_moles(Output)),
             ];
 
             PropertyRegistry::new(DESCRIPTORS)
@@ -109,7 +150,9 @@ impl Device for VolumePump {
     }
 
     fn get_name_hash(&self) -> i32 {
-        string_to_hash(self.name.as_str())
+        self.name_hash_override
+            .borrow()
+            .unwrap_or_else(|| string_to_hash(self.name.as_str()))
     }
 
     fn get_name(&self) -> &str {
@@ -128,6 +171,7 @@ impl Device for VolumePump {
     fn rename(&mut self, name: &str) {
         let old_name_hash = self.get_name_hash();
         self.name = name.to_string();
+        *self.name_hash_override.get_mut() = None;
 
         if let Some(net_rc) = self.get_network() {
             net_rc.borrow_mut().update_device_name(
@@ -158,6 +202,14 @@ impl Device for VolumePump {
         Self::properties().supported_types()
     }
 
+    fn power_demand(&self) -> f64 {
+        if *self.on.borrow() == 0.0 {
+            0.0
+        } else {
+            VOLUME_PUMP_POWER_DRAW
+        }
+    }
+
     fn update(&self, _tick: u64) -> SimulationResult<bool> {
         // Only run when device is On and Mode is enabled
         if *self.on.borrow() == 0.0 {
@@ -182,25 +234,53 @@ impl Device for VolumePump {
                 line: 0,
             })?;
 
-        let setting = *self.setting.borrow();
+        // Mode selects the flow direction
+        let (source_rc, target_rc) = if self.is_reversed() {
+            (output_rc, input_rc)
+        } else {
+            (input_rc, output_rc)
+        };
+
+        let setting = self.setting.borrow().clamp(0.0, MAX_VOLUME_PER_TICK);
+
+        let (source_total_volume, total_moles, source_temperature) = {
+            let source = source_rc.borrow();
+            (
+                source.total_volume(),
+                source.total_moles(),
+                source.temperature(),
+            )
+        };
 
-        let (input_total_volume, total_moles) = {
-            let input = input_rc.borrow();
-            (input.total_volume(), input.total_moles())
+        // Moles the destination can take before reaching the pressure cutoff, if one is set
+        let headroom_moles = match *self.max_output_pressure.borrow() {
+            Some(max_pressure) => {
+                let target = target_rc.borrow();
+                let pressure_headroom = max_pressure - target.pressure();
+                if pressure_headroom <= PRESSURE_EQUALIZATION_EPSILON {
+                    return Ok(false);
+                }
+                calculate_moles(pressure_headroom, target.gas_volume(), source_temperature)
+            }
+            None => f64::INFINITY,
         };
 
         // Clamp setting to available volume
-        let volume_to_move = setting.min(input_total_volume);
+        let volume_to_move = setting.min(source_total_volume);
 
         // Proportional transfer of all matter (gases + liquids)
         if volume_to_move > 0.0 {
-            let ratio = (volume_to_move / input_total_volume).clamp(0.0, 1.0);
+            let ratio = (volume_to_move / source_total_volume).clamp(0.0, 1.0);
             if ratio > 0.0 {
-                let moles_to_move = total_moles * ratio;
-                output_rc.borrow_mut().add_mixture(
-                    &input_rc
-                        .borrow_mut()
-                        .remove_moles(moles_to_move, MatterState::All),
+                // Never push the destination past the pressure cutoff
+                let moles_to_move = (total_moles * ratio).min(headroom_moles);
+                if moles_to_move <= 0.0 {
+                    return Ok(false);
+                }
+                source_rc.borrow_mut().transfer_moles_to(
+                    &mut target_rc.borrow_mut(),
+                    TransferAmount::Moles(moles_to_move),
+                    MatterState::All,
                 );
                 return Ok(true);
             }
@@ -218,8 +298,7 @@ impl Device for VolumePump {
     }
 
     fn required_atmospheric_connections() -> Vec<DeviceAtmosphericNetworkType> {
-        use DeviceAtmosphericNetworkType::*;
-        vec![Input, Output]
+        Self::ATMOSPHERIC_CONNECTIONS.to_vec()
     }
 
     fn as_atmospheric_device(&self) -> Option<&dyn AtmosphericDevice> {
@@ -239,11 +318,16 @@ impl Display for VolumePump {
             "On"
         };
         let setting_str = fmt_trim(*self.setting.borrow(), 3);
+        let mode_str = if self.is_reversed() {
+            "Reverse"
+        } else {
+            "Forward"
+        };
 
         write!(
             f,
-            "VolumePump {{ name: \"{}\", id: {}, on: {}, setting: {}",
-            self.name, self.reference_id, on_str, setting_str
+            "VolumePump {{ name: \"{}\", id: {}, on: {}, mode: {}, setting: {}",
+            self.name, self.reference_id, on_str, mode_str, setting_str
         )?;
 
         if let Some(weak) = &self.input_network
@@ -269,28 +353,19 @@ impl Debug for VolumePump {
 
 /// `AtmosphericDevice` implementation for `VolumePump` that manages input/output atmospheric network connections.
 impl AtmosphericDevice for VolumePump {
-    fn set_atmospheric_network(
+    fn supported_atmospheric_connections(&self) -> &'static [DeviceAtmosphericNetworkType] {
+        Self::ATMOSPHERIC_CONNECTIONS
+    }
+
+    fn atmospheric_network_slot(
         &mut self,
         connection: DeviceAtmosphericNetworkType,
-        network: OptShared<AtmosphericNetwork>,
-    ) -> SimulationResult<()> {
+    ) -> Option<&mut OptWeakShared<AtmosphericNetwork>> {
         use DeviceAtmosphericNetworkType::*;
         match connection {
-            Input => {
-                self.input_network = network.as_ref().map(Rc::downgrade);
-                Ok(())
-            }
-            Output => {
-                self.output_network = network.as_ref().map(Rc::downgrade);
-                Ok(())
-            }
-            _ => Err(SimulationError::RuntimeError {
-                message: format!(
-                    "VolumePump does not support atmospheric connection {:?}",
-                    connection
-                ),
-                line: 0,
-            }),
+            Input => Some(&mut self.input_network),
+            Output => Some(&mut self.output_network),
+            _ => None,
         }
     }
 
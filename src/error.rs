@@ -1,5 +1,6 @@
 //! Error types for the IC10 emulator
 
+use serde::{Serialize, Serializer, ser::SerializeStruct};
 use thiserror::Error;
 
 /// Simulation error types
@@ -11,11 +12,11 @@ pub enum SimulationError {
     #[error("Runtime error at line {line}: {message}")]
     RuntimeError { line: usize, message: String },
 
-    #[error("Register index {0} out of bounds (valid range: 0-17)")]
-    RegisterOutOfBounds(usize),
+    #[error("Register index {index} out of bounds (valid range: 0-17){}", line_suffix(.line))]
+    RegisterOutOfBounds { index: usize, line: Option<usize> },
 
-    #[error("Stack index {0} out of bounds (valid range: 0-511)")]
-    StackOutOfBounds(usize),
+    #[error("Stack index {index} out of bounds (valid range: 0-511){}", line_suffix(.line))]
+    StackOutOfBounds { index: usize, line: Option<usize> },
 
     #[error("Unrecognized instruction: {0}")]
     UnrecognizedInstruction(String),
@@ -28,5 +29,83 @@ pub enum SimulationError {
     },
 }
 
+impl SimulationError {
+    /// Short name of the error variant, as used in the JSON form (`IC10ParseError` is
+    /// reported as `ParseError`)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SimulationError::IC10ParseError { .. } => "ParseError",
+            SimulationError::RuntimeError { .. } => "RuntimeError",
+            SimulationError::RegisterOutOfBounds { .. } => "RegisterOutOfBounds",
+            SimulationError::StackOutOfBounds { .. } => "StackOutOfBounds",
+            SimulationError::UnrecognizedInstruction(_) => "UnrecognizedInstruction",
+            SimulationError::IncorrectArgumentCount { .. } => "IncorrectArgumentCount",
+        }
+    }
+
+    /// Source line the error refers to, for the variants that carry one
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            SimulationError::IC10ParseError { line, .. }
+            | SimulationError::RuntimeError { line, .. } => Some(*line),
+            SimulationError::RegisterOutOfBounds { line, .. }
+            | SimulationError::StackOutOfBounds { line, .. } => *line,
+            _ => None,
+        }
+    }
+
+    /// Message without the line for variants that carry one, otherwise the full display text
+    pub fn message(&self) -> String {
+        match self {
+            SimulationError::IC10ParseError { message, .. }
+            | SimulationError::RuntimeError { message, .. } => message.clone(),
+            SimulationError::RegisterOutOfBounds { .. }
+            | SimulationError::StackOutOfBounds { .. } => {
+                let text = self.to_string();
+                let suffix = line_suffix(&self.line());
+                text.strip_suffix(&suffix).unwrap_or(&text).to_string()
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Attribute an error raised while executing `line` to that line.
+    ///
+    /// Runtime errors take `line` over whatever the raising code filled in (devices and the
+    /// network do not know which line is executing and report 0). Register and stack bounds
+    /// errors keep their variant and gain the line. Parse-time variants are returned unchanged.
+    pub fn at_runtime_line(mut self, line: usize) -> Self {
+        match &mut self {
+            SimulationError::RuntimeError { line: at, .. } => *at = line,
+            SimulationError::RegisterOutOfBounds { line: at, .. }
+            | SimulationError::StackOutOfBounds { line: at, .. } => *at = Some(line),
+            _ => {}
+        }
+        self
+    }
+
+    /// Serialize as a JSON object `{ "kind": ..., "line": ..., "message": ... }`; `line` is
+    /// `null` for variants without one
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("error serialization cannot fail")
+    }
+}
+
+/// Display suffix naming the line of a bounds error, once it is known
+fn line_suffix(line: &Option<usize>) -> String {
+    line.map_or_else(String::new, |line| format!(" at line {line}"))
+}
+
+/// Serializes as `{ kind, line, message }` so front ends can show errors without parsing text
+impl Serialize for SimulationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SimulationError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("line", &self.line())?;
+        state.serialize_field("message", &self.message())?;
+        state.end()
+    }
+}
+
 /// Result type for simulation operations
 pub type SimulationResult<T> = Result<T, SimulationError>;
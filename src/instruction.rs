@@ -1,10 +1,14 @@
 //! IC10 instruction definitions and parsing
 
-use crate::constants::REGISTER_COUNT;
-use crate::devices::LogicType;
+use crate::constants::{
+    REGISTER_COUNT, RETURN_ADDRESS_INDEX, STACK_POINTER_INDEX, get_builtin_constants,
+};
+use crate::devices::{LogicType, ReagentMode};
 use crate::error::{SimulationError, SimulationResult};
 use crate::items::item_integrated_circuit_10::{AliasTarget, Operand};
 use crate::{BatchMode, LogicSlotType};
+use std::collections::HashMap;
+use std::ops::Range;
 
 /// All IC10 instructions
 #[derive(Debug, Clone, PartialEq)]
@@ -141,6 +145,7 @@ pub enum Instruction {
     Not { dest: Operand, arg: Operand },
 
     // ==================== Bit Shifting Operations ====================
+    // Shift amounts are taken modulo 64, as in game
     /// sll r, a, b - Shift left logical
     Sll {
         dest: Operand,
@@ -153,13 +158,13 @@ pub enum Instruction {
         arg1: Operand,
         arg2: Operand,
     },
-    /// srl r, a, b - Shift right logical (unsigned)
+    /// srl r, a, b - Shift right logical (unsigned, zero-fills)
     Srl {
         dest: Operand,
         arg1: Operand,
         arg2: Operand,
     },
-    /// sra r, a, b - Shift right arithmetic (signed)
+    /// sra r, a, b - Shift right arithmetic (signed, preserves sign)
     Sra {
         dest: Operand,
         arg1: Operand,
@@ -167,14 +172,14 @@ pub enum Instruction {
     },
 
     // ==================== Bit Field Operations ====================
-    /// ext r, source, start, length - Extract bit field from source
+    /// ext r, source, start, length - Extract bit field from source (bits 0..=52)
     Ext {
         dest: Operand,
         source: Operand,
         start: Operand,
         length: Operand,
     },
-    /// ins r, value, start, length - Insert value into bit field at start position for length bits
+    /// ins r, value, start, length - Insert value into bit field at start position for length bits (bits 0..=52)
     Ins {
         dest: Operand,
         value: Operand,
@@ -184,6 +189,8 @@ pub enum Instruction {
 
     // ==================== Comparison Operations (Set Instructions) ====================
     /// slt r, a, b - Set if a < b
+    ///
+    /// Comparisons follow the game: any ordered or equality comparison with NaN is false.
     Slt {
         dest: Operand,
         arg1: Operand,
@@ -690,7 +697,7 @@ pub enum Instruction {
     Sleep { duration: Operand },
     /// hcf - Halt and Catch Fire (hard stop)
     Hcf,
-    /// select r, cond, a, b - r = cond ? a : b
+    /// select r, cond, a, b - r = cond ? a : b (a NaN cond picks b)
     Select {
         dest: Operand,
         cond: Operand,
@@ -703,20 +710,399 @@ pub enum Instruction {
     Clrd { id: Operand },
 
     // ==================== No Operation ====================
-    /// Empty line or comment
+    /// `name:` label line. Like `Noop` it occupies its line slot, so a jump to the label lands
+    /// on this line and continues with the next one.
+    Label { name: String },
+    /// Empty or comment-only line. It still occupies its line slot, so absolute and relative
+    /// jump targets match source line numbers, and executing it moves to the next line.
     Noop,
 }
 
-// Parse a destination operand (register or alias like sp/ra)
+/// How an instruction uses one of its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandRole {
+    /// Register written by the instruction
+    Destination,
+    /// Device the instruction reads from or writes to
+    Device,
+    /// Value read by the instruction
+    Value,
+    /// Value read as a `LogicType`, which source may give by name
+    LogicType,
+    /// Value read as a `LogicSlotType`, which source may give by name
+    LogicSlotType,
+    /// Value read as a `BatchMode`, which source may give by name
+    BatchMode,
+    /// Value read as a `ReagentMode`, which source may give by name
+    ReagentMode,
+}
+
+/// Generate `Instruction::for_each_operand_mut` from a table of operand roles. Each entry lists
+/// variants whose operands share names and roles, with operands in source order.
+macro_rules! operand_roles {
+    ($($($variant:ident)|+ { $($field:ident: $role:ident),* $(,)? }),* $(,)?) => {
+        operand_roles!(@arms $([$($variant)|+] { $($field),* } [$($field: $role),*])*);
+    };
+    (@arms $([$($variant:ident)|+] $fields:tt [$($field:ident: $role:ident),*])*) => {
+        impl Instruction {
+            /// Call `f` with every operand of the instruction and the role it plays
+            pub fn for_each_operand_mut(&mut self, mut f: impl FnMut(OperandRole, &mut Operand)) {
+                match self {
+                    $($(Instruction::$variant $fields)|+ => {
+                        $(f(OperandRole::$role, $field);)*
+                    })*
+                    Instruction::Alias { .. }
+                    | Instruction::Define { .. }
+                    | Instruction::Yield
+                    | Instruction::Hcf
+                    | Instruction::Label { .. }
+                    | Instruction::Noop => {}
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+operand_roles! {
+    Move | Sqrt | Abs | Exp | Log | Ceil | Floor | Round | Trunc | Sin | Cos | Tan | Asin | Acos |
+    Atan | Not | Sltz | Sgtz | Slez | Sgez | Seqz | Snez | Snan | Snanz
+        { dest: Destination, arg: Value },
+    Add | Sub | Mul | Div | Mod | Pow | Max | Min | Atan2 | And | Or | Xor | Nor | Sll | Sla | Srl |
+    Sra | Slt | Sgt | Sle | Sge | Seq | Sne | Sapz | Snaz
+        { dest: Destination, arg1: Value, arg2: Value },
+    Rand | Pop | Peek { dest: Destination },
+    Lerp | Sap | Sna { dest: Destination, arg1: Value, arg2: Value, arg3: Value },
+    Ext { dest: Destination, source: Value, start: Value, length: Value },
+    Ins { dest: Destination, value: Value, start: Value, length: Value },
+    Sdse | Sdns { dest: Destination, device: Device },
+    Beq | Bne | Blt | Bgt | Ble | Bge | Beqal | Bneal | Bltal | Bgtal | Bleal | Bgeal | Bapz |
+    Bnaz | Bapzal | Bnazal
+        { arg1: Value, arg2: Value, line: Value },
+    Beqz | Bnez | Bltz | Bgez | Blez | Bgtz | Bnan | Beqzal | Bnezal | Bltzal | Bgezal | Blezal |
+    Bgtzal
+        { arg: Value, line: Value },
+    Breq | Brne | Brlt | Brgt | Brle | Brge | Brapz | Brnaz
+        { arg1: Value, arg2: Value, offset: Value },
+    Breqz | Brnez | Brltz | Brgez | Brlez | Brgtz | Brnan { arg: Value, offset: Value },
+    Bap | Bna | Bapal | Bnaal { arg1: Value, arg2: Value, arg3: Value, line: Value },
+    Brap | Brna { arg1: Value, arg2: Value, arg3: Value, offset: Value },
+    Bdse | Bdns | Bdseal | Bdnsal { device: Device, line: Value },
+    Brdse | Brdns { device: Device, offset: Value },
+    Bdnvl | Bdnvs { device: Device, logic_type: LogicType, line: Value },
+    J | Jal { line: Value },
+    Jr { offset: Value },
+    Push { arg: Value },
+    Poke { index: Value, value: Value },
+    L { dest: Destination, device: Device, logic_type: LogicType },
+    S { device: Device, logic_type: LogicType, value: Value },
+    Ls { dest: Destination, device: Device, slot_index: Value, slot_logic_type: LogicSlotType },
+    Ss { device: Device, slot_index: Value, slot_logic_type: LogicSlotType, value: Value },
+    Lr { dest: Destination, device: Device, reagent_mode: ReagentMode, reagent: Value },
+    Rmap { dest: Destination, device: Device, reagent_hash: Value },
+    Ld { dest: Destination, id: Value, logic_type: LogicType },
+    Sd { id: Value, logic_type: LogicType, value: Value },
+    Lb { dest: Destination, device_hash: Value, logic_type: LogicType, batch_mode: BatchMode },
+    Sb { device_hash: Value, logic_type: LogicType, value: Value },
+    Lbn {
+        dest: Destination, device_hash: Value, name_hash: Value, logic_type: LogicType,
+        batch_mode: BatchMode,
+    },
+    Sbn { device_hash: Value, name_hash: Value, logic_type: LogicType, value: Value },
+    Lbs {
+        dest: Destination, device_hash: Value, slot_index: Value, slot_logic_type: LogicSlotType,
+        batch_mode: BatchMode,
+    },
+    Sbs { device_hash: Value, slot_index: Value, slot_logic_type: LogicSlotType, value: Value },
+    Lbns {
+        dest: Destination, device_hash: Value, name_hash: Value, slot_index: Value,
+        slot_logic_type: LogicSlotType, batch_mode: BatchMode,
+    },
+    Get { dest: Destination, device: Device, stack_index: Value },
+    Put { device: Device, stack_index: Value, value: Value },
+    Getd { dest: Destination, id: Value, stack_index: Value },
+    Putd { id: Value, stack_index: Value, value: Value },
+    Sleep { duration: Value },
+    Select { dest: Destination, cond: Value, arg1: Value, arg2: Value },
+    Clr { device: Device },
+    Clrd { id: Value },
+}
+
+impl Instruction {
+    /// IC10 mnemonic of the instruction (empty for `Label` and `Noop`)
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Move { .. } => "move",
+            Instruction::Alias { .. } => "alias",
+            Instruction::Define { .. } => "define",
+            Instruction::Add { .. } => "add",
+            Instruction::Sub { .. } => "sub",
+            Instruction::Mul { .. } => "mul",
+            Instruction::Div { .. } => "div",
+            Instruction::Mod { .. } => "mod",
+            Instruction::Sqrt { .. } => "sqrt",
+            Instruction::Abs { .. } => "abs",
+            Instruction::Exp { .. } => "exp",
+            Instruction::Log { .. } => "log",
+            Instruction::Pow { .. } => "pow",
+            Instruction::Max { .. } => "max",
+            Instruction::Min { .. } => "min",
+            Instruction::Ceil { .. } => "ceil",
+            Instruction::Floor { .. } => "floor",
+            Instruction::Round { .. } => "round",
+            Instruction::Trunc { .. } => "trunc",
+            Instruction::Rand { .. } => "rand",
+            Instruction::Lerp { .. } => "lerp",
+            Instruction::Sin { .. } => "sin",
+            Instruction::Cos { .. } => "cos",
+            Instruction::Tan { .. } => "tan",
+            Instruction::Asin { .. } => "asin",
+            Instruction::Acos { .. } => "acos",
+            Instruction::Atan { .. } => "atan",
+            Instruction::Atan2 { .. } => "atan2",
+            Instruction::And { .. } => "and",
+            Instruction::Or { .. } => "or",
+            Instruction::Xor { .. } => "xor",
+            Instruction::Nor { .. } => "nor",
+            Instruction::Not { .. } => "not",
+            Instruction::Sll { .. } => "sll",
+            Instruction::Sla { .. } => "sla",
+            Instruction::Srl { .. } => "srl",
+            Instruction::Sra { .. } => "sra",
+            Instruction::Ext { .. } => "ext",
+            Instruction::Ins { .. } => "ins",
+            Instruction::Slt { .. } => "slt",
+            Instruction::Sgt { .. } => "sgt",
+            Instruction::Sle { .. } => "sle",
+            Instruction::Sge { .. } => "sge",
+            Instruction::Seq { .. } => "seq",
+            Instruction::Sne { .. } => "sne",
+            Instruction::Sltz { .. } => "sltz",
+            Instruction::Sgtz { .. } => "sgtz",
+            Instruction::Slez { .. } => "slez",
+            Instruction::Sgez { .. } => "sgez",
+            Instruction::Seqz { .. } => "seqz",
+            Instruction::Snez { .. } => "snez",
+            Instruction::Snan { .. } => "snan",
+            Instruction::Snanz { .. } => "snanz",
+            Instruction::Sap { .. } => "sap",
+            Instruction::Sna { .. } => "sna",
+            Instruction::Sapz { .. } => "sapz",
+            Instruction::Snaz { .. } => "snaz",
+            Instruction::Sdse { .. } => "sdse",
+            Instruction::Sdns { .. } => "sdns",
+            Instruction::Beq { .. } => "beq",
+            Instruction::Bne { .. } => "bne",
+            Instruction::Blt { .. } => "blt",
+            Instruction::Bgt { .. } => "bgt",
+            Instruction::Ble { .. } => "ble",
+            Instruction::Bge { .. } => "bge",
+            Instruction::Beqz { .. } => "beqz",
+            Instruction::Bnez { .. } => "bnez",
+            Instruction::Bltz { .. } => "bltz",
+            Instruction::Bgez { .. } => "bgez",
+            Instruction::Blez { .. } => "blez",
+            Instruction::Bgtz { .. } => "bgtz",
+            Instruction::Bnan { .. } => "bnan",
+            Instruction::Breq { .. } => "breq",
+            Instruction::Brne { .. } => "brne",
+            Instruction::Brlt { .. } => "brlt",
+            Instruction::Brgt { .. } => "brgt",
+            Instruction::Brle { .. } => "brle",
+            Instruction::Brge { .. } => "brge",
+            Instruction::Breqz { .. } => "breqz",
+            Instruction::Brnez { .. } => "brnez",
+            Instruction::Brltz { .. } => "brltz",
+            Instruction::Brgez { .. } => "brgez",
+            Instruction::Brlez { .. } => "brlez",
+            Instruction::Brgtz { .. } => "brgtz",
+            Instruction::Brnan { .. } => "brnan",
+            Instruction::Beqal { .. } => "beqal",
+            Instruction::Bneal { .. } => "bneal",
+            Instruction::Bltal { .. } => "bltal",
+            Instruction::Bgtal { .. } => "bgtal",
+            Instruction::Bleal { .. } => "bleal",
+            Instruction::Bgeal { .. } => "bgeal",
+            Instruction::Beqzal { .. } => "beqzal",
+            Instruction::Bnezal { .. } => "bnezal",
+            Instruction::Bltzal { .. } => "bltzal",
+            Instruction::Bgezal { .. } => "bgezal",
+            Instruction::Blezal { .. } => "blezal",
+            Instruction::Bgtzal { .. } => "bgtzal",
+            Instruction::Bap { .. } => "bap",
+            Instruction::Bna { .. } => "bna",
+            Instruction::Brap { .. } => "brap",
+            Instruction::Brna { .. } => "brna",
+            Instruction::Bapz { .. } => "bapz",
+            Instruction::Bnaz { .. } => "bnaz",
+            Instruction::Brapz { .. } => "brapz",
+            Instruction::Brnaz { .. } => "brnaz",
+            Instruction::Bapal { .. } => "bapal",
+            Instruction::Bnaal { .. } => "bnaal",
+            Instruction::Bapzal { .. } => "bapzal",
+            Instruction::Bnazal { .. } => "bnazal",
+            Instruction::Bdse { .. } => "bdse",
+            Instruction::Bdns { .. } => "bdns",
+            Instruction::Brdse { .. } => "brdse",
+            Instruction::Brdns { .. } => "brdns",
+            Instruction::Bdseal { .. } => "bdseal",
+            Instruction::Bdnsal { .. } => "bdnsal",
+            Instruction::Bdnvl { .. } => "bdnvl",
+            Instruction::Bdnvs { .. } => "bdnvs",
+            Instruction::J { .. } => "j",
+            Instruction::Jr { .. } => "jr",
+            Instruction::Jal { .. } => "jal",
+            Instruction::Push { .. } => "push",
+            Instruction::Pop { .. } => "pop",
+            Instruction::Peek { .. } => "peek",
+            Instruction::Poke { .. } => "poke",
+            Instruction::L { .. } => "l",
+            Instruction::S { .. } => "s",
+            Instruction::Ls { .. } => "ls",
+            Instruction::Ss { .. } => "ss",
+            Instruction::Lr { .. } => "lr",
+            Instruction::Rmap { .. } => "rmap",
+            Instruction::Ld { .. } => "ld",
+            Instruction::Sd { .. } => "sd",
+            Instruction::Lb { .. } => "lb",
+            Instruction::Sb { .. } => "sb",
+            Instruction::Lbn { .. } => "lbn",
+            Instruction::Sbn { .. } => "sbn",
+            Instruction::Lbs { .. } => "lbs",
+            Instruction::Sbs { .. } => "sbs",
+            Instruction::Lbns { .. } => "lbns",
+            Instruction::Get { .. } => "get",
+            Instruction::Put { .. } => "put",
+            Instruction::Getd { .. } => "getd",
+            Instruction::Putd { .. } => "putd",
+            Instruction::Yield => "yield",
+            Instruction::Sleep { .. } => "sleep",
+            Instruction::Hcf => "hcf",
+            Instruction::Select { .. } => "select",
+            Instruction::Clr { .. } => "clr",
+            Instruction::Clrd { .. } => "clrd",
+            Instruction::Label { .. } | Instruction::Noop => "",
+        }
+    }
+
+    /// Render the instruction as canonical IC10 source.
+    ///
+    /// Registers print as `rN` (`sp`/`ra` for the stack pointer and return address), device
+    /// pins as `dN`, aliases by name and immediates as integers when whole. Operands that take
+    /// a logic type, slot logic type, batch mode or reagent mode print the name of the value
+    /// they select, or the number when it names nothing. `Label` renders as `name:` and `Noop`
+    /// as an empty line.
+    pub fn to_source(&self) -> String {
+        let mut parts = vec![self.mnemonic().to_string()];
+        match self {
+            Instruction::Alias { name, target } => {
+                parts.push(name.clone());
+                parts.push(match target {
+                    AliasTarget::Register(idx) => register_source(*idx),
+                    AliasTarget::Device(pin) => format!("d{pin}"),
+                    AliasTarget::Alias(alias) => alias.clone(),
+                });
+            }
+            Instruction::Define { name, value } => {
+                parts.push(name.clone());
+                parts.push(immediate_source(*value));
+            }
+            Instruction::Label { name } => return format!("{name}:"),
+            Instruction::Noop => return String::new(),
+            _ => self
+                .clone()
+                .for_each_operand_mut(|role, operand| parts.push(operand_source(role, operand))),
+        }
+        parts.join(" ")
+    }
+}
+
+/// Render a register index, using the `sp`/`ra` names for the fixed registers
+fn register_source(idx: usize) -> String {
+    match idx {
+        STACK_POINTER_INDEX => "sp".to_string(),
+        RETURN_ADDRESS_INDEX => "ra".to_string(),
+        _ => format!("r{idx}"),
+    }
+}
+
+/// Render an immediate so it parses back to the same value (`3`, `0.5`, `nan`, `-inf`)
+fn immediate_source(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else {
+        format!("{value}")
+    }
+}
+
+/// Name of the enum value an immediate selects, for operands whose role takes one
+fn enum_name_source(role: OperandRole, value: f64) -> Option<String> {
+    if value.fract() != 0.0 {
+        return None;
+    }
+    match role {
+        OperandRole::LogicType => LogicType::from_value(value).map(|v| format!("{v:?}")),
+        OperandRole::LogicSlotType => LogicSlotType::from_value(value).map(|v| format!("{v:?}")),
+        OperandRole::BatchMode => BatchMode::from_value(value).map(|v| format!("{v:?}")),
+        OperandRole::ReagentMode => ReagentMode::from_value(value).map(|v| format!("{v:?}")),
+        OperandRole::Destination | OperandRole::Device | OperandRole::Value => None,
+    }
+}
+
+/// Render an operand as IC10 source
+fn operand_source(role: OperandRole, operand: &Operand) -> String {
+    match operand {
+        Operand::Register(idx) => register_source(*idx),
+        Operand::Immediate(value) => {
+            enum_name_source(role, *value).unwrap_or_else(|| immediate_source(*value))
+        }
+        Operand::Alias(name) => name.clone(),
+        Operand::DevicePin(pin) => format!("d{pin}"),
+    }
+}
+
+/// Parse a register name (r0-r17, or the fixed `sp`/`ra` registers) to its index
+fn parse_register_name(token: &str) -> Option<usize> {
+    match token {
+        "sp" => Some(STACK_POINTER_INDEX),
+        "ra" => Some(RETURN_ADDRESS_INDEX),
+        _ => token
+            .strip_prefix('r')
+            .and_then(|stripped| stripped.parse::<usize>().ok())
+            .filter(|&idx| idx < REGISTER_COUNT),
+    }
+}
+
+/// Reject operands written as registers (`r<digits>`) whose index is out of range, instead
+/// of letting them fall through to an alias lookup at runtime
+fn check_register_operands(tokens: &[&str], line: usize) -> SimulationResult<()> {
+    for token in tokens {
+        if let Some(digits) = token.strip_prefix('r')
+            && !digits.is_empty()
+            && digits.chars().all(|c| c.is_ascii_digit())
+            && digits
+                .parse::<usize>()
+                .map_or(true, |idx| idx >= REGISTER_COUNT)
+        {
+            return Err(SimulationError::IC10ParseError {
+                line,
+                message: format!(
+                    "Register index out of range (r0-r{}): {token}",
+                    REGISTER_COUNT - 1
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Parse a destination operand (register or alias)
 fn parse_dest_operand(token: &str) -> Operand {
-    // Try to parse as a register first
-    if let Some(stripped) = token.strip_prefix('r')
-        && let Ok(idx) = stripped.parse::<usize>()
-        && idx < REGISTER_COUNT
-    {
+    // Try to parse as a register first (including sp/ra)
+    if let Some(idx) = parse_register_name(token) {
         return Operand::Register(idx);
     }
-    // Otherwise treat as an alias (including sp/ra)
+    // Otherwise treat as an alias
     Operand::Alias(token.to_string())
 }
 
@@ -728,10 +1114,8 @@ fn parse_operand(token: &str) -> Operand {
         return Operand::DevicePin(idx);
     }
 
-    if let Some(stripped) = token.strip_prefix('r')
-        && let Ok(idx) = stripped.parse::<usize>()
-        && idx < REGISTER_COUNT
-    {
+    // Registers, including the stack pointer (sp) and return address (ra)
+    if let Some(idx) = parse_register_name(token) {
         return Operand::Register(idx);
     }
     if let Ok(val) = token.parse::<f64>() {
@@ -767,18 +1151,27 @@ fn parse_batch_mode_operand(token: &str) -> Operand {
     parse_operand(token)
 }
 
-fn parse_alias_target(token: &str) -> SimulationResult<AliasTarget> {
+/// Parse an operand that could be a ReagentMode name
+fn parse_reagent_mode_operand(token: &str) -> Operand {
+    // Check if this token matches a ReagentMode name
+    if let Some(reagent_mode) = ReagentMode::from_name(token) {
+        return Operand::Immediate((reagent_mode as i32) as f64);
+    }
+    parse_operand(token)
+}
+
+fn parse_alias_target(token: &str, line: usize) -> SimulationResult<AliasTarget> {
     // Match only register aliases
     if token.len() > 1 && token.starts_with('r') && token[1..].chars().all(|c| c.is_ascii_digit()) {
         let idx = token[1..]
             .parse::<usize>()
             .map_err(|_| SimulationError::IC10ParseError {
-                line: 0,
+                line,
                 message: format!("Invalid register for alias: {token}"),
             })?;
         if idx >= REGISTER_COUNT {
             return Err(SimulationError::IC10ParseError {
-                line: 0,
+                line,
                 message: format!("Register index out of range (r0-r17): {token}"),
             });
         }
@@ -791,7 +1184,7 @@ fn parse_alias_target(token: &str) -> SimulationResult<AliasTarget> {
         let idx = token[1..]
             .parse::<usize>()
             .map_err(|_| SimulationError::IC10ParseError {
-                line: 0,
+                line,
                 message: format!("Invalid device for alias: {token}"),
             })?;
         // Store as i32 (will be interpreted as pin index during execution and resolved to ref ID)
@@ -801,32 +1194,229 @@ fn parse_alias_target(token: &str) -> SimulationResult<AliasTarget> {
         Ok(AliasTarget::Alias(token.to_string()))
     } else {
         Err(SimulationError::IC10ParseError {
-            line: 0,
+            line,
             message: format!("Invalid alias target: {token}"),
         })
     }
 }
 
+/// Evaluate a `define` value: a number, or an expression of `+ - * /`, unary signs and
+/// parentheses over numbers and constants.
+///
+/// Identifiers are looked up in `defines` first, then in the builtin constants (whose names
+/// also match case-insensitively, so `PI` is `pi`). Division follows `div`, so dividing by
+/// zero gives an infinity or NaN rather than an error. Returns a description of the problem
+/// for unknown identifiers and malformed expressions.
+pub fn evaluate_constant_expression(
+    expression: &str,
+    defines: &HashMap<String, f64>,
+) -> Result<f64, String> {
+    // Plain numbers keep the exact `f64` parsing used for single-token defines
+    if let Ok(value) = expression.trim().parse::<f64>() {
+        return Ok(value);
+    }
+
+    let mut evaluator = ConstantExpression {
+        chars: expression.chars().collect(),
+        pos: 0,
+        defines,
+        builtins: get_builtin_constants(),
+    };
+    let value = evaluator.sum()?;
+    evaluator.skip_whitespace();
+    match evaluator.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected '{c}' in '{expression}'")),
+    }
+}
+
+/// Recursive descent evaluator behind `evaluate_constant_expression`
+struct ConstantExpression<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    defines: &'a HashMap<String, f64>,
+    builtins: HashMap<String, f64>,
+}
+
+impl ConstantExpression<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('+') => {
+                    self.pos += 1;
+                    value += self.product()?;
+                }
+                Some('-') => {
+                    self.pos += 1;
+                    value -= self.product()?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    /// product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('*') => {
+                    self.pos += 1;
+                    value *= self.unary()?;
+                }
+                Some('/') => {
+                    self.pos += 1;
+                    value /= self.unary()?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    /// unary := ('+' | '-') unary | primary
+    fn unary(&mut self) -> Result<f64, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.primary(),
+        }
+    }
+
+    /// primary := number | identifier | '(' sum ')'
+    fn primary(&mut self) -> Result<f64, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.sum()?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    return Err("missing ')'".to_string());
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_alphanumeric() || c == '.' || c == '_' => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    // Keep the sign of an exponent such as `1e-3` with its number
+                    let exponent_sign = (c == '+' || c == '-')
+                        && self.chars[start].is_ascii_digit()
+                        && matches!(self.chars[self.pos - 1], 'e' | 'E');
+                    if !(c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                let token: String = self.chars[start..self.pos].iter().collect();
+                self.token_value(&token)
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn token_value(&self, token: &str) -> Result<f64, String> {
+        if token.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            return token
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number '{token}'"));
+        }
+        self.defines
+            .get(token)
+            .or_else(|| self.builtins.get(token))
+            .or_else(|| self.builtins.get(&token.to_lowercase()))
+            .copied()
+            .ok_or_else(|| format!("unknown identifier '{token}'"))
+    }
+}
+
 /// A parsed instruction with metadata
 #[derive(Debug, Clone)]
 pub struct ParsedInstruction {
+    /// The parsed instruction
     pub instruction: Instruction,
+    /// 0-based line index in the program (the instruction's address)
     pub line_number: usize,
+    /// The source text of the line
     pub original_line: String,
 }
 
 impl ParsedInstruction {
+    /// The source text of the line
+    pub fn source(&self) -> &str {
+        &self.original_line
+    }
+
+    /// 1-based line number, as shown in editors
+    pub fn line(&self) -> usize {
+        self.line_number + 1
+    }
+
+    /// Byte range of the opcode token within `source`, or `None` for blank, comment and
+    /// label lines
+    pub fn opcode_span(&self) -> Option<Range<usize>> {
+        let line = self.original_line.trim();
+        if line.is_empty() || line.starts_with('#') || line.ends_with(':') {
+            return None;
+        }
+
+        let start = self.original_line.len() - self.original_line.trim_start().len();
+        let opcode = line.split_whitespace().next()?;
+        Some(start..start + opcode.len())
+    }
+
     /// Parse a single line of IC10 code into a ParsedInstruction
     pub fn parse(line: &str, line_number: usize) -> SimulationResult<Self> {
+        Self::parse_with_defines(line, line_number, &HashMap::new())
+    }
+
+    /// Parse a single line like `parse`, letting a `define` expression refer to the constants
+    /// in `defines` (typically the defines on earlier lines) as well as the builtin constants
+    pub fn parse_with_defines(
+        line: &str,
+        line_number: usize,
+        defines: &HashMap<String, f64>,
+    ) -> SimulationResult<Self> {
         let original_line = line.to_string();
         let line = line.trim();
-        if line.is_empty() || line.starts_with('#') || line.ends_with(':') {
+        if line.is_empty() || line.starts_with('#') {
             return Ok(ParsedInstruction {
                 instruction: Instruction::Noop,
                 line_number,
                 original_line,
             });
         }
+        if let Some(name) = line.strip_suffix(':') {
+            return Ok(ParsedInstruction {
+                instruction: Instruction::Label {
+                    name: name.trim().to_string(),
+                },
+                line_number,
+                original_line,
+            });
+        }
 
         let tokens: Vec<&str> = line.split_whitespace().collect();
         if tokens.is_empty() {
@@ -837,6 +1427,8 @@ impl ParsedInstruction {
             });
         }
 
+        check_register_operands(&tokens[1..], line_number)?;
+
         match tokens[0].to_lowercase().as_str() {
             // ==================== Data Movement ====================
             "move" => {
@@ -864,7 +1456,7 @@ impl ParsedInstruction {
                     });
                 }
                 let name = tokens[1].to_string();
-                let target = parse_alias_target(tokens[2])?;
+                let target = parse_alias_target(tokens[2], line_number)?;
                 Ok(ParsedInstruction {
                     instruction: Instruction::Alias { name, target },
                     line_number,
@@ -872,7 +1464,7 @@ impl ParsedInstruction {
                 })
             }
             "define" => {
-                if tokens.len() != 3 {
+                if tokens.len() < 3 {
                     return Err(SimulationError::IncorrectArgumentCount {
                         instruction: "define".to_string(),
                         expected: 2,
@@ -880,13 +1472,13 @@ impl ParsedInstruction {
                     });
                 }
                 let name = tokens[1].to_string();
-                let value =
-                    tokens[2]
-                        .parse::<f64>()
-                        .map_err(|_| SimulationError::IC10ParseError {
-                            line: line_number,
-                            message: format!("Invalid value for define: {}", tokens[2]),
-                        })?;
+                let expression = tokens[2..].join(" ");
+                let value = evaluate_constant_expression(&expression, defines).map_err(|e| {
+                    SimulationError::IC10ParseError {
+                        line: line_number,
+                        message: format!("Invalid value for define {name}: {e}"),
+                    }
+                })?;
                 Ok(ParsedInstruction {
                     instruction: Instruction::Define { name, value },
                     line_number,
@@ -1938,7 +2530,7 @@ impl ParsedInstruction {
                 }
                 let dest = parse_dest_operand(tokens[1]);
                 let device = parse_operand(tokens[2]);
-                let reagent_mode = parse_operand(tokens[3]);
+                let reagent_mode = parse_reagent_mode_operand(tokens[3]);
                 let reagent = parse_operand(tokens[4]);
                 Ok(ParsedInstruction {
                     instruction: Instruction::Lr {
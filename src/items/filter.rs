@@ -15,6 +15,18 @@ pub enum FilterSize {
     Infinite,
 }
 
+impl FilterSize {
+    /// Filter life consumed per mole of gas filtered
+    pub fn degradation_per_mole(&self) -> f64 {
+        match self {
+            FilterSize::Small => 0.1,
+            FilterSize::Medium => 0.05,
+            FilterSize::Large => 0.025,
+            FilterSize::Infinite => 0.0,
+        }
+    }
+}
+
 /// Filter item for filtration devices
 #[derive(Debug)]
 pub struct Filter {
@@ -71,6 +83,21 @@ impl Filter {
         }
     }
 
+    /// Remaining filter life, including partially consumed units
+    pub fn life(&self) -> f64 {
+        self.quantity
+    }
+
+    /// Whether the filter has no life left
+    pub fn is_spent(&self) -> bool {
+        self.quantity <= 0.0
+    }
+
+    /// Consume filter life for `moles` of filtered gas
+    pub fn degrade(&mut self, moles: f64) {
+        self.quantity = (self.quantity - moles * self.size.degradation_per_mole()).max(0.0);
+    }
+
     /// Compute prefab hash for `gas` and `size`
     pub fn prefab_hash_for(gas: GasType, size: FilterSize) -> i32 {
         let gas_name = gas.filter_name();
@@ -126,8 +153,27 @@ impl Item for Filter {
         100
     }
 
-    fn merge(&mut self, _other: &mut dyn Item) -> bool {
-        false
+    /// Filters only stack with filters of the same gas type and size. Life moves from `other`
+    /// into this filter until it is full; whatever does not fit stays in `other`.
+    fn merge(&mut self, other: &mut dyn Item) -> bool {
+        let Some(other) = other.as_any_mut().downcast_mut::<Filter>() else {
+            return false;
+        };
+        if other.gas_type != self.gas_type || other.size != self.size {
+            return false;
+        }
+
+        let moved = other
+            .quantity
+            .min(self.max_quantity() as f64 - self.quantity)
+            .max(0.0);
+        if moved <= 0.0 {
+            return false;
+        }
+
+        self.quantity += moved;
+        other.quantity -= moved;
+        true
     }
 
     fn as_any(&self) -> &dyn Any {
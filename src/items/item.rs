@@ -1,5 +1,6 @@
 //! Item trait and types
 
+use crate::types::MaybeSend;
 use std::any::Any;
 use std::fmt::Debug;
 use std::str::FromStr;
@@ -11,6 +12,8 @@ pub enum ItemType {
     ItemIntegratedCircuit10,
     /// Filter for filtration devices
     Filter,
+    /// Seed planted in hydroponics devices
+    Seed,
 }
 
 impl ItemType {
@@ -19,6 +22,7 @@ impl ItemType {
         match self {
             ItemType::ItemIntegratedCircuit10 => "ItemIntegratedCircuit10",
             ItemType::Filter => "Filter",
+            ItemType::Seed => "Seed",
         }
     }
 }
@@ -30,13 +34,14 @@ impl FromStr for ItemType {
         match s {
             "ItemIntegratedCircuit10" => Ok(ItemType::ItemIntegratedCircuit10),
             "Filter" => Ok(ItemType::Filter),
+            "Seed" => Ok(ItemType::Seed),
             _ => Err(()),
         }
     }
 }
 
 /// Trait for items; supports quantity, prefab, and merging
-pub trait Item: Debug {
+pub trait Item: Debug + MaybeSend {
     /// Get the type of this item
     fn item_type(&self) -> ItemType;
 
@@ -1,6 +1,7 @@
 //! Item factory registry for item creation
 
 use crate::atmospherics::GasType;
+use crate::items::Seed;
 use crate::items::{FilterSize, SimulationItemSettings};
 use crate::types::{Shared, shared};
 use crate::{Filter, Item, ItemIntegratedCircuit10, ItemType, atmospherics, items};
@@ -140,6 +141,7 @@ fn initialize_item_factory() {
             "ItemIntegratedCircuit10",
             ItemType::ItemIntegratedCircuit10
         );
+        register_item!(Seed, "Potato Seeds", ItemType::Seed);
 
         // Register filter prefabs for all gas type + size combinations
         let gas_types = [
@@ -1,18 +1,40 @@
 //! IC10 programmable chip item implementation
 
-use crate::constants::{REGISTER_COUNT, RETURN_ADDRESS_INDEX, STACK_POINTER_INDEX, STACK_SIZE};
+use crate::constants::{
+    DEFAULT_TICKS_PER_SECOND, REGISTER_COUNT, RETURN_ADDRESS_INDEX, STACK_POINTER_INDEX, STACK_SIZE,
+};
 use crate::devices::ChipSlot;
 use crate::error::{SimulationError, SimulationResult};
 use crate::instruction::{Instruction, ParsedInstruction};
-use crate::items::SimulationItemSettings;
-use crate::parser::{preprocess, string_to_hash};
-use crate::types::{OptShared, OptWeakShared, Shared};
+use crate::items::{ReagentRegistry, SimulationItemSettings};
+use crate::parser::{Module, check_source_limits, compile_module_with_constants, string_to_hash};
+use crate::types::{OptShared, OptWeakShared, Shared, SharedPtr};
 use crate::{CableNetwork, Item, ItemType, get_builtin_constants};
 use crate::{LogicType, logic};
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::fmt::Debug;
+
+/// Callback invoked before each instruction executes with its line and instruction
+#[cfg(not(feature = "parallel"))]
+pub type TraceCallback = Box<dyn FnMut(usize, &Instruction)>;
+
+/// Callback invoked before each instruction executes with its line and instruction. The
+/// `parallel` feature requires it to be `Send` so chips can move between threads.
+#[cfg(feature = "parallel")]
+pub type TraceCallback = Box<dyn FnMut(usize, &Instruction) + Send>;
+
+/// Holder for an optional trace callback so the chip can keep deriving `Debug`
+#[derive(Default)]
+struct TraceHook(Option<TraceCallback>);
+
+impl Debug for TraceHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = if self.0.is_some() { "set" } else { "none" };
+        write!(f, "TraceHook({state})")
+    }
+}
 
 /// The IC10 programmable chip
 #[derive(Debug)]
@@ -23,8 +45,8 @@ pub struct ItemIntegratedCircuit10 {
     /// Program counter - current line being executed
     pc: RefCell<usize>,
 
-    /// Compiled program lines
-    program: RefCell<Vec<ParsedInstruction>>,
+    /// Compiled program lines, shared so execution can hold them without cloning instructions
+    program: RefCell<SharedPtr<[ParsedInstruction]>>,
 
     /// Aliases mapping names to register/device indices
     aliases: RefCell<HashMap<String, AliasTarget>>,
@@ -35,6 +57,9 @@ pub struct ItemIntegratedCircuit10 {
     /// Compile-time constants
     defines: RefCell<HashMap<String, f64>>,
 
+    /// Named constants registered with `add_constant`, available to every loaded program
+    constants: HashMap<String, f64>,
+
     /// Original source text
     source: RefCell<Option<String>>,
 
@@ -50,11 +75,51 @@ pub struct ItemIntegratedCircuit10 {
     /// Execution state
     halted: RefCell<bool>,
 
+    /// Whether the last run ended on `yield` or `sleep`
+    yielded: RefCell<bool>,
+
+    /// Whether the last run was spent counting down a sleep
+    asleep: RefCell<bool>,
+
     /// Remaining sleep ticks (if sleeping)
     sleep_ticks: RefCell<u64>,
 
+    /// Number of ticks the chip has been run for, used to report when a sleep ends
+    ticks_run: RefCell<u64>,
+
+    /// Simulation ticks per second of game time, used to convert `sleep` durations
+    ticks_per_second: f64,
+
+    /// Reagents `rmap` maps to prefab hashes
+    reagents: SharedPtr<ReagentRegistry>,
+
     /// Error state
     error_line: RefCell<Option<usize>>,
+
+    /// Optional callback invoked before each instruction (for external debuggers)
+    trace_callback: RefCell<TraceHook>,
+
+    /// Whether `load_program` enforces the game's program line count and line length limits
+    enforce_source_limits: bool,
+
+    /// Whether reading a known slot logic type a device does not track is an error (otherwise 0)
+    strict_logic: bool,
+
+    /// Whether `hcf` surfaces a runtime error in addition to halting the chip
+    hcf_errors: bool,
+}
+
+/// Why a chip is or is not advancing, as reported by `ItemIntegratedCircuit10::state`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipState {
+    /// The chip continues executing on its next run
+    Running,
+    /// The last run ended on `yield` (or a `sleep` shorter than a tick)
+    Yielded,
+    /// The chip is asleep and executes again on the run with this tick count (see `ticks_run`)
+    Sleeping { until_tick: u64 },
+    /// The chip has halted, by `hcf`, a runtime error or running past the last line
+    Halted,
 }
 
 /// Alias target - can reference a register or device
@@ -79,73 +144,106 @@ impl ItemIntegratedCircuit10 {
         Self {
             id: settings.id.unwrap(),
             pc: RefCell::new(0),
-            program: RefCell::new(Vec::new()),
+            program: RefCell::new(SharedPtr::from(Vec::new())),
             aliases: RefCell::new(aliases),
             labels: RefCell::new(HashMap::new()),
             defines: RefCell::new(get_builtin_constants()),
+            constants: HashMap::new(),
             source: RefCell::new(None),
             chip_slot: None,
             registers: RefCell::new([0.0; REGISTER_COUNT]),
             stack: RefCell::new([0.0; STACK_SIZE]),
             halted: RefCell::new(false),
             error_line: RefCell::new(None),
+            yielded: RefCell::new(false),
+            asleep: RefCell::new(false),
             sleep_ticks: RefCell::new(0),
+            ticks_run: RefCell::new(0),
+            ticks_per_second: DEFAULT_TICKS_PER_SECOND,
+            reagents: SharedPtr::default(),
+            trace_callback: RefCell::new(TraceHook::default()),
+            enforce_source_limits: true,
+            strict_logic: false,
+            hcf_errors: false,
         }
     }
 
     /// Load IC10 source code into the chip
     pub fn load_program(&mut self, source: &str) -> SimulationResult<()> {
-        self.program.borrow_mut().clear();
+        *self.program.borrow_mut() = SharedPtr::from(Vec::new());
         self.labels.borrow_mut().clear();
         *self.pc.borrow_mut() = 0;
         *self.halted.borrow_mut() = false;
+        *self.yielded.borrow_mut() = false;
+        *self.asleep.borrow_mut() = false;
         *self.error_line.borrow_mut() = None;
         *self.source.borrow_mut() = Some(source.to_string());
 
-        // Preprocess the source
-        let preprocessed = preprocess(source)?;
-
-        // First pass: collect labels
-        for (line_num, line) in preprocessed.lines().enumerate() {
-            let trimmed = line.trim();
-
-            // Check if this is a label (ends with ':')
-            if trimmed.ends_with(':') && !trimmed.starts_with('#') {
-                let label_name = trimmed[..trimmed.len() - 1].trim().to_string();
-                if self.labels.borrow().contains_key(&label_name) {
-                    return Err(SimulationError::IC10ParseError {
-                        line: line_num,
-                        message: format!("Duplicate label: {label_name}"),
-                    });
-                }
-                self.labels.borrow_mut().insert(label_name, line_num);
-            }
+        if self.enforce_source_limits {
+            check_source_limits(source)?;
         }
 
-        // Second pass: parse instructions
-        for (line_num, line) in preprocessed.lines().enumerate() {
-            let parsed = ParsedInstruction::parse(line, line_num)?;
+        // Report the first problem in line order
+        let module = compile_module_with_constants(source, &self.constants)
+            .map_err(|mut diagnostics| diagnostics.remove(0).error)?;
+        self.load_compiled(module);
 
-            // If this is an alias instruction for a device, validate the device pin
-            if let Instruction::Alias {
-                name: _,
-                target: AliasTarget::Device(pin_idx),
-            } = parsed.instruction
-            {
-                // pin_idx in parsed instruction is the pin index (stored as i32)
-                let pin = pin_idx as usize;
-                if pin >= 6 {
-                    return Err(SimulationError::IC10ParseError {
-                        line: line_num,
-                        message: format!("Device pin out of range: d{} (max d{})", pin, 6 - 1),
-                    });
-                }
-            }
+        Ok(())
+    }
 
-            self.program.borrow_mut().push(parsed);
-        }
+    /// Enable or disable the game's program size limits in `load_program` (enabled by default)
+    pub fn set_enforce_source_limits(&mut self, enforce: bool) {
+        self.enforce_source_limits = enforce;
+    }
 
-        Ok(())
+    /// Check whether `load_program` enforces the game's program size limits
+    pub fn enforces_source_limits(&self) -> bool {
+        self.enforce_source_limits
+    }
+
+    /// Enable or disable strict logic. When strict, `ls` of a slot logic type the device does
+    /// not track is a runtime error; otherwise it reads 0 like the game (disabled by default).
+    pub fn set_strict_logic(&mut self, strict: bool) {
+        self.strict_logic = strict;
+    }
+
+    /// Check whether strict logic is enabled
+    pub fn is_strict_logic(&self) -> bool {
+        self.strict_logic
+    }
+
+    /// Choose whether `hcf` returns a runtime error from `step`/`run` after halting the chip,
+    /// so a harness can detect it explicitly. By default `hcf` halts silently.
+    pub fn set_hcf_errors(&mut self, enabled: bool) {
+        self.hcf_errors = enabled;
+    }
+
+    /// Check whether `hcf` returns a runtime error
+    pub fn hcf_errors(&self) -> bool {
+        self.hcf_errors
+    }
+
+    /// Load a program compiled with `parser::compile_module`
+    pub fn load_compiled(&mut self, module: Module) {
+        let (source, program, labels) = module.into_parts();
+        *self.pc.borrow_mut() = 0;
+        *self.halted.borrow_mut() = false;
+        *self.yielded.borrow_mut() = false;
+        *self.asleep.borrow_mut() = false;
+        *self.error_line.borrow_mut() = None;
+        *self.source.borrow_mut() = Some(source);
+        *self.labels.borrow_mut() = labels;
+        *self.program.borrow_mut() = SharedPtr::from(program);
+    }
+
+    /// Set a callback invoked before each instruction executed by `step`/`run`
+    pub fn set_trace_callback(&self, callback: TraceCallback) {
+        self.trace_callback.borrow_mut().0 = Some(callback);
+    }
+
+    /// Remove the trace callback
+    pub fn clear_trace_callback(&self) {
+        self.trace_callback.borrow_mut().0 = None;
     }
 
     /// Execute a single instruction; returns whether executed
@@ -159,7 +257,14 @@ impl ItemIntegratedCircuit10 {
             return Ok(false);
         }
 
-        let instruction = &self.program.borrow()[*self.pc.borrow()].clone();
+        let program = SharedPtr::clone(&self.program.borrow());
+        let instruction = &program[*self.pc.borrow()];
+        *self.yielded.borrow_mut() = false;
+        *self.asleep.borrow_mut() = false;
+
+        if let Some(callback) = self.trace_callback.borrow_mut().0.as_mut() {
+            callback(*self.pc.borrow(), &instruction.instruction);
+        }
 
         match self.execute_instruction(instruction) {
             Ok(next_pc) => {
@@ -167,15 +272,20 @@ impl ItemIntegratedCircuit10 {
                 Ok(true)
             }
             Err(e) => {
-                *self.error_line.borrow_mut() = Some(*self.pc.borrow());
+                let line = *self.pc.borrow();
+                *self.error_line.borrow_mut() = Some(line);
                 *self.halted.borrow_mut() = true;
-                Err(e)
+                Err(e.at_runtime_line(line))
             }
         }
     }
 
-    /// Run up to `max_steps`, stopping at yield or sleep
+    /// Run up to `max_steps`, stopping at yield or sleep.
+    ///
+    /// `yield` and `sleep` end the tick right after executing. While asleep each call uses one
+    /// step to count down the remaining ticks and executes nothing else.
     pub fn run(&self, max_steps: usize) -> SimulationResult<usize> {
+        *self.ticks_run.borrow_mut() += 1;
         let mut steps = 0;
 
         while steps < max_steps {
@@ -189,15 +299,18 @@ impl ItemIntegratedCircuit10 {
             steps += 1;
             if *self.sleep_ticks.borrow() > 0 {
                 *self.sleep_ticks.borrow_mut() -= 1;
+                *self.asleep.borrow_mut() = true;
                 return Ok(steps);
             }
 
-            let current_instruction = self.program.borrow()[*self.pc.borrow()].clone();
+            let program = SharedPtr::clone(&self.program.borrow());
+            let current_instruction = &program[*self.pc.borrow()];
 
             self.step()?;
 
             match current_instruction.instruction {
                 Instruction::Yield | Instruction::Sleep { duration: _ } => {
+                    *self.yielded.borrow_mut() = true;
                     return Ok(steps);
                 }
                 _ => {}
@@ -370,18 +483,55 @@ impl ItemIntegratedCircuit10 {
         }
     }
 
+    /// Check whether the device referenced by `operand` is set, i.e. resolves to a device
+    /// connected to the chip's network. An unassigned device pin counts as not set.
+    pub(crate) fn device_is_set(&self, operand: &Operand) -> SimulationResult<bool> {
+        if let Operand::DevicePin(pin_idx) = operand
+            && self
+                .get_chip_slot()
+                .borrow()
+                .get_device_pin(*pin_idx)
+                .is_none()
+        {
+            return Ok(false);
+        }
+
+        let ref_id = self.resolve_device_ref_id(operand)?;
+        Ok(self.device_exists_by_id(ref_id))
+    }
+
     /// Get register value at `index`
     pub fn get_register(&self, index: usize) -> SimulationResult<f64> {
         if index >= REGISTER_COUNT {
-            return Err(SimulationError::RegisterOutOfBounds(index));
+            return Err(SimulationError::RegisterOutOfBounds { index, line: None });
         }
         Ok(self.registers.borrow()[index])
     }
 
+    /// Get a copy of all registers (r0-r15, sp, ra)
+    pub fn registers(&self) -> [f64; REGISTER_COUNT] {
+        *self.registers.borrow()
+    }
+
+    /// Get a copy of the current aliases, including the built-in `sp`, `ra` and `db`
+    pub fn aliases(&self) -> HashMap<String, AliasTarget> {
+        self.aliases.borrow().clone()
+    }
+
+    /// Get a copy of the loaded program's labels, mapping each name to the line it is on
+    pub fn labels(&self) -> HashMap<String, usize> {
+        self.labels.borrow().clone()
+    }
+
+    /// Get a copy of the current defines, including the built-in constants
+    pub fn defines(&self) -> HashMap<String, f64> {
+        self.defines.borrow().clone()
+    }
+
     /// Set register at `index` to `value`
     pub fn set_register(&self, index: usize, value: f64) -> SimulationResult<()> {
         if index >= REGISTER_COUNT {
-            return Err(SimulationError::RegisterOutOfBounds(index));
+            return Err(SimulationError::RegisterOutOfBounds { index, line: None });
         }
         self.registers.borrow_mut()[index] = value;
         Ok(())
@@ -390,7 +540,10 @@ impl ItemIntegratedCircuit10 {
     /// Read from stack memory at `address`
     pub fn read_stack(&self, address: usize) -> SimulationResult<f64> {
         if address >= STACK_SIZE {
-            return Err(SimulationError::StackOutOfBounds(address));
+            return Err(SimulationError::StackOutOfBounds {
+                index: address,
+                line: None,
+            });
         }
         Ok(self.stack.borrow()[address])
     }
@@ -398,7 +551,10 @@ impl ItemIntegratedCircuit10 {
     /// Write `value` into stack memory at `address`
     pub fn write_stack(&self, address: usize, value: f64) -> SimulationResult<()> {
         if address >= STACK_SIZE {
-            return Err(SimulationError::StackOutOfBounds(address));
+            return Err(SimulationError::StackOutOfBounds {
+                index: address,
+                line: None,
+            });
         }
         self.stack.borrow_mut()[address] = value;
         Ok(())
@@ -414,6 +570,39 @@ impl ItemIntegratedCircuit10 {
         self.registers.borrow_mut().fill(0.0);
     }
 
+    /// Restart the loaded program from scratch: zero the registers and stack, return to line 0
+    /// and clear the halted, yielded, sleeping and error state. The compiled program, aliases
+    /// and defines are kept.
+    pub fn reset(&self) {
+        self.clear_registers();
+        self.clear_stack();
+        *self.pc.borrow_mut() = 0;
+        *self.halted.borrow_mut() = false;
+        *self.yielded.borrow_mut() = false;
+        *self.asleep.borrow_mut() = false;
+        *self.sleep_ticks.borrow_mut() = 0;
+        *self.ticks_run.borrow_mut() = 0;
+        *self.error_line.borrow_mut() = None;
+    }
+
+    /// Register a named constant for programs loaded afterwards, as if every program began
+    /// with `define name value`. Overrides a builtin constant of the same name.
+    pub fn add_constant(&mut self, name: &str, value: f64) {
+        self.constants.insert(name.to_string(), value);
+        self.insert_define(name, value);
+    }
+
+    /// Get the constants available to programs: the builtins plus those added with `add_constant`
+    pub fn constants(&self) -> HashMap<String, f64> {
+        let mut constants = get_builtin_constants();
+        constants.extend(
+            self.constants
+                .iter()
+                .map(|(name, value)| (name.clone(), *value)),
+        );
+        constants
+    }
+
     /// Insert a compile-time define constant
     pub fn insert_define(&self, name: &str, value: f64) {
         self.defines.borrow_mut().insert(name.to_string(), value);
@@ -447,6 +636,27 @@ impl ItemIntegratedCircuit10 {
         *self.halted.borrow()
     }
 
+    /// Report whether the chip is running, yielded, asleep or halted
+    pub fn state(&self) -> ChipState {
+        let sleep_ticks = *self.sleep_ticks.borrow();
+        if *self.halted.borrow() {
+            ChipState::Halted
+        } else if sleep_ticks > 0 || *self.asleep.borrow() {
+            ChipState::Sleeping {
+                until_tick: *self.ticks_run.borrow() + sleep_ticks + 1,
+            }
+        } else if *self.yielded.borrow() {
+            ChipState::Yielded
+        } else {
+            ChipState::Running
+        }
+    }
+
+    /// Number of ticks the chip has been run for; each call to `run` counts as one tick
+    pub fn ticks_run(&self) -> u64 {
+        *self.ticks_run.borrow()
+    }
+
     /// Halt the chip
     pub fn halt(&self) {
         *self.halted.borrow_mut() = true;
@@ -467,6 +677,46 @@ impl ItemIntegratedCircuit10 {
         *self.sleep_ticks.borrow_mut() = ticks;
     }
 
+    /// Get the number of simulation ticks per second used by `sleep`
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks_per_second
+    }
+
+    /// Set the number of simulation ticks per second used by `sleep` (must be positive)
+    pub fn set_ticks_per_second(&mut self, ticks_per_second: f64) {
+        if ticks_per_second > 0.0 {
+            self.ticks_per_second = ticks_per_second;
+        }
+    }
+
+    /// Get the reagents `rmap` maps to prefab hashes
+    pub fn reagents(&self) -> &ReagentRegistry {
+        &self.reagents
+    }
+
+    /// Set the reagents `rmap` maps to prefab hashes
+    pub fn set_reagents(&mut self, reagents: SharedPtr<ReagentRegistry>) {
+        self.reagents = reagents;
+    }
+
+    /// Put the chip to sleep for `seconds` of game time, starting with the current tick.
+    ///
+    /// The chip resumes on the first tick at which the full duration has elapsed, so a
+    /// duration that is not a whole number of ticks is rounded up. Durations shorter than a
+    /// tick behave like `yield`.
+    pub fn sleep_for(&self, seconds: f64) {
+        let ticks = seconds * self.ticks_per_second;
+        // Ignore rounding noise so e.g. 0.3 * 10 does not round up to 4 ticks
+        let ticks = if (ticks - ticks.round()).abs() < 1e-9 {
+            ticks.round()
+        } else {
+            ticks.ceil()
+        };
+
+        // The tick that executed `sleep` counts toward the duration
+        *self.sleep_ticks.borrow_mut() = if ticks > 1.0 { (ticks - 1.0) as u64 } else { 0 };
+    }
+
     /// Get the `Shared<ChipSlot>` for this chip (clone of the internal Rc)
     pub fn get_chip_slot(&self) -> Shared<ChipSlot> {
         self.chip_slot.as_ref().unwrap().upgrade().unwrap().clone()
@@ -497,7 +747,7 @@ impl ItemIntegratedCircuit10 {
     /// Attach the chip to a `ChipSlot` and register self device aliases
     pub fn set_chip_slot(&mut self, slot: Shared<ChipSlot>, device_id: i32) {
         // Store weak slot reference
-        self.chip_slot = Some(Rc::downgrade(&slot));
+        self.chip_slot = Some(SharedPtr::downgrade(&slot));
 
         // Add a convenient alias `db` referencing the device itself
         self.add_device_alias("db".to_string(), device_id);
@@ -508,6 +758,12 @@ impl ItemIntegratedCircuit10 {
         self.get_chip_slot().borrow().get_network()
     }
 
+    /// Get a reference to the cable network (if connected), failing when the host device is
+    /// busy rather than reporting it as unconnected
+    pub fn try_get_network(&self) -> SimulationResult<OptShared<CableNetwork>> {
+        self.get_chip_slot().borrow().try_get_network()
+    }
+
     /// Print debug information: registers and non-zero stack values
     pub fn print_debug_info(&self) {
         println!(
@@ -1,21 +1,24 @@
 //! Item and slot system for devices
-use std::cell::{Ref, RefMut};
 use std::collections::HashSet;
 
 use crate::atmospherics::GasType;
 use crate::types::OptShared;
-use crate::types::Shared;
+use crate::types::{Shared, SharedRef, SharedRefMut};
 
 pub mod filter;
 pub mod item;
 pub mod item_factory;
 pub mod item_integrated_circuit_10;
+pub mod reagent;
+pub mod seed;
 
 pub use filter::Filter;
 pub use filter::FilterSize;
 pub use item::{Item, ItemType};
 pub use item_factory::{get_prefab_metadata, get_registered_item_prefabs};
-pub use item_integrated_circuit_10::ItemIntegratedCircuit10;
+pub use item_integrated_circuit_10::{ChipState, ItemIntegratedCircuit10};
+pub use reagent::{Reagent, ReagentRegistry};
+pub use seed::Seed;
 
 /// Settings used when creating items during simulation. Fields are optional and
 /// when provided will be applied during initialization.
@@ -62,6 +65,21 @@ impl Slot {
         }
     }
 
+    /// Create a slot that only accepts the listed item types (any type when empty)
+    pub fn with_allowed_types(item_types: &[ItemType]) -> Self {
+        Self {
+            allowed_types: item_types.iter().copied().collect(),
+            ..Self::new(None)
+        }
+    }
+
+    /// Maximum quantity the slot can hold of its current item (0 when empty)
+    pub fn max_quantity(&self) -> u32 {
+        self.item
+            .as_ref()
+            .map_or(0, |item| item.borrow().max_quantity())
+    }
+
     /// Try to insert an item into the slot.
     ///
     /// On complete merge returns `Ok(())`.
@@ -126,10 +144,7 @@ impl Slot {
     pub fn available_space(&self) -> u32 {
         match &self.item {
             None => u32::MAX,
-            Some(item) => item
-                .borrow()
-                .max_quantity()
-                .saturating_sub(item.borrow().quantity()),
+            Some(item) => self.max_quantity().saturating_sub(item.borrow().quantity()),
         }
     }
 
@@ -160,12 +175,12 @@ impl Slot {
     }
 
     /// Borrow the item in the slot as type T, if it matches
-    pub fn borrow_item<T: Item + 'static>(&self) -> Option<Ref<'_, T>> {
+    pub fn borrow_item<T: Item + 'static>(&self) -> Option<SharedRef<'_, T>> {
         let shared = self.item.as_ref()?;
         let borrow = shared.borrow();
 
         if borrow.as_any().is::<T>() {
-            Some(Ref::map(borrow, |item| {
+            Some(SharedRef::map(borrow, |item| {
                 item.as_any().downcast_ref::<T>().unwrap()
             }))
         } else {
@@ -174,12 +189,12 @@ impl Slot {
     }
 
     /// Borrow the item in the slot as type T mutably, if it matches
-    pub fn borrow_item_mut<T: Item + 'static>(&self) -> Option<RefMut<'_, T>> {
+    pub fn borrow_item_mut<T: Item + 'static>(&self) -> Option<SharedRefMut<'_, T>> {
         let shared = self.item.as_ref()?;
         let borrow = shared.borrow_mut();
 
         if borrow.as_any().is::<T>() {
-            Some(RefMut::map(borrow, |item| {
+            Some(SharedRefMut::map(borrow, |item| {
                 item.as_any_mut().downcast_mut::<T>().unwrap()
             }))
         } else {
@@ -0,0 +1,82 @@
+//! Reagent registry mapping reagent hashes to item prefabs
+
+use crate::parser::string_to_hash;
+use std::collections::HashMap;
+
+/// A reagent known to the simulation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reagent {
+    /// Reagent name (e.g. "Iron")
+    pub name: String,
+    /// Hash of the reagent name
+    pub hash: i32,
+    /// Prefab hash of the item that supplies this reagent in recipes
+    pub prefab_hash: i32,
+}
+
+/// Built-in reagents as (reagent name, supplying item prefab name)
+const BUILTIN_REAGENTS: &[(&str, &str)] = &[
+    ("Iron", "ItemIronIngot"),
+    ("Copper", "ItemCopperIngot"),
+    ("Gold", "ItemGoldIngot"),
+    ("Silver", "ItemSilverIngot"),
+    ("Lead", "ItemLeadIngot"),
+    ("Nickel", "ItemNickelIngot"),
+    ("Silicon", "ItemSiliconIngot"),
+    ("Steel", "ItemSteelIngot"),
+    ("Constantan", "ItemConstantanIngot"),
+    ("Electrum", "ItemElectrumIngot"),
+    ("Invar", "ItemInvarIngot"),
+    ("Solder", "ItemSolderIngot"),
+];
+
+/// Reagents known to a simulation, keyed by reagent hash and seeded with the built-in
+/// reagents. Callers can register custom reagents on top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReagentRegistry {
+    reagents: HashMap<i32, Reagent>,
+}
+
+impl Default for ReagentRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            reagents: HashMap::new(),
+        };
+        for (name, prefab) in BUILTIN_REAGENTS {
+            registry.register(name, string_to_hash(prefab));
+        }
+        registry
+    }
+}
+
+impl ReagentRegistry {
+    /// Register a reagent by name, returning its hash. Replaces any existing entry with the
+    /// same hash.
+    pub fn register(&mut self, name: &str, prefab_hash: i32) -> i32 {
+        let hash = string_to_hash(name);
+        self.reagents.insert(
+            hash,
+            Reagent {
+                name: name.to_string(),
+                hash,
+                prefab_hash,
+            },
+        );
+        hash
+    }
+
+    /// Look up a reagent by hash
+    pub fn get(&self, hash: i32) -> Option<&Reagent> {
+        self.reagents.get(&hash)
+    }
+
+    /// Get the prefab hash of the item supplying the reagent with the given hash
+    pub fn prefab_hash(&self, hash: i32) -> Option<i32> {
+        self.reagents.get(&hash).map(|reagent| reagent.prefab_hash)
+    }
+
+    /// Get all registered reagent hashes
+    pub fn hashes(&self) -> Vec<i32> {
+        self.reagents.keys().copied().collect()
+    }
+}
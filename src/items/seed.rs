@@ -0,0 +1,105 @@
+//! Seed item implementation
+
+use super::item::{Item, ItemType};
+use crate::{items::SimulationItemSettings, parser::string_to_hash};
+use std::any::Any;
+
+/// Ticks of growth a seed needs to become mature
+pub const SEED_GROWTH_TICKS: f64 = 600.0;
+
+/// Ticks of growth a mature plant needs to produce seeds
+pub const SEED_SEEDING_TICKS: f64 = 300.0;
+
+/// Number of growth stages a plant passes through before it is mature
+pub const SEED_GROWTH_STAGES: u32 = 4;
+
+/// Seed item planted in hydroponics devices.
+///
+/// The seed tracks its own growth, so a plant keeps its progress when moved between devices.
+#[derive(Debug)]
+pub struct Seed {
+    id: i32,
+    /// Ticks of growth accumulated so far
+    growth_ticks: f64,
+}
+
+impl Seed {
+    /// Compile-time prefab hash constant for this item
+    pub const PREFAB_HASH: i32 = string_to_hash("SeedBag_Potato");
+
+    /// Create a new, freshly planted `Seed`
+    pub fn new(settings: SimulationItemSettings) -> Self {
+        Self {
+            id: settings.id.unwrap(),
+            growth_ticks: 0.0,
+        }
+    }
+
+    /// Advance growth by `ticks`
+    pub fn grow(&mut self, ticks: f64) {
+        self.growth_ticks += ticks.max(0.0);
+    }
+
+    /// Progress towards maturity in `[0, 1]`
+    pub fn maturity_ratio(&self) -> f64 {
+        (self.growth_ticks / SEED_GROWTH_TICKS).min(1.0)
+    }
+
+    /// Current growth stage, from 0 when planted to `SEED_GROWTH_STAGES` when mature
+    pub fn growth_stage(&self) -> u32 {
+        (self.maturity_ratio() * SEED_GROWTH_STAGES as f64).floor() as u32
+    }
+
+    /// Whether the plant is mature
+    pub fn is_mature(&self) -> bool {
+        self.maturity_ratio() >= 1.0
+    }
+
+    /// Progress of a mature plant towards producing seeds in `[0, 1]`
+    pub fn seeding_ratio(&self) -> f64 {
+        ((self.growth_ticks - SEED_GROWTH_TICKS) / SEED_SEEDING_TICKS).clamp(0.0, 1.0)
+    }
+
+    /// Whether the plant has produced seeds
+    pub fn is_seeding(&self) -> bool {
+        self.seeding_ratio() >= 1.0
+    }
+}
+
+impl Item for Seed {
+    fn item_type(&self) -> ItemType {
+        ItemType::Seed
+    }
+
+    fn get_id(&self) -> i32 {
+        self.id
+    }
+
+    fn get_prefab_hash(&self) -> i32 {
+        Self::PREFAB_HASH
+    }
+
+    fn quantity(&self) -> u32 {
+        1
+    }
+
+    fn set_quantity(&mut self, quantity: u32) -> bool {
+        quantity == 1
+    }
+
+    fn max_quantity(&self) -> u32 {
+        1
+    }
+
+    fn merge(&mut self, _other: &mut dyn Item) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
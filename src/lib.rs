@@ -21,11 +21,11 @@ pub mod types;
 pub mod tests;
 
 pub use constants::get_builtin_constants;
-pub use devices::{DaylightSensor, Device, ICHousing, LogicSlotType, LogicType};
+pub use devices::{DaylightSensor, Device, ICHostDevice, ICHousing, LogicSlotType, LogicType};
 pub use error::{SimulationError, SimulationResult};
 
 pub use instruction::Instruction;
-pub use items::{Filter, Item, ItemIntegratedCircuit10, ItemType, Slot};
+pub use items::{ChipState, Filter, Item, ItemIntegratedCircuit10, ItemType, Slot};
 pub use networks::{AtmosphericNetwork, BatchMode, CableNetwork};
 pub use simulation_manager::SimulationManager;
 
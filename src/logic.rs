@@ -2,11 +2,63 @@
 
 use crate::constants::{RETURN_ADDRESS_INDEX, STACK_POINTER_INDEX};
 use crate::conversions::{double_to_long, lerp, long_to_double};
+use crate::devices::ReagentMode;
 use crate::error::{SimulationError, SimulationResult};
 use crate::instruction::{Instruction, ParsedInstruction};
 use crate::items::item_integrated_circuit_10::AliasTarget;
 use crate::networks::BatchMode;
-use crate::{ItemIntegratedCircuit10, LogicSlotType, LogicType};
+use crate::types::{OptShared, Shared, SharedRef};
+use crate::{CableNetwork, Device, ItemIntegratedCircuit10, LogicSlotType, LogicType};
+
+/// Borrow the chip's cable network for an instruction, failing instead of panicking when it
+/// is already mutably borrowed elsewhere
+fn borrow_network(
+    network: &Shared<CableNetwork>,
+    line: usize,
+) -> SimulationResult<SharedRef<'_, CableNetwork>> {
+    network
+        .try_borrow()
+        .map_err(|_| SimulationError::RuntimeError {
+            message: "Cable network is busy and cannot be accessed".to_string(),
+            line,
+        })
+}
+
+/// The chip's cable network, `None` when it is not connected, reporting a busy host device
+/// as a runtime error on `line`
+fn chip_network(
+    chip: &ItemIntegratedCircuit10,
+    line: usize,
+) -> SimulationResult<OptShared<CableNetwork>> {
+    chip.try_get_network().map_err(|e| e.at_runtime_line(line))
+}
+
+/// The chip's cable network, reporting a chip that is not connected or a busy host device as
+/// a runtime error on `line`
+fn require_network(
+    chip: &ItemIntegratedCircuit10,
+    line: usize,
+) -> SimulationResult<Shared<CableNetwork>> {
+    chip_network(chip, line)?.ok_or(SimulationError::RuntimeError {
+        message: "Chip not connected to network".to_string(),
+        line,
+    })
+}
+
+/// Borrow device `ref_id` on `network` (see `CableNetwork::try_get_device`), reporting a busy
+/// device as a runtime error on `line`
+fn borrow_device(
+    network: &CableNetwork,
+    ref_id: i32,
+    line: usize,
+) -> SimulationResult<Option<SharedRef<'_, dyn Device>>> {
+    network
+        .try_get_device(ref_id)
+        .map_err(|e| SimulationError::RuntimeError {
+            message: e.message(),
+            line,
+        })
+}
 
 /// Execute a single IC10 instruction and return the next program counter
 pub fn execute_instruction(
@@ -227,33 +279,50 @@ pub fn execute_instruction(
         }
 
         // ==================== Bit Shifting Operations ====================
-        // Shift ops use double_to_long/long_to_double for IC10 53-bit compatibility
+        // Shift ops use double_to_long/long_to_double for IC10 53-bit compatibility. As with
+        // C# `long` shifts in game, only the low 6 bits of the amount are used, so a shift of 64
+        // leaves the value unchanged and a shift of 65 is a shift of 1.
         Instruction::Sll { dest, arg1, arg2 } => {
             let val = double_to_long(chip.resolve_value(arg1)?, true);
-            let shift = chip.resolve_value(arg2)? as i32;
-            chip.set_register(chip.resolve_register(dest)?, long_to_double(val << shift))?;
+            let shift = chip.resolve_value(arg2)? as i32 as u32;
+            chip.set_register(
+                chip.resolve_register(dest)?,
+                long_to_double(val.wrapping_shl(shift)),
+            )?;
             Ok(chip.get_pc() + 1)
         }
         Instruction::Sla { dest, arg1, arg2 } => {
             let val = double_to_long(chip.resolve_value(arg1)?, true);
-            let shift = chip.resolve_value(arg2)? as i32;
-            chip.set_register(chip.resolve_register(dest)?, long_to_double(val << shift))?;
+            let shift = chip.resolve_value(arg2)? as i32 as u32;
+            chip.set_register(
+                chip.resolve_register(dest)?,
+                long_to_double(val.wrapping_shl(shift)),
+            )?;
             Ok(chip.get_pc() + 1)
         }
         Instruction::Srl { dest, arg1, arg2 } => {
             let val = double_to_long(chip.resolve_value(arg1)?, false);
-            let shift = chip.resolve_value(arg2)? as i32;
-            chip.set_register(chip.resolve_register(dest)?, long_to_double(val >> shift))?;
+            let shift = chip.resolve_value(arg2)? as i32 as u32;
+            chip.set_register(
+                chip.resolve_register(dest)?,
+                long_to_double(val.wrapping_shr(shift)),
+            )?;
             Ok(chip.get_pc() + 1)
         }
         Instruction::Sra { dest, arg1, arg2 } => {
             let val = double_to_long(chip.resolve_value(arg1)?, true);
-            let shift = chip.resolve_value(arg2)? as i32;
-            chip.set_register(chip.resolve_register(dest)?, long_to_double(val >> shift))?;
+            let shift = chip.resolve_value(arg2)? as i32 as u32;
+            chip.set_register(
+                chip.resolve_register(dest)?,
+                long_to_double(val.wrapping_shr(shift)),
+            )?;
             Ok(chip.get_pc() + 1)
         }
 
         // ==================== Bit Field Operations ====================
+        // Fields address the 53-bit integer reinterpretation of the value (bits 0..=52). A
+        // negative start or length, a start past bit 52, or a field running past bit 52 is an
+        // error; a zero-length field is a no-op (`ext` yields 0, `ins` leaves `dest` as is).
         Instruction::Ext {
             dest,
             source,
@@ -264,9 +333,9 @@ pub fn execute_instruction(
             let start_bit = chip.resolve_value(start)? as i32;
             let num_bits = chip.resolve_value(length)? as i32;
 
-            if num_bits <= 0 {
+            if num_bits < 0 {
                 return Err(SimulationError::RuntimeError {
-                    message: "EXT: length must be > 0 (ShiftUnderflow)".to_string(),
+                    message: "EXT: length must be >= 0 (ShiftUnderflow)".to_string(),
                     line: instruction.line_number,
                 });
             }
@@ -289,6 +358,12 @@ pub fn execute_instruction(
                 });
             }
 
+            // A zero-length field holds no bits
+            if num_bits == 0 {
+                chip.set_register(chip.resolve_register(dest)?, 0.0)?;
+                return Ok(chip.get_pc() + 1);
+            }
+
             const MANTISSA_MASK: i64 = 0x1FFFFFFFFFFFFF;
 
             let length_mask = if num_bits == 53 {
@@ -313,9 +388,9 @@ pub fn execute_instruction(
             let bit_position = chip.resolve_value(start)? as i32;
             let num_bits = chip.resolve_value(length)? as i32;
 
-            if num_bits <= 0 {
+            if num_bits < 0 {
                 return Err(SimulationError::RuntimeError {
-                    message: "INS: bit count must be > 0 (ShiftUnderflow)".to_string(),
+                    message: "INS: bit count must be >= 0 (ShiftUnderflow)".to_string(),
                     line: instruction.line_number,
                 });
             }
@@ -338,6 +413,11 @@ pub fn execute_instruction(
                 });
             }
 
+            // Inserting a zero-length field leaves the destination untouched
+            if num_bits == 0 {
+                return Ok(chip.get_pc() + 1);
+            }
+
             const MANTISSA_MASK: u64 = 0x1FFFFFFFFFFFFF;
 
             let current_val =
@@ -364,6 +444,9 @@ pub fn execute_instruction(
         }
 
         // ==================== Comparison Operations ====================
+        // As in game, every ordered or equality comparison involving NaN is false, so `slt`,
+        // `sgt`, `sle`, `sge` and `seq` (and their `z` forms) set 0; `sne`/`snez` are the
+        // negation of `seq`/`seqz` and set 1. `select` treats a NaN condition as false.
         Instruction::Slt { dest, arg1, arg2 } => {
             let val1 = chip.resolve_value(arg1)?;
             let val2 = chip.resolve_value(arg2)?;
@@ -429,7 +512,11 @@ pub fn execute_instruction(
             let b = chip.resolve_value(arg2)?;
             chip.set_register(
                 chip.resolve_register(dest)?,
-                if cond_val != 0.0 { a } else { b },
+                if cond_val != 0.0 && !cond_val.is_nan() {
+                    a
+                } else {
+                    b
+                },
             )?;
             Ok(chip.get_pc() + 1)
         }
@@ -566,14 +653,12 @@ pub fn execute_instruction(
 
         // ==================== Device State Detection ====================
         Instruction::Sdse { dest, device } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            let exists = chip.device_exists_by_id(ref_id);
+            let exists = chip.device_is_set(device)?;
             chip.set_register(chip.resolve_register(dest)?, if exists { 1.0 } else { 0.0 })?;
             Ok(chip.get_pc() + 1)
         }
         Instruction::Sdns { dest, device } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            let exists = chip.device_exists_by_id(ref_id);
+            let exists = chip.device_is_set(device)?;
             chip.set_register(
                 chip.resolve_register(dest)?,
                 if !exists { 1.0 } else { 0.0 },
@@ -1153,8 +1238,7 @@ pub fn execute_instruction(
 
         // ==================== Device State Branches ====================
         Instruction::Bdse { device, line } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            if chip.device_exists_by_id(ref_id) {
+            if chip.device_is_set(device)? {
                 let target = chip.resolve_value(line)? as usize;
                 Ok(target)
             } else {
@@ -1162,8 +1246,7 @@ pub fn execute_instruction(
             }
         }
         Instruction::Bdns { device, line } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            if !chip.device_exists_by_id(ref_id) {
+            if !chip.device_is_set(device)? {
                 let target = chip.resolve_value(line)? as usize;
                 Ok(target)
             } else {
@@ -1171,8 +1254,7 @@ pub fn execute_instruction(
             }
         }
         Instruction::Brdse { device, offset } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            if chip.device_exists_by_id(ref_id) {
+            if chip.device_is_set(device)? {
                 let off = chip.resolve_value(offset)? as i32;
                 let target = (chip.get_pc() as i32 + off) as usize;
                 Ok(target)
@@ -1181,8 +1263,7 @@ pub fn execute_instruction(
             }
         }
         Instruction::Brdns { device, offset } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            if !chip.device_exists_by_id(ref_id) {
+            if !chip.device_is_set(device)? {
                 let off = chip.resolve_value(offset)? as i32;
                 let target = (chip.get_pc() as i32 + off) as usize;
                 Ok(target)
@@ -1191,8 +1272,7 @@ pub fn execute_instruction(
             }
         }
         Instruction::Bdseal { device, line } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            if chip.device_exists_by_id(ref_id) {
+            if chip.device_is_set(device)? {
                 chip.set_register(RETURN_ADDRESS_INDEX, (chip.get_pc() + 1) as f64)?;
                 let target = chip.resolve_value(line)? as usize;
                 Ok(target)
@@ -1201,8 +1281,7 @@ pub fn execute_instruction(
             }
         }
         Instruction::Bdnsal { device, line } => {
-            let ref_id = chip.resolve_device_ref_id(device)?;
-            if !chip.device_exists_by_id(ref_id) {
+            if !chip.device_is_set(device)? {
                 chip.set_register(RETURN_ADDRESS_INDEX, (chip.get_pc() + 1) as f64)?;
                 let target = chip.resolve_value(line)? as usize;
                 Ok(target)
@@ -1222,10 +1301,10 @@ pub fn execute_instruction(
 
             // Check if device can read this logic type
             let can_load = if let Some(lt) = logic_type_enum {
-                if let Some(network) = chip.get_network() {
-                    let network_ref = network.borrow();
+                if let Some(network) = chip_network(chip, instruction.line_number)? {
+                    let network_ref = borrow_network(&network, instruction.line_number)?;
 
-                    match network_ref.get_device(ref_id) {
+                    match borrow_device(&network_ref, ref_id, instruction.line_number)? {
                         Some(device) => device.can_read(lt),
                         None => false,
                     }
@@ -1255,10 +1334,10 @@ pub fn execute_instruction(
 
             // Check if device can write this logic type
             let can_store = if let Some(lt) = logic_type_enum {
-                if let Some(network) = chip.get_network() {
-                    let network_ref = network.borrow();
+                if let Some(network) = chip_network(chip, instruction.line_number)? {
+                    let network_ref = borrow_network(&network, instruction.line_number)?;
 
-                    match network_ref.get_device(ref_id) {
+                    match borrow_device(&network_ref, ref_id, instruction.line_number)? {
                         Some(device) => device.can_write(lt),
                         None => false,
                     }
@@ -1348,17 +1427,14 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             let value = device.read(logic_type)?;
 
             chip.set_register(chip.resolve_register(dest)?, value)?;
@@ -1378,17 +1454,14 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             device.write(logic_type, value)?;
             Ok(chip.get_pc() + 1)
         }
@@ -1407,19 +1480,29 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
 
-            let val = device.read_slot(slot_index, slot_logic)?;
+            let val = match device.read_slot(slot_index, slot_logic) {
+                Ok(val) => val,
+                // Known slot types the device does not track read as 0 on existing slots
+                Err(_)
+                    if !chip.is_strict_logic()
+                        && !device.supported_slot_types().contains(&slot_logic)
+                        && device
+                            .as_slot_host_device()
+                            .is_some_and(|host| slot_index < host.slot_count()) =>
+                {
+                    0.0
+                }
+                Err(e) => return Err(e),
+            };
             chip.set_register(chip.resolve_register(dest)?, val)?;
             Ok(chip.get_pc() + 1)
         }
@@ -1439,43 +1522,68 @@ pub fn execute_instruction(
                 })?;
             let value = chip.resolve_value(value)?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
 
             device.write_slot(slot_index, slot_logic, value)?;
             Ok(chip.get_pc() + 1)
         }
         Instruction::Lr {
-            dest: _,
-            device: _,
-            reagent_mode: _,
-            reagent: _,
+            dest,
+            device,
+            reagent_mode,
+            reagent,
         } => {
-            // Reagent operations not yet fully implemented
-            Err(SimulationError::RuntimeError {
-                message: "Reagent operations not yet implemented".to_string(),
-                line: instruction.line_number,
-            })
+            let ref_id = chip.resolve_device_ref_id(device)?;
+            let reagent_mode_val = chip.resolve_value(reagent_mode)?;
+            let reagent_mode =
+                ReagentMode::from_value(reagent_mode_val).ok_or(SimulationError::RuntimeError {
+                    message: format!("Invalid reagent mode: {reagent_mode_val}"),
+                    line: instruction.line_number,
+                })?;
+            let reagent = chip.resolve_value(reagent)? as i32;
+
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
+                    message: format!("Device with reference ID {ref_id} not found"),
+                    line: instruction.line_number,
+                },
+            )?;
+
+            let val = device.read_reagent(reagent_mode, reagent)?;
+            chip.set_register(chip.resolve_register(dest)?, val)?;
+            Ok(chip.get_pc() + 1)
         }
         Instruction::Rmap {
-            dest: _,
-            device: _,
-            reagent_hash: _,
+            dest,
+            device,
+            reagent_hash,
         } => {
-            // Reagent operations not yet fully implemented
-            Err(SimulationError::RuntimeError {
-                message: "Reagent operations not yet implemented".to_string(),
-                line: instruction.line_number,
-            })
+            let ref_id = chip.resolve_device_ref_id(device)?;
+            let reagent_hash = chip.resolve_value(reagent_hash)? as i32;
+
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
+                    message: format!("Device with reference ID {ref_id} not found"),
+                    line: instruction.line_number,
+                },
+            )?;
+
+            let val = device
+                .map_reagent(reagent_hash, chip.reagents())
+                .map_or(f64::NAN, |prefab_hash| prefab_hash as f64);
+            chip.set_register(chip.resolve_register(dest)?, val)?;
+            Ok(chip.get_pc() + 1)
         }
 
         // ==================== ID-Based Device Access ====================
@@ -1492,17 +1600,14 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
 
             let value = device.read(logic_type)?;
             drop(device);
@@ -1523,17 +1628,14 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
 
             device.write(logic_type, value)?;
             Ok(chip.get_pc() + 1)
@@ -1562,12 +1664,8 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let value = network
-                .borrow()
+            let network = require_network(chip, instruction.line_number)?;
+            let value = borrow_network(&network, instruction.line_number)?
                 .batch_read_by_prefab(prefab_hash, logic_type, batch_mode)
                 .map_err(|e| SimulationError::RuntimeError {
                     message: e.to_string(),
@@ -1592,12 +1690,8 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            network
-                .borrow()
+            let network = require_network(chip, instruction.line_number)?;
+            borrow_network(&network, instruction.line_number)?
                 .batch_write_by_prefab(prefab_hash, logic_type, value)
                 .map_err(|e| SimulationError::RuntimeError {
                     message: e.to_string(),
@@ -1629,12 +1723,8 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let value = network
-                .borrow()
+            let network = require_network(chip, instruction.line_number)?;
+            let value = borrow_network(&network, instruction.line_number)?
                 .batch_read_by_name(prefab_hash, name_hash, logic_type, batch_mode)
                 .map_err(|e| SimulationError::RuntimeError {
                     message: e.to_string(),
@@ -1661,12 +1751,8 @@ pub fn execute_instruction(
                     line: instruction.line_number,
                 })?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            network
-                .borrow()
+            let network = require_network(chip, instruction.line_number)?;
+            borrow_network(&network, instruction.line_number)?
                 .batch_write_by_name(prefab_hash, name_hash, logic_type, value)
                 .map_err(|e| SimulationError::RuntimeError {
                     message: e.to_string(),
@@ -1684,17 +1770,14 @@ pub fn execute_instruction(
             let ref_id = chip.resolve_device_ref_id(device)?;
             let index = chip.resolve_value(stack_index)? as usize;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             let value = device
                 .get_memory(index)
                 .map_err(|e| SimulationError::RuntimeError {
@@ -1714,17 +1797,14 @@ pub fn execute_instruction(
             let index = chip.resolve_value(stack_index)? as usize;
             let val = chip.resolve_value(value)?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             device
                 .set_memory(index, val)
                 .map_err(|e| SimulationError::RuntimeError {
@@ -1741,17 +1821,14 @@ pub fn execute_instruction(
             let ref_id = chip.resolve_value(id)? as i32;
             let index = chip.resolve_value(stack_index)? as usize;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             let value = device
                 .get_memory(index)
                 .map_err(|e| SimulationError::RuntimeError {
@@ -1771,17 +1848,14 @@ pub fn execute_instruction(
             let index = chip.resolve_value(stack_index)? as usize;
             let val = chip.resolve_value(value)?;
 
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             device
                 .set_memory(index, val)
                 .map_err(|e| SimulationError::RuntimeError {
@@ -1794,32 +1868,29 @@ pub fn execute_instruction(
         // ==================== Special Instructions ====================
         Instruction::Yield => Ok(chip.get_pc() + 1),
         Instruction::Sleep { duration: seconds } => {
-            let ticks = chip.resolve_value(seconds)? * 2.0;
-            if ticks > 1f64 {
-                chip.set_sleep_ticks((ticks - 1f64) as u64);
-            }
+            chip.sleep_for(chip.resolve_value(seconds)?);
             Ok(chip.get_pc() + 1)
         }
         Instruction::Hcf => {
             chip.halt();
-            Err(SimulationError::RuntimeError {
-                message: "(HCF) - chip execution terminated".to_string(),
-                line: instruction.line_number,
-            })
+            if chip.hcf_errors() {
+                return Err(SimulationError::RuntimeError {
+                    message: "(HCF) - chip execution terminated".to_string(),
+                    line: instruction.line_number,
+                });
+            }
+            Ok(chip.get_pc())
         }
         Instruction::Clr { device } => {
             let ref_id = chip.resolve_device_ref_id(device)?;
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             device.clear().map_err(|e| SimulationError::RuntimeError {
                 message: e.to_string(),
                 line: instruction.line_number,
@@ -1828,24 +1899,21 @@ pub fn execute_instruction(
         }
         Instruction::Clrd { id } => {
             let ref_id = chip.resolve_value(id)? as i32;
-            let network = chip.get_network().ok_or(SimulationError::RuntimeError {
-                message: "Chip not connected to network".to_string(),
-                line: instruction.line_number,
-            })?;
-            let network_ref = network.borrow();
-            let device = network_ref
-                .get_device(ref_id)
-                .ok_or(SimulationError::RuntimeError {
+            let network = require_network(chip, instruction.line_number)?;
+            let network_ref = borrow_network(&network, instruction.line_number)?;
+            let device = borrow_device(&network_ref, ref_id, instruction.line_number)?.ok_or(
+                SimulationError::RuntimeError {
                     message: format!("Device with reference ID {ref_id} not found"),
                     line: instruction.line_number,
-                })?;
+                },
+            )?;
             device.clear().map_err(|e| SimulationError::RuntimeError {
                 message: e.to_string(),
                 line: instruction.line_number,
             })?;
             Ok(chip.get_pc() + 1)
         }
-        Instruction::Noop => Ok(chip.get_pc() + 1),
+        Instruction::Label { .. } | Instruction::Noop => Ok(chip.get_pc() + 1),
         _ => Err(SimulationError::UnrecognizedInstruction(format!(
             "Unknown or unimplemented instruction {}: {:?}",
             instruction.line_number, instruction.instruction
@@ -36,13 +36,13 @@ fn main() -> Result<(), Box<dyn Error>> {
 fn phase_change_test() -> Result<(), Box<dyn Error>> {
     let mut manager = SimulationManager::new();
 
-    let cable_network = manager.create_cable_network();
+    let cable_network = manager.create_cable_network()?;
 
-    let input_network = manager.create_atmospheric_network(10.0);
-    let output_network = manager.create_atmospheric_network(10.0);
+    let input_network = manager.create_atmospheric_network(10.0)?;
+    let output_network = manager.create_atmospheric_network(10.0)?;
 
     let pump = VolumePump::new(SimulationDeviceSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationDeviceSettings::default()
     });
     cable_network
@@ -61,7 +61,7 @@ fn phase_change_test() -> Result<(), Box<dyn Error>> {
 
     input_network
         .borrow_mut()
-        .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0));
+        .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0))?;
 
     println!("Initial state:\n{}", input_network.borrow());
 
@@ -97,11 +97,11 @@ fn phase_change_test() -> Result<(), Box<dyn Error>> {
 fn phase_change_test_2() -> Result<(), Box<dyn Error>> {
     let mut manager = SimulationManager::new();
 
-    let network = manager.create_atmospheric_network(10.0);
+    let network = manager.create_atmospheric_network(10.0)?;
 
     network
         .borrow_mut()
-        .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0));
+        .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0))?;
 
     println!("Initial state:\n{}", network.borrow());
 
@@ -157,12 +157,12 @@ fn phase_change_test_2() -> Result<(), Box<dyn Error>> {
 fn elmo_ac_test() -> Result<(), Box<dyn Error>> {
     let mut manager = SimulationManager::new();
 
-    let tank = manager.create_atmospheric_network(780.0); // Gas tank / pump input / ac waste
-    let input = manager.create_atmospheric_network(10.0); // AC input
-    let vent = manager.create_atmospheric_network(1130.0); // AC hot gas output
+    let tank = manager.create_atmospheric_network(780.0)?; // Gas tank / pump input / ac waste
+    let input = manager.create_atmospheric_network(10.0)?; // AC input
+    let vent = manager.create_atmospheric_network(1130.0)?; // AC hot gas output
 
     let ac = AirConditioner::new(SimulationDeviceSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationDeviceSettings::default()
     });
     ac.borrow_mut()
@@ -173,7 +173,7 @@ fn elmo_ac_test() -> Result<(), Box<dyn Error>> {
         .set_atmospheric_network(DeviceAtmosphericNetworkType::Output2, Some(tank.clone()))?;
 
     let pump = VolumePump::new(SimulationDeviceSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationDeviceSettings::default()
     });
     pump.borrow_mut()
@@ -182,12 +182,12 @@ fn elmo_ac_test() -> Result<(), Box<dyn Error>> {
         .set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(input.clone()))?;
 
     let chip = shared(ItemIntegratedCircuit10::new(SimulationItemSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationItemSettings::default()
     }));
     ac.borrow().set_chip(chip.clone())?;
 
-    let network = manager.create_cable_network();
+    let network = manager.create_cable_network()?;
 
     network
         .borrow_mut()
@@ -196,7 +196,8 @@ fn elmo_ac_test() -> Result<(), Box<dyn Error>> {
         .borrow_mut()
         .add_device(pump.clone(), network.clone())?;
 
-    tank.borrow_mut().add_gas(GasType::Volatiles, 500.0, 315.15); // Fill tank with hot gas
+    tank.borrow_mut()
+        .add_gas(GasType::Volatiles, 500.0, 315.15)?; // Fill tank with hot gas
 
     let program = format!(
         r#"
@@ -236,6 +237,8 @@ fn elmo_ac_test() -> Result<(), Box<dyn Error>> {
         pump.borrow().get_id(),
     );
 
+    // The indented demo source exceeds the game's line length limit
+    chip.borrow_mut().set_enforce_source_limits(false);
     chip.borrow_mut().load_program(program.as_str())?;
 
     println!("Elmo AC Test Program:\n{}\n", program);
@@ -275,11 +278,11 @@ fn ac_device_test() -> Result<(), Box<dyn Error>> {
     // AC device test
     let mut manager = SimulationManager::new();
 
-    let input = manager.create_atmospheric_network(120.0);
-    let waste = manager.create_atmospheric_network(60.0);
+    let input = manager.create_atmospheric_network(120.0)?;
+    let waste = manager.create_atmospheric_network(60.0)?;
 
     let airconditioner = AirConditioner::new(SimulationDeviceSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationDeviceSettings::default()
     });
     {
@@ -293,10 +296,10 @@ fn ac_device_test() -> Result<(), Box<dyn Error>> {
 
     input
         .borrow_mut()
-        .add_gas(GasType::Oxygen, 100.0, celsius_to_kelvin(15.0));
+        .add_gas(GasType::Oxygen, 100.0, celsius_to_kelvin(15.0))?;
     waste
         .borrow_mut()
-        .add_gas(GasType::Oxygen, 100.0, celsius_to_kelvin(15.0));
+        .add_gas(GasType::Oxygen, 100.0, celsius_to_kelvin(15.0))?;
 
     println!(
         "Initial state:\n Input: {}\n Output: {}\n Waste: {}",
@@ -327,14 +330,14 @@ fn filtration_device_test() -> Result<(), Box<dyn Error>> {
     // Filtration device test
     let mut manager = SimulationManager::new();
 
-    let network = manager.create_cable_network();
+    let network = manager.create_cable_network()?;
 
-    let input = manager.create_atmospheric_network(10.0);
-    let filtered = manager.create_atmospheric_network(20.0);
-    let waste = manager.create_atmospheric_network(10.0);
+    let input = manager.create_atmospheric_network(10.0)?;
+    let filtered = manager.create_atmospheric_network(20.0)?;
+    let waste = manager.create_atmospheric_network(10.0)?;
 
     let filtration = Filtration::new(SimulationDeviceSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationDeviceSettings::default()
     });
     {
@@ -342,7 +345,7 @@ fn filtration_device_test() -> Result<(), Box<dyn Error>> {
         // Insert a filter item into slot 0
         let slot = f.get_slot_mut(0).unwrap();
         let mut filter_item = Filter::new(SimulationItemSettings {
-            id: Some(manager.allocate_next_id()),
+            id: Some(manager.allocate_next_id()?),
             ..SimulationItemSettings::default()
         });
         filter_item.set_gas_type(GasType::Oxygen);
@@ -357,10 +360,10 @@ fn filtration_device_test() -> Result<(), Box<dyn Error>> {
         f.write(LogicType::Mode, 1.0)?;
     }
 
-    input.borrow_mut().add_gas(GasType::Oxygen, 63.0, 273.15);
+    input.borrow_mut().add_gas(GasType::Oxygen, 63.0, 273.15)?;
     input
         .borrow_mut()
-        .add_gas(GasType::CarbonDioxide, 4.0, 273.15);
+        .add_gas(GasType::CarbonDioxide, 4.0, 273.15)?;
 
     network
         .borrow_mut()
@@ -397,13 +400,13 @@ fn ic_program_test() -> Result<(), Box<dyn Error>> {
     let mut manager = SimulationManager::new();
 
     // Create a network
-    let network = manager.create_cable_network();
+    let network = manager.create_cable_network()?;
     let chip = shared(ItemIntegratedCircuit10::new(SimulationItemSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationItemSettings::default()
     }));
     let housing = ICHousing::new(SimulationDeviceSettings {
-        id: Some(manager.allocate_next_id()),
+        id: Some(manager.allocate_next_id()?),
         ..SimulationDeviceSettings::default()
     });
 
@@ -476,7 +479,8 @@ add r0 r0 1
 jr -39
 s db Setting STR("DONE")"#.to_string();
 
-    // Load the program
+    // Load the program (its generated header exceeds the game's line length limit)
+    chip.borrow_mut().set_enforce_source_limits(false);
     chip.borrow_mut().load_program(program.as_str())?;
 
     let processed = preprocess(program.as_str());
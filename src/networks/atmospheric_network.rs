@@ -1,10 +1,19 @@
 //! Atmospheric Network - manages a shared gas mixture that can be accessed by multiple devices.
 
-use crate::atmospherics::{GasMixture, GasType, MatterState, Mole};
+use crate::atmospherics::{
+    GasMixture, GasPropertiesTable, GasType, MINIMUM_VALID_TOTAL_MOLES, MatterState, Mole,
+    TransferAmount,
+};
 use crate::types::{Shared, shared};
 use crate::{SimulationError, SimulationResult};
 use std::fmt::{Debug, Display};
 
+/// Default total moles below which `AtmosphericNetwork::is_empty` reports a network as empty.
+///
+/// Matches the game's minimum valid mixture size, so trace amounts left behind by transfers
+/// and pumping count as empty.
+pub const DEFAULT_EMPTY_EPSILON: f64 = MINIMUM_VALID_TOTAL_MOLES;
+
 /// An atmospheric network that manages a shared gas mixture.
 #[derive(Clone)]
 pub struct AtmosphericNetwork {
@@ -16,6 +25,33 @@ pub struct AtmosphericNetwork {
 
     /// Constant mixture to copy after each update
     constant_mixture: Option<GasMixture>,
+
+    /// Total moles below which the network counts as empty
+    empty_epsilon: f64,
+
+    /// Optional cap on the moles devices may transfer through this network each tick
+    max_flow_per_tick: Option<f64>,
+
+    /// Flow still available this tick
+    flow_remaining: f64,
+
+    /// Connected devices that have not yet taken their share of the flow this tick
+    flow_users_left: usize,
+
+    /// Flow left for the device currently updating, if one has started
+    flow_allowance: Option<f64>,
+
+    /// Optional pressure (kPa) above which the network is over pressure
+    max_pressure: Option<f64>,
+
+    /// Whether the network loses its contents when it goes over pressure
+    vent_on_burst: bool,
+
+    /// Whether the network has burst and not been repaired since
+    burst: bool,
+
+    /// Moles of fuel burned this tick, as reported by the combustion model
+    combustion: f64,
 }
 
 impl AtmosphericNetwork {
@@ -31,9 +67,29 @@ impl AtmosphericNetwork {
             id: None,
             mixture: GasMixture::new(volume),
             constant_mixture: None,
+            empty_epsilon: DEFAULT_EMPTY_EPSILON,
+            max_flow_per_tick: None,
+            flow_remaining: f64::INFINITY,
+            flow_users_left: 0,
+            flow_allowance: None,
+            max_pressure: None,
+            vent_on_burst: false,
+            burst: false,
+            combustion: 0.0,
         })
     }
 
+    /// Create a new atmospheric network whose gases use an override property table.
+    /// Panics if volume is 0 or negative
+    pub fn with_properties(
+        volume: f64,
+        properties: &GasPropertiesTable,
+    ) -> Shared<AtmosphericNetwork> {
+        let network = Self::new(volume);
+        network.borrow_mut().mixture.set_properties(properties);
+        network
+    }
+
     /// Perform tasks after a mixture update
     fn after_update(&mut self) {
         if let Some(constant) = &self.constant_mixture {
@@ -58,6 +114,11 @@ impl AtmosphericNetwork {
 
     /// Set the network volume
     /// If the network is constant, the constant mixture volume is also updated
+    ///
+    /// Moles and internal energy are left as they are, so temperature is unchanged and the gas
+    /// pressure scales inversely with the gas volume. Phase equilibrium is not settled here;
+    /// the next `process_phase_changes` (run every tick by the simulation manager) evaluates
+    /// it at the new pressure.
     pub fn set_volume(&mut self, volume: f64) -> SimulationResult<()> {
         if volume <= 0.0 {
             return Err(SimulationError::RuntimeError {
@@ -76,9 +137,16 @@ impl AtmosphericNetwork {
     }
 
     /// Add gas to the network
-    pub fn add_gas(&mut self, gas_type: GasType, moles: f64, temperature: f64) {
-        self.mixture.add_gas(gas_type, moles, temperature);
+    /// Errors on negative or non-finite moles and on temperatures at or below absolute zero
+    pub fn add_gas(
+        &mut self,
+        gas_type: GasType,
+        moles: f64,
+        temperature: f64,
+    ) -> SimulationResult<()> {
+        self.mixture.add_gas(gas_type, moles, temperature)?;
         self.after_update();
+        Ok(())
     }
 
     /// Add a gas mixture to the network
@@ -93,7 +161,11 @@ impl AtmosphericNetwork {
         self.after_update();
     }
 
-    /// Remove gas from the network
+    /// Remove up to `moles` of `gas_type` from the network and return the moles removed.
+    ///
+    /// The amount is clamped to what is present, so asking for more removes all of it, and
+    /// negative or NaN amounts remove nothing. Energy leaves in proportion to the moles
+    /// removed, so the temperature of what remains is unchanged.
     pub fn remove_gas(&mut self, gas_type: GasType, moles: f64) -> f64 {
         let removed = self.mixture.remove_gas(gas_type, moles);
         self.after_update();
@@ -142,6 +214,22 @@ impl AtmosphericNetwork {
         changes
     }
 
+    /// Record the moles of fuel burned in the network this tick. The emulator has no
+    /// combustion model yet, so this stays 0 unless a caller reports a burn.
+    pub fn set_combustion(&mut self, moles: f64) {
+        self.combustion = moles.max(0.0);
+    }
+
+    /// Moles of fuel burned this tick, as last recorded with `set_combustion`
+    pub fn combustion(&self) -> f64 {
+        self.combustion
+    }
+
+    /// Whether the network is burning fuel, as read through the `Combustion*` logic types
+    pub fn is_combusting(&self) -> bool {
+        self.combustion > 0.0
+    }
+
     /// Get the total moles in the network
     pub fn total_moles(&self) -> f64 {
         self.mixture.total_moles()
@@ -152,11 +240,27 @@ impl AtmosphericNetwork {
         self.mixture.gas_ratio(gas_type)
     }
 
+    /// Get the fraction (0.0 to 1.0) of the network's content that is in `state`
+    pub fn state_ratio(&self, state: MatterState) -> f64 {
+        self.mixture.state_ratio(state)
+    }
+
     /// Get the partial pressure of a specific gas (kPa)
     pub fn partial_pressure(&self, gas_type: GasType) -> f64 {
         self.mixture.partial_pressure(gas_type)
     }
 
+    /// Get the combined partial pressure of a group of gases (kPa), e.g. `GasType::BREATHABLE`.
+    /// Each gas is counted once; liquids contribute nothing.
+    pub fn pressure_of_group(&self, group: &[GasType]) -> f64 {
+        group
+            .iter()
+            .enumerate()
+            .filter(|(i, gas)| !group[..*i].contains(gas))
+            .map(|(_, &gas)| self.partial_pressure(gas))
+            .sum()
+    }
+
     /// Get the volume of the network
     pub fn volume(&self) -> f64 {
         self.mixture.volume()
@@ -207,6 +311,11 @@ impl AtmosphericNetwork {
         self.mixture.total_energy()
     }
 
+    /// Get the thermal energy of the network (J); same as `total_energy()`
+    pub fn energy(&self) -> f64 {
+        self.total_energy()
+    }
+
     /// Get the total heat capacity of gases in the network
     pub fn total_heat_capacity_gases(&self) -> f64 {
         self.mixture.total_heat_capacity_gases()
@@ -228,9 +337,26 @@ impl AtmosphericNetwork {
         self.mixture.pressure_gases()
     }
 
-    /// Check if the network is empty
+    /// Check if the network is empty: it holds fewer total moles than the empty epsilon
+    /// (`DEFAULT_EMPTY_EPSILON` unless changed with `set_empty_epsilon`)
     pub fn is_empty(&self) -> bool {
-        self.mixture.is_empty()
+        self.total_moles() < self.empty_epsilon
+    }
+
+    /// Set the total moles below which the network counts as empty. Mixtures already discard
+    /// contents below `MINIMUM_VALID_TOTAL_MOLES`, so only raising the epsilon changes results.
+    pub fn set_empty_epsilon(&mut self, epsilon: f64) {
+        self.empty_epsilon = epsilon;
+    }
+
+    /// Get the total moles below which the network counts as empty
+    pub fn empty_epsilon(&self) -> f64 {
+        self.empty_epsilon
+    }
+
+    /// Copy the network's mixture for inspection; changing the copy leaves the network untouched
+    pub fn clone_mixture(&self) -> GasMixture {
+        self.mixture.clone()
     }
 
     /// Consume all gas from the network and return it
@@ -261,12 +387,191 @@ impl AtmosphericNetwork {
         self.after_update();
     }
 
+    /// Conduct heat to another atmospheric network through a shared wall (no mass transfer)
+    /// Returns the joules moved from `self` to `other`
+    pub fn conduct_heat_with(&mut self, other: &mut AtmosphericNetwork, conductivity: f64) -> f64 {
+        let energy = self
+            .mixture
+            .conduct_heat_with(&mut other.mixture, conductivity);
+        self.after_update();
+        other.after_update();
+        energy
+    }
+
+    /// Set the pressure (kPa) above which the network is over pressure, simulating the pipe
+    /// rating; `None` removes the limit. Also repairs a burst network.
+    pub fn set_max_pressure(&mut self, max_pressure: Option<f64>) {
+        self.max_pressure = max_pressure;
+        self.burst = false;
+    }
+
+    /// Get the pressure limit, if any
+    pub fn max_pressure(&self) -> Option<f64> {
+        self.max_pressure
+    }
+
+    /// Whether the network is above its pressure limit, or has burst and not been repaired.
+    /// Devices connected to the network report this through `LogicType::Error`.
+    pub fn over_pressure(&self) -> bool {
+        self.burst || self.max_pressure.is_some_and(|max| self.pressure() > max)
+    }
+
+    /// Choose whether the network bursts when it goes over pressure, losing all its contents.
+    /// The `SimulationManager` checks for bursts during each tick's atmospheric updates; a
+    /// burst network stays over pressure until it is repaired.
+    pub fn set_vent_on_burst(&mut self, vent: bool) {
+        self.vent_on_burst = vent;
+    }
+
+    /// Whether the network bursts when it goes over pressure
+    pub fn vent_on_burst(&self) -> bool {
+        self.vent_on_burst
+    }
+
+    /// Whether the network has burst and not been repaired since
+    pub fn has_burst(&self) -> bool {
+        self.burst
+    }
+
+    /// Clear the burst state, leaving the (vented) contents as they are
+    pub fn repair(&mut self) {
+        self.burst = false;
+    }
+
+    /// Burst the network if it is over pressure and set to vent, clearing its contents.
+    /// Returns true if the network burst.
+    pub(crate) fn process_over_pressure(&mut self) -> bool {
+        if !self.vent_on_burst || self.burst || !self.over_pressure() {
+            return false;
+        }
+
+        self.burst = true;
+        self.clear();
+        true
+    }
+
+    /// Cap the moles devices may move into or out of this network per tick with
+    /// `transfer_moles_to` (pumps, active vents, air conditioners); `None` removes the cap.
+    ///
+    /// The `SimulationManager` splits the cap between the devices connected to the network:
+    /// each one in update order may use an equal share of what is left, so flow a device does
+    /// not use rolls forward. Passive equalization and filtration are not limited.
+    pub fn set_max_flow_per_tick(&mut self, max_flow: Option<f64>) {
+        self.max_flow_per_tick = max_flow.map(|max| max.max(0.0));
+        self.begin_flow_tick();
+    }
+
+    /// Get the per-tick flow cap, if any
+    pub fn max_flow_per_tick(&self) -> Option<f64> {
+        self.max_flow_per_tick
+    }
+
+    /// Moles that may still be transferred through this network this tick
+    pub fn flow_remaining(&self) -> f64 {
+        self.flow_remaining
+    }
+
+    /// Restore the full flow cap and forget the connected devices, at the start of a tick
+    pub(crate) fn begin_flow_tick(&mut self) {
+        self.flow_remaining = self.max_flow_per_tick.unwrap_or(f64::INFINITY);
+        self.flow_users_left = 0;
+        self.flow_allowance = None;
+    }
+
+    /// Count one more device sharing this tick's flow
+    pub(crate) fn add_flow_user(&mut self) {
+        self.flow_users_left += 1;
+    }
+
+    /// Give the device about to update its share of the remaining flow
+    pub(crate) fn begin_device_flow(&mut self) {
+        self.flow_allowance = Some(self.flow_remaining / self.flow_users_left.max(1) as f64);
+    }
+
+    /// Finish the updating device's turn; flow it left unused stays for later devices
+    pub(crate) fn end_device_flow(&mut self) {
+        self.flow_users_left = self.flow_users_left.saturating_sub(1);
+        self.flow_allowance = None;
+    }
+
+    /// Moles the current transfer may still move through this network
+    fn flow_limit(&self) -> f64 {
+        self.flow_allowance.unwrap_or(self.flow_remaining)
+    }
+
+    /// Record `moles` moved through this network
+    fn use_flow(&mut self, moles: f64) {
+        if self.max_flow_per_tick.is_some() {
+            self.flow_remaining = (self.flow_remaining - moles).max(0.0);
+            if let Some(allowance) = &mut self.flow_allowance {
+                *allowance = (*allowance - moles).max(0.0);
+            }
+        }
+    }
+
     /// Transfer a specific amount of gas to another network
     pub fn transfer_to(&mut self, other: &mut AtmosphericNetwork, moles: f64) {
-        let transferred = self.mixture.remove_moles(moles, MatterState::All);
-        other.mixture.merge(&transferred);
+        self.transfer_moles_to(other, TransferAmount::Moles(moles), MatterState::All);
+    }
+
+    /// Move `amount` of the contents matching `state` to another network in one step,
+    /// conserving moles and energy; returns the moles transferred.
+    ///
+    /// The amount is limited by the flow either network has left this tick (see
+    /// `set_max_flow_per_tick`).
+    pub fn transfer_moles_to(
+        &mut self,
+        other: &mut AtmosphericNetwork,
+        amount: TransferAmount,
+        state: MatterState,
+    ) -> f64 {
+        let limit = self.flow_limit().min(other.flow_limit());
+        let amount = if limit.is_finite() {
+            let requested = match amount {
+                TransferAmount::Fraction(fraction) => {
+                    fraction.clamp(0.0, 1.0) * self.mixture.total_moles_by_state(state)
+                }
+                TransferAmount::Moles(moles) => moles,
+            };
+            TransferAmount::Moles(requested.min(limit))
+        } else {
+            amount
+        };
+
+        let transferred = self
+            .mixture
+            .transfer_moles_to(&mut other.mixture, amount, state);
+        self.use_flow(transferred);
+        other.use_flow(transferred);
         self.after_update();
         other.after_update();
+        transferred
+    }
+
+    /// Move `amount` of the contents matching `state` into a standalone mixture, such as a
+    /// device's working buffer; returns the moles transferred. Not limited by the flow cap.
+    pub fn transfer_moles_into(
+        &mut self,
+        mixture: &mut GasMixture,
+        amount: TransferAmount,
+        state: MatterState,
+    ) -> f64 {
+        let transferred = self.mixture.transfer_moles_to(mixture, amount, state);
+        self.after_update();
+        transferred
+    }
+
+    /// Move `amount` of the contents matching `state` out of a standalone mixture into this
+    /// network; returns the moles transferred. Not limited by the flow cap.
+    pub fn transfer_moles_from(
+        &mut self,
+        mixture: &mut GasMixture,
+        amount: TransferAmount,
+        state: MatterState,
+    ) -> f64 {
+        let transferred = mixture.transfer_moles_to(&mut self.mixture, amount, state);
+        self.after_update();
+        transferred
     }
 
     /// Set the temperature of the network
@@ -3,11 +3,10 @@
 use crate::SimulationError;
 use crate::devices::{Device, LogicType};
 use crate::error::SimulationResult;
-use crate::types::{OptShared, Shared, shared};
-use std::cell::{Ref, RefMut};
+use crate::types::{OptShared, Shared, SharedPtr, SharedRef, SharedRefMut, shared};
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
-use std::rc::Rc;
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -27,13 +26,18 @@ pub struct CableNetwork {
     /// All devices on the network, keyed by their reference ID
     devices: BTreeMap<i32, Shared<dyn Device>>,
 
+    /// Reference IDs of the devices on the network, in the order they were added
+    insertion_order: Vec<i32>,
+
     /// Index for quick lookup by prefab hash
     /// Maps prefab_hash -> list of device reference IDs
     prefab_index: BTreeMap<i32, Vec<i32>>,
 
     /// Index for quick lookup by name hash
-    /// Maps name_hash -> list of device reference IDs
-    name_index: BTreeMap<i32, Vec<i32>>,
+    /// Maps name_hash -> list of device reference IDs. Behind a `RefCell` so a device can
+    /// re-index itself through `update_device_name` while the network is borrowed, e.g. when a
+    /// chip writes `NameHash` with `s` or `sbn`
+    name_index: RefCell<BTreeMap<i32, Vec<i32>>>,
 }
 
 impl CableNetwork {
@@ -147,24 +151,21 @@ impl CableNetwork {
         shared(CableNetwork {
             id: None,
             devices: BTreeMap::new(),
+            insertion_order: Vec::new(),
             prefab_index: BTreeMap::new(),
-            name_index: BTreeMap::new(),
+            name_index: RefCell::new(BTreeMap::new()),
         })
     }
 
     /// Add a device to the network and set up the bidirectional connection
     /// The device will be indexed by its reference ID, prefab hash, and name hash
     /// The devices list will remain sorted by reference ID
+    /// Errors if a different device with the same reference ID is already on the network
     pub fn add_device(
         &mut self,
         device: Shared<dyn Device>,
         network_rc: Shared<CableNetwork>,
     ) -> SimulationResult<()> {
-        // Set the device's network reference
-        device
-            .borrow_mut()
-            .set_network(Some(Rc::downgrade(&network_rc)))?;
-
         let borrowed = device.borrow();
         let ref_id = borrowed.get_id();
         let prefab_hash = borrowed.get_prefab_hash();
@@ -173,18 +174,26 @@ impl CableNetwork {
 
         if let Some(existing) = self.devices.get(&ref_id) {
             // Check if device is the same
-            if Rc::ptr_eq(existing, &device) {
+            if SharedPtr::ptr_eq(existing, &device) {
                 // Same device already present, no action needed
                 return Ok(());
             }
-            panic!(
-                "Different device with reference ID {} already exists on the network",
-                ref_id
-            );
+            return Err(SimulationError::RuntimeError {
+                line: 0,
+                message: format!(
+                    "Different device with reference ID {ref_id} already exists on the network"
+                ),
+            });
         }
 
+        // Set the device's network reference
+        device
+            .borrow_mut()
+            .set_network(Some(SharedPtr::downgrade(&network_rc)))?;
+
         // Add to main device map
-        self.devices.insert(ref_id, Rc::clone(&device));
+        self.devices.insert(ref_id, SharedPtr::clone(&device));
+        self.insertion_order.push(ref_id);
 
         // Add to prefab index and insert in sorted order
         let prefab_ids = self.prefab_index.entry(prefab_hash).or_default();
@@ -194,7 +203,8 @@ impl CableNetwork {
         }
 
         // Add to name index and insert in sorted order
-        let name_ids = self.name_index.entry(name_hash).or_default();
+        let name_index = self.name_index.get_mut();
+        let name_ids = name_index.entry(name_hash).or_default();
         match name_ids.binary_search(&ref_id) {
             Ok(_) => {}
             Err(pos) => name_ids.insert(pos, ref_id),
@@ -206,6 +216,8 @@ impl CableNetwork {
     /// Remove a device from the network by its reference ID
     pub fn remove_device(&mut self, ref_id: i32) -> OptShared<dyn Device> {
         if let Some(device) = self.devices.remove(&ref_id) {
+            self.insertion_order.retain(|&id| id != ref_id);
+
             let borrowed = device.borrow();
             let prefab_hash = borrowed.get_prefab_hash();
             let name_hash = borrowed.get_name_hash();
@@ -223,10 +235,11 @@ impl CableNetwork {
             }
 
             // Remove from name index
-            if let Some(ids) = self.name_index.get_mut(&name_hash) {
+            let name_index = self.name_index.get_mut();
+            if let Some(ids) = name_index.get_mut(&name_hash) {
                 ids.retain(|&id| id != ref_id);
                 if ids.is_empty() {
-                    self.name_index.remove(&name_hash);
+                    name_index.remove(&name_hash);
                 }
             }
 
@@ -237,17 +250,19 @@ impl CableNetwork {
     }
 
     /// Update the device name index when a device's name changes
-    pub fn update_device_name(&mut self, ref_id: i32, old_name_hash: i32, new_name_hash: i32) {
+    pub fn update_device_name(&self, ref_id: i32, old_name_hash: i32, new_name_hash: i32) {
+        let mut name_index = self.name_index.borrow_mut();
+
         // Remove from old name index
-        if let Some(ids) = self.name_index.get_mut(&old_name_hash) {
+        if let Some(ids) = name_index.get_mut(&old_name_hash) {
             ids.retain(|&id| id != ref_id);
             if ids.is_empty() {
-                self.name_index.remove(&old_name_hash);
+                name_index.remove(&old_name_hash);
             }
         }
 
         // Add to new name index and insert in sorted order
-        let ids = self.name_index.entry(new_name_hash).or_default();
+        let ids = name_index.entry(new_name_hash).or_default();
         match ids.binary_search(&ref_id) {
             Ok(_) => {}
             Err(pos) => ids.insert(pos, ref_id),
@@ -260,13 +275,33 @@ impl CableNetwork {
     }
 
     /// Get a device by its reference ID (immutable borrow)
-    pub fn get_device(&self, ref_id: i32) -> Option<Ref<'_, dyn Device>> {
+    pub fn get_device(&self, ref_id: i32) -> Option<SharedRef<'_, dyn Device>> {
         let device = self.devices.get(&ref_id)?;
         Some(device.borrow())
     }
 
+    /// Get a device by its reference ID like `get_device`, but report a device that is already
+    /// mutably borrowed (e.g. by the host while a chip runs) as an error instead of panicking
+    pub fn try_get_device(
+        &self,
+        ref_id: i32,
+    ) -> SimulationResult<Option<SharedRef<'_, dyn Device>>> {
+        let Some(device) = self.devices.get(&ref_id) else {
+            return Ok(None);
+        };
+        device
+            .try_borrow()
+            .map(Some)
+            .map_err(|_| SimulationError::RuntimeError {
+                message: format!(
+                    "Device with reference ID {ref_id} is busy and cannot be accessed"
+                ),
+                line: 0,
+            })
+    }
+
     /// Get a device by its reference ID (mutable borrow)
-    pub fn get_device_mut(&self, ref_id: i32) -> Option<RefMut<'_, dyn Device>> {
+    pub fn get_device_mut(&self, ref_id: i32) -> Option<SharedRefMut<'_, dyn Device>> {
         let device = self.devices.get(&ref_id)?;
         Some(device.borrow_mut())
     }
@@ -281,6 +316,28 @@ impl CableNetwork {
         self.devices.values().cloned().collect()
     }
 
+    /// Iterate over the devices on the network in the order they were added
+    pub fn devices(&self) -> impl Iterator<Item = Shared<dyn Device>> + '_ {
+        self.insertion_order
+            .iter()
+            .filter_map(|id| self.devices.get(id).cloned())
+    }
+
+    /// Total power supplied by the devices on the network (W)
+    pub fn power_generation(&self) -> f64 {
+        self.devices().map(|d| d.borrow().power_generation()).sum()
+    }
+
+    /// Total power drawn by the devices on the network (W)
+    pub fn power_demand(&self) -> f64 {
+        self.devices().map(|d| d.borrow().power_demand()).sum()
+    }
+
+    /// Power left over after demand is met (W); negative when demand exceeds supply
+    pub fn power_surplus(&self) -> f64 {
+        self.power_generation() - self.power_demand()
+    }
+
     /// Get all devices with a specific prefab hash
     /// Returns a vector of reference IDs
     pub fn get_devices_by_prefab(&self, prefab_hash: i32) -> Vec<i32> {
@@ -293,7 +350,11 @@ impl CableNetwork {
     /// Get all devices with a specific name hash
     /// Returns a vector of reference IDs
     pub fn get_devices_by_name(&self, name_hash: i32) -> Vec<i32> {
-        self.name_index.get(&name_hash).cloned().unwrap_or_default()
+        self.name_index
+            .borrow()
+            .get(&name_hash)
+            .cloned()
+            .unwrap_or_default()
     }
 
     /// Count devices with a specific prefab hash
@@ -307,6 +368,7 @@ impl CableNetwork {
     /// Count devices with a specific name hash
     pub fn count_devices_by_name(&self, name_hash: i32) -> usize {
         self.name_index
+            .borrow()
             .get(&name_hash)
             .map(|v| v.len())
             .unwrap_or(0)
@@ -325,8 +387,9 @@ impl CableNetwork {
     /// Clear all devices from the network
     pub fn clear(&mut self) {
         self.devices.clear();
+        self.insertion_order.clear();
         self.prefab_index.clear();
-        self.name_index.clear();
+        self.name_index.get_mut().clear();
     }
 
     // ==================== Batch Read Operations ====================
@@ -380,15 +443,15 @@ impl CableNetwork {
         let mut values = Vec::with_capacity(device_ids.len());
 
         for &ref_id in device_ids {
-            let device = self
-                .get_device(ref_id)
-                .ok_or_else(|| SimulationError::RuntimeError {
-                    message: format!(
-                        "Device with reference ID {} not found for batch read",
-                        ref_id
-                    ),
-                    line: 0,
-                })?;
+            let device =
+                self.try_get_device(ref_id)?
+                    .ok_or_else(|| SimulationError::RuntimeError {
+                        message: format!(
+                            "Device with reference ID {} not found for batch read",
+                            ref_id
+                        ),
+                        line: 0,
+                    })?;
 
             values.push(device.read(logic_type)?);
         }
@@ -396,6 +459,91 @@ impl CableNetwork {
         Ok(batch_mode.aggregate(&values))
     }
 
+    // ==================== Batch Select Operations ====================
+
+    /// Select the device holding the minimum or maximum value among devices matching a prefab hash.
+    /// See `batch_select_from_ids` for the tie-break rule.
+    pub fn batch_select_by_prefab(
+        &self,
+        prefab_hash: i32,
+        logic_type: LogicType,
+        batch_mode: BatchMode,
+    ) -> SimulationResult<Option<i32>> {
+        let device_ids = self.get_devices_by_prefab(prefab_hash);
+        self.batch_select_from_ids(&device_ids, logic_type, batch_mode)
+    }
+
+    /// Select the device holding the minimum or maximum value among devices matching both a
+    /// prefab hash and a name hash. See `batch_select_from_ids` for the tie-break rule.
+    pub fn batch_select_by_name(
+        &self,
+        prefab_hash: i32,
+        name_hash: i32,
+        logic_type: LogicType,
+        batch_mode: BatchMode,
+    ) -> SimulationResult<Option<i32>> {
+        let name_devices = self.get_devices_by_name(name_hash);
+        let device_ids: Vec<i32> = self
+            .get_devices_by_prefab(prefab_hash)
+            .into_iter()
+            .filter(|id| name_devices.contains(id))
+            .collect();
+
+        self.batch_select_from_ids(&device_ids, logic_type, batch_mode)
+    }
+
+    /// Internal helper returning the reference ID of the device whose value is selected by
+    /// `batch_mode`, or `None` when no device has a comparable (non-NaN) value.
+    ///
+    /// Only `Minimum` and `Maximum` select a device. When several devices share the selected
+    /// value the one with the lowest reference ID wins, independent of the order they were
+    /// added to the network.
+    fn batch_select_from_ids(
+        &self,
+        device_ids: &[i32],
+        logic_type: LogicType,
+        batch_mode: BatchMode,
+    ) -> SimulationResult<Option<i32>> {
+        let better = match batch_mode {
+            BatchMode::Minimum => |value: f64, best: f64| value < best,
+            BatchMode::Maximum => |value: f64, best: f64| value > best,
+            BatchMode::Average | BatchMode::Sum => {
+                return Err(SimulationError::RuntimeError {
+                    message: format!("Batch mode {batch_mode:?} does not select a device"),
+                    line: 0,
+                });
+            }
+        };
+
+        let mut sorted_ids = device_ids.to_vec();
+        sorted_ids.sort_unstable();
+
+        let mut selected: Option<(i32, f64)> = None;
+        for ref_id in sorted_ids {
+            let device =
+                self.try_get_device(ref_id)?
+                    .ok_or_else(|| SimulationError::RuntimeError {
+                        message: format!(
+                            "Device with reference ID {} not found for batch select",
+                            ref_id
+                        ),
+                        line: 0,
+                    })?;
+
+            let value = device.read(logic_type)?;
+            if value.is_nan() {
+                continue;
+            }
+
+            // Strict comparison keeps the earlier (lower) ID on ties
+            if selected.is_none_or(|(_, best)| better(value, best)) {
+                selected = Some((ref_id, value));
+            }
+        }
+
+        Ok(selected.map(|(ref_id, _)| ref_id))
+    }
+
     // ==================== Batch Write Operations ====================
 
     /// Write a logic value to all devices matching a prefab hash
@@ -442,15 +590,15 @@ impl CableNetwork {
         let mut write_count = 0;
 
         for &ref_id in device_ids {
-            let device = self
-                .get_device(ref_id)
-                .ok_or_else(|| SimulationError::RuntimeError {
-                    message: format!(
-                        "Device with reference ID {} not found for batch write",
-                        ref_id
-                    ),
-                    line: 0,
-                })?;
+            let device =
+                self.try_get_device(ref_id)?
+                    .ok_or_else(|| SimulationError::RuntimeError {
+                        message: format!(
+                            "Device with reference ID {} not found for batch write",
+                            ref_id
+                        ),
+                        line: 0,
+                    })?;
 
             device.write(logic_type, value)?;
             write_count += 1;
@@ -469,9 +617,9 @@ pub enum BatchMode {
     Average = 0,
     /// Sum of all values
     Sum = 1,
-    /// Minimum value
+    /// Minimum value (batch select breaks ties by lowest reference ID)
     Minimum = 2,
-    /// Maximum value
+    /// Maximum value (batch select breaks ties by lowest reference ID)
     Maximum = 3,
 }
 
@@ -3,5 +3,5 @@
 mod atmospheric_network;
 mod cable_network;
 
-pub use atmospheric_network::AtmosphericNetwork;
+pub use atmospheric_network::{AtmosphericNetwork, DEFAULT_EMPTY_EPSILON};
 pub use cable_network::{BatchMode, CableNetwork};
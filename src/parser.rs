@@ -1,16 +1,74 @@
 //! IC10 source code preprocessing and parsing
 
+use crate::constants::{
+    MAX_EXACT_INTEGER, MAX_LINE_LENGTH, MAX_PROGRAM_LINES, get_builtin_constants,
+};
+use crate::devices::device_factory;
 use crate::error::{SimulationError, SimulationResult};
+use crate::instruction::{Instruction, OperandRole, ParsedInstruction};
+use crate::items::item_factory;
+use crate::items::item_integrated_circuit_10::{AliasTarget, Operand};
 use crc::{CRC_32_ISO_HDLC, Crc};
 use regex::Regex;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Number of device pins (d0-d5) an alias may target
+const DEVICE_PIN_COUNT: usize = 6;
+
+/// Compiled regular expressions used by `preprocess`, built once per process
+struct PreprocessRegexes {
+    comment: Regex,
+    str: Regex,
+    hash_str: Regex,
+    bin: Regex,
+    hex: Regex,
+}
+
+fn preprocess_regexes() -> &'static PreprocessRegexes {
+    static REGEXES: OnceLock<PreprocessRegexes> = OnceLock::new();
+    REGEXES.get_or_init(|| PreprocessRegexes {
+        comment: Regex::new(r"#.*$").unwrap(),
+        str: Regex::new(r#"STR\("((?:[^"\\]|\\.)*)"\)"#).unwrap(),
+        hash_str: Regex::new(r#"HASH\("((?:[^"\\]|\\.)*)"\)"#).unwrap(),
+        bin: Regex::new(r"%([01_]+)").unwrap(),
+        hex: Regex::new(r"\$([A-Fa-f0-9_]+)").unwrap(),
+    })
+}
+
+/// Resolve `\"` and `\\` escapes inside a `STR`/`HASH` string literal
+fn unescape_literal(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('"' | '\\'))) => {
+                result.push(next);
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
 
 /// Preprocess IC10 source (handle defines, strings, hex/bin literals).
+///
+/// `STR("...")` and `HASH("...")` are replaced wherever they appear, so they can be used as
+/// any immediate operand as well as in `define`. Their string may contain `\"` and `\\` escapes.
+/// `HASH("")` is 0, while an empty, non-ASCII or over-long `STR` becomes an `<ERR:...>` marker.
+/// Hex and binary literals above `MAX_EXACT_INTEGER` in magnitude also become a marker, since
+/// they cannot be stored in a register without losing precision.
 pub fn preprocess(source: &str) -> SimulationResult<String> {
-    let comment_re = Regex::new(r"#.*$").unwrap();
-    let str_re = Regex::new(r#"STR\("([^"]+)"\)"#).unwrap();
-    let hash_str_re = Regex::new(r#"HASH\("([^"]+)"\)"#).unwrap();
-    let bin_re = Regex::new(r"%([01_]+)").unwrap();
-    let hex_re = Regex::new(r"\$([A-Fa-f0-9_]+)").unwrap();
+    let PreprocessRegexes {
+        comment: comment_re,
+        str: str_re,
+        hash_str: hash_str_re,
+        bin: bin_re,
+        hex: hex_re,
+    } = preprocess_regexes();
 
     let mut result = Vec::new();
     for line in source.lines() {
@@ -19,13 +77,11 @@ pub fn preprocess(source: &str) -> SimulationResult<String> {
 
         line = str_re
             .replace_all(&line, |caps: &regex::Captures| {
-                let text = &caps[1];
-                match pack_ascii6(text) {
+                let text = unescape_literal(&caps[1]);
+                match pack_ascii6(&text) {
                     Some(num) => format!("{}", num as f64),
+                    None if !text.is_ascii() => "<ERR:InvalidStringNonAscii>".to_string(),
                     None if text.len() > 6 => "<ERR:InvalidStringLength>".to_string(),
-                    None if text.chars().any(|c| c as u32 > 0x7F) => {
-                        "<ERR:InvalidStringNonAscii>".to_string()
-                    }
                     None => "<ERR:InvalidStringNull>".to_string(),
                 }
             })
@@ -33,14 +89,17 @@ pub fn preprocess(source: &str) -> SimulationResult<String> {
 
         line = hash_str_re
             .replace_all(&line, |caps: &regex::Captures| {
-                let text = &caps[1];
-                format!("{}", string_to_hash(text))
+                let text = unescape_literal(&caps[1]);
+                format!("{}", string_to_hash(&text))
             })
             .to_string();
 
         line = bin_re
             .replace_all(&line, |caps: &regex::Captures| {
                 match parse_binary_str(&caps[1]) {
+                    Some(val) if val.unsigned_abs() > MAX_EXACT_INTEGER as u64 => {
+                        "<ERR:LiteralExceedsPrecision>".to_string()
+                    }
                     Some(val) => format!("{val}"),
                     None => "<ERR:InvalidProcessBinary>".to_string(),
                 }
@@ -50,6 +109,9 @@ pub fn preprocess(source: &str) -> SimulationResult<String> {
         line = hex_re
             .replace_all(&line, |caps: &regex::Captures| {
                 match parse_hex_str(&caps[1]) {
+                    Some(val) if val.unsigned_abs() > MAX_EXACT_INTEGER as u64 => {
+                        "<ERR:LiteralExceedsPrecision>".to_string()
+                    }
                     Some(val) => format!("{val}"),
                     None => "<ERR:InvalidPreprocessHex>".to_string(),
                 }
@@ -61,6 +123,287 @@ pub fn preprocess(source: &str) -> SimulationResult<String> {
     Ok(result.join("\n"))
 }
 
+/// Check `source` against the game's program size limits.
+///
+/// Every line counts, including blank and comment-only lines, and a line's length includes
+/// its comment. The first offending line is reported.
+pub fn check_source_limits(source: &str) -> SimulationResult<()> {
+    for (line_num, line) in source.lines().enumerate() {
+        if line_num >= MAX_PROGRAM_LINES {
+            return Err(SimulationError::IC10ParseError {
+                line: line_num,
+                message: format!("Program exceeds {MAX_PROGRAM_LINES} lines"),
+            });
+        }
+
+        let length = line.chars().count();
+        if length > MAX_LINE_LENGTH {
+            return Err(SimulationError::IC10ParseError {
+                line: line_num,
+                message: format!("Line is {length} characters long (max {MAX_LINE_LENGTH})"),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// A preprocessed, parsed and validated IC10 program ready to load onto a chip
+#[derive(Debug, Clone)]
+pub struct Module {
+    source: String,
+    instructions: Vec<ParsedInstruction>,
+    labels: HashMap<String, usize>,
+}
+
+impl Module {
+    /// The original source the module was compiled from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Parsed instructions, one per source line, with define and label names already
+    /// replaced by their values where that cannot change behaviour
+    pub fn instructions(&self) -> &[ParsedInstruction] {
+        &self.instructions
+    }
+
+    /// Label names mapped to their line numbers
+    pub fn labels(&self) -> &HashMap<String, usize> {
+        &self.labels
+    }
+
+    /// Split the module into its source, instructions and labels
+    pub fn into_parts(self) -> (String, Vec<ParsedInstruction>, HashMap<String, usize>) {
+        (self.source, self.instructions, self.labels)
+    }
+}
+
+/// A problem found while compiling a program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Zero-based source line the problem was found on
+    pub line: usize,
+    /// The underlying error
+    pub error: SimulationError,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+/// Preprocess, parse and validate a whole program.
+///
+/// Every line is checked so all problems are reported at once, in line order. Checks cover
+/// instruction names and arity, duplicate labels and alias device pins. Literals that failed
+/// to preprocess are left as `<ERR:...>` markers, reported when the line runs.
+///
+/// A `define` value may be a constant expression over earlier defines and the builtin constants
+/// (see `instruction::evaluate_constant_expression`); it is evaluated here, at load time.
+///
+/// Defines and labels referenced by name are resolved to numeric operands up front (see
+/// `resolve_symbols`), so executing the module does no name lookups for them.
+pub fn compile_module(source: &str) -> Result<Module, Vec<Diagnostic>> {
+    compile_module_with_constants(source, &HashMap::new())
+}
+
+/// Compile like `compile_module`, with extra named constants available to the program.
+///
+/// The constants behave like defines declared before the first line: `define` expressions
+/// and operands may reference them, and they take precedence over builtin constants of the
+/// same name.
+pub fn compile_module_with_constants(
+    source: &str,
+    constants: &HashMap<String, f64>,
+) -> Result<Module, Vec<Diagnostic>> {
+    let preprocessed = preprocess(source).map_err(|error| {
+        let line = match error {
+            SimulationError::IC10ParseError { line, .. } => line,
+            _ => 0,
+        };
+        vec![Diagnostic { line, error }]
+    })?;
+
+    let (mut instructions, labels, diagnostics) = parse_lines(&preprocessed, constants);
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    resolve_symbols(&mut instructions, &labels, constants);
+
+    Ok(Module {
+        source: source.to_string(),
+        instructions,
+        labels,
+    })
+}
+
+/// Parse a whole program, reporting every bad line instead of stopping at the first.
+///
+/// Returns one instruction per source line; a line that fails to parse is a `Noop`
+/// placeholder keeping its source text, so indices still match line numbers. Errors come in
+/// line order and all carry their zero-based line: errors without one are reported as parse
+/// errors on the offending line. Unlike `compile_module`, names are left unresolved.
+pub fn parse_program(source: &str) -> (Vec<ParsedInstruction>, Vec<SimulationError>) {
+    let preprocessed = match preprocess(source) {
+        Ok(preprocessed) => preprocessed,
+        Err(error) => return (Vec::new(), vec![error]),
+    };
+
+    let (instructions, _, diagnostics) = parse_lines(&preprocessed, &HashMap::new());
+    let errors = diagnostics
+        .into_iter()
+        .map(|Diagnostic { line, error }| match error.line() {
+            Some(_) => error,
+            None => SimulationError::IC10ParseError {
+                line,
+                message: error.to_string(),
+            },
+        })
+        .collect();
+
+    (instructions, errors)
+}
+
+/// Parse every line of preprocessed source, collecting labels and problems.
+///
+/// A line that fails to parse becomes a `Noop` placeholder so instructions stay aligned with
+/// source lines.
+fn parse_lines(
+    preprocessed: &str,
+    constants: &HashMap<String, f64>,
+) -> (
+    Vec<ParsedInstruction>,
+    HashMap<String, usize>,
+    Vec<Diagnostic>,
+) {
+    let mut diagnostics = Vec::new();
+    let mut labels = HashMap::new();
+    let mut defines = constants.clone();
+    let mut instructions = Vec::new();
+
+    for (line_num, line) in preprocessed.lines().enumerate() {
+        let mut report = |error: SimulationError| {
+            diagnostics.push(Diagnostic {
+                line: line_num,
+                error,
+            })
+        };
+        let parsed = match ParsedInstruction::parse_with_defines(line, line_num, &defines) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                report(error);
+                instructions.push(ParsedInstruction {
+                    instruction: Instruction::Noop,
+                    line_number: line_num,
+                    original_line: line.to_string(),
+                });
+                continue;
+            }
+        };
+        match &parsed.instruction {
+            Instruction::Define { name, value } => {
+                defines.insert(name.clone(), *value);
+            }
+            // A label resolves to its own line, which executes as a no-op
+            Instruction::Label { name } => match labels.entry(name.clone()) {
+                Entry::Occupied(entry) => report(SimulationError::IC10ParseError {
+                    line: line_num,
+                    message: format!("Duplicate label: {}", entry.key()),
+                }),
+                Entry::Vacant(entry) => {
+                    entry.insert(line_num);
+                }
+            },
+            _ => {}
+        }
+
+        // Device aliases must target an existing pin
+        if let Instruction::Alias {
+            target: AliasTarget::Device(pin_idx),
+            ..
+        } = parsed.instruction
+            && pin_idx as usize >= DEVICE_PIN_COUNT
+        {
+            report(SimulationError::IC10ParseError {
+                line: line_num,
+                message: format!(
+                    "Device pin out of range: d{} (max d{})",
+                    pin_idx,
+                    DEVICE_PIN_COUNT - 1
+                ),
+            });
+        }
+
+        instructions.push(parsed);
+    }
+
+    (instructions, labels, diagnostics)
+}
+
+/// Replace define and label names with their values.
+///
+/// Defines apply to the whole program, as IC10 treats them as compile-time constants. At
+/// runtime values resolve defines, then aliases, then labels, while devices resolve aliases
+/// before defines, so a name is only inlined when that order cannot pick something else.
+/// Names bound by `alias` can change while the program runs and are left for runtime, as are
+/// names defined more than once with different values.
+fn resolve_symbols(
+    instructions: &mut [ParsedInstruction],
+    labels: &HashMap<String, usize>,
+    constants: &HashMap<String, f64>,
+) {
+    let mut defines = get_builtin_constants();
+    defines.extend(constants.iter().map(|(name, value)| (name.clone(), *value)));
+    let mut redefined = HashSet::new();
+    let mut aliases = HashSet::new();
+
+    for parsed in instructions.iter() {
+        match &parsed.instruction {
+            Instruction::Define { name, value } => {
+                if let Some(previous) = defines.insert(name.clone(), *value)
+                    && previous.to_bits() != value.to_bits()
+                {
+                    redefined.insert(name.clone());
+                }
+            }
+            Instruction::Alias { name, .. } => {
+                aliases.insert(name.clone());
+            }
+            _ => {}
+        }
+    }
+
+    let define = |name: &String| defines.get(name).filter(|_| !redefined.contains(name));
+
+    for parsed in instructions.iter_mut() {
+        parsed.instruction.for_each_operand_mut(|role, operand| {
+            let Operand::Alias(name) = operand else {
+                return;
+            };
+
+            let value = match role {
+                OperandRole::Destination => None,
+                OperandRole::Device if aliases.contains(name) => None,
+                OperandRole::Device => define(name).copied(),
+                _ if redefined.contains(name) => None,
+                _ => define(name).copied().or_else(|| {
+                    (!aliases.contains(name))
+                        .then(|| labels.get(name).map(|&line| line as f64))
+                        .flatten()
+                }),
+            };
+
+            if let Some(value) = value {
+                *operand = Operand::Immediate(value);
+            }
+        });
+    }
+}
+
 /// Pack an ASCII string (<=6 chars) into a 48-bit integer.
 pub fn pack_ascii6(text: &str) -> Option<i64> {
     if text.is_empty() || text.len() > 6 {
@@ -76,6 +419,28 @@ pub fn pack_ascii6(text: &str) -> Option<i64> {
     Some(num)
 }
 
+/// Unpack a value produced by `pack_ascii6` back into its text.
+///
+/// Returns `None` unless `value` is a non-negative integer below 2^48 whose non-zero bytes are
+/// all ASCII.
+pub fn unpack_ascii6(value: f64) -> Option<String> {
+    if !(0.0..(1u64 << 48) as f64).contains(&value) || value.fract() != 0.0 {
+        return None;
+    }
+    let num = value as u64;
+    let mut text = String::new();
+    for shift in (0..6).rev() {
+        let byte = (num >> (shift * 8)) as u8;
+        if byte > 0x7F {
+            return None;
+        }
+        if byte != 0 {
+            text.push(byte as char);
+        }
+    }
+    (!text.is_empty()).then_some(text)
+}
+
 /// Compute a CRC32-based hash compatible with Unity's hashing.
 pub const fn string_to_hash(text: &str) -> i32 {
     const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
@@ -83,6 +448,15 @@ pub const fn string_to_hash(text: &str) -> i32 {
     checksum as i32
 }
 
+/// Look up the display name of the device or item prefab whose prefab hash is `hash`, as
+/// registered with their factories (see `SimulationManager::hash_to_known_name` for names
+/// registered on a manager)
+pub fn hash_to_known_name(hash: i32) -> Option<&'static str> {
+    device_factory::get_prefab_metadata(hash)
+        .map(|(name, _)| name)
+        .or_else(|| item_factory::get_prefab_metadata(hash).map(|(name, _)| name))
+}
+
 /// Parse a binary literal string (no leading `%`).
 pub fn parse_binary_str(bin_str: &str) -> Option<i64> {
     let clean = bin_str.replace('_', "");
@@ -7,24 +7,154 @@
 //! - Runs simulation ticks in deterministic order
 //!
 //! Update order implemented here:
-//! 1. Process atmospheric network updates
-//! 2. Update all devices (by the manager's device list): first updates, then IC runners
+//! 1. Process atmospheric network updates: heat conduction across thermal links (ordered by
+//!    their network ids), then phase changes and over-pressure bursts (ascending network id)
+//! 2. Update all devices (ascending reference ID): first updates, then IC runners
+//!
+//! Every step iterates in id order rather than insertion order, so a scenario produces the
+//! same state regardless of the order its devices, networks and links were created in.
+//!
+//! `update_with_report` runs the same tick and returns a `TickReport` with per-tick metrics.
+//! `run_until_halt` repeats ticks until a chip halts or a total instruction cap is reached.
+//! `snapshot` captures the full device and network state in a serializable form, and
+//! `step_and_snapshot` runs a tick and returns that state as JSON.
+//!
+//! With the `parallel` feature, step 1 processes networks on worker threads, and step 2
+//! updates groups of devices that share no atmospheric or cable network concurrently. Each
+//! group still updates in ascending reference ID, so the result matches the sequential order.
+//! Ticks with fewer than `PARALLEL_MIN_WORK` networks or devices stay on the calling thread,
+//! and `set_parallel_updates(false)` forces the sequential path. One difference remains when a
+//! device update fails: the tick returns the lowest-ID failure in both modes, but in parallel
+//! the other groups have already updated, including devices with higher IDs that the
+//! sequential path never reached. Treat the state after a failed tick as unspecified.
+//!
+//! When flow logging is enabled, the moles each device moves through its atmospheric
+//! connections during step 2 are recorded in the flow log for the current tick.
 
 use crate::ItemIntegratedCircuit10;
 use crate::LogicSlotType;
 use crate::LogicType;
+use crate::atmospherics::{GasPropertiesTable, GasType};
 use crate::conversions::fmt_trim;
 use crate::devices::DeviceAtmosphericNetworkType;
 use crate::devices::device_factory;
+use crate::devices::{AirConditioner, DeviceBuilder, Filtration, VolumePump};
 use crate::devices::{Device, SimulationDeviceSettings};
-use crate::error::SimulationResult;
+use crate::error::{SimulationError, SimulationResult};
 use crate::items::item_factory;
-use crate::items::{self, Item, SimulationItemSettings};
+use crate::items::{self, ChipState, Item, ReagentRegistry, SimulationItemSettings};
 use crate::networks::{AtmosphericNetwork, CableNetwork};
+use crate::parser::{self, string_to_hash};
 use crate::types::Shared;
+use crate::types::SharedPtr;
 use crate::types::shared;
-use std::collections::{BTreeMap, HashSet};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
+use std::time::Duration;
+
+/// Networks or devices a tick step needs before the `parallel` feature splits it across the
+/// worker threads. Below this spawning the threads costs more than the work itself.
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_WORK: usize = 64;
+
+/// A single flow log record: moles of one gas moved through one device connection in a tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlowLogEntry {
+    /// Device reference ID
+    pub device_id: i32,
+    /// The device connection the network is attached to
+    pub connection: DeviceAtmosphericNetworkType,
+    /// The gas or liquid that moved
+    pub gas: GasType,
+    /// Moles added to the connected network (negative when removed)
+    pub moles: f64,
+}
+
+/// A wall conducting heat between two atmospheric networks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThermalLink {
+    /// First atmospheric network id
+    pub a: i32,
+    /// Second atmospheric network id
+    pub b: i32,
+    /// Joules conducted per kelvin of temperature difference per tick
+    pub conductivity: f64,
+}
+
+/// Metrics collected while running a single simulation tick
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TickReport {
+    /// Phase changes processed across all atmospheric networks
+    pub phase_changes: u32,
+    /// Total effects as returned by `update()`: phase changes plus devices reporting activity
+    pub effects: u32,
+    /// Instructions executed across all IC chips this tick
+    pub instructions_executed: usize,
+    /// Number of devices updated this tick
+    pub devices_updated: usize,
+    /// Wall-clock time spent in the tick (always zero on wasm32, which has no monotonic clock)
+    pub duration: Duration,
+}
+
+/// Serializable view of a simulation's state, as produced by `SimulationManager::snapshot`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SimulationSnapshot {
+    /// Simulation tick counter
+    pub ticks: u64,
+    /// Tracked devices in ascending reference ID order
+    pub devices: Vec<DeviceSnapshot>,
+    /// Cable networks in ascending id order
+    pub cable_networks: Vec<CableNetworkSnapshot>,
+    /// Atmospheric networks in ascending id order
+    pub atmospheric_networks: Vec<AtmosphericNetworkSnapshot>,
+}
+
+/// Serializable view of a device and its readable logic values
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DeviceSnapshot {
+    /// Device reference ID
+    pub id: i32,
+    /// Device prefab hash
+    pub prefab_hash: i32,
+    /// Device name
+    pub name: String,
+    /// Readable logic values keyed by logic type name; values that fail to read are omitted
+    pub logic: BTreeMap<String, f64>,
+}
+
+/// Serializable view of a cable network
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CableNetworkSnapshot {
+    /// Network id
+    pub id: i32,
+    /// Reference IDs of the devices on the network
+    pub device_ids: Vec<i32>,
+}
+
+/// Serializable view of an atmospheric network
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AtmosphericNetworkSnapshot {
+    /// Network id
+    pub id: i32,
+    /// Network volume in litres
+    pub volume: f64,
+    /// Pressure in kPa
+    pub pressure: f64,
+    /// Temperature in kelvin
+    pub temperature: f64,
+    /// Total moles of all gases and liquids
+    pub total_moles: f64,
+    /// Moles of each gas or liquid present, keyed by gas type name
+    pub moles: BTreeMap<String, f64>,
+}
+
+/// Per-gas moles of a network attached to a device connection, captured before a device update
+type ConnectionSnapshot = (
+    DeviceAtmosphericNetworkType,
+    Shared<AtmosphericNetwork>,
+    Vec<(GasType, f64)>,
+);
 
 /// Central manager for running the simulation
 #[derive(Default, Clone, Debug)]
@@ -40,12 +170,45 @@ pub struct SimulationManager {
     next_cable_network_id: i32,
     next_atmospheric_network_id: i32,
 
-    // Device/Item ID management
-    next_id: i32,
+    // Device/Item ID management; wider than the IDs so exhaustion can be detected
+    next_id: i64,
     allocated_ids: HashSet<i32>,
 
     // Simulation tick counter
     pub ticks: u64,
+
+    // Gas property overrides applied to atmospheric networks created by the manager
+    gas_properties: Option<GasPropertiesTable>,
+
+    // Heat-conducting walls between atmospheric networks
+    thermal_links: Vec<ThermalLink>,
+
+    // Atmospheric flow logging (disabled by default)
+    flow_logging: bool,
+    flow_log: Vec<FlowLogEntry>,
+
+    // Optional caps on tracked devices and manager-created networks (unlimited by default)
+    max_devices: Option<usize>,
+    max_networks: Option<usize>,
+
+    // Instruction limit for new devices that do not set their own
+    default_instruction_limit: Option<usize>,
+    // Optional cap on the instructions all chips together execute per tick
+    global_instruction_budget: Option<usize>,
+    // Tick rate for new devices and chips that do not set their own
+    ticks_per_second: Option<f64>,
+
+    // Names registered for reverse hash lookups, keyed by hash
+    names: HashMap<i32, String>,
+    // Reagents given to chips created by the manager, for `rmap`
+    reagents: SharedPtr<ReagentRegistry>,
+
+    // Run every tick on the calling thread even though the `parallel` feature is enabled
+    #[cfg(feature = "parallel")]
+    sequential_updates: bool,
+    // Worker count used for parallel work in tests, bypassing the work threshold
+    #[cfg(all(feature = "parallel", test))]
+    forced_workers: Option<usize>,
 }
 
 impl SimulationManager {
@@ -60,24 +223,136 @@ impl SimulationManager {
         }
     }
 
-    /// Allocate the next available ID
-    pub fn allocate_next_id(&mut self) -> i32 {
-        let id = self.next_id;
+    /// Create a new `SimulationManager` whose atmospheric networks use `properties`
+    pub fn with_gas_properties(properties: GasPropertiesTable) -> Self {
+        Self {
+            gas_properties: Some(properties),
+            ..Self::new()
+        }
+    }
+
+    /// Allocate the next available ID.
+    ///
+    /// IDs are handed out in increasing order above every ID allocated or reserved so far, so
+    /// the result is never a reserved or previously allocated ID (until `reset`). Errors once
+    /// the `i32` range is used up.
+    pub fn allocate_next_id(&mut self) -> SimulationResult<i32> {
+        let id = i32::try_from(self.next_id).map_err(|_| SimulationError::RuntimeError {
+            line: 0,
+            message: "No reference IDs left to allocate".to_string(),
+        })?;
         self.allocated_ids.insert(id);
         self.next_id += 1;
-        id
+        Ok(id)
     }
 
-    /// Reserve a specific ID; returns true if successful
+    /// Reserve a specific ID; returns true if successful, false if it is already allocated.
+    /// Later calls to `allocate_next_id` only return IDs above it.
     pub fn reserve_id(&mut self, id: i32) -> bool {
-        if self.allocated_ids.contains(&id) {
-            false
-        } else {
-            self.allocated_ids.insert(id);
-            if id >= self.next_id {
-                self.next_id = id + 1;
-            }
-            true
+        if !self.allocated_ids.insert(id) {
+            return false;
+        }
+        self.next_id = self.next_id.max(i64::from(id) + 1);
+        true
+    }
+
+    /// Limit the number of devices this manager tracks; `None` removes the limit
+    pub fn set_max_devices(&mut self, max: Option<usize>) {
+        self.max_devices = max;
+    }
+
+    /// Get the device limit, if any
+    pub fn max_devices(&self) -> Option<usize> {
+        self.max_devices
+    }
+
+    /// Limit the number of cable and atmospheric networks created through this manager.
+    /// Internal networks owned by devices are covered by the device limit instead.
+    /// `None` removes the limit.
+    pub fn set_max_networks(&mut self, max: Option<usize>) {
+        self.max_networks = max;
+    }
+
+    /// Get the network limit, if any
+    pub fn max_networks(&self) -> Option<usize> {
+        self.max_networks
+    }
+
+    /// Set the `max_instructions_per_tick` given to devices created afterwards that do not set
+    /// their own; `None` restores the built-in default
+    pub fn set_default_instruction_limit(&mut self, limit: Option<usize>) {
+        self.default_instruction_limit = limit;
+    }
+
+    /// Get the default instruction limit for new devices, if any
+    pub fn default_instruction_limit(&self) -> Option<usize> {
+        self.default_instruction_limit
+    }
+
+    /// Set the simulation ticks per second of game time given to devices created afterwards
+    /// that do not set their own, and to chips created afterwards; `None` restores
+    /// `DEFAULT_TICKS_PER_SECOND`
+    pub fn set_ticks_per_second(&mut self, ticks_per_second: Option<f64>) {
+        self.ticks_per_second = ticks_per_second;
+    }
+
+    /// Get the tick rate for new devices and chips, if any
+    pub fn ticks_per_second(&self) -> Option<f64> {
+        self.ticks_per_second
+    }
+
+    /// Register `name` for reverse lookups with `hash_to_known_name`, returning its hash
+    pub fn register_name(&mut self, name: &str) -> i32 {
+        let hash = string_to_hash(name);
+        self.names.insert(hash, name.to_string());
+        hash
+    }
+
+    /// Look up a human-readable name for `hash`.
+    ///
+    /// Names registered with `register_name` are checked first, then the display names of
+    /// device and item prefabs (see `parser::hash_to_known_name`).
+    pub fn hash_to_known_name(&self, hash: i32) -> Option<&str> {
+        self.names
+            .get(&hash)
+            .map(String::as_str)
+            .or_else(|| parser::hash_to_known_name(hash))
+    }
+
+    /// Register a reagent for `rmap` on chips created afterwards, returning its hash (see
+    /// `ReagentRegistry::register`)
+    pub fn register_reagent(&mut self, name: &str, prefab_hash: i32) -> i32 {
+        SharedPtr::make_mut(&mut self.reagents).register(name, prefab_hash)
+    }
+
+    /// Get the reagents given to chips created by this manager
+    pub fn reagents(&self) -> &ReagentRegistry {
+        &self.reagents
+    }
+
+    /// Limit the instructions all chips together execute per tick; `None` removes the limit.
+    ///
+    /// Each tick the budget is shared between the IC hosts in update order: every host may use
+    /// an equal share of what is left, within its own limit, and whatever a host does not use
+    /// passes on to the hosts after it.
+    pub fn set_global_instruction_budget(&mut self, budget: Option<usize>) {
+        self.global_instruction_budget = budget;
+    }
+
+    /// Get the global per-tick instruction budget, if any
+    pub fn global_instruction_budget(&self) -> Option<usize> {
+        self.global_instruction_budget
+    }
+
+    /// Return an error when creating another network would exceed the network limit
+    fn check_network_limit(&self) -> SimulationResult<()> {
+        let count = self.cable_networks.len() + self.atmospheric_networks.len();
+        match self.max_networks {
+            Some(max) if count >= max => Err(SimulationError::RuntimeError {
+                line: 0,
+                message: format!("Network limit of {max} reached"),
+            }),
+            _ => Ok(()),
         }
     }
 
@@ -88,32 +363,479 @@ impl SimulationManager {
 
     /// Perform a simulation tick in the correct order and return the total number of phase changes.
     pub fn update(&mut self) -> SimulationResult<u32> {
+        Ok(self.update_with_report()?.effects)
+    }
+
+    /// Perform a simulation tick like `update()` and return metrics about it. Returns the
+    /// error of the lowest-ID device whose update failed; with parallel updates, devices in
+    /// other groups may already have updated by then (see the module docs).
+    pub fn update_with_report(&mut self) -> SimulationResult<TickReport> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = std::time::Instant::now();
+
+        let mut report = TickReport::default();
         self.ticks += 1;
 
         // 1) Process atmospheric updates
-        let mut total_effects: u32 = 0;
-        for net in self.atmospheric_networks.values() {
-            total_effects += net.borrow_mut().process_phase_changes();
+        for link in &self.thermal_links {
+            if let (Some(a), Some(b)) = (
+                self.atmospheric_networks.get(&link.a),
+                self.atmospheric_networks.get(&link.b),
+            ) {
+                a.borrow_mut()
+                    .conduct_heat_with(&mut b.borrow_mut(), link.conductivity);
+            }
         }
 
+        // Each network's own processing is independent of every other network
+        let networks = self.atmospheric_networks.values().collect::<Vec<_>>();
+        report.phase_changes = self
+            .map_independent(&networks, self.parallel_workers(networks.len()), |net| {
+                let mut net = net.borrow_mut();
+                let phase_changes = net.process_phase_changes();
+                net.process_over_pressure();
+                phase_changes
+            })
+            .into_iter()
+            .sum();
+        let mut total_effects = report.phase_changes;
+
         // 2) Update all devices tracked by the manager (ascending reference ID)
         let devices = self.devices.values().collect::<Vec<_>>();
 
-        // First, call update on all devices in ascending order
-        for device in &devices {
-            if device.borrow().update(self.ticks)? {
+        self.flow_log.clear();
+
+        // Reset each network's flow cap and count the devices sharing it this tick
+        let connections = devices
+            .iter()
+            .map(|device| Self::atmospheric_connections(&*device.borrow()))
+            .collect::<Vec<_>>();
+        for net in self
+            .atmospheric_networks
+            .values()
+            .chain(connections.iter().flatten())
+        {
+            net.borrow_mut().begin_flow_tick();
+        }
+        for net in connections.iter().flatten() {
+            net.borrow_mut().add_flow_user();
+        }
+
+        // First, call update on all devices in ascending order; groups that share no network
+        // cannot affect each other, so only the order within a group matters
+        let (ticks, flow_logging) = (self.ticks, self.flow_logging);
+        let workers = self.parallel_workers(devices.len());
+        let groups = self.device_groups(&devices, &connections, workers);
+        let mut results = self
+            .map_independent(&groups, workers, |group| {
+                let mut results = Vec::with_capacity(group.len());
+                for &index in group {
+                    let result = Self::update_device(
+                        devices[index],
+                        &connections[index],
+                        ticks,
+                        flow_logging,
+                    );
+                    let failed = result.is_err();
+                    results.push((index, result));
+                    if failed {
+                        break;
+                    }
+                }
+                results
+            })
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        results.sort_by_key(|(index, _)| *index);
+
+        for (_, result) in results {
+            let (updated, entries) = result?;
+            if updated {
                 total_effects = total_effects.saturating_add(1);
             }
+            self.flow_log.extend(entries);
         }
 
         // Then execute run() on all devices in the same order
+        let mut hosts_left = devices
+            .iter()
+            .filter(|device| device.borrow().as_ic_host_device().is_some())
+            .count();
+        let mut budget_left = self.global_instruction_budget;
+
         for device in &devices {
-            if device.borrow().run()? {
+            let device = device.borrow();
+            let host = device.as_ic_host_device();
+
+            // Offer this host its share of the remaining budget
+            if let Some(host) = host {
+                let share = budget_left.map(|left| left.div_ceil(hosts_left.max(1)));
+                host.chip_slot().borrow().set_instruction_budget(share);
+                hosts_left = hosts_left.saturating_sub(1);
+            }
+
+            let ran = device.run();
+            if let Some(host) = host {
+                host.chip_slot().borrow().set_instruction_budget(None);
+            }
+
+            if ran? {
                 total_effects = total_effects.saturating_add(1);
+
+                // A host reports activity only when its chip ran this tick
+                if let Some(host) = host {
+                    let executed = host.get_last_executed_instructions();
+                    report.instructions_executed += executed;
+                    budget_left = budget_left.map(|left| left.saturating_sub(executed));
+                }
+            }
+        }
+
+        report.effects = total_effects;
+        report.devices_updated = devices.len();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            report.duration = start.elapsed();
+        }
+
+        Ok(report)
+    }
+
+    /// Run ticks until the chip with `chip_id` halts or `max_total_instructions` is reached.
+    /// The cap is checked between ticks, so the last tick may run past it.
+    /// Returns the instructions the chip executed and whether it halted.
+    /// Ticks spent asleep are run through; ticking stops early only when the chip executes
+    /// nothing without being asleep (e.g. its host is switched off).
+    pub fn run_until_halt(
+        &mut self,
+        chip_id: i32,
+        max_total_instructions: usize,
+    ) -> SimulationResult<(usize, bool)> {
+        let (host, chip) = self.find_chip_host(chip_id)?;
+
+        let mut executed = 0;
+        while !chip.borrow().is_halted() && executed < max_total_instructions {
+            self.update()?;
+
+            let last = host
+                .borrow()
+                .as_ic_host_device()
+                .map_or(0, |h| h.get_last_executed_instructions());
+            let asleep = matches!(chip.borrow().state(), ChipState::Sleeping { .. });
+            if last == 0 && !asleep {
+                break;
+            }
+            executed += last;
+        }
+
+        let halted = chip.borrow().is_halted();
+        Ok((executed, halted))
+    }
+
+    /// Find the tracked device hosting the chip with `chip_id`, along with the chip
+    fn find_chip_host(
+        &self,
+        chip_id: i32,
+    ) -> SimulationResult<(Shared<dyn Device>, Shared<ItemIntegratedCircuit10>)> {
+        let busy = |what: String| SimulationError::RuntimeError {
+            line: 0,
+            message: format!("{what} is busy and cannot be searched for chip {chip_id}"),
+        };
+        for (id, device) in &self.devices {
+            let device_ref = device
+                .try_borrow()
+                .map_err(|_| busy(format!("Device {id}")))?;
+            let Some(host) = device_ref.as_ic_host_device() else {
+                continue;
+            };
+            let slot = host.chip_slot();
+            let chip = slot
+                .try_borrow()
+                .map_err(|_| busy(format!("Chip slot of device {id}")))?
+                .get_chip_shared();
+            let Some(chip) = chip else {
+                continue;
+            };
+            let hosted_id = chip
+                .try_borrow()
+                .map_err(|_| busy(format!("Chip in device {id}")))?
+                .get_id();
+            if hosted_id == chip_id {
+                return Ok((device.clone(), chip));
+            }
+        }
+
+        Err(SimulationError::RuntimeError {
+            line: 0,
+            message: format!("No tracked device hosts chip {chip_id}"),
+        })
+    }
+
+    /// List the distinct atmospheric networks connected to `device`
+    fn atmospheric_connections(device: &dyn Device) -> Vec<Shared<AtmosphericNetwork>> {
+        let mut networks: Vec<Shared<AtmosphericNetwork>> = Vec::new();
+        if let Some(atmo_device) = device.as_atmospheric_device() {
+            for connection in DeviceAtmosphericNetworkType::all() {
+                if let Some(net) = atmo_device.get_atmospheric_network(connection)
+                    && !networks.iter().any(|known| SharedPtr::ptr_eq(known, &net))
+                {
+                    networks.push(net);
+                }
+            }
+        }
+        networks
+    }
+
+    /// Update one device, bracketing it with the flow cap bookkeeping of its `connections`.
+    /// Returns whether the device changed anything and, when `flow_logging` is set, the
+    /// moles it moved through each connection.
+    fn update_device(
+        device: &Shared<dyn Device>,
+        connections: &[Shared<AtmosphericNetwork>],
+        tick: u64,
+        flow_logging: bool,
+    ) -> SimulationResult<(bool, Vec<FlowLogEntry>)> {
+        let snapshot = if flow_logging {
+            Self::snapshot_connections(&*device.borrow())
+        } else {
+            Vec::new()
+        };
+
+        for net in connections {
+            net.borrow_mut().begin_device_flow();
+        }
+        let updated = device.borrow().update(tick);
+        for net in connections {
+            net.borrow_mut().end_device_flow();
+        }
+        let updated = updated?;
+
+        let device_id = device.borrow().get_id();
+        let mut entries = Vec::new();
+        for (connection, net, before) in snapshot {
+            let net = net.borrow();
+            for (gas, moles_before) in before {
+                let moles = net.get_moles(gas) - moles_before;
+                if moles != 0.0 {
+                    entries.push(FlowLogEntry {
+                        device_id,
+                        connection,
+                        gas,
+                        moles,
+                    });
+                }
+            }
+        }
+
+        Ok((updated, entries))
+    }
+
+    /// Split `devices` (by index) into groups that can update independently. Without the
+    /// `parallel` feature every device is updated in one group.
+    #[cfg(not(feature = "parallel"))]
+    fn device_groups(
+        &self,
+        devices: &[&Shared<dyn Device>],
+        _connections: &[Vec<Shared<AtmosphericNetwork>>],
+        _workers: usize,
+    ) -> Vec<Vec<usize>> {
+        vec![(0..devices.len()).collect()]
+    }
+
+    /// Split `devices` (by index) into groups that can update independently: devices sharing
+    /// an atmospheric or cable network end up in the same group. Each group keeps ascending
+    /// index order. When the update runs on a single worker, every device is in one group.
+    #[cfg(feature = "parallel")]
+    fn device_groups(
+        &self,
+        devices: &[&Shared<dyn Device>],
+        connections: &[Vec<Shared<AtmosphericNetwork>>],
+        workers: usize,
+    ) -> Vec<Vec<usize>> {
+        if workers <= 1 {
+            return vec![(0..devices.len()).collect()];
+        }
+
+        fn find(parents: &mut [usize], mut index: usize) -> usize {
+            while parents[index] != index {
+                parents[index] = parents[parents[index]];
+                index = parents[index];
             }
+            index
         }
 
-        Ok(total_effects)
+        // Union each device with the first device seen on each of its networks
+        let mut parents = (0..devices.len()).collect::<Vec<_>>();
+        let mut first_user = std::collections::HashMap::new();
+        for (index, (device, connections)) in devices.iter().zip(connections).enumerate() {
+            let cable = device.borrow().get_network();
+            let keys = connections
+                .iter()
+                .map(|net| SharedPtr::as_ptr(net) as *const () as usize)
+                .chain(cable.map(|net| SharedPtr::as_ptr(&net) as *const () as usize));
+            for key in keys {
+                let first = *first_user.entry(key).or_insert(index);
+                let (a, b) = (find(&mut parents, first), find(&mut parents, index));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for index in 0..devices.len() {
+            let root = find(&mut parents, index);
+            groups.entry(root).or_default().push(index);
+        }
+        groups.into_values().collect()
+    }
+
+    /// Number of threads to split a step over `work` networks or devices across. Without the
+    /// `parallel` feature everything runs on the calling thread.
+    #[cfg(not(feature = "parallel"))]
+    fn parallel_workers(&self, _work: usize) -> usize {
+        1
+    }
+
+    /// Number of threads to split a step over `work` networks or devices across. Steps below
+    /// `PARALLEL_MIN_WORK` stay on the calling thread, where handing work to other threads
+    /// would cost more than it saves.
+    #[cfg(feature = "parallel")]
+    fn parallel_workers(&self, work: usize) -> usize {
+        if self.sequential_updates {
+            return 1;
+        }
+
+        #[cfg(test)]
+        if let Some(workers) = self.forced_workers {
+            return workers;
+        }
+
+        if work < PARALLEL_MIN_WORK {
+            1
+        } else {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        }
+    }
+
+    /// Apply `f` to every item, returning the results in item order. Without the `parallel`
+    /// feature this is a plain sequential map.
+    #[cfg(not(feature = "parallel"))]
+    fn map_independent<T, R>(&self, items: &[T], _workers: usize, f: impl Fn(&T) -> R) -> Vec<R> {
+        items.iter().map(f).collect()
+    }
+
+    /// Apply `f` to every item, returning the results in item order. With more than one
+    /// worker, items are split into contiguous chunks processed on scoped threads, so `f`
+    /// must only touch state owned by its item.
+    #[cfg(feature = "parallel")]
+    fn map_independent<T: Sync, R: Send>(
+        &self,
+        items: &[T],
+        workers: usize,
+        f: impl Fn(&T) -> R + Sync,
+    ) -> Vec<R> {
+        if workers.min(items.len()) <= 1 {
+            return items.iter().map(f).collect();
+        }
+
+        let f = &f;
+        std::thread::scope(|scope| {
+            let mut chunks = items.chunks(items.len().div_ceil(workers));
+            let first = chunks.next().unwrap_or_default();
+            let handles = chunks
+                .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+
+            // The calling thread takes the first chunk instead of waiting idle
+            let mut results: Vec<R> = first.iter().map(f).collect();
+            for handle in handles {
+                let chunk_results = handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                results.extend(chunk_results);
+            }
+            results
+        })
+    }
+
+    /// Split parallel work across exactly `workers` threads regardless of the work threshold
+    /// and the machine's core count, so tests exercise the concurrent path everywhere
+    #[cfg(all(feature = "parallel", test))]
+    pub(crate) fn force_parallel_workers(&mut self, workers: usize) {
+        self.forced_workers = Some(workers);
+    }
+
+    /// Capture the per-gas moles of every atmospheric network connected to `device`
+    fn snapshot_connections(device: &dyn Device) -> Vec<ConnectionSnapshot> {
+        let Some(atmo_device) = device.as_atmospheric_device() else {
+            return Vec::new();
+        };
+
+        DeviceAtmosphericNetworkType::all()
+            .filter_map(|connection| {
+                let net = atmo_device.get_atmospheric_network(connection)?;
+                let moles = GasType::all()
+                    .map(|gas| (gas, net.borrow().get_moles(gas)))
+                    .collect();
+                Some((connection, net, moles))
+            })
+            .collect()
+    }
+
+    /// Enable or disable atmospheric flow logging
+    pub fn set_flow_logging(&mut self, enabled: bool) {
+        self.flow_logging = enabled;
+        if !enabled {
+            self.flow_log.clear();
+        }
+    }
+
+    /// Check whether atmospheric flow logging is enabled
+    pub fn is_flow_logging(&self) -> bool {
+        self.flow_logging
+    }
+
+    /// Enable or disable concurrent network and device updates (enabled by default)
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel_updates(&mut self, enabled: bool) {
+        self.sequential_updates = !enabled;
+    }
+
+    /// Check whether ticks process independent networks and devices concurrently
+    #[cfg(feature = "parallel")]
+    pub fn is_parallel_updates(&self) -> bool {
+        !self.sequential_updates
+    }
+
+    /// Get the flow log recorded during the most recent tick
+    pub fn flow_log(&self) -> &[FlowLogEntry] {
+        &self.flow_log
+    }
+
+    /// Thermally link two atmospheric networks by id so heat conducts between them each tick
+    /// without moving moles. Returns false if either network is unknown or both ids match.
+    pub fn thermally_link(&mut self, a: i32, b: i32, conductivity: f64) -> bool {
+        if a == b
+            || !self.atmospheric_networks.contains_key(&a)
+            || !self.atmospheric_networks.contains_key(&b)
+        {
+            return false;
+        }
+
+        // Keep links ordered by their network ids so heat conduction is applied deterministically
+        let key = |link: &ThermalLink| (link.a.min(link.b), link.a.max(link.b));
+        let link = ThermalLink { a, b, conductivity };
+        let pos = self
+            .thermal_links
+            .partition_point(|existing| key(existing) <= key(&link));
+        self.thermal_links.insert(pos, link);
+        true
+    }
+
+    /// Get all thermal links between atmospheric networks, ordered by their network ids
+    pub fn thermal_links(&self) -> &[ThermalLink] {
+        &self.thermal_links
     }
 
     /// Reset internal manager state by removing devices and clearing networks.
@@ -131,6 +853,7 @@ impl SimulationManager {
 
         self.cable_networks.clear();
         self.atmospheric_networks.clear();
+        self.thermal_links.clear();
 
         // Reset ID counters
         self.next_cable_network_id = 1;
@@ -143,55 +866,123 @@ impl SimulationManager {
     }
 
     /// Create a new device by prefab hash using the device factory and track it.
+    /// Fails when the ID is taken, the prefab is unknown or the device limit is reached.
     pub fn create_device(
         &mut self,
         prefab_hash: i32,
         settings: Option<SimulationDeviceSettings>,
-    ) -> Option<Shared<dyn Device>> {
+    ) -> SimulationResult<Shared<dyn Device>> {
+        let settings = self.prepare_device_settings(settings)?;
+        let id = settings.id.unwrap();
+
+        if let Some(d) = device_factory::create_device(prefab_hash, settings) {
+            self.track_device(d.clone());
+            Ok(d)
+        } else {
+            // Creation failed, free reserved id
+            self.allocated_ids.remove(&id);
+            Err(SimulationError::RuntimeError {
+                line: 0,
+                message: format!("Unsupported prefab hash for device creation: {prefab_hash}"),
+            })
+        }
+    }
+
+    /// Start a fluent builder for a device of type `T` constructed by `new`.
+    /// The built device is tracked by this manager and registered on a cable network.
+    pub fn build_device<T: Device + 'static>(
+        &mut self,
+        new: fn(SimulationDeviceSettings) -> Shared<T>,
+    ) -> DeviceBuilder<'_, T> {
+        DeviceBuilder::new(self, new)
+    }
+
+    /// Start a fluent builder for an `AirConditioner`
+    pub fn build_air_conditioner(&mut self) -> DeviceBuilder<'_, AirConditioner> {
+        self.build_device(AirConditioner::new)
+    }
+
+    /// Start a fluent builder for a `Filtration`
+    pub fn build_filtration(&mut self) -> DeviceBuilder<'_, Filtration> {
+        self.build_device(Filtration::new)
+    }
+
+    /// Start a fluent builder for a `VolumePump`
+    pub fn build_volume_pump(&mut self) -> DeviceBuilder<'_, VolumePump> {
+        self.build_device(VolumePump::new)
+    }
+
+    /// Check the device limit and assign a reference ID to `settings`, reserving the requested
+    /// ID or allocating the next free one
+    pub(crate) fn prepare_device_settings(
+        &mut self,
+        settings: Option<SimulationDeviceSettings>,
+    ) -> SimulationResult<SimulationDeviceSettings> {
+        if let Some(max) = self.max_devices
+            && self.devices.len() >= max
+        {
+            return Err(SimulationError::RuntimeError {
+                line: 0,
+                message: format!("Device limit of {max} reached"),
+            });
+        }
+
         // Prepare settings and ensure an ID is set; when not provided (0) use the manager's counter
         let mut settings = settings.unwrap_or_default();
+        if settings.max_instructions_per_tick.is_none() {
+            settings.max_instructions_per_tick = self.default_instruction_limit;
+        }
+        if settings.ticks_per_second.is_none() {
+            settings.ticks_per_second = self.ticks_per_second;
+        }
 
         let id = if let Some(id) = settings.id {
             if !self.reserve_id(id) {
-                return None;
+                return Err(SimulationError::RuntimeError {
+                    line: 0,
+                    message: format!("ID {id} is already allocated"),
+                });
             }
             id
         } else {
-            self.allocate_next_id()
+            self.allocate_next_id()?
         };
 
         settings.id = Some(id);
+        Ok(settings)
+    }
 
-        if let Some(d) = device_factory::create_device(prefab_hash, settings) {
-            // If the device created an internal atmospheric network, track it as well
-            if let Some(atmo_device) = d.borrow().as_atmospheric_device()
-                && let Some(atmo_net) =
-                    atmo_device.get_atmospheric_network(DeviceAtmosphericNetworkType::Internal)
-            {
-                if let Some(atmo_net_id) = atmo_net.borrow().get_id() {
-                    if !self.atmospheric_networks.contains_key(&atmo_net_id) {
-                        panic!("Internal atmospheric network has an ID not tracked by the manager");
-                    }
-                } else {
-                    let atmo_id = self.next_atmospheric_network_id;
-                    self.next_atmospheric_network_id += 1;
-                    atmo_net.borrow_mut().set_id(Some(atmo_id));
-                    self.atmospheric_networks.insert(atmo_id, atmo_net.clone());
+    /// Release a reference ID reserved for a device that was never tracked
+    pub(crate) fn release_id(&mut self, id: i32) {
+        self.allocated_ids.remove(&id);
+    }
+
+    /// Track a created device and its internal atmospheric network, if any
+    pub(crate) fn track_device(&mut self, d: Shared<dyn Device>) {
+        // If the device created an internal atmospheric network, track it as well
+        if let Some(atmo_device) = d.borrow().as_atmospheric_device()
+            && let Some(atmo_net) =
+                atmo_device.get_atmospheric_network(DeviceAtmosphericNetworkType::Internal)
+        {
+            if let Some(atmo_net_id) = atmo_net.borrow().get_id() {
+                if !self.atmospheric_networks.contains_key(&atmo_net_id) {
+                    panic!("Internal atmospheric network has an ID not tracked by the manager");
                 }
+            } else {
+                let atmo_id = self.next_atmospheric_network_id;
+                self.next_atmospheric_network_id += 1;
+                atmo_net.borrow_mut().set_id(Some(atmo_id));
+                self.atmospheric_networks.insert(atmo_id, atmo_net.clone());
             }
-
-            // Track the created device
-            self.devices.insert(d.borrow().get_id(), d.clone());
-
-            Some(d)
-        } else {
-            // Creation failed, free reserved id
-            self.allocated_ids.remove(&id);
-            None
         }
+
+        // Track the created device
+        let id = d.borrow().get_id();
+        self.devices.insert(id, d);
     }
 
     /// Create a new item by prefab hash using the item factory and track it.
+    /// Returns `None` when the ID is taken, no IDs are left or the prefab is unknown.
     pub fn create_item(
         &mut self,
         prefab_hash: i32,
@@ -205,7 +996,7 @@ impl SimulationManager {
             }
             id
         } else {
-            self.allocate_next_id()
+            self.allocate_next_id().ok()?
         };
 
         settings.id = Some(id);
@@ -214,13 +1005,19 @@ impl SimulationManager {
     }
 
     /// Create an IC10 chip item via this `SimulationManager`.
-    pub fn create_chip(&mut self) -> Shared<ItemIntegratedCircuit10> {
+    /// Fails when no reference IDs are left.
+    pub fn create_chip(&mut self) -> SimulationResult<Shared<ItemIntegratedCircuit10>> {
         let settings = SimulationItemSettings {
-            id: Some(self.allocate_next_id()),
+            id: Some(self.allocate_next_id()?),
             ..Default::default()
         };
 
-        shared(ItemIntegratedCircuit10::new(settings))
+        let mut chip = ItemIntegratedCircuit10::new(settings);
+        if let Some(ticks_per_second) = self.ticks_per_second {
+            chip.set_ticks_per_second(ticks_per_second);
+        }
+        chip.set_reagents(SharedPtr::clone(&self.reagents));
+        Ok(shared(chip))
     }
 
     /// Remove a device tracked by this manager by reference ID
@@ -260,6 +1057,17 @@ impl SimulationManager {
         self.atmospheric_networks.values().cloned().collect()
     }
 
+    /// Number of cable networks registered with this manager
+    pub fn cable_network_count(&self) -> usize {
+        self.cable_networks.len()
+    }
+
+    /// Number of atmospheric networks registered with this manager, including the internal
+    /// networks of tracked devices
+    pub fn atmospheric_network_count(&self) -> usize {
+        self.atmospheric_networks.len()
+    }
+
     /// Get a cable network by its assigned id
     pub fn get_cable_network_by_id(&self, id: i32) -> Option<Shared<CableNetwork>> {
         self.cable_networks.get(&id).cloned()
@@ -272,24 +1080,34 @@ impl SimulationManager {
 
     /// Create a new cable network and register it with this manager.
     /// The manager assigns a unique id and stores it in the network.
-    pub fn create_cable_network(&mut self) -> Shared<CableNetwork> {
+    /// Fails when the network limit is reached.
+    pub fn create_cable_network(&mut self) -> SimulationResult<Shared<CableNetwork>> {
+        self.check_network_limit()?;
         let network = CableNetwork::new();
         let id = self.next_cable_network_id;
         self.next_cable_network_id += 1;
         network.borrow_mut().set_id(Some(id));
         self.cable_networks.insert(id, network.clone());
-        network
+        Ok(network)
     }
 
     /// Create a new atmospheric network and register it with this manager.
     /// The manager assigns a unique id and stores it in the network.
-    pub fn create_atmospheric_network(&mut self, volume: f64) -> Shared<AtmosphericNetwork> {
-        let network = AtmosphericNetwork::new(volume);
+    /// Fails when the network limit is reached.
+    pub fn create_atmospheric_network(
+        &mut self,
+        volume: f64,
+    ) -> SimulationResult<Shared<AtmosphericNetwork>> {
+        self.check_network_limit()?;
+        let network = match &self.gas_properties {
+            Some(properties) => AtmosphericNetwork::with_properties(volume, properties),
+            None => AtmosphericNetwork::new(volume),
+        };
         let id = self.next_atmospheric_network_id;
         self.next_atmospheric_network_id += 1;
         network.borrow_mut().set_id(Some(id));
         self.atmospheric_networks.insert(id, network.clone());
-        network
+        Ok(network)
     }
 
     /// Remove a cable network by its assigned id
@@ -299,8 +1117,197 @@ impl SimulationManager {
 
     /// Remove an atmospheric network by its assigned id
     pub fn remove_atmospheric_network(&mut self, id: i32) -> Option<Shared<AtmosphericNetwork>> {
+        self.thermal_links
+            .retain(|link| link.a != id && link.b != id);
         self.atmospheric_networks.remove(&id)
     }
+
+    /// Capture the current state of every tracked device and registered network
+    pub fn snapshot(&self) -> SimulationSnapshot {
+        let devices = self
+            .devices
+            .iter()
+            .map(|(id, device)| {
+                let device = device.borrow();
+                let logic = device
+                    .supported_types()
+                    .into_iter()
+                    .filter(|&logic_type| device.can_read(logic_type))
+                    .filter_map(|logic_type| {
+                        let value = device.read(logic_type).ok()?;
+                        Some((format!("{:?}", logic_type), value))
+                    })
+                    .collect();
+                DeviceSnapshot {
+                    id: *id,
+                    prefab_hash: device.get_prefab_hash(),
+                    name: device.get_name().to_string(),
+                    logic,
+                }
+            })
+            .collect();
+
+        let cable_networks = self
+            .cable_networks
+            .iter()
+            .map(|(id, network)| {
+                let mut device_ids = network.borrow().all_device_ids();
+                device_ids.sort_unstable();
+                CableNetworkSnapshot {
+                    id: *id,
+                    device_ids,
+                }
+            })
+            .collect();
+
+        let atmospheric_networks = self
+            .atmospheric_networks
+            .iter()
+            .map(|(id, network)| {
+                let network = network.borrow();
+                let moles = GasType::all()
+                    .map(|gas| (gas, network.get_moles(gas)))
+                    .filter(|&(_, moles)| moles > 0.0)
+                    .map(|(gas, moles)| (format!("{:?}", gas), moles))
+                    .collect();
+                AtmosphericNetworkSnapshot {
+                    id: *id,
+                    volume: network.volume(),
+                    pressure: network.pressure(),
+                    temperature: network.temperature(),
+                    total_moles: network.total_moles(),
+                    moles,
+                }
+            })
+            .collect();
+
+        SimulationSnapshot {
+            ticks: self.ticks,
+            devices,
+            cable_networks,
+            atmospheric_networks,
+        }
+    }
+
+    /// Serialize `snapshot()` as a JSON string. Non-finite values are written as `null`.
+    pub fn snapshot_json(&self) -> String {
+        serde_json::to_string(&self.snapshot()).expect("snapshot serialization cannot fail")
+    }
+
+    /// Run one simulation tick and return the resulting state as a JSON string
+    pub fn step_and_snapshot(&mut self) -> SimulationResult<String> {
+        self.update()?;
+        Ok(self.snapshot_json())
+    }
+
+    /// Compare this manager's state against `other` and describe every difference found.
+    ///
+    /// Reports the tick counter, devices present in only one manager, differing readable logic
+    /// values of shared devices (by reference ID), atmospheric networks present in only one
+    /// manager, and differing per-gas moles and temperature of shared networks (by network id).
+    /// Values are compared exactly, so an empty result means both states are indistinguishable
+    /// through these observations.
+    pub fn diff(&self, other: &SimulationManager) -> Vec<String> {
+        fn same(a: f64, b: f64) -> bool {
+            a == b || (a.is_nan() && b.is_nan())
+        }
+
+        let mut differences = Vec::new();
+
+        if self.ticks != other.ticks {
+            differences.push(format!("Ticks: {} != {}", self.ticks, other.ticks));
+        }
+
+        let device_ids: BTreeSet<i32> = self
+            .devices
+            .keys()
+            .chain(other.devices.keys())
+            .copied()
+            .collect();
+        for id in device_ids {
+            let (ours, theirs) = match (self.devices.get(&id), other.devices.get(&id)) {
+                (Some(a), Some(b)) => (a.borrow(), b.borrow()),
+                (Some(_), None) => {
+                    differences.push(format!("Device #{}: missing from other", id));
+                    continue;
+                }
+                (None, _) => {
+                    differences.push(format!("Device #{}: missing from self", id));
+                    continue;
+                }
+            };
+
+            if ours.get_prefab_hash() != theirs.get_prefab_hash() {
+                differences.push(format!(
+                    "Device #{} prefab: {} != {}",
+                    id,
+                    ours.get_prefab_hash(),
+                    theirs.get_prefab_hash()
+                ));
+                continue;
+            }
+
+            for logic_type in ours.supported_types() {
+                if !ours.can_read(logic_type) {
+                    continue;
+                }
+                match (ours.read(logic_type), theirs.read(logic_type)) {
+                    (Ok(a), Ok(b)) if same(a, b) => {}
+                    (Err(_), Err(_)) => {}
+                    (a, b) => differences.push(format!(
+                        "Device #{} {:?}: {} != {}",
+                        id,
+                        logic_type,
+                        a.map_or_else(|_| "error".to_string(), |v| v.to_string()),
+                        b.map_or_else(|_| "error".to_string(), |v| v.to_string())
+                    )),
+                }
+            }
+        }
+
+        let network_ids: BTreeSet<i32> = self
+            .atmospheric_networks
+            .keys()
+            .chain(other.atmospheric_networks.keys())
+            .copied()
+            .collect();
+        for id in network_ids {
+            let (ours, theirs) = match (
+                self.atmospheric_networks.get(&id),
+                other.atmospheric_networks.get(&id),
+            ) {
+                (Some(a), Some(b)) => (a.borrow(), b.borrow()),
+                (Some(_), None) => {
+                    differences.push(format!("Atmospheric network #{}: missing from other", id));
+                    continue;
+                }
+                (None, _) => {
+                    differences.push(format!("Atmospheric network #{}: missing from self", id));
+                    continue;
+                }
+            };
+
+            for gas in GasType::all() {
+                let (a, b) = (ours.get_moles(gas), theirs.get_moles(gas));
+                if !same(a, b) {
+                    differences.push(format!(
+                        "Atmospheric network #{} {:?}: {} mol != {} mol",
+                        id, gas, a, b
+                    ));
+                }
+            }
+
+            let (a, b) = (ours.temperature(), theirs.temperature());
+            if !same(a, b) {
+                differences.push(format!(
+                    "Atmospheric network #{} temperature: {} K != {} K",
+                    id, a, b
+                ));
+            }
+        }
+
+        differences
+    }
 }
 
 impl Display for SimulationManager {
@@ -1,14 +1,21 @@
 //! Unit tests for atmospheric networks
 #[cfg(test)]
 mod tests {
-    use crate::{atmospherics::GasType, networks::AtmosphericNetwork};
+    use crate::{
+        SimulationManager,
+        atmospherics::{GasType, celsius_to_kelvin},
+        networks::{AtmosphericNetwork, DEFAULT_EMPTY_EPSILON},
+    };
 
     #[test]
     fn test_gas_operations() {
         let network = AtmosphericNetwork::new(1000.0);
 
         // Add gas
-        network.borrow_mut().add_gas(GasType::Oxygen, 10.0, 300.0);
+        network
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 10.0, 300.0)
+            .unwrap();
         assert!(!network.borrow().is_empty());
         assert!((network.borrow().total_moles() - 10.0).abs() < 0.001);
 
@@ -18,12 +25,50 @@ mod tests {
         assert!((network.borrow().total_moles() - 5.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_remove_gas_clamps_and_preserves_temperature() {
+        let network = AtmosphericNetwork::new(100.0);
+        {
+            let mut net = network.borrow_mut();
+            net.add_gas(GasType::Oxygen, 10.0, 350.0).unwrap();
+            net.add_gas(GasType::Nitrogen, 4.0, 350.0).unwrap();
+        }
+
+        // Partial removal takes exactly what was asked for
+        let removed = network.borrow_mut().remove_gas(GasType::Oxygen, 2.5);
+        assert!((removed - 2.5).abs() < 1e-9);
+        assert!((network.borrow().get_moles(GasType::Oxygen) - 7.5).abs() < 1e-9);
+        assert!((network.borrow().temperature() - 350.0).abs() < 1e-9);
+
+        // Asking for more than is present removes it all
+        let removed = network.borrow_mut().remove_gas(GasType::Oxygen, 100.0);
+        assert!((removed - 7.5).abs() < 1e-9);
+        assert_eq!(network.borrow().get_moles(GasType::Oxygen), 0.0);
+        assert!((network.borrow().total_moles() - 4.0).abs() < 1e-9);
+        assert!((network.borrow().temperature() - 350.0).abs() < 1e-9);
+
+        // Absent gases and invalid amounts remove nothing
+        assert_eq!(network.borrow_mut().remove_gas(GasType::Oxygen, 1.0), 0.0);
+        assert_eq!(
+            network.borrow_mut().remove_gas(GasType::Nitrogen, -1.0),
+            0.0
+        );
+        assert_eq!(
+            network.borrow_mut().remove_gas(GasType::Nitrogen, f64::NAN),
+            0.0
+        );
+        assert!((network.borrow().get_moles(GasType::Nitrogen) - 4.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_transfer_between_networks() {
         let network1 = AtmosphericNetwork::new(1000.0);
         let network2 = AtmosphericNetwork::new(1000.0);
 
-        network1.borrow_mut().add_gas(GasType::Oxygen, 20.0, 300.0);
+        network1
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 20.0, 300.0)
+            .unwrap();
 
         network1
             .borrow_mut()
@@ -33,13 +78,51 @@ mod tests {
         assert!((network2.borrow().total_moles() - 10.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_clone_mixture_is_independent_of_the_network() {
+        let network = AtmosphericNetwork::new(1000.0);
+        network
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 20.0, 300.0)
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 10.0, 300.0)
+            .unwrap();
+        let pressure = network.borrow().pressure();
+        let energy = network.borrow().total_energy();
+
+        let mut mixture = network.borrow().clone_mixture();
+        assert_eq!(mixture.total_moles(), 30.0);
+        assert_eq!(mixture.pressure(), pressure);
+
+        mixture.remove_gas(GasType::Oxygen, 15.0);
+        mixture.add_gas(GasType::CarbonDioxide, 5.0, 500.0).unwrap();
+        mixture.oxygen.set_temperature(100.0);
+        mixture.set_volume(10.0);
+
+        let network = network.borrow();
+        assert_eq!(network.get_moles(GasType::Oxygen), 20.0);
+        assert_eq!(network.get_moles(GasType::CarbonDioxide), 0.0);
+        assert_eq!(network.get_gas(GasType::Oxygen).temperature(), 300.0);
+        assert_eq!(network.total_volume(), 1000.0);
+        assert_eq!(network.pressure(), pressure);
+        assert_eq!(network.total_energy(), energy);
+    }
+
     #[test]
     fn test_equalization() {
         let network1 = AtmosphericNetwork::new(1000.0);
         let network2 = AtmosphericNetwork::new(1000.0);
 
-        network1.borrow_mut().add_gas(GasType::Oxygen, 20.0, 300.0);
-        network2.borrow_mut().add_gas(GasType::Oxygen, 10.0, 300.0);
+        network1
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 20.0, 300.0)
+            .unwrap();
+        network2
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 10.0, 300.0)
+            .unwrap();
 
         network1
             .borrow_mut()
@@ -49,4 +132,178 @@ mod tests {
         let diff = (network1.borrow().pressure() - network2.borrow().pressure()).abs();
         assert!(diff < 0.1);
     }
+
+    #[test]
+    fn test_pressure_of_group() {
+        let network = AtmosphericNetwork::new(100.0);
+        network
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 10.0, 300.0)
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 30.0, 300.0)
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Volatiles, 5.0, 300.0)
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::CarbonDioxide, 2.0, 300.0)
+            .unwrap();
+
+        let net = network.borrow();
+        let breathable = net.pressure_of_group(GasType::BREATHABLE);
+        let expected =
+            net.partial_pressure(GasType::Oxygen) + net.partial_pressure(GasType::Nitrogen);
+        assert!(breathable > 0.0);
+        assert!((breathable - expected).abs() < 1e-9);
+
+        let combustible = net.pressure_of_group(GasType::COMBUSTIBLE);
+        assert!((combustible - net.partial_pressure(GasType::Volatiles)).abs() < 1e-9);
+
+        // Duplicates are counted once and an empty group has no pressure
+        let doubled = net.pressure_of_group(&[GasType::Oxygen, GasType::Oxygen]);
+        assert!((doubled - net.partial_pressure(GasType::Oxygen)).abs() < 1e-9);
+        assert_eq!(net.pressure_of_group(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_add_gas_rejects_invalid_values() {
+        let network = AtmosphericNetwork::new(10.0);
+        network
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 2.0, 300.0)
+            .unwrap();
+
+        for (moles, temperature) in [
+            (-1.0, 300.0),
+            (f64::NAN, 300.0),
+            (f64::INFINITY, 300.0),
+            (1.0, f64::NAN),
+            (1.0, 0.0),
+            (1.0, -5.0),
+        ] {
+            assert!(
+                network
+                    .borrow_mut()
+                    .add_gas(GasType::Oxygen, moles, temperature)
+                    .is_err(),
+                "{moles} mol at {temperature} K should be rejected"
+            );
+        }
+
+        // Rejected adds leave the mixture untouched
+        assert_eq!(network.borrow().total_moles(), 2.0);
+        assert!((network.borrow().temperature() - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_add_gas_water_phase_change_regression() {
+        // Same setup as the `phase_change_test_2` demo
+        let mut manager = SimulationManager::new();
+        let network = manager.create_atmospheric_network(10.0).unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0))
+            .unwrap();
+        assert_eq!(network.borrow().get_moles(GasType::Water), 1.0);
+        assert!((network.borrow().temperature() - celsius_to_kelvin(30.0)).abs() < 1e-9);
+
+        let mut ticks = 0;
+        while manager.update().unwrap() > 0 && ticks < 1000 {
+            ticks += 1;
+        }
+        assert!(ticks < 1000, "water never settled");
+        assert!((network.borrow().total_moles() - 1.0).abs() < 1e-9);
+        assert!(network.borrow().get_moles(GasType::Steam) > 0.0);
+    }
+
+    #[test]
+    fn test_is_empty_uses_empty_epsilon() {
+        let trace = AtmosphericNetwork::new(100.0);
+        trace
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 1e-12, 300.0)
+            .unwrap();
+        assert_eq!(trace.borrow().empty_epsilon(), DEFAULT_EMPTY_EPSILON);
+        assert!(trace.borrow().is_empty());
+
+        let small = AtmosphericNetwork::new(100.0);
+        small
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 1e-3, 300.0)
+            .unwrap();
+        assert!(!small.borrow().is_empty());
+        assert!(small.borrow().energy() > 0.0);
+        assert_eq!(small.borrow().energy(), small.borrow().total_energy());
+
+        // A larger epsilon treats more gas as a trace amount
+        small.borrow_mut().set_empty_epsilon(1e-2);
+        assert!(small.borrow().is_empty());
+        small.borrow_mut().set_empty_epsilon(DEFAULT_EMPTY_EPSILON);
+        assert!(!small.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_set_volume_preserves_moles_and_temperature() {
+        let network = AtmosphericNetwork::new(100.0);
+        network
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 2.0, 300.0)
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 3.0, 280.0)
+            .unwrap();
+
+        let (moles, temperature, energy, pressure) = {
+            let net = network.borrow();
+            (
+                net.total_moles(),
+                net.temperature(),
+                net.energy(),
+                net.pressure(),
+            )
+        };
+
+        for (volume, factor) in [(50.0, 2.0), (200.0, 0.5), (100.0, 1.0)] {
+            network.borrow_mut().set_volume(volume).unwrap();
+            let net = network.borrow();
+            assert_eq!(net.volume(), volume);
+            assert_eq!(net.total_moles(), moles);
+            assert_eq!(net.temperature(), temperature);
+            assert_eq!(net.energy(), energy);
+            assert!(
+                (net.pressure() - pressure * factor).abs() < 1e-9 * pressure,
+                "{volume} L: {} kPa",
+                net.pressure()
+            );
+        }
+
+        assert!(network.borrow_mut().set_volume(0.0).is_err());
+        assert_eq!(network.borrow().volume(), 100.0);
+    }
+
+    #[test]
+    fn test_set_volume_reevaluates_phases_on_next_tick() {
+        // Same setup as the `phase_change_test_2` demo
+        let mut manager = SimulationManager::new();
+        let network = manager.create_atmospheric_network(10.0).unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0))
+            .unwrap();
+        while manager.update().unwrap() > 0 {}
+        let steam = network.borrow().get_moles(GasType::Steam);
+
+        // More room lowers the vapour pressure, so more water evaporates on the next ticks
+        network.borrow_mut().set_volume(20.0).unwrap();
+        assert_eq!(network.borrow().get_moles(GasType::Steam), steam);
+        assert!(manager.update().unwrap() > 0);
+        while manager.update().unwrap() > 0 {}
+        assert!(network.borrow().get_moles(GasType::Steam) > steam);
+        assert!((network.borrow().total_moles() - 1.0).abs() < 1e-9);
+    }
 }
@@ -1,11 +1,30 @@
 //! Unit tests for atmospherics utilities
 #[cfg(test)]
 mod tests {
+    use crate::SimulationManager;
     use crate::atmospherics::{
-        GasMixture, GasType, MatterState, Mole, calculate_moles, calculate_pressure,
-        celsius_to_kelvin, kelvin_to_celsius,
+        ContentsDisplay, FormatOptions, GasMixture, GasProperties, GasPropertiesTable, GasType,
+        MatterState, Mole, PressureUnit, TemperatureUnit, TransferAmount, calculate_moles,
+        calculate_pressure, celsius_to_kelvin, kelvin_to_celsius,
     };
 
+    /// Run the water phase-change scenario until stable; returns (ticks, steam moles)
+    fn run_water_phase_change(mut manager: SimulationManager) -> (u32, f64) {
+        let network = manager.create_atmospheric_network(10.0).unwrap();
+        network
+            .borrow_mut()
+            .add_gas(GasType::Water, 1.0, celsius_to_kelvin(30.0))
+            .unwrap();
+
+        let mut ticks = 1;
+        while manager.update().unwrap() > 0 && ticks < 1000 {
+            ticks += 1;
+        }
+
+        let steam = network.borrow().get_moles(GasType::Steam);
+        (ticks, steam)
+    }
+
     #[test]
     fn test_empty_mixture() {
         let mixture = GasMixture::new(1000.0);
@@ -16,7 +35,7 @@ mod tests {
     #[test]
     fn test_add_gas() {
         let mut mixture = GasMixture::new(1000.0);
-        mixture.add_gas(GasType::Oxygen, 10.0, 300.0);
+        mixture.add_gas(GasType::Oxygen, 10.0, 300.0).unwrap();
 
         assert!((mixture.get_moles(GasType::Oxygen) - 10.0).abs() < 0.0001);
         assert!((mixture.temperature() - 300.0).abs() < 0.01);
@@ -25,7 +44,7 @@ mod tests {
     #[test]
     fn test_pressure_calculation() {
         let mut mixture = GasMixture::new(1000.0);
-        mixture.add_gas(GasType::Nitrogen, 10.0, 300.0);
+        mixture.add_gas(GasType::Nitrogen, 10.0, 300.0).unwrap();
 
         let pressure = mixture.pressure();
         // P = nRT/V = 10 * 8.3144 * 300 / 1000 = 24.94 kPa
@@ -35,8 +54,8 @@ mod tests {
     #[test]
     fn test_partial_pressure() {
         let mut mixture = GasMixture::new(1000.0);
-        mixture.add_gas(GasType::Oxygen, 5.0, 300.0);
-        mixture.add_gas(GasType::Nitrogen, 15.0, 300.0);
+        mixture.add_gas(GasType::Oxygen, 5.0, 300.0).unwrap();
+        mixture.add_gas(GasType::Nitrogen, 15.0, 300.0).unwrap();
 
         let total_pressure = mixture.pressure();
         let o2_pp = mixture.partial_pressure(GasType::Oxygen);
@@ -52,7 +71,7 @@ mod tests {
     #[test]
     fn test_gas_transfer() {
         let mut source = GasMixture::new(1000.0);
-        source.add_gas(GasType::Oxygen, 10.0, 300.0);
+        source.add_gas(GasType::Oxygen, 10.0, 300.0).unwrap();
 
         let mut target = GasMixture::new(1000.0);
 
@@ -63,11 +82,70 @@ mod tests {
         assert!((target.get_moles(GasType::Oxygen) - 5.0).abs() < 0.0001);
     }
 
+    #[test]
+    fn test_transfer_moles_conserves_moles_and_energy() {
+        let mut source = GasMixture::new(100.0);
+        source.add_gas(GasType::Oxygen, 6.0, 350.0).unwrap();
+        source.add_gas(GasType::Nitrogen, 4.0, 350.0).unwrap();
+        source.add_gas(GasType::Water, 2.0, 290.0).unwrap();
+        let mut target = GasMixture::new(100.0);
+        target.add_gas(GasType::Nitrogen, 5.0, 250.0).unwrap();
+
+        let moles = source.total_moles() + target.total_moles();
+        let energy = source.total_energy() + target.total_energy();
+        let source_temperature = source.temperature();
+
+        // Absolute moles of gas only: the water stays behind, the gases go in proportion
+        let transferred =
+            source.transfer_moles_to(&mut target, TransferAmount::Moles(5.0), MatterState::Gas);
+        assert!((transferred - 5.0).abs() < 1e-9);
+        assert!((source.get_moles(GasType::Oxygen) - 3.0).abs() < 1e-9);
+        assert!((target.get_moles(GasType::Nitrogen) - 7.0).abs() < 1e-9);
+        assert_eq!(source.get_moles(GasType::Water), 2.0);
+
+        // A fraction of everything
+        let transferred =
+            source.transfer_moles_to(&mut target, TransferAmount::Fraction(0.5), MatterState::All);
+        assert!((transferred - 3.5).abs() < 1e-9);
+
+        assert!((source.total_moles() + target.total_moles() - moles).abs() < 1e-9);
+        assert!((source.total_energy() + target.total_energy() - energy).abs() < 1e-6);
+        // Moving part of a mixture does not change its temperature
+        assert!((source.temperature() - source_temperature).abs() < 1e-9);
+
+        // Requests beyond the contents are capped; nothing to move moves nothing
+        let mut empty = GasMixture::new(100.0);
+        assert_eq!(
+            empty.transfer_moles_to(&mut target, TransferAmount::Moles(1.0), MatterState::All),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_transferring_everything_empties_the_source() {
+        let mut target = GasMixture::new(100.0);
+        for amount in [TransferAmount::Fraction(1.0), TransferAmount::Moles(1000.0)] {
+            let mut source = GasMixture::new(100.0);
+            source.add_gas(GasType::Oxygen, 3.0, 300.0).unwrap();
+            source.add_gas(GasType::Water, 1.0, 290.0).unwrap();
+            let energy = source.total_energy() + target.total_energy();
+            let before = target.total_moles();
+
+            let transferred = source.transfer_moles_to(&mut target, amount, MatterState::All);
+            assert!((transferred - 4.0).abs() < 1e-9, "{amount:?}");
+            assert!(source.is_empty());
+            assert_eq!(source.total_moles(), 0.0);
+            assert_eq!(source.total_energy(), 0.0);
+            assert!((target.total_moles() - before - 4.0).abs() < 1e-9);
+            assert!((target.total_energy() - energy).abs() < 1e-6);
+        }
+    }
+
     #[test]
     fn test_temperature_mixing() {
         let mut mixture = GasMixture::new(1000.0);
-        mixture.add_gas(GasType::Nitrogen, 10.0, 200.0);
-        mixture.add_gas(GasType::Nitrogen, 10.0, 400.0);
+        mixture.add_gas(GasType::Nitrogen, 10.0, 200.0).unwrap();
+        mixture.add_gas(GasType::Nitrogen, 10.0, 400.0).unwrap();
 
         // Temperature should be ~300K (average weighted by heat capacity)
         let temp = mixture.temperature();
@@ -77,10 +155,10 @@ mod tests {
     #[test]
     fn test_equalization() {
         let mut high = GasMixture::new(1000.0);
-        high.add_gas(GasType::Oxygen, 20.0, 300.0);
+        high.add_gas(GasType::Oxygen, 20.0, 300.0).unwrap();
 
         let mut low = GasMixture::new(1000.0);
-        low.add_gas(GasType::Oxygen, 10.0, 300.0);
+        low.add_gas(GasType::Oxygen, 10.0, 300.0).unwrap();
 
         high.equalize_with(&mut low);
 
@@ -92,8 +170,8 @@ mod tests {
     #[test]
     fn test_temperature_mixing_different_gases() {
         let mut mixture = GasMixture::new(1000.0);
-        mixture.add_gas(GasType::Oxygen, 5.0, 200.0);
-        mixture.add_gas(GasType::Nitrogen, 10.0, 400.0);
+        mixture.add_gas(GasType::Oxygen, 5.0, 200.0).unwrap();
+        mixture.add_gas(GasType::Nitrogen, 10.0, 400.0).unwrap();
 
         // Calculate expected equilibrium temperature
         let oxygen_capacity = 5.0 * GasType::Oxygen.specific_heat();
@@ -179,10 +257,345 @@ mod tests {
         assert!((removed.temperature() - 300.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_only_transfers_keep_trace_moles() {
+        // Removing never takes more than asked; the trace left behind is cleaned up
+        let mut mole = Mole::new(GasType::Oxygen, 1.0, 300.0);
+        let removed = mole.remove(1.0 - 1e-6);
+        assert_eq!(removed.quantity(), 1.0 - 1e-6);
+        assert_eq!(mole.quantity(), 0.0);
+
+        // Adding a trace to nothing cleans it up too
+        let mut empty = Mole::zero(GasType::Oxygen);
+        empty.add(&Mole::new(GasType::Oxygen, 1e-6, 300.0));
+        assert_eq!(empty.quantity(), 0.0);
+
+        // A transfer keeps both sides' traces so moles are conserved exactly
+        let mut source = Mole::new(GasType::Oxygen, 1.0, 300.0);
+        let mut target = Mole::zero(GasType::Oxygen);
+        assert_eq!(source.transfer_to(&mut target, 1.0 - 1e-6), 1.0 - 1e-6);
+        assert_eq!(source.quantity() + target.quantity(), 1.0);
+        assert!(source.quantity() > 0.0);
+        source.transfer_to(&mut target, 1.0);
+        assert_eq!(source.quantity(), 0.0);
+        assert_eq!(target.quantity(), 1.0);
+        assert!((target.temperature() - 300.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_temperature_consistency() {
         let mut mole = Mole::new(GasType::Steam, 5.0, 400.0);
         mole.set_temperature(500.0);
         assert!((mole.temperature() - 500.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_configured_gas_properties_reach_manager_networks() {
+        let oxygen = GasProperties::of(GasType::Oxygen);
+        let mut table = GasPropertiesTable::default();
+        table.set(
+            GasType::Oxygen,
+            GasProperties {
+                specific_heat: oxygen.specific_heat * 2.0,
+                ..oxygen
+            },
+        );
+
+        let mut configured = SimulationManager::with_gas_properties(table);
+        let mut builtin = SimulationManager::new();
+        let configured_net = configured.create_atmospheric_network(10.0).unwrap();
+        let builtin_net = builtin.create_atmospheric_network(10.0).unwrap();
+        for network in [&configured_net, &builtin_net] {
+            network
+                .borrow_mut()
+                .add_gas(GasType::Oxygen, 1.0, 300.0)
+                .unwrap();
+        }
+
+        // The configured specific heat is used, doubling the energy held at the same temperature
+        let configured_net = configured_net.borrow();
+        let builtin_net = builtin_net.borrow();
+        assert_eq!(
+            configured_net
+                .get_gas(GasType::Oxygen)
+                .properties()
+                .specific_heat,
+            oxygen.specific_heat * 2.0
+        );
+        assert_eq!(*builtin_net.get_gas(GasType::Oxygen).properties(), oxygen);
+        assert!((configured_net.temperature() - 300.0).abs() < 1e-9);
+        assert!((configured_net.total_energy() - 2.0 * builtin_net.total_energy()).abs() < 1e-6);
+
+        let water = GasProperties::of(GasType::Water);
+        let shifted = water.with_boiling_point(350.0);
+        assert!((shifted.boiling_point() - 350.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_boiling_point_override_shifts_phase_change() {
+        let (default_ticks, default_steam) = run_water_phase_change(SimulationManager::new());
+
+        let mut table = GasPropertiesTable::default();
+        let boiling_point = GasProperties::of(GasType::Water).boiling_point() - 60.0;
+        for gas_type in [GasType::Water, GasType::Steam] {
+            table.set(
+                gas_type,
+                table.get(gas_type).with_boiling_point(boiling_point),
+            );
+        }
+        let (shifted_ticks, shifted_steam) =
+            run_water_phase_change(SimulationManager::with_gas_properties(table));
+
+        // A lower boiling point evaporates more water and settles at a different tick
+        assert!(shifted_steam > default_steam);
+        assert_ne!(shifted_ticks, default_ticks);
+    }
+
+    #[test]
+    fn test_partial_pressure_single_gas() {
+        let mut mixture = GasMixture::new(100.0);
+        mixture.add_gas(GasType::Oxygen, 2.0, 300.0).unwrap();
+
+        // P = nRT/V = 2 * 8.31446261815324 * 300 / 100
+        let expected = 2.0 * 8.314_462_618_153_24 * 300.0 / 100.0;
+        assert!((mixture.partial_pressure(GasType::Oxygen) - expected).abs() < 1e-9);
+        assert!((mixture.total_pressure() - expected).abs() < 1e-9);
+        assert_eq!(mixture.partial_pressure(GasType::Nitrogen), 0.0);
+    }
+
+    #[test]
+    fn test_partial_pressure_two_gases_sum_to_total() {
+        let mut mixture = GasMixture::new(50.0);
+        mixture.add_gas(GasType::Oxygen, 1.0, 250.0).unwrap();
+        mixture.add_gas(GasType::Nitrogen, 3.0, 250.0).unwrap();
+
+        let temperature = mixture.temperature();
+        let o2 = mixture.partial_pressure(GasType::Oxygen);
+        let n2 = mixture.partial_pressure(GasType::Nitrogen);
+        assert!((o2 - 1.0 * 8.314_462_618_153_24 * temperature / 50.0).abs() < 1e-9);
+        assert!((n2 - 3.0 * 8.314_462_618_153_24 * temperature / 50.0).abs() < 1e-9);
+        assert!((o2 + n2 - mixture.total_pressure()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_partial_pressure_empty_mixture() {
+        let mixture = GasMixture::new(0.0);
+        assert_eq!(mixture.total_pressure(), 0.0);
+        assert_eq!(mixture.partial_pressure(GasType::Oxygen), 0.0);
+        assert!(mixture.partial_pressure(GasType::Oxygen).is_finite());
+    }
+
+    #[test]
+    fn test_plan_to_ratio_breathable_air() {
+        let mut mixture = GasMixture::new(100.0);
+        mixture
+            .add_gas(GasType::CarbonDioxide, 8.0, 293.15)
+            .unwrap();
+        mixture.add_gas(GasType::Oxygen, 1.0, 293.15).unwrap();
+        mixture.add_gas(GasType::Nitrogen, 1.0, 293.15).unwrap();
+
+        let targets = [(GasType::Oxygen, 0.21), (GasType::Nitrogen, 0.79)];
+        let plan = mixture.plan_to_ratio(&targets);
+
+        let delta = |gas_type| {
+            plan.iter()
+                .find(|(g, _)| *g == gas_type)
+                .map(|(_, d)| *d)
+                .unwrap_or(0.0)
+        };
+        assert!((delta(GasType::CarbonDioxide) + 8.0).abs() < 1e-9);
+        assert!((delta(GasType::Oxygen) - 1.1).abs() < 1e-9);
+        assert!((delta(GasType::Nitrogen) - 6.9).abs() < 1e-9);
+
+        for (gas_type, moles) in plan {
+            if moles > 0.0 {
+                mixture.add_gas(gas_type, moles, 293.15).unwrap();
+            } else {
+                mixture.remove_gas(gas_type, -moles);
+            }
+        }
+
+        assert!((mixture.total_moles() - 10.0).abs() < 1e-9);
+        assert!((mixture.gas_ratio(GasType::Oxygen) - 0.21).abs() < 1e-9);
+        assert!((mixture.gas_ratio(GasType::Nitrogen) - 0.79).abs() < 1e-9);
+        assert_eq!(mixture.get_moles(GasType::CarbonDioxide), 0.0);
+    }
+
+    #[test]
+    fn test_plan_to_ratio_empty_mixture() {
+        let mixture = GasMixture::new(100.0);
+        assert!(mixture.plan_to_ratio(&[(GasType::Oxygen, 1.0)]).is_empty());
+    }
+
+    #[test]
+    fn test_move_toward_temperature_respects_energy_budget() {
+        let mut mixture = GasMixture::new(100.0);
+        mixture.add_gas(GasType::Nitrogen, 10.0, 400.0).unwrap();
+        let heat_capacity = mixture.total_heat_capacity();
+        let full_cooling = (400.0 - 300.0) * heat_capacity;
+
+        // Half the energy needed only gets half way
+        let moved = mixture.move_toward_temperature(300.0, full_cooling / 2.0);
+        assert!((moved + full_cooling / 2.0).abs() < 1e-6);
+        assert!((mixture.temperature() - 350.0).abs() < 1e-9);
+        assert!((mixture.total_moles() - 10.0).abs() < 1e-9);
+
+        // A generous budget stops at the target instead of overshooting
+        let moved = mixture.cool_to(300.0, full_cooling * 10.0);
+        assert!((moved - full_cooling / 2.0).abs() < 1e-6);
+        assert!((mixture.temperature() - 300.0).abs() < 1e-9);
+
+        // Already at the target: nothing moves in either direction
+        assert_eq!(mixture.cool_to(300.0, 1000.0), 0.0);
+        assert_eq!(mixture.heat_to(250.0, 1000.0), 0.0);
+
+        // Heating is bounded the same way
+        let moved = mixture.heat_to(320.0, 10.0 * heat_capacity);
+        assert!((moved - 10.0 * heat_capacity).abs() < 1e-6);
+        assert!((mixture.temperature() - 310.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_move_toward_temperature_empty_mixture() {
+        let mut mixture = GasMixture::new(100.0);
+        assert_eq!(mixture.move_toward_temperature(300.0, 1000.0), 0.0);
+    }
+
+    /// Temperature at which `moles` of steam in `volume` sit exactly on the evaporation curve
+    fn steam_boundary_temperature(moles: f64, volume: f64) -> f64 {
+        let steam = Mole::new(GasType::Steam, moles, 300.0);
+        let (mut low, mut high) = (280.0, 600.0);
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            let pressure = calculate_pressure(moles, mid, volume);
+            if mid > steam.evaporation_temperature_clamped(pressure) {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        low
+    }
+
+    #[test]
+    fn test_phase_boundary_settles_within_hysteresis() {
+        let temperature = steam_boundary_temperature(0.5, 10.0);
+        let mut mixture = GasMixture::new(10.0);
+        mixture.add_gas(GasType::Steam, 0.5, temperature).unwrap();
+        mixture.add_gas(GasType::Water, 0.2, temperature).unwrap();
+
+        let settled_at = (0..5).find(|_| mixture.process_phase_changes() == 0);
+        assert!(
+            settled_at.is_some(),
+            "mixture did not settle on the boundary"
+        );
+
+        // Once settled, the mixture stays put instead of flipping between states
+        let water = mixture.get_moles(GasType::Water);
+        for _ in 0..100 {
+            assert_eq!(mixture.process_phase_changes(), 0);
+        }
+        assert_eq!(mixture.get_moles(GasType::Water), water);
+    }
+
+    #[test]
+    fn test_condensing_steam_conserves_moles_and_energy() {
+        let latent_heat = GasType::Steam.latent_heat_of_vaporization();
+        let mut mixture = GasMixture::new(10.0);
+        mixture.add_gas(GasType::Steam, 1.0, 330.0).unwrap();
+
+        // Condensation turns latent heat into sensible heat, so their sum is conserved
+        let held_energy = |mixture: &GasMixture| {
+            mixture.total_energy() + mixture.get_moles(GasType::Steam) * latent_heat
+        };
+        let initial_energy = held_energy(&mixture);
+
+        let mut saw_mixed = false;
+        for _ in 0..200 {
+            if mixture.process_phase_changes() == 0 {
+                break;
+            }
+
+            let steam = mixture.gas_ratio(GasType::Steam);
+            let water = mixture.gas_ratio(GasType::Water);
+            saw_mixed |= steam > 0.0 && water > 0.0;
+            assert!((steam + water - 1.0).abs() < 1e-9);
+            assert!((mixture.state_ratio(MatterState::Gas) - steam).abs() < 1e-12);
+            assert!((mixture.state_ratio(MatterState::Liquid) - water).abs() < 1e-12);
+
+            assert!((mixture.total_moles() - 1.0).abs() < 1e-9);
+            assert!((held_energy(&mixture) - initial_energy).abs() < 1e-6 * initial_energy);
+        }
+
+        assert!(saw_mixed, "steam never partially condensed");
+        assert!(mixture.get_moles(GasType::Water) > 0.0);
+    }
+
+    #[test]
+    fn test_gases_lists_present_gases_in_discriminant_order() {
+        let mut mixture = GasMixture::new(100.0);
+        assert!(mixture.gases().is_empty());
+
+        mixture.add_gas(GasType::Hydrogen, 3.0, 300.0).unwrap();
+        mixture.add_gas(GasType::Water, 2.0, 300.0).unwrap();
+        mixture.add_gas(GasType::Oxygen, 1.0, 300.0).unwrap();
+
+        let gases = mixture.gases();
+        let types: Vec<GasType> = gases.iter().map(|(gas_type, _)| *gas_type).collect();
+        assert_eq!(
+            types,
+            vec![GasType::Oxygen, GasType::Water, GasType::Hydrogen]
+        );
+        for (gas_type, mole) in &gases {
+            assert_eq!(mole.gas_type(), *gas_type);
+            assert_eq!(mole.quantity(), mixture.get_moles(*gas_type));
+        }
+
+        assert_eq!(mixture.get(GasType::Water).unwrap().quantity(), 2.0);
+        assert!(mixture.get(GasType::Nitrogen).is_none());
+    }
+
+    #[test]
+    fn test_format_mixture_in_both_unit_systems() {
+        let mut mixture = GasMixture::new(100.0);
+        mixture.add_gas(GasType::Oxygen, 3.0, 300.0).unwrap();
+        mixture.add_gas(GasType::Nitrogen, 1.0, 300.0).unwrap();
+
+        let default = mixture.format(FormatOptions::default());
+        assert_eq!(default, mixture.to_string());
+        assert_eq!(
+            default,
+            "Mixture (total: 100 L, liquid volume: 0 L)\n  \
+             Temperature: 26.85 °C (300 K)\n  \
+             Pressure: 99.774 kPa\n  \
+             Moles: 4 (gases: 4, liquids: 0)\n  \
+             Latent: 0 J\n  \
+             Contents:\n    \
+             O2: 3 mol\n    \
+             N2: 1 mol"
+        );
+
+        let metric = mixture.format(FormatOptions {
+            pressure: PressureUnit::Atmospheres,
+            temperature: TemperatureUnit::Kelvin,
+            contents: ContentsDisplay::PartialPressures,
+        });
+        assert_eq!(
+            metric,
+            "Mixture (total: 100 L, liquid volume: 0 L)\n  \
+             Temperature: 300 K\n  \
+             Pressure: 0.985 atm\n  \
+             Moles: 4 (gases: 4, liquids: 0)\n  \
+             Latent: 0 J\n  \
+             Contents:\n    \
+             O2: 0.739 atm\n    \
+             N2: 0.246 atm"
+        );
+
+        let ratios = mixture.format(FormatOptions {
+            contents: ContentsDisplay::Ratios,
+            ..FormatOptions::default()
+        });
+        assert!(ratios.ends_with("    O2: 75 %\n    N2: 25 %"), "{ratios}");
+    }
 }
@@ -3,11 +3,16 @@
 mod tests {
     use crate::Device;
     use crate::SimulationResult;
-    use crate::devices::LogicType;
+    use crate::devices::battery::BATTERY_CAPACITY;
+    use crate::devices::daylight_sensor::sun_angles;
     use crate::devices::property_descriptor::{PropertyDescriptor, PropertyRegistry};
+    use crate::devices::solar_panel::SOLAR_PANEL_MAX_POWER;
+    use crate::devices::volume_pump::VOLUME_PUMP_POWER_DRAW;
+    use crate::devices::{Battery, LogicType, SimulationDeviceSettings, SolarPanel, VolumePump};
     use crate::networks::{BatchMode, CableNetwork};
     use crate::types::OptWeakShared;
     use crate::types::{OptShared, shared};
+
     use std::cell::{Cell, RefCell};
 
     /// Test device for cable network testing
@@ -20,6 +25,8 @@ mod tests {
         setting: Cell<f64>,
         horizontal: Cell<f64>,
         vertical: Cell<f64>,
+        /// Power supplied (positive) or drawn (negative) in W
+        power: Cell<f64>,
         network: RefCell<OptWeakShared<CableNetwork>>,
     }
 
@@ -33,6 +40,7 @@ mod tests {
                 setting: Cell::new(0.0),
                 horizontal: Cell::new(0.0),
                 vertical: Cell::new(0.0),
+                power: Cell::new(0.0),
                 network: RefCell::new(None),
             }
         }
@@ -53,6 +61,7 @@ mod tests {
                 setting: Cell::new(setting),
                 horizontal: Cell::new(horizontal),
                 vertical: Cell::new(vertical),
+                power: Cell::new(0.0),
                 network: RefCell::new(None),
             }
         }
@@ -113,6 +122,14 @@ mod tests {
             Self::properties().supported_types()
         }
 
+        fn power_generation(&self) -> f64 {
+            self.power.get().max(0.0)
+        }
+
+        fn power_demand(&self) -> f64 {
+            (-self.power.get()).max(0.0)
+        }
+
         fn properties() -> &'static PropertyRegistry<Self>
         where
             Self: Sized,
@@ -198,6 +215,28 @@ mod tests {
         assert_eq!(network.borrow().all_device_ids(), vec![1, 2, 3, 4, 5]);
     }
 
+    #[test]
+    fn test_add_device_rejects_duplicate_reference_id() {
+        let network = CableNetwork::new();
+        let first = shared(MockDevice::new(1, 100, 200));
+        network
+            .borrow_mut()
+            .add_device(first.clone(), network.clone())
+            .unwrap();
+
+        // Re-adding the same device is a no-op, a different one with its ID is an error
+        network
+            .borrow_mut()
+            .add_device(first, network.clone())
+            .unwrap();
+        let err = network
+            .borrow_mut()
+            .add_device(shared(MockDevice::new(1, 101, 201)), network.clone())
+            .unwrap_err();
+        assert!(err.to_string().contains("reference ID 1"), "{err}");
+        assert_eq!(network.borrow().device_count(), 1);
+    }
+
     #[test]
     fn test_device_ref_id_ordering() {
         let network = CableNetwork::new();
@@ -217,6 +256,38 @@ mod tests {
         assert_eq!(all_ids, vec![1, 2, 3, 5, 8]);
     }
 
+    #[test]
+    fn test_devices_iterate_in_insertion_order() {
+        let network = CableNetwork::new();
+
+        for &id in &[5, 2, 8, 1, 3] {
+            let device = shared(MockDevice::new(id, 100, 200));
+            network
+                .borrow_mut()
+                .add_device(device, network.clone())
+                .unwrap();
+        }
+
+        let ids = |network: &CableNetwork| -> Vec<i32> {
+            network.devices().map(|d| d.borrow().get_id()).collect()
+        };
+
+        assert_eq!(network.borrow().device_count(), 5);
+        assert_eq!(ids(&network.borrow()), vec![5, 2, 8, 1, 3]);
+
+        network.borrow_mut().remove_device(8);
+        assert_eq!(network.borrow().device_count(), 4);
+        assert_eq!(ids(&network.borrow()), vec![5, 2, 1, 3]);
+
+        // A re-added device goes to the end
+        let device = shared(MockDevice::new(8, 100, 200));
+        network
+            .borrow_mut()
+            .add_device(device, network.clone())
+            .unwrap();
+        assert_eq!(ids(&network.borrow()), vec![5, 2, 1, 3, 8]);
+    }
+
     #[test]
     fn test_remove_device() {
         let network = CableNetwork::new();
@@ -557,6 +628,49 @@ mod tests {
         assert_eq!(result, 50.0);
     }
 
+    #[test]
+    fn test_batch_select_tie_breaks_by_lowest_id() {
+        let network = CableNetwork::new();
+
+        // Added out of ID order; devices 2 and 5 share the maximum, 3 and 4 the minimum
+        for (id, val) in [(5, 50.0), (3, 10.0), (2, 50.0), (4, 10.0), (1, 30.0)] {
+            let device = shared(MockDevice::with_values(id, 100, 200, val, 0.0, 0.0));
+            network
+                .borrow_mut()
+                .add_device(device, network.clone())
+                .unwrap();
+        }
+
+        let net = network.borrow();
+        for _ in 0..3 {
+            assert_eq!(
+                net.batch_select_by_prefab(100, LogicType::Setting, BatchMode::Maximum)
+                    .unwrap(),
+                Some(2)
+            );
+            assert_eq!(
+                net.batch_select_by_prefab(100, LogicType::Setting, BatchMode::Minimum)
+                    .unwrap(),
+                Some(3)
+            );
+        }
+
+        assert_eq!(
+            net.batch_select_by_name(100, 200, LogicType::Setting, BatchMode::Maximum)
+                .unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            net.batch_select_by_prefab(999, LogicType::Setting, BatchMode::Maximum)
+                .unwrap(),
+            None
+        );
+        assert!(
+            net.batch_select_by_prefab(100, LogicType::Setting, BatchMode::Sum)
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_batch_read_empty_returns_zero() {
         let network = CableNetwork::new();
@@ -1110,4 +1224,244 @@ mod tests {
         // Should handle floating point correctly
         assert!((result - 0.6).abs() < 1e-10);
     }
+
+    // ==================== Power Tests ====================
+
+    #[test]
+    fn test_battery_charges_from_surplus_and_discharges_under_load() {
+        let network = CableNetwork::new();
+        let source = shared(MockDevice::new(1, 100, 0));
+        let battery = Battery::new(SimulationDeviceSettings {
+            id: Some(2),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(source.clone(), network.clone())
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_device(battery.clone(), network.clone())
+            .unwrap();
+
+        let ratio = || battery.borrow().read(LogicType::Ratio).unwrap();
+        assert_eq!(ratio(), 0.0);
+        assert_eq!(
+            battery.borrow().read(LogicType::Maximum).unwrap(),
+            BATTERY_CAPACITY
+        );
+
+        // 100 kW over half-second ticks fills 3.6 MJ in 72 ticks
+        source.borrow().power.set(100_000.0);
+        assert_eq!(network.borrow().power_surplus(), 100_000.0);
+        let mut previous = ratio();
+        for tick in 0..100 {
+            battery.borrow().update(tick).unwrap();
+            let current = ratio();
+            assert!((0.0..=1.0).contains(&current));
+            assert!(current >= previous);
+            if tick == 35 {
+                assert!((current - 0.5).abs() < 1e-9, "{current}");
+            }
+            previous = current;
+        }
+        assert_eq!(ratio(), 1.0);
+        assert_eq!(
+            battery.borrow().read(LogicType::Charge).unwrap(),
+            BATTERY_CAPACITY
+        );
+        assert!(!battery.borrow().update(100).unwrap());
+
+        // A 50 kW load drains it in 144 ticks, never below empty
+        source.borrow().power.set(-50_000.0);
+        assert_eq!(network.borrow().power_demand(), 50_000.0);
+        for tick in 100..300 {
+            battery.borrow().update(tick).unwrap();
+            let current = ratio();
+            assert!((0.0..=1.0).contains(&current));
+            assert!(current <= previous);
+            if tick == 171 {
+                assert!((current - 0.5).abs() < 1e-9, "{current}");
+            }
+            // While charged, the discharge goes back to the network and covers the load
+            if tick < 243 {
+                assert!((battery.borrow().power_generation() - 50_000.0).abs() < 1e-6);
+                assert!(network.borrow().power_surplus().abs() < 1e-6);
+            }
+            previous = current;
+        }
+        assert_eq!(ratio(), 0.0);
+        assert_eq!(battery.borrow().output(), 0.0);
+        assert_eq!(network.borrow().power_surplus(), -50_000.0);
+    }
+
+    #[test]
+    fn test_battery_covers_a_running_volume_pump() {
+        let network = CableNetwork::new();
+        let pump = VolumePump::new(SimulationDeviceSettings {
+            id: Some(1),
+            ..SimulationDeviceSettings::default()
+        });
+        let battery = Battery::new(SimulationDeviceSettings {
+            id: Some(2),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(pump.clone(), network.clone())
+            .unwrap();
+        network
+            .borrow_mut()
+            .add_device(battery.clone(), network.clone())
+            .unwrap();
+        battery.borrow().set_charge(BATTERY_CAPACITY);
+
+        // An idle pump draws nothing, so the battery holds its charge
+        assert!(!battery.borrow().update(0).unwrap());
+        assert_eq!(network.borrow().power_demand(), 0.0);
+
+        pump.borrow().write(LogicType::On, 1.0).unwrap();
+        battery.borrow().update(1).unwrap();
+        let battery_ref = battery.borrow();
+        assert_eq!(
+            battery_ref.charge(),
+            BATTERY_CAPACITY - VOLUME_PUMP_POWER_DRAW / battery_ref.ticks_per_second()
+        );
+        assert_eq!(battery_ref.power_generation(), VOLUME_PUMP_POWER_DRAW);
+        assert_eq!(network.borrow().power_surplus(), 0.0);
+    }
+
+    #[test]
+    fn test_battery_reports_a_busy_network() {
+        let network = CableNetwork::new();
+        let battery = Battery::new(SimulationDeviceSettings {
+            id: Some(1),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(battery.clone(), network.clone())
+            .unwrap();
+
+        let _held = network.borrow_mut();
+        assert!(battery.borrow().update(0).is_err());
+    }
+
+    #[test]
+    fn test_batteries_share_network_surplus() {
+        let network = CableNetwork::new();
+        let source = shared(MockDevice::new(1, 100, 0));
+        source.borrow().power.set(10_000.0);
+        network
+            .borrow_mut()
+            .add_device(source, network.clone())
+            .unwrap();
+
+        let batteries: Vec<_> = (2..4)
+            .map(|id| {
+                let battery = Battery::new(SimulationDeviceSettings {
+                    id: Some(id),
+                    ..SimulationDeviceSettings::default()
+                });
+                network
+                    .borrow_mut()
+                    .add_device(battery.clone(), network.clone())
+                    .unwrap();
+                battery
+            })
+            .collect();
+
+        for battery in &batteries {
+            battery.borrow().update(0).unwrap();
+        }
+        // Each half-second tick stores half of 10 kW, split between two batteries
+        for battery in &batteries {
+            assert_eq!(battery.borrow().charge(), 2_500.0);
+        }
+    }
+
+    #[test]
+    fn test_battery_integrates_over_the_configured_tick_rate() {
+        let network = CableNetwork::new();
+        let source = shared(MockDevice::new(1, 100, 0));
+        source.borrow().power.set(10_000.0);
+        network
+            .borrow_mut()
+            .add_device(source, network.clone())
+            .unwrap();
+        let battery = Battery::new(SimulationDeviceSettings {
+            id: Some(2),
+            ticks_per_second: Some(5.0),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(battery.clone(), network.clone())
+            .unwrap();
+
+        // A fifth-of-a-second tick stores a fifth of 10 kW
+        battery.borrow().update(0).unwrap();
+        assert_eq!(battery.borrow().charge(), 2_000.0);
+    }
+
+    #[test]
+    fn test_solar_panel_output_peaks_when_facing_the_sun() {
+        let network = CableNetwork::new();
+        let panel = SolarPanel::new(SimulationDeviceSettings {
+            id: Some(1),
+            ticks_per_day: Some(2400.0),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(panel.clone(), network.clone())
+            .unwrap();
+
+        // Mid-morning: sun at 135 degrees around, ~26 degrees from the zenith
+        let tick = 900;
+        let (sun_horizontal, sun_vertical) = sun_angles(tick, 2400.0);
+
+        let output_at = |horizontal: f64, vertical: f64| {
+            let panel = panel.borrow();
+            panel.write(LogicType::Horizontal, horizontal).unwrap();
+            panel.write(LogicType::Vertical, vertical).unwrap();
+            panel.update(tick).unwrap();
+            let ratio = panel.read(LogicType::Ratio).unwrap();
+            let charge = panel.read(LogicType::Charge).unwrap();
+            assert!((0.0..=1.0).contains(&ratio));
+            assert!((charge - ratio * SOLAR_PANEL_MAX_POWER).abs() < 1e-9);
+            assert_eq!(network.borrow().power_generation(), charge);
+            ratio
+        };
+
+        let mut best = (0.0, -1.0);
+        for step in 0..24 {
+            let horizontal = step as f64 * 15.0;
+            let ratio = output_at(horizontal, sun_vertical);
+            if ratio > best.1 {
+                best = (horizontal, ratio);
+            }
+        }
+        assert_eq!(best.0, sun_horizontal);
+        assert!((best.1 - 1.0).abs() < 1e-9, "{}", best.1);
+
+        // Tilting away from the sun lowers the output
+        assert!(output_at(sun_horizontal, sun_vertical + 30.0) < best.1);
+
+        // Facing directly away produces nothing
+        assert_eq!(output_at(sun_horizontal + 180.0, 180.0 - sun_vertical), 0.0);
+        assert_eq!(output_at(sun_horizontal, 180.0), 0.0);
+
+        // No output at night, even facing the sun's position below the horizon
+        let (night_horizontal, night_vertical) = sun_angles(100, 2400.0);
+        let panel_ref = panel.borrow();
+        panel_ref
+            .write(LogicType::Horizontal, night_horizontal)
+            .unwrap();
+        panel_ref
+            .write(LogicType::Vertical, night_vertical)
+            .unwrap();
+        panel_ref.update(100).unwrap();
+        assert_eq!(panel_ref.read(LogicType::Charge).unwrap(), 0.0);
+    }
 }
@@ -1,7 +1,11 @@
 //! Unit tests for numeric conversions
 #[cfg(test)]
 mod tests {
-    use crate::conversions::{BIT_53, UNSIGNED_MASK, double_to_long, long_to_double};
+    use crate::atmospherics::{GasType, Mole};
+    use crate::conversions::{
+        BIT_53, UNSIGNED_MASK, double_to_long, energy_for_temperature_change, grams_to_moles,
+        long_to_double, moles_to_grams, round_to, temperature_change_for_energy,
+    };
 
     #[test]
     fn test_double_to_long_positive() {
@@ -85,4 +89,56 @@ mod tests {
         assert_eq!(double_to_long(f64::INFINITY, true), 0);
         assert_eq!(double_to_long(f64::NEG_INFINITY, true), 0);
     }
+
+    #[test]
+    fn test_round_to() {
+        assert_eq!(round_to(0.123456789, 3), 0.123);
+        assert_eq!(round_to(2.5, 0), 3.0);
+        assert_eq!(round_to(-2.5, 0), -3.0);
+        assert_eq!(round_to(1.23456, 2), 1.23);
+        assert!(round_to(f64::NAN, 3).is_nan());
+        assert_eq!(round_to(f64::INFINITY, 3), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_moles_to_grams_uses_molar_mass() {
+        assert_eq!(moles_to_grams(GasType::CarbonDioxide, 1.0), 44.0);
+        assert_eq!(moles_to_grams(GasType::Hydrogen, 10.0), 20.0);
+        assert_eq!(moles_to_grams(GasType::Water, 2.0), 36.0);
+        assert_eq!(grams_to_moles(GasType::CarbonDioxide, 88.0), 2.0);
+
+        // Agrees with the mass a mixture reports
+        let mole = Mole::new(GasType::Oxygen, 5.0, 300.0);
+        assert_eq!(moles_to_grams(GasType::Oxygen, 5.0), mole.mass());
+    }
+
+    #[test]
+    fn test_energy_for_temperature_change_uses_specific_heat() {
+        // Nitrogen: 20.6 J/(mol·K)
+        assert_eq!(
+            energy_for_temperature_change(GasType::Nitrogen, 2.0, 10.0),
+            412.0
+        );
+        assert_eq!(
+            energy_for_temperature_change(GasType::Nitrogen, 2.0, -10.0),
+            -412.0
+        );
+        assert_eq!(
+            temperature_change_for_energy(GasType::Nitrogen, 2.0, 412.0),
+            10.0
+        );
+        assert_eq!(
+            temperature_change_for_energy(GasType::Nitrogen, 0.0, 412.0),
+            0.0
+        );
+
+        // Adding that energy to a mole warms it by the requested amount
+        let mut mole = Mole::new(GasType::CarbonDioxide, 3.0, 280.0);
+        mole.add_energy(energy_for_temperature_change(
+            GasType::CarbonDioxide,
+            3.0,
+            20.0,
+        ));
+        assert!((mole.temperature() - 300.0).abs() < 1e-9);
+    }
 }
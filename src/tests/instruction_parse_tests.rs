@@ -4,6 +4,7 @@ mod tests {
     use crate::{
         instruction::{Instruction, ParsedInstruction},
         items::item_integrated_circuit_10::{AliasTarget, Operand},
+        parser::compile_module,
     };
 
     fn parse(line: &str) -> Instruction {
@@ -6692,4 +6693,245 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_to_source_renders_canonical_text() {
+        assert_eq!(
+            Instruction::Add {
+                dest: register(1),
+                arg1: register(2),
+                arg2: immediate(3.0),
+            }
+            .to_source(),
+            "add r1 r2 3"
+        );
+        assert_eq!(parse("s d5 On 0.5").to_source(), "s d5 On 0.5");
+        assert_eq!(parse("s d5 28 0.5").to_source(), "s d5 On 0.5");
+        assert_eq!(parse("l r0 d0 9999").to_source(), "l r0 d0 9999");
+        assert_eq!(parse("l r0 d0 28.5").to_source(), "l r0 d0 28.5");
+        assert_eq!(
+            parse("ls r0 d0 1 Occupied").to_source(),
+            "ls r0 d0 1 Occupied"
+        );
+        assert_eq!(
+            parse("lbns r0 -128473777 5 0 Quantity Sum").to_source(),
+            "lbns r0 -128473777 5 0 Quantity Sum"
+        );
+        assert_eq!(parse("lr r0 d0 Recipe 7").to_source(), "lr r0 d0 Recipe 7");
+        assert_eq!(parse("add r0 r1 28").to_source(), "add r0 r1 28");
+        assert_eq!(parse("move sp ra").to_source(), "move sp ra");
+        assert_eq!(parse("alias pump d0").to_source(), "alias pump d0");
+        assert_eq!(parse("alias t r9").to_source(), "alias t r9");
+        assert_eq!(parse("define K -273.15").to_source(), "define K -273.15");
+        assert_eq!(parse("push nan").to_source(), "push nan");
+        assert_eq!(parse("# comment").to_source(), "");
+        assert_eq!(parse("yield").to_source(), "yield");
+    }
+
+    #[test]
+    fn test_to_source_round_trips_every_instruction() {
+        let source = r#"
+alias pump d0
+alias temp r9
+alias other temp
+define K 273.15
+loop:
+move r1 temp
+add r1 temp 3
+sub r1 temp 3
+mul r1 temp 3
+div r1 temp 3
+mod r1 temp 3
+sqrt r1 temp
+abs r1 temp
+exp r1 temp
+log r1 temp
+pow r1 temp 3
+max r1 temp 3
+min r1 temp 3
+ceil r1 temp
+floor r1 temp
+round r1 temp
+trunc r1 temp
+rand r1
+lerp r1 temp 3 0.5
+sin r1 temp
+cos r1 temp
+tan r1 temp
+asin r1 temp
+acos r1 temp
+atan r1 temp
+atan2 r1 1e-7 -0.125
+and r1 temp 3
+or r1 temp 3
+xor r1 temp 3
+nor r1 temp 3
+not r1 temp
+sll r1 temp 3
+sla r1 temp 3
+srl r1 temp 3
+sra r1 temp 3
+ext r1 r5 0 8
+ins r1 K 0 8
+slt r1 temp 3
+sgt r1 temp 3
+sle r1 temp 3
+sge r1 temp 3
+seq r1 temp 3
+sne r1 temp 3
+sltz r1 temp
+sgtz r1 temp
+slez r1 temp
+sgez r1 temp
+seqz r1 temp
+snez r1 temp
+snan r1 temp
+snanz r1 temp
+sap r1 temp 3 0.5
+sna r1 temp 3 0.5
+sapz r1 temp 0.5
+snaz r1 temp 0.5
+sdse r1 pump
+sdns r1 pump
+beq temp 3 loop
+bne temp 3 loop
+blt temp 3 loop
+bgt temp 3 loop
+ble temp 3 loop
+bge temp 3 loop
+beqz temp loop
+bnez temp loop
+bltz temp loop
+bgez temp loop
+blez temp loop
+bgtz temp loop
+bnan temp loop
+breq temp 3 -2
+brne temp 3 -2
+brlt temp 3 -2
+brgt temp 3 -2
+brle temp 3 -2
+brge temp 3 -2
+breqz temp -2
+brnez temp -2
+brltz temp -2
+brgez temp -2
+brlez temp -2
+brgtz temp -2
+brnan temp -2
+beqal temp 3 loop
+bneal temp 3 loop
+bltal temp 3 loop
+bgtal temp 3 loop
+bleal temp 3 loop
+bgeal temp 3 loop
+beqzal temp loop
+bnezal temp loop
+bltzal temp loop
+bgezal temp loop
+blezal temp loop
+bgtzal temp loop
+bap temp 3 0.5 loop
+bna temp 3 0.5 loop
+brap temp 3 0.5 -2
+brna temp 3 0.5 -2
+bapz temp 0.5 loop
+bnaz temp 0.5 loop
+brapz temp 0.5 -2
+brnaz temp 0.5 -2
+bapal temp 3 0.5 loop
+bnaal temp 3 0.5 loop
+bapzal temp 0.5 loop
+bnazal temp 0.5 loop
+bdse pump loop
+bdns pump loop
+brdse pump -2
+brdns pump -2
+bdseal pump loop
+bdnsal pump loop
+bdnvl pump Setting loop
+bdnvs pump Setting loop
+j loop
+jr -2
+jal loop
+push temp
+pop r1
+peek r1
+poke r0 K
+l r1 pump Setting
+s pump Setting K
+ls r1 pump 0 Occupied
+ss pump 0 Occupied K
+lr r1 pump Contents HASH("Iron")
+rmap r1 pump r6
+ld r1 r3 Setting
+sd r3 Setting K
+lb r1 -128473777 Setting Average
+sb -128473777 Setting K
+lbn r1 -128473777 HASH("Name") Setting Average
+sbn -128473777 HASH("Name") Setting K
+lbs r1 -128473777 0 Occupied Average
+sbs -128473777 0 Occupied K
+lbns r1 -128473777 HASH("Name") 0 Occupied Average
+get r1 pump sp
+put pump sp K
+getd r1 r3 sp
+putd r3 sp K
+yield
+sleep 0.25
+hcf
+select r1 r4 temp 3
+clr pump
+clrd r3
+s d5 On 1
+s db Setting ra
+move other 123456789
+j ra
+"#;
+        let instructions = |source: &str| -> Vec<Instruction> {
+            compile_module(source)
+                .unwrap()
+                .instructions()
+                .iter()
+                .map(|parsed| parsed.instruction.clone())
+                .collect()
+        };
+
+        let original = instructions(source);
+        let rendered = original
+            .iter()
+            .map(Instruction::to_source)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(instructions(&rendered), original, "{rendered}");
+
+        // All 149 mnemonics appear in the program, plus the empty one of the no-op lines
+        let mnemonics: std::collections::HashSet<&str> =
+            original.iter().map(Instruction::mnemonic).collect();
+        assert_eq!(mnemonics.len(), 150);
+    }
+
+    #[test]
+    fn test_parsed_instruction_line_metadata() {
+        let parsed = ParsedInstruction::parse("  add r1 r2 r3", 4).unwrap();
+        assert_eq!(parsed.source(), "  add r1 r2 r3");
+        assert_eq!(parsed.line(), 5);
+        let span = parsed.opcode_span().unwrap();
+        assert_eq!(span, 2..5);
+        assert_eq!(&parsed.source()[span], "add");
+
+        let parsed = ParsedInstruction::parse("\tyield", 0).unwrap();
+        assert_eq!(parsed.opcode_span(), Some(1..6));
+
+        for line in ["", "   ", "# comment", "  start:"] {
+            let parsed = ParsedInstruction::parse(line, 0).unwrap();
+            assert_eq!(parsed.opcode_span(), None, "{line:?}");
+        }
+
+        // Compiled programs number lines from 1 as well
+        let module = compile_module("move r0 1\n\n  sub r0 r0 1").unwrap();
+        let sub = &module.instructions()[2];
+        assert_eq!(sub.line(), 3);
+        assert_eq!(sub.opcode_span(), Some(2..5));
+    }
 }
@@ -3,23 +3,39 @@
 mod tests {
     use std::f64;
 
+    use crate::AtmosphericNetwork;
     use crate::CableNetwork;
+    use crate::ChipState;
     use crate::Filter;
     use crate::Item;
     use crate::ItemIntegratedCircuit10;
+    use crate::ItemType;
     use crate::LogicType;
     use crate::atmospherics::GasType;
-    use crate::constants::STACK_SIZE;
-    use crate::constants::{RETURN_ADDRESS_INDEX, STACK_POINTER_INDEX};
+    use crate::constants::{MAX_EXACT_INTEGER, RETURN_ADDRESS_INDEX, STACK_POINTER_INDEX};
+    use crate::constants::{MAX_LINE_LENGTH, MAX_PROGRAM_LINES, REGISTER_COUNT, STACK_SIZE};
+    use crate::conversions::fmt_trim;
     use crate::devices::Filtration;
     use crate::devices::ICHostDevice;
     use crate::devices::SimulationDeviceSettings;
-    use crate::devices::{DaylightSensor, Device, ICHousing};
-    use crate::instruction::ParsedInstruction;
+    use crate::devices::console::CONSOLE_HISTORY_SIZE;
+    use crate::devices::hydroponics::{HYDROPONICS_CO2_PER_TICK, Hydroponics};
+    use crate::devices::{
+        AirConditioner, Console, DaylightSensor, Device, ICHousing, Locker, LogicMemory,
+        SlotHostDevice, Switch,
+    };
+    use crate::devices::{AtmosphericDevice, DeviceAtmosphericNetworkType};
+    use crate::error::SimulationError;
+    use crate::instruction::{Instruction, OperandRole, ParsedInstruction};
     use crate::items::FilterSize;
+    use crate::items::ReagentRegistry;
     use crate::items::SimulationItemSettings;
+    use crate::items::Slot;
+    use crate::items::item_integrated_circuit_10::{AliasTarget, Operand};
+    use crate::items::seed::{SEED_GROWTH_STAGES, SEED_GROWTH_TICKS, SEED_SEEDING_TICKS, Seed};
     use crate::logic::execute_instruction;
-    use crate::types::{Shared, shared};
+    use crate::parser::{compile_module, pack_ascii6, string_to_hash};
+    use crate::types::{Shared, SharedPtr, shared};
 
     // ==================== Test Helpers ====================
 
@@ -368,6 +384,41 @@ mod tests {
         assert_reg(&chip, 0, 4.0);
     }
 
+    #[test]
+    fn test_shift_sign_and_large_amounts() {
+        let mut chip = chip();
+
+        // sra keeps the sign of a negative value
+        exec_ok(&mut chip, "sra r0 -16 2");
+        assert_reg(&chip, 0, -4.0);
+        exec_ok(&mut chip, "sra r0 -1 10");
+        assert_reg(&chip, 0, -1.0);
+
+        // srl zero-fills from the top of the integer
+        exec_ok(&mut chip, "srl r0 -1 1");
+        assert_reg(&chip, 0, 9007199254740991.0); // 2^53 - 1
+        exec_ok(&mut chip, "srl r0 -16 2");
+        assert!(reg(&chip, 0) > 0.0);
+
+        // Shift amounts are taken modulo 64: 64 leaves the value unchanged
+        exec_ok(&mut chip, "sll r0 5 64");
+        assert_reg(&chip, 0, 5.0);
+        exec_ok(&mut chip, "sla r0 5 64");
+        assert_reg(&chip, 0, 5.0);
+        exec_ok(&mut chip, "srl r0 5 64");
+        assert_reg(&chip, 0, 5.0);
+        exec_ok(&mut chip, "sra r0 -8 64");
+        assert_reg(&chip, 0, -8.0);
+        exec_ok(&mut chip, "sll r0 1 65");
+        assert_reg(&chip, 0, 2.0);
+
+        // Bits shifted past the 53-bit integer are lost
+        exec_ok(&mut chip, "sll r0 1 53");
+        assert_reg(&chip, 0, -9007199254740992.0);
+        exec_ok(&mut chip, "sll r0 1 54");
+        assert_reg(&chip, 0, 0.0);
+    }
+
     #[test]
     fn test_bit_field_operations() {
         let mut chip = chip();
@@ -383,6 +434,51 @@ mod tests {
         assert_reg(&chip, 0, 240.0); // 0b11110000 = 240
     }
 
+    #[test]
+    fn test_bit_field_boundaries() {
+        let mut chip = chip();
+
+        // Bit 31 is an ordinary bit of the 53-bit integer
+        set_reg(&mut chip, 0, 2147483648.0); // 1 << 31
+        exec_ok(&mut chip, "ext r1 r0 31 1");
+        assert_reg(&chip, 1, 1.0);
+        exec_ok(&mut chip, "ext r1 r0 30 1");
+        assert_reg(&chip, 1, 0.0);
+        exec_ok(&mut chip, "ext r1 r0 28 8");
+        assert_reg(&chip, 1, 8.0);
+
+        // A field ending at the top bit (52) is allowed
+        set_reg(&mut chip, 0, 0.0);
+        exec_ok(&mut chip, "ins r0 3 51 2");
+        assert_reg(&chip, 0, 6755399441055744.0); // 0b11 << 51
+        exec_ok(&mut chip, "ext r1 r0 51 2");
+        assert_reg(&chip, 1, 3.0);
+
+        // A field straddling the top bit is an error and leaves the register untouched
+        set_reg(&mut chip, 0, 5.0);
+        let err = exec(&mut chip, "ins r0 15 50 4").unwrap_err();
+        assert!(err.contains("PayloadOverflow"), "{err}");
+        assert_reg(&chip, 0, 5.0);
+        let err = exec(&mut chip, "ext r1 r0 50 4").unwrap_err();
+        assert!(err.contains("PayloadOverflow"), "{err}");
+        let err = exec(&mut chip, "ext r1 r0 53 1").unwrap_err();
+        assert!(err.contains("ShiftOverflow"), "{err}");
+
+        // Negative start or length is an error
+        let err = exec(&mut chip, "ext r1 r0 -1 4").unwrap_err();
+        assert!(err.contains("ShiftUnderflow"), "{err}");
+        let err = exec(&mut chip, "ins r0 1 0 -1").unwrap_err();
+        assert!(err.contains("ShiftUnderflow"), "{err}");
+
+        // A zero-length field is a no-op
+        set_reg(&mut chip, 0, -7.0);
+        exec_ok(&mut chip, "ins r0 15 4 0");
+        assert_reg(&chip, 0, -7.0);
+        set_reg(&mut chip, 1, 9.0);
+        exec_ok(&mut chip, "ext r1 r0 4 0");
+        assert_reg(&chip, 1, 0.0);
+    }
+
     // ==================== Comparison Instructions ====================
 
     #[test]
@@ -462,6 +558,39 @@ mod tests {
         assert_reg(&chip, 0, 0.0);
     }
 
+    #[test]
+    fn test_comparisons_with_nan_are_false() {
+        let mut chip = chip();
+
+        // Create NaN
+        exec_ok(&mut chip, "div r1 0 0");
+
+        for program in [
+            "slt r0 r1 5",
+            "slt r0 5 r1",
+            "slt r0 r1 r1",
+            "sgt r0 r1 5",
+            "sgt r0 5 r1",
+            "sle r0 r1 r1",
+            "sge r0 r1 r1",
+            "seq r0 r1 r1",
+            "seq r0 r1 5",
+            "sltz r0 r1",
+            "sgtz r0 r1",
+            "seqz r0 r1",
+        ] {
+            exec_ok(&mut chip, "move r0 -1");
+            exec_ok(&mut chip, program);
+            assert_eq!(reg(&chip, 0), 0.0, "{program}");
+        }
+
+        // sne is the negation of seq
+        exec_ok(&mut chip, "sne r0 r1 r1");
+        assert_reg(&chip, 0, 1.0);
+        exec_ok(&mut chip, "snez r0 r1");
+        assert_reg(&chip, 0, 1.0);
+    }
+
     #[test]
     fn test_approximate_comparisons() {
         let mut chip = chip();
@@ -534,6 +663,95 @@ yield
         assert_eq!(chip.borrow().get_register(0).unwrap(), 222.0);
     }
 
+    #[test]
+    fn test_jump_onto_comment_and_blank_lines() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        // Comment, blank and label lines keep their slots: line 3 is the comment, line 6 blank
+        let program = "move r0 0
+j 3
+move r0 111
+# landing on a comment
+add r0 r0 1
+jr 1
+
+add r0 r0 10
+label:
+add r0 r0 100
+yield";
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+        // j 3 skips line 2, runs on from the comment; jr 1 lands on the blank line 6
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 111.0);
+
+        // Absolute line arithmetic counts the non-instruction lines too
+        let program = "move r1 4
+j r1
+move r0 -1
+move r0 -2
+
+move r0 7
+yield";
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 7.0);
+        // Stopped just past the yield on line 6
+        assert_eq!(chip.borrow().get_pc(), 7);
+    }
+
+    #[test]
+    fn test_move_preserves_large_integer_literals() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        let program = "move r0 $FFFFFFFF\nmove r1 $1FFFFFFFFFFFFF\nmove r2 r1\nyield";
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 4_294_967_295.0);
+        assert_eq!(
+            chip.borrow().get_register(2).unwrap() as i64,
+            MAX_EXACT_INTEGER - 1
+        );
+
+        // Above 2^53 the value would be rounded, so the literal is rejected when it runs
+        chip.borrow_mut()
+            .load_program("move r0 $20000000000001")
+            .unwrap();
+        let err = chip.borrow().run(128).unwrap_err();
+        assert!(
+            err.to_string().contains("<ERR:LiteralExceedsPrecision>"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_registers_are_rejected_at_load() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        let err = chip
+            .borrow_mut()
+            .load_program("move r0 1\nmove r99 0")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SimulationError::IC10ParseError { line: 1, .. }
+        ));
+        assert!(err.message().contains("r99"), "{err}");
+
+        let err = chip.borrow_mut().load_program("add r0 r18 1").unwrap_err();
+        assert!(matches!(
+            err,
+            SimulationError::IC10ParseError { line: 0, .. }
+        ));
+
+        // r17 is the highest register and is accepted
+        chip.borrow_mut()
+            .load_program("move r17 5\nmove r0 r17")
+            .unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(chip.borrow().get_register(17).unwrap(), 5.0);
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 5.0);
+    }
+
     #[test]
     fn test_jal() {
         let mut chip = chip();
@@ -676,6 +894,87 @@ yield
         assert_reg(&chip, STACK_POINTER_INDEX, 0.0);
     }
 
+    #[test]
+    fn test_read_sp_and_ra_as_operands() {
+        let mut chip = chip();
+
+        exec_ok(&mut chip, "move r0 sp");
+        assert_reg(&chip, 0, 0.0);
+
+        for value in [3.0, 1.0, 4.0] {
+            exec_ok(&mut chip, &format!("push {value}"));
+        }
+        exec_ok(&mut chip, "move r0 sp");
+        assert_reg(&chip, 0, 3.0);
+        exec_ok(&mut chip, "add r1 sp 1");
+        assert_reg(&chip, 1, 4.0);
+
+        exec_ok(&mut chip, "pop r2");
+        exec_ok(&mut chip, "move r0 sp");
+        assert_reg(&chip, 0, 2.0);
+
+        exec_ok(&mut chip, "move ra 7");
+        exec_ok(&mut chip, "move r3 ra");
+        assert_reg(&chip, 3, 7.0);
+    }
+
+    #[test]
+    fn test_sp_and_ra_parse_as_registers() {
+        let parsed = ParsedInstruction::parse("move r0 sp", 0).unwrap();
+        assert!(matches!(
+            parsed.instruction,
+            Instruction::Move {
+                arg: Operand::Register(STACK_POINTER_INDEX),
+                ..
+            }
+        ));
+
+        let parsed = ParsedInstruction::parse("move ra r0", 0).unwrap();
+        assert!(matches!(
+            parsed.instruction,
+            Instruction::Move {
+                dest: Operand::Register(RETURN_ADDRESS_INDEX),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_write_sp_moves_stack_pointer() {
+        let mut chip = chip();
+
+        exec_ok(&mut chip, "push 10");
+        exec_ok(&mut chip, "push 20");
+        exec_ok(&mut chip, "add sp sp 1");
+        assert_reg(&chip, STACK_POINTER_INDEX, 3.0);
+
+        // Stepping sp back below the pushes makes pop return the earlier value
+        exec_ok(&mut chip, "sub sp sp 2");
+        exec_ok(&mut chip, "pop r0");
+        assert_reg(&chip, 0, 10.0);
+        assert_reg(&chip, STACK_POINTER_INDEX, 0.0);
+    }
+
+    #[test]
+    fn test_write_ra_redirects_return() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        // The subroutine overwrites ra, so `j ra` skips the line after the call
+        let program = r#"
+jal sub
+move r0 111
+move r1 222
+yield
+sub:
+add ra ra 1
+j ra
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(1).unwrap(), 222.0);
+    }
+
     #[test]
     fn test_poke() {
         let mut chip = chip();
@@ -706,6 +1005,15 @@ yield
         // Non-zero condition
         exec_ok(&mut chip, "select r0 -5 100 200");
         assert_reg(&chip, 0, 100.0);
+
+        // NaN condition picks the false branch
+        exec_ok(&mut chip, "div r1 0 0");
+        exec_ok(&mut chip, "select r0 r1 100 200");
+        assert_reg(&chip, 0, 200.0);
+
+        // NaN values pass through unchanged
+        exec_ok(&mut chip, "select r0 1 r1 200");
+        assert_reg(&chip, 0, f64::NAN);
     }
 
     // ==================== Special Instructions ====================
@@ -723,6 +1031,146 @@ yield
         // Sleep of 2 seconds = 4 ticks, chip stores remaining ticks
     }
 
+    /// Run `chip` one tick at a time and return the first tick (1-based) at which the
+    /// housing's Setting becomes non-zero
+    fn ticks_until_setting(
+        chip: &Shared<ItemIntegratedCircuit10>,
+        housing: &Shared<ICHousing>,
+    ) -> u32 {
+        for tick in 1..=100 {
+            chip.borrow().run(128).unwrap();
+            if housing.borrow().read(LogicType::Setting).unwrap() != 0.0 {
+                return tick;
+            }
+        }
+        panic!("Setting was never written");
+    }
+
+    #[test]
+    fn test_sleep_resumes_after_duration() {
+        let program = "sleep 1\ns db Setting 1";
+
+        // Two ticks per second by default: sleeping on tick 1 resumes on tick 3
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        chip.borrow_mut().load_program(program).unwrap();
+        assert_eq!(ticks_until_setting(&chip, &housing), 3);
+
+        // The conversion follows the configured tick rate
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        chip.borrow_mut().set_ticks_per_second(5.0);
+        chip.borrow_mut().load_program(program).unwrap();
+        assert_eq!(ticks_until_setting(&chip, &housing), 6);
+
+        // Partial ticks round up so the chip never wakes early
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        chip.borrow_mut()
+            .load_program("sleep 0.7\ns db Setting 1")
+            .unwrap();
+        assert_eq!(ticks_until_setting(&chip, &housing), 3);
+
+        // A zero sleep behaves like yield
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        chip.borrow_mut()
+            .load_program("sleep 0\ns db Setting 1")
+            .unwrap();
+        assert_eq!(ticks_until_setting(&chip, &housing), 2);
+    }
+
+    #[test]
+    fn test_yield_loop_runs_once_per_tick() {
+        let mut chip = chip();
+        chip.load_program("add r0 r0 1\nyield\nj 0").unwrap();
+
+        for tick in 1..=10 {
+            let steps = chip.run(128).unwrap();
+            assert_eq!(steps, if tick == 1 { 2 } else { 3 });
+            assert_reg(&chip, 0, tick as f64);
+        }
+    }
+
+    #[test]
+    fn test_chip_state_reports_why_the_chip_is_not_advancing() {
+        // Out of instructions for this tick but not waiting on anything
+        let mut running = chip();
+        running.load_program("add r0 r0 1\nj 0").unwrap();
+        assert_eq!(running.state(), ChipState::Running);
+        running.run(10).unwrap();
+        assert_eq!(running.state(), ChipState::Running);
+
+        let mut yielded = chip();
+        yielded.load_program("add r0 r0 1\nyield\nj 0").unwrap();
+        yielded.run(128).unwrap();
+        assert_eq!(yielded.state(), ChipState::Yielded);
+
+        // Two ticks per second: sleeping 2 seconds on tick 1 resumes on tick 5
+        let mut sleeping = chip();
+        sleeping.load_program("sleep 2\nyield\nj 0").unwrap();
+        sleeping.run(128).unwrap();
+        assert_eq!(sleeping.state(), ChipState::Sleeping { until_tick: 5 });
+        for _ in 2..5 {
+            sleeping.run(128).unwrap();
+            assert_eq!(sleeping.state(), ChipState::Sleeping { until_tick: 5 });
+        }
+        sleeping.run(128).unwrap();
+        assert_eq!(sleeping.ticks_run(), 5);
+        assert_eq!(sleeping.state(), ChipState::Yielded);
+
+        let mut finished = chip();
+        finished.load_program("move r0 1").unwrap();
+        finished.run(128).unwrap();
+        assert_eq!(finished.state(), ChipState::Halted);
+
+        let mut burned = chip();
+        burned.load_program("hcf").unwrap();
+        burned.run(128).unwrap();
+        assert_eq!(burned.state(), ChipState::Halted);
+
+        // Reloading clears the previous state
+        burned.load_program("move r0 1").unwrap();
+        assert_eq!(burned.state(), ChipState::Running);
+    }
+
+    #[test]
+    fn test_reset_reruns_the_loaded_program() {
+        let mut chip = chip();
+        chip.load_program(
+            "alias counter r5\n\
+             define Step 3\n\
+             add counter counter Step\n\
+             push counter\n\
+             sleep 1\n\
+             add r6 counter 1\n\
+             push r6\n\
+             move r0 sp",
+        )
+        .unwrap();
+
+        let run_to_halt = |chip: &ItemIntegratedCircuit10| {
+            while !chip.is_halted() {
+                chip.run(128).unwrap();
+            }
+            (
+                chip.registers(),
+                chip.read_stack(0).unwrap(),
+                chip.read_stack(1).unwrap(),
+            )
+        };
+        let first = run_to_halt(&chip);
+        assert_eq!(first.0[0], 2.0);
+        assert_eq!(first.0[5], 3.0);
+        assert_eq!((first.1, first.2), (3.0, 4.0));
+
+        chip.reset();
+        assert_eq!(chip.get_pc(), 0);
+        assert_eq!(chip.state(), ChipState::Running);
+        assert_eq!(chip.registers(), [0.0; REGISTER_COUNT]);
+        assert_eq!(chip.read_stack(0).unwrap(), 0.0);
+        assert!(chip.aliases().contains_key("counter"));
+        assert_eq!(chip.defines().get("Step"), Some(&3.0));
+
+        assert_eq!(run_to_halt(&chip), first);
+    }
+
     #[test]
     fn test_move_instruction() {
         let mut chip = chip();
@@ -809,6 +1257,309 @@ j ra
         assert_eq!(chip.borrow().get_register(0).unwrap(), 15.0);
     }
 
+    #[test]
+    fn test_trace_callback_sees_every_instruction() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        // CRC-8 (polynomial 0x07) of the byte 0x31
+        let program = r#"
+move r0 49
+move r1 0
+xor r1 r1 r0
+move r2 8
+loop:
+and r3 r1 128
+sll r1 r1 1
+and r1 r1 255
+beqz r3 skip
+xor r1 r1 7
+skip:
+sub r2 r2 1
+bgtz r2 loop
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+
+        let trace = shared(Vec::new());
+        let sink = trace.clone();
+        chip.borrow()
+            .set_trace_callback(Box::new(move |line, instruction: &Instruction| {
+                sink.borrow_mut().push((line, instruction.clone()));
+            }));
+
+        let executed = chip.borrow().run(1000).unwrap();
+        assert_eq!(chip.borrow().get_register(1).unwrap(), 151.0);
+        assert_eq!(trace.borrow().len(), executed);
+
+        // Blank lines execute as no-ops, so tracing starts at line 0 and ends at the yield
+        assert_eq!(trace.borrow()[0].0, 0);
+        assert_eq!(trace.borrow()[1].0, 1);
+        assert!(matches!(trace.borrow()[1].1, Instruction::Move { .. }));
+        assert!(matches!(
+            trace.borrow().last(),
+            Some((_, Instruction::Yield))
+        ));
+
+        // Clearing the callback stops tracing
+        chip.borrow().clear_trace_callback();
+        chip.borrow().run(1000).unwrap();
+        assert_eq!(trace.borrow().len(), executed);
+    }
+
+    /// CRC-8 (polynomial 0x07) of the ASCII bytes "123456789", using defines, aliases and labels
+    const CRC8_PROGRAM: &str = r#"define POLY 7
+define MASK 255
+define TOP 128
+alias crc r1
+alias byte r0
+move crc 0
+move byte 49
+nextByte:
+xor crc crc byte
+move r2 8
+bitLoop:
+and r3 crc TOP
+sll crc crc 1
+and crc crc MASK
+beqz r3 noPoly
+xor crc crc POLY
+noPoly:
+sub r2 r2 1
+bgtz r2 bitLoop
+add byte byte 1
+ble byte 57 nextByte
+yield
+"#;
+
+    #[test]
+    fn test_compiled_program_matches_naive_execution() {
+        let mut compiled = chip();
+        compiled.load_program(CRC8_PROGRAM).unwrap();
+        compiled.run(10_000).unwrap();
+        assert_eq!(compiled.get_register(1).unwrap(), 244.0);
+
+        // Naive execution: re-parse each line and resolve every name at runtime
+        let mut naive = chip();
+        naive.load_program(CRC8_PROGRAM).unwrap();
+        let lines: Vec<ParsedInstruction> = CRC8_PROGRAM
+            .lines()
+            .enumerate()
+            .map(|(i, line)| ParsedInstruction::parse(line, i).unwrap())
+            .collect();
+        loop {
+            let parsed = &lines[naive.get_pc()];
+            if parsed.instruction == Instruction::Yield {
+                break;
+            }
+            let next = execute_instruction(&naive, parsed).unwrap();
+            naive.set_pc(next);
+        }
+
+        for idx in 0..18 {
+            assert_eq!(
+                compiled.get_register(idx).unwrap(),
+                naive.get_register(idx).unwrap(),
+                "register {idx} differs"
+            );
+        }
+
+        // Only the runtime-rebindable alias names are left for lookup
+        let module = compile_module(CRC8_PROGRAM).unwrap();
+        let mut names = Vec::new();
+        for parsed in module.instructions() {
+            let mut instruction = parsed.instruction.clone();
+            instruction.for_each_operand_mut(|_, operand| {
+                if let Operand::Alias(name) = operand {
+                    names.push(name.clone());
+                }
+            });
+        }
+        names.sort();
+        names.dedup();
+        assert_eq!(names, vec!["byte".to_string(), "crc".to_string()]);
+    }
+
+    #[test]
+    fn test_compiled_program_runs_tight_loops_quickly() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        let program =
+            "define STEP 1\ndefine LIMIT 200000\nloop:\nadd r0 r0 STEP\nblt r0 LIMIT loop\nyield\n";
+        chip.borrow_mut().load_program(program).unwrap();
+
+        let start = std::time::Instant::now();
+        let executed = chip.borrow().run(1_000_000).unwrap();
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(5),
+            "Execution took too long: {:?}",
+            start.elapsed()
+        );
+
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 200_000.0);
+        assert!(executed > 600_000);
+    }
+
+    #[test]
+    fn test_redefined_and_aliased_names_resolve_at_runtime() {
+        let mut chip = chip();
+
+        // `x` is defined twice and `loop` is rebound by an alias, so neither can be inlined
+        let program = "define x 1\nmove r0 x\ndefine x 2\nmove r1 x\nalias loop r5\nmove r5 9\nmove r2 loop\nloop:\nyield\n";
+        chip.load_program(program).unwrap();
+        chip.run(100).unwrap();
+
+        assert_eq!(chip.get_register(0).unwrap(), 1.0);
+        assert_eq!(chip.get_register(1).unwrap(), 2.0);
+        assert_eq!(chip.get_register(2).unwrap(), 9.0);
+
+        let module = compile_module(program).unwrap();
+        let mut roles = Vec::new();
+        let mut instruction = module.instructions()[6].instruction.clone();
+        instruction.for_each_operand_mut(|role, operand| roles.push((role, operand.clone())));
+        assert_eq!(
+            roles,
+            vec![
+                (OperandRole::Destination, Operand::Register(2)),
+                (OperandRole::Value, Operand::Alias("loop".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_large_program_parses_in_linear_time() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        // 10k lines with a label every other line and a forward jump at the top
+        let mut program = String::from("define STEP 1\nj label4999\n");
+        for i in 0..5000 {
+            program.push_str(&format!("label{i}:\n"));
+            program.push_str(&format!("add r0 r0 STEP # line {i}\n"));
+        }
+        program.push_str("yield\n");
+
+        // Exceeds the game's program size limits
+        chip.borrow_mut().set_enforce_source_limits(false);
+
+        let start = std::time::Instant::now();
+        chip.borrow_mut().load_program(&program).unwrap();
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(5),
+            "Parsing took too long: {:?}",
+            start.elapsed()
+        );
+
+        assert_eq!(chip.borrow().get_line_count(), 10003);
+        chip.borrow().run(128).unwrap();
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_load_program_enforces_line_count_limit() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+        assert!(chip.borrow().enforces_source_limits());
+
+        // Blank and comment lines count toward the limit
+        let mut program = String::from("# counter\n\n");
+        for _ in 2..MAX_PROGRAM_LINES {
+            program.push_str("add r0 r0 1\n");
+        }
+        chip.borrow_mut().load_program(&program).unwrap();
+        assert_eq!(chip.borrow().get_line_count(), MAX_PROGRAM_LINES);
+
+        program.push_str("yield\n");
+        match chip.borrow_mut().load_program(&program) {
+            Err(SimulationError::IC10ParseError { line, .. }) => {
+                assert_eq!(line, MAX_PROGRAM_LINES)
+            }
+            other => panic!("expected line count error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_program_enforces_line_length_limit() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        // The comment counts toward the line length
+        let long_line = format!("move r0 1 #{}", "x".repeat(MAX_LINE_LENGTH - 10));
+        assert_eq!(long_line.len(), MAX_LINE_LENGTH + 1);
+        let program = format!("yield\n{long_line}\n");
+        match chip.borrow_mut().load_program(&program) {
+            Err(SimulationError::IC10ParseError { line, .. }) => assert_eq!(line, 1),
+            other => panic!("expected line length error, got {other:?}"),
+        }
+
+        chip.borrow_mut()
+            .load_program(&format!("yield\n{}\n", &long_line[..MAX_LINE_LENGTH]))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_load_program_relaxed_source_limits() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+        chip.borrow_mut().set_enforce_source_limits(false);
+
+        let mut program = format!("move r0 1 #{}\n", "x".repeat(100));
+        for _ in 0..200 {
+            program.push_str("add r0 r0 1\n");
+        }
+        chip.borrow_mut().load_program(&program).unwrap();
+        assert_eq!(chip.borrow().get_line_count(), 201);
+    }
+
+    #[test]
+    fn test_labels_resolve_to_their_own_lines() {
+        let source = "# counts to three, then skips ahead\n\
+             main:\n\
+             add r0 r0 1 # step\n\
+             \n\
+             blt r0 3 main\n\
+             j done\n\
+             move r1 99\n\
+             done: # the end\n\
+             move r2 r0\n\
+             j 10\n\
+             after:";
+        let mut chip = chip();
+        chip.load_program(source).unwrap();
+
+        let labels = chip.labels();
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels["main"], 1);
+        assert_eq!(labels["done"], 7);
+        assert_eq!(labels["after"], 10);
+
+        // Jumps by name land on the label line itself, like a numeric jump to that line
+        let module = compile_module(source).unwrap();
+        let program = module.instructions();
+        assert_eq!(
+            program[5].instruction,
+            Instruction::J {
+                line: Operand::Immediate(7.0)
+            }
+        );
+        assert_eq!(
+            program[7].instruction,
+            Instruction::Label {
+                name: "done".to_string()
+            }
+        );
+
+        while !chip.is_halted() {
+            chip.run(128).unwrap();
+        }
+        assert_reg(&chip, 0, 3.0);
+        assert_reg(&chip, 1, 0.0);
+        assert_reg(&chip, 2, 3.0);
+    }
+
+    #[test]
+    fn test_load_program_duplicate_label_rejected() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+
+        let program = "start:\nmove r0 1\nstart:\n";
+        assert!(chip.borrow_mut().load_program(program).is_err());
+    }
+
     // ==================== Additional Branch Instructions ====================
 
     #[test]
@@ -1069,17 +1820,191 @@ j ra
         assert_reg(&chip, RETURN_ADDRESS_INDEX, 1.0);
     }
 
-    // ==================== Device State Detection Tests ====================
+    // ==================== Borrow Conflict Tests ====================
 
     #[test]
-    fn test_device_state_detection() {
-        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+    fn test_chip_reads_own_housing_while_housing_runs() {
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        let housing_id = housing.borrow().get_id();
 
-        // Add a device and assign it to d0
-        let sensor = DaylightSensor::new(SimulationDeviceSettings {
-            id: Some(3),
-            ..SimulationDeviceSettings::default()
-        });
+        let program = format!(
+            "s db Setting 5
+l r0 db Setting
+add r0 r0 1
+s db Setting r0
+ld r1 {housing_id} Setting
+lb r2 {} Setting 0
+get r3 db 0
+yield",
+            ICHousing::PREFAB_HASH
+        );
+        chip.borrow_mut().load_program(&program).unwrap();
+
+        // The housing is borrowed while it runs its chip, as during a simulation tick
+        Device::run(&*housing.borrow()).unwrap();
+        assert_reg(&chip.borrow(), 0, 6.0);
+        assert_reg(&chip.borrow(), 1, 6.0);
+        assert_reg(&chip.borrow(), 2, 6.0);
+        assert_eq!(housing.borrow().read(LogicType::Setting).unwrap(), 6.0);
+    }
+
+    #[test]
+    fn test_runtime_errors_report_the_executing_line() {
+        let (chip, _housing, _network) = ItemIntegratedCircuit10::new_with_network();
+
+        let program = "move r0 1
+move r1 2
+# comment
+
+alias sensor db
+add r2 r0 r1
+move r3 r2
+l r4 sensor Horizontal
+yield";
+        chip.borrow_mut().load_program(program).unwrap();
+        let err = chip.borrow().run(128).unwrap_err();
+        assert!(
+            matches!(err, SimulationError::RuntimeError { line: 7, .. }),
+            "{err}"
+        );
+        assert_eq!(chip.borrow().get_error_line(), Some(7));
+        assert_reg(&chip.borrow(), 3, 3.0);
+
+        // Bounds errors from the executing line keep their kind and gain the line
+        chip.borrow_mut()
+            .load_program("move r0 1\nmove sp 600\npush r0")
+            .unwrap();
+        let err = chip.borrow().run(128).unwrap_err();
+        assert_eq!(
+            err,
+            SimulationError::StackOutOfBounds {
+                index: 600,
+                line: Some(2),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Stack index 600 out of bounds (valid range: 0-511) at line 2"
+        );
+    }
+
+    #[test]
+    fn test_busy_devices_are_runtime_errors() {
+        let (chip, housing, network) = ItemIntegratedCircuit10::new_with_network();
+        let memory = LogicMemory::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(memory.clone(), network.clone())
+            .unwrap();
+        housing.borrow().set_device_pin(0, Some(3));
+
+        // A device the host holds mutably borrowed is reported, not a panic
+        for line in [
+            "l r0 d0 Setting",
+            "s d0 Setting 1",
+            "ld r0 3 Setting",
+            "get r0 d0 0",
+            "lb r0 HASH(\"StructureLogicMemory\") Setting 0",
+            "sb HASH(\"StructureLogicMemory\") Setting 1",
+        ] {
+            chip.borrow_mut().load_program(line).unwrap();
+            let guard = memory.borrow_mut();
+            let err = chip.borrow().run(128).unwrap_err();
+            drop(guard);
+            assert!(err.message().contains("busy"), "{line}: {err}");
+            assert_eq!(err.line(), Some(0), "{line}: {err}");
+        }
+
+        // So is the network itself
+        chip.borrow_mut().load_program("l r0 d0 Setting").unwrap();
+        let guard = network.borrow_mut();
+        let err = chip.borrow().run(128).unwrap_err();
+        drop(guard);
+        assert!(err.message().contains("busy"), "{err}");
+
+        // And the chip's own housing, which is not mistaken for a missing network
+        for line in ["s db Setting 1", "l r0 d0 Setting", "bdnvl d0 Setting 0"] {
+            chip.borrow_mut().load_program(line).unwrap();
+            let guard = housing.borrow_mut();
+            let err = chip.borrow().run(128).unwrap_err();
+            drop(guard);
+            assert!(err.message().contains("busy"), "{line}: {err}");
+            assert_eq!(err.line(), Some(0), "{line}: {err}");
+        }
+
+        // Once released, the same program runs
+        chip.borrow_mut()
+            .load_program("s db Setting 1\nyield")
+            .unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(housing.borrow().read(LogicType::Setting).unwrap(), 1.0);
+    }
+
+    // ==================== Device State Detection Tests ====================
+
+    #[test]
+    fn test_device_pins_and_db_in_device_io() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let filtration = Filtration::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(filtration.clone(), network.clone())
+            .unwrap();
+        let slot = chip.borrow().get_chip_slot();
+        slot.borrow_mut().set_device_pin(0, Some(3));
+        slot.borrow_mut().set_device_pin(5, Some(3));
+
+        let program = r#"
+alias pump d0
+s pump On 0
+l r0 d5 On
+ls r1 pump 0 Occupied
+s db Setting 5
+l r2 db Setting
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        assert_eq!(filtration.borrow().read(LogicType::On).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(1).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(2).unwrap(), 5.0);
+
+        // Every form of device access on an unconnected pin names the pin
+        for line in [
+            "s d3 On 1",
+            "l r0 d3 On",
+            "ss d3 0 Occupied 1",
+            "ls r0 d3 0 Occupied",
+            "alias pump d3",
+        ] {
+            chip.borrow_mut().load_program(line).unwrap();
+            match chip.borrow().run(128) {
+                Err(SimulationError::RuntimeError { message, .. }) => {
+                    assert!(message.contains("d3"), "{line}: {message}");
+                }
+                other => panic!("{line}: expected runtime error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_device_state_detection() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        // Add a device and assign it to d0
+        let sensor = DaylightSensor::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
         let sensor_id = sensor.borrow().get_id();
         network
             .borrow_mut()
@@ -1211,6 +2136,121 @@ yield
         assert_eq!(chip.borrow().get_register(1).unwrap(), 3.0);
     }
 
+    #[test]
+    fn test_device_set_present_absent_and_removed() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let sensor = DaylightSensor::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(sensor.clone(), network.clone())
+            .unwrap();
+        chip.borrow()
+            .get_chip_slot()
+            .borrow_mut()
+            .set_device_pin(0, Some(3));
+
+        // d0 is present, d1 is unassigned, id 99 does not exist on the network
+        let program = r#"
+sdse r0 d0
+sdns r1 d0
+sdse r2 d1
+sdns r3 d1
+move r9 99
+sdse r4 r9
+sdns r5 r9
+yield
+sdse r6 d0
+sdns r7 d0
+move r8 0
+bdns d0 14
+move r8 1
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 1.0);
+        assert_eq!(chip.borrow().get_register(1).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(2).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(3).unwrap(), 1.0);
+        assert_eq!(chip.borrow().get_register(4).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(5).unwrap(), 1.0);
+
+        // Remove the device while the program is paused at the yield
+        network.borrow_mut().remove_device(3);
+        chip.borrow().run(128).unwrap();
+
+        assert_eq!(chip.borrow().get_register(6).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(7).unwrap(), 1.0);
+        // bdns branched over "move r8 1"
+        assert_eq!(chip.borrow().get_register(8).unwrap(), 0.0);
+        assert!(!chip.borrow().is_halted());
+    }
+
+    #[test]
+    fn test_device_set_by_id() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let sensor = DaylightSensor::new(SimulationDeviceSettings {
+            id: Some(123),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(sensor.clone(), network.clone())
+            .unwrap();
+
+        let program = r#"
+define SENSOR 123
+sdse r0 123
+sdns r1 123
+sdse r2 456
+sdns r3 456
+sdse r4 SENSOR
+move r10 123
+sdse r5 r10
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        let chip_ref = chip.borrow();
+        assert_reg(&chip_ref, 0, 1.0);
+        assert_reg(&chip_ref, 1, 0.0);
+        assert_reg(&chip_ref, 2, 0.0);
+        assert_reg(&chip_ref, 3, 1.0);
+        assert_reg(&chip_ref, 4, 1.0);
+        assert_reg(&chip_ref, 5, 1.0);
+    }
+
+    #[test]
+    fn test_rmap_resolves_registered_reagent() {
+        let (chip, _housing, _network) = ItemIntegratedCircuit10::new_with_network();
+
+        let mut reagents = ReagentRegistry::default();
+        let reagent_hash = reagents.register("TestReagentRmap", 424242);
+        assert_eq!(reagent_hash, string_to_hash("TestReagentRmap"));
+        chip.borrow_mut().set_reagents(SharedPtr::new(reagents));
+
+        let program = r#"
+rmap r0 db HASH("TestReagentRmap")
+rmap r1 db 999
+rmap r2 db HASH("Iron")
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        let chip_ref = chip.borrow();
+        assert_reg(&chip_ref, 0, 424242.0);
+        assert_reg(&chip_ref, 1, f64::NAN);
+        assert_reg(&chip_ref, 2, string_to_hash("ItemIronIngot") as f64);
+    }
+
     // ==================== Special Instructions Tests ====================
 
     #[test]
@@ -1225,13 +2265,73 @@ yield
         assert_eq!(pc, 1);
     }
 
+    #[test]
+    fn test_register_alias_and_define_snapshots() {
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        housing.borrow().set_device_pin(0, Some(1));
+
+        chip.borrow_mut()
+            .load_program(
+                "define LIMIT 42\nalias counter r3\nalias sensor d0\nmove counter LIMIT\nmove sp 7",
+            )
+            .unwrap();
+        chip.borrow().run(128).unwrap();
+
+        let registers = chip.borrow().registers();
+        assert_eq!(registers[3], 42.0);
+        assert_eq!(registers[STACK_POINTER_INDEX], 7.0);
+        assert_eq!(registers.iter().filter(|&&v| v != 0.0).count(), 2);
+
+        let aliases = chip.borrow().aliases();
+        assert_eq!(aliases.get("counter"), Some(&AliasTarget::Register(3)));
+        assert_eq!(aliases.get("sensor"), Some(&AliasTarget::Device(1)));
+        assert_eq!(aliases.get("db"), Some(&AliasTarget::Device(1)));
+
+        let defines = chip.borrow().defines();
+        assert_eq!(defines.get("LIMIT"), Some(&42.0));
+        assert_eq!(defines.get("pi"), Some(&f64::consts::PI));
+
+        // Snapshots are copies and do not track later changes
+        chip.borrow().set_register(3, 0.0).unwrap();
+        assert_eq!(registers[3], 42.0);
+    }
+
     #[test]
     fn test_hcf() {
         let mut chip = chip();
 
-        // hcf should return an error (halt and catch fire)
+        // By default hcf halts silently and stays on its line
+        assert_eq!(exec(&mut chip, "hcf"), Ok(0));
+        assert!(chip.is_halted());
+
+        // With hcf errors enabled it also returns an error (halt and catch fire)
+        chip.resume();
+        chip.set_hcf_errors(true);
         let result = exec(&mut chip, "hcf");
         assert!(result.is_err());
+        assert!(chip.is_halted());
+    }
+
+    #[test]
+    fn test_hcf_errors_policy_in_run() {
+        let program = "move r0 1\nhcf\nmove r0 2";
+
+        let mut silent = chip();
+        silent.load_program(program).unwrap();
+        assert!(!silent.hcf_errors());
+        assert!(silent.run(128).is_ok());
+        assert!(silent.is_halted());
+        assert_reg(&silent, 0, 1.0);
+
+        let mut loud = chip();
+        loud.set_hcf_errors(true);
+        loud.load_program(program).unwrap();
+        assert!(matches!(
+            loud.run(128),
+            Err(SimulationError::RuntimeError { line: 1, .. })
+        ));
+        assert!(loud.is_halted());
+        assert_reg(&loud, 0, 1.0);
     }
 
     // ==================== Device I/O Tests ====================
@@ -1311,6 +2411,8 @@ l r2 db r5
 
 yield
 "#;
+        // Exceeds the game's program size limits
+        chip.borrow_mut().set_enforce_source_limits(false);
         chip.borrow_mut().load_program(program).unwrap();
         chip.borrow().run(128).unwrap();
 
@@ -1373,6 +2475,8 @@ yield
 "#
         .to_string();
 
+        // Exceeds the game's program size limits
+        chip.borrow_mut().set_enforce_source_limits(false);
         chip.borrow_mut().load_program(&program).unwrap();
         chip.borrow().run(128).unwrap();
 
@@ -1467,6 +2571,192 @@ yield
         assert_reg(&chip_ref, 5, 0.0); // After clear
     }
 
+    #[test]
+    fn test_read_display_rounds_without_changing_stored_value() {
+        let memory = LogicMemory::new(SimulationDeviceSettings {
+            id: Some(5),
+            ..SimulationDeviceSettings::default()
+        });
+        memory
+            .borrow()
+            .write(LogicType::Setting, 0.123456789)
+            .unwrap();
+
+        let stored = memory.borrow().read(LogicType::Setting).unwrap();
+        let displayed = memory.borrow().read_display(LogicType::Setting).unwrap();
+        assert_eq!(stored, 0.123456789);
+        assert_eq!(displayed, 0.123);
+        assert_ne!(stored, displayed);
+
+        // Reading for display does not alter the stored value
+        assert_eq!(
+            memory.borrow().read(LogicType::Setting).unwrap(),
+            0.123456789
+        );
+    }
+
+    #[test]
+    fn test_console_shows_text_written_by_chip() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let console = Console::new(SimulationDeviceSettings {
+            id: Some(3),
+            name: Some("Status".to_string()),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(console.clone(), network.clone())
+            .unwrap();
+
+        let program = r#"
+sd 3 Setting 42
+sd 3 Mode 1
+sd 3 Setting STR("BUSY")
+sd 3 Setting 42.5
+sd 3 Setting STR("DONE")
+ld r0 3 NameHash
+ld r1 3 Setting
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        // Integers show as numbers unless text was asked for: 42 would decode as "*"
+        let console = console.borrow();
+        assert_eq!(console.history(), vec!["42", "BUSY", "42.5", "DONE"]);
+        assert_eq!(console.text(), "DONE");
+        assert_reg(&chip.borrow(), 0, string_to_hash("Status") as f64);
+        assert_reg(&chip.borrow(), 1, pack_ascii6("DONE").unwrap() as f64);
+
+        // Back in number mode the packed value shows as a number
+        console.write(LogicType::Mode, 0.0).unwrap();
+        assert_eq!(
+            console.text(),
+            fmt_trim(pack_ascii6("DONE").unwrap() as f64, 3)
+        );
+
+        // Only the most recent writes are kept
+        for i in 0..CONSOLE_HISTORY_SIZE {
+            console.write(LogicType::Setting, i as f64).unwrap();
+        }
+        let history = console.history_values();
+        assert_eq!(history.len(), CONSOLE_HISTORY_SIZE);
+        assert_eq!(history[0], 0.0);
+    }
+
+    #[test]
+    fn test_switch_flipped_between_ticks_changes_branch() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let switch = Switch::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(switch.clone(), network.clone())
+            .unwrap();
+
+        let program = r#"
+loop:
+ld r0 3 On
+beqz r0 off
+move r1 1
+yield
+j loop
+off:
+move r1 -1
+yield
+j loop
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+
+        chip.borrow().run(128).unwrap();
+        assert_reg(&chip.borrow(), 1, -1.0);
+
+        assert!(switch.borrow().toggle());
+        chip.borrow().run(128).unwrap();
+        assert_reg(&chip.borrow(), 1, 1.0);
+
+        switch.borrow().set(false);
+        chip.borrow().run(128).unwrap();
+        assert_reg(&chip.borrow(), 1, -1.0);
+
+        // Setting mirrors On, so a chip can flip the switch too
+        chip.borrow_mut()
+            .load_program("sd 3 Setting 1\nld r2 3 On\nyield")
+            .unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_reg(&chip.borrow(), 2, 1.0);
+        assert!(switch.borrow().is_on());
+    }
+
+    #[test]
+    fn test_logic_memory_shared_between_chips() {
+        let (chip_a, housing_a, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let housing_b = ICHousing::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        let chip_b = shared(ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(4),
+            ..SimulationItemSettings::default()
+        }));
+        housing_b.borrow().set_chip(chip_b.clone()).unwrap();
+        network
+            .borrow_mut()
+            .add_device(housing_b.clone(), network.clone())
+            .unwrap();
+
+        let memory = LogicMemory::new(SimulationDeviceSettings {
+            id: Some(5),
+            memory_size: Some(4),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(memory.clone(), network.clone())
+            .unwrap();
+        assert_eq!(memory.borrow().memory_size(), 4);
+
+        housing_a.borrow().set_device_pin(0, Some(5));
+        housing_b.borrow().set_device_pin(0, Some(5));
+
+        let writer = r#"
+put d0 0 10
+put d0 1 11
+putd 5 2 12
+putd 5 3 13
+yield
+"#;
+        let reader = r#"
+get r0 d0 0
+get r1 d0 1
+getd r2 5 2
+getd r3 5 3
+yield
+"#;
+        chip_a.borrow_mut().load_program(writer).unwrap();
+        chip_b.borrow_mut().load_program(reader).unwrap();
+        chip_a.borrow().run(128).unwrap();
+        chip_b.borrow().run(128).unwrap();
+
+        let chip_ref = chip_b.borrow();
+        assert_reg(&chip_ref, 0, 10.0);
+        assert_reg(&chip_ref, 1, 11.0);
+        assert_reg(&chip_ref, 2, 12.0);
+        assert_reg(&chip_ref, 3, 13.0);
+
+        // Index outside the configured bank size is an error
+        assert!(memory.borrow().get_memory(4).is_err());
+        assert!(memory.borrow().set_memory(4, 1.0).is_err());
+
+        memory.borrow().clear().unwrap();
+        assert_eq!(memory.borrow().get_memory(0).unwrap(), 0.0);
+    }
+
     // ==================== ID-Based Device Access Tests ====================
 
     #[test]
@@ -1616,6 +2906,47 @@ yield
         );
     }
 
+    #[test]
+    fn test_chip_host_reports_stack_size_and_depth() {
+        let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
+        let host = housing.borrow();
+        let host = host.as_ic_host_device().unwrap();
+        assert_eq!(
+            housing.borrow().read(LogicType::StackSize).unwrap(),
+            STACK_SIZE as f64
+        );
+        assert_eq!(host.stack_depth(), 0);
+
+        chip.borrow_mut()
+            .load_program("push 1\npush 2\npush 3\nyield\npop r0\nyield")
+            .unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(host.stack_depth(), 3);
+        chip.borrow().run(128).unwrap();
+        assert_eq!(host.stack_depth(), 2);
+
+        // The capacity does not change with the contents
+        assert_eq!(
+            housing.borrow().read(LogicType::StackSize).unwrap(),
+            STACK_SIZE as f64
+        );
+
+        // Every chip host answers StackSize, not only the housing
+        let settings = || SimulationDeviceSettings {
+            id: Some(7),
+            ..SimulationDeviceSettings::default()
+        };
+        let hosts: [Shared<dyn Device>; 2] =
+            [AirConditioner::new(settings()), Filtration::new(settings())];
+        for host in hosts {
+            let host = host.borrow();
+            assert!(host.can_read(LogicType::StackSize));
+            assert!(!host.can_write(LogicType::StackSize));
+            assert!(host.supported_types().contains(&LogicType::StackSize));
+            assert_eq!(host.read(LogicType::StackSize).unwrap(), STACK_SIZE as f64);
+        }
+    }
+
     #[test]
     fn test_ichousing_line_number_write_sets_pc() {
         let (chip, housing, _network) = ItemIntegratedCircuit10::new_with_network();
@@ -1655,6 +2986,36 @@ yield
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_ls_untracked_slot_type_reads_zero_unless_strict() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let filtration = Filtration::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(filtration, network.clone())
+            .unwrap();
+
+        assert!(!chip.borrow().is_strict_logic());
+        chip.borrow_mut()
+            .load_program("move r0 -1\nmove r1 -1\nls r0 3 0 Growth\nls r1 3 1 MaturityRatio")
+            .unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(1).unwrap(), 0.0);
+
+        // Slots the device does not have still error
+        chip.borrow_mut().load_program("ls r0 3 9 Growth").unwrap();
+        assert!(chip.borrow().run(128).is_err());
+
+        chip.borrow_mut().set_strict_logic(true);
+        chip.borrow_mut().load_program("ls r0 3 0 Growth").unwrap();
+        assert!(chip.borrow().run(128).is_err());
+    }
+
     #[test]
     fn test_filtration_slot_ls() {
         let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
@@ -1732,8 +3093,8 @@ yield
         assert_eq!(chip.borrow().get_register(3).unwrap(), expected_max);
         assert_eq!(chip.borrow().get_register(4).unwrap(), expected_type);
         assert_eq!(chip.borrow().get_register(5).unwrap(), expected_id);
-        assert_eq!(chip.borrow().get_register(6).unwrap(), 0.0);
-        assert_eq!(chip.borrow().get_register(7).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(6).unwrap(), 1.0);
+        assert_eq!(chip.borrow().get_register(7).unwrap(), 2.0);
 
         assert_eq!(chip.borrow().get_register(8).unwrap(), 0.0);
         assert_eq!(chip.borrow().get_register(9).unwrap(), 0.0);
@@ -1741,8 +3102,350 @@ yield
         assert_eq!(chip.borrow().get_register(11).unwrap(), 0.0);
         assert_eq!(chip.borrow().get_register(12).unwrap(), 0.0);
         assert_eq!(chip.borrow().get_register(13).unwrap(), 0.0);
-        assert_eq!(chip.borrow().get_register(14).unwrap(), 0.0);
-        assert_eq!(chip.borrow().get_register(15).unwrap(), 0.0);
+        assert_eq!(chip.borrow().get_register(14).unwrap(), 1.0);
+        assert_eq!(chip.borrow().get_register(15).unwrap(), 2.0);
+    }
+
+    fn filter_item(id: i32, quantity: u32) -> Shared<dyn Item> {
+        let mut filter = Filter::new(SimulationItemSettings {
+            id: Some(id),
+            ..SimulationItemSettings::default()
+        });
+        filter.set_quantity(quantity);
+        shared(filter)
+    }
+
+    #[test]
+    fn test_locker_free_and_total_slots_track_items() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let locker = Locker::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(locker.clone(), network.clone())
+            .unwrap();
+
+        let program = r#"
+ls r0 3 0 FreeSlots
+ls r1 3 29 TotalSlots
+ls r2 3 4 Occupied
+ls r3 3 4 MaxQuantity
+yield
+"#;
+        let read_counts = || {
+            chip.borrow_mut().load_program(program).unwrap();
+            chip.borrow().run(128).unwrap();
+            let c = chip.borrow();
+            [0, 1, 2, 3].map(|i| c.get_register(i).unwrap())
+        };
+
+        assert_eq!(read_counts(), [30.0, 30.0, 0.0, 0.0]);
+
+        {
+            let mut l = locker.borrow_mut();
+            assert!(l.try_insert_item(4, filter_item(10, 5)).is_ok());
+            assert!(l.try_insert_item(7, filter_item(11, 5)).is_ok());
+            // Matching filters stack into an occupied slot
+            assert!(l.try_insert_item(7, filter_item(12, 5)).is_ok());
+            assert!(l.try_insert_item(30, filter_item(13, 5)).is_err());
+        }
+        assert_eq!(read_counts(), [28.0, 30.0, 1.0, 100.0]);
+
+        assert!(locker.borrow_mut().remove_item(4).is_some());
+        assert_eq!(read_counts(), [29.0, 30.0, 0.0, 0.0]);
+        assert_eq!(locker.borrow().free_slots(), 29);
+    }
+
+    #[test]
+    fn test_hydroponics_grows_seed_with_light_and_co2() {
+        use crate::LogicSlotType::*;
+        use DeviceAtmosphericNetworkType::Input;
+
+        let hydroponics = |id: i32, co2: f64| {
+            let device = Hydroponics::new(SimulationDeviceSettings {
+                id: Some(id),
+                ticks_per_day: Some(2400.0),
+                ..SimulationDeviceSettings::default()
+            });
+            let room = AtmosphericNetwork::new(100.0);
+            room.borrow_mut()
+                .add_gas(GasType::Nitrogen, 2.0, 293.15)
+                .unwrap();
+            if co2 > 0.0 {
+                room.borrow_mut()
+                    .add_gas(GasType::CarbonDioxide, co2, 293.15)
+                    .unwrap();
+            }
+            let seed = shared(Seed::new(SimulationItemSettings {
+                id: Some(id + 100),
+                ..SimulationItemSettings::default()
+            }));
+            {
+                let mut h = device.borrow_mut();
+                h.set_atmospheric_network(Input, Some(room.clone()))
+                    .unwrap();
+                h.try_insert_item(0, seed).unwrap();
+            }
+            (device, room)
+        };
+
+        let (device, room) = hydroponics(1, 1.0);
+        let read = |slot_type| device.borrow().read_slot(0, slot_type).unwrap();
+        assert_eq!(read(Occupied), 1.0);
+        assert_eq!(read(OccupantHash), Seed::PREFAB_HASH as f64);
+
+        // Nothing grows at night
+        for tick in 0..600 {
+            assert!(!device.borrow().update(tick).unwrap());
+        }
+        assert_eq!(read(MaturityRatio), 0.0);
+
+        // During the day the plant grows once per tick until it matures
+        let mut previous = 0.0;
+        let mut grown = 0;
+        for tick in 600..1800 {
+            if device.borrow().update(tick).unwrap() {
+                grown += 1;
+            }
+            let ratio = read(MaturityRatio);
+            assert!(ratio >= previous);
+            previous = ratio;
+            let mature = (grown as f64) >= SEED_GROWTH_TICKS;
+            assert_eq!(read(Mature), if mature { 1.0 } else { 0.0 }, "tick {tick}");
+        }
+        assert!(grown as f64 >= SEED_GROWTH_TICKS + SEED_SEEDING_TICKS);
+        assert_eq!(read(MaturityRatio), 1.0);
+        assert_eq!(read(Growth), SEED_GROWTH_STAGES as f64);
+
+        // Growing on past maturity produces seeds
+        assert_eq!(read(SeedingRatio), 1.0);
+        assert_eq!(read(Seeding), 1.0);
+
+        // Every growth tick converted carbon dioxide to oxygen
+        let converted = grown as f64 * HYDROPONICS_CO2_PER_TICK;
+        {
+            let room = room.borrow();
+            assert!((room.get_moles(GasType::Oxygen) - converted).abs() < 1e-9);
+            assert!((room.get_moles(GasType::CarbonDioxide) - (1.0 - converted)).abs() < 1e-9);
+        }
+
+        // Without carbon dioxide the plant does not grow
+        let (starved, _) = hydroponics(2, 0.0);
+        for tick in 0..2400 {
+            starved.borrow().update(tick).unwrap();
+        }
+        assert_eq!(starved.borrow().read_slot(0, MaturityRatio).unwrap(), 0.0);
+        assert_eq!(starved.borrow().read_slot(0, Mature).unwrap(), 0.0);
+
+        // Only seeds can be planted
+        let filter = filter_item(3, 10);
+        assert!(device.borrow_mut().try_insert_item(0, filter).is_err());
+    }
+
+    #[test]
+    fn test_filtration_slots_reject_non_filter_items() {
+        let filtration = Filtration::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        let mut fil = filtration.borrow_mut();
+        assert_eq!(fil.allowed_item_types(0), Some(&[ItemType::Filter][..]));
+        assert_eq!(fil.allowed_item_types(1), Some(&[ItemType::Filter][..]));
+
+        let ic: Shared<dyn Item> = shared(chip());
+        let err = fil.check_insert(0, &*ic.borrow()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("only accepts Filter items, not ItemIntegratedCircuit10"),
+            "{err}"
+        );
+
+        // The rejected item is handed back and the slot stays empty
+        let rejected = fil.try_insert_item(0, ic.clone()).unwrap_err();
+        assert!(SharedPtr::ptr_eq(&rejected.item, &ic));
+        assert!(
+            rejected
+                .reason
+                .to_string()
+                .contains("only accepts Filter items"),
+            "{}",
+            rejected.reason
+        );
+        assert!(fil.get_slot(0).unwrap().is_empty());
+        assert!(
+            !fil.get_slot(0)
+                .unwrap()
+                .is_allowed(ItemType::ItemIntegratedCircuit10)
+        );
+
+        assert!(fil.try_insert_item(0, filter_item(4, 10)).is_ok());
+        assert!(fil.check_insert(2, &*ic.borrow()).is_err());
+
+        // Locker slots accept any item type
+        let locker = Locker::new(SimulationDeviceSettings {
+            id: Some(5),
+            ..SimulationDeviceSettings::default()
+        });
+        assert_eq!(locker.borrow().allowed_item_types(0), None);
+        assert!(locker.borrow_mut().try_insert_item(0, ic).is_ok());
+    }
+
+    #[test]
+    fn test_slot_max_quantity_follows_the_item() {
+        let mut slot = Slot::new(None);
+        assert_eq!(slot.max_quantity(), 0);
+        assert_eq!(slot.available_space(), u32::MAX);
+
+        assert!(slot.try_insert(filter_item(1, 50)).is_ok());
+        assert_eq!(slot.max_quantity(), 100);
+        assert_eq!(slot.available_space(), 50);
+        assert!(!slot.is_full());
+
+        // Merging stops at the item's own stack size and hands back the rest
+        let rejected = slot.try_insert(filter_item(2, 60)).unwrap_err();
+        assert_eq!(rejected.borrow().quantity(), 10);
+        assert!(slot.is_full());
+    }
+
+    #[test]
+    fn test_filters_merge_only_with_same_gas_and_size() {
+        let oxygen_filter = |id: i32, size: FilterSize, quantity: u32| -> Shared<dyn Item> {
+            shared(Filter::new(SimulationItemSettings {
+                id: Some(id),
+                gas_type: Some(GasType::Oxygen),
+                filter_size: Some(size),
+                quantity: Some(quantity),
+            }))
+        };
+
+        let mut slot = Slot::new(Some(ItemType::Filter));
+        assert!(
+            slot.try_insert(oxygen_filter(1, FilterSize::Small, 30))
+                .is_ok()
+        );
+
+        // Two Oxygen Small filters add their quantities
+        assert!(
+            slot.try_insert(oxygen_filter(2, FilterSize::Small, 50))
+                .is_ok()
+        );
+        assert_eq!(slot.get_item().unwrap().borrow().quantity(), 80);
+
+        // Quantities beyond the stack size are handed back
+        let leftover = slot
+            .try_insert(oxygen_filter(3, FilterSize::Small, 40))
+            .unwrap_err();
+        assert_eq!(slot.get_item().unwrap().borrow().quantity(), 100);
+        assert_eq!(leftover.borrow().quantity(), 20);
+
+        // A different size does not merge and is returned untouched
+        let mut slot = Slot::new(Some(ItemType::Filter));
+        assert!(
+            slot.try_insert(oxygen_filter(4, FilterSize::Small, 30))
+                .is_ok()
+        );
+        let large = oxygen_filter(5, FilterSize::Large, 30);
+        let rejected = slot.try_insert(large.clone()).unwrap_err();
+        assert!(SharedPtr::ptr_eq(&rejected, &large));
+        assert_eq!(rejected.borrow().quantity(), 30);
+        assert_eq!(slot.get_item().unwrap().borrow().quantity(), 30);
+
+        // As does a different gas type
+        let nitrogen = filter_item(6, 10);
+        nitrogen
+            .borrow_mut()
+            .as_any_mut()
+            .downcast_mut::<Filter>()
+            .unwrap()
+            .set_gas_type(GasType::Nitrogen);
+        assert!(slot.try_insert(nitrogen).is_err());
+        assert_eq!(slot.get_item().unwrap().borrow().quantity(), 30);
+    }
+
+    #[test]
+    fn test_filtration_lr_reagent_modes() {
+        let (chip, _housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let filtration = Filtration::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        network
+            .borrow_mut()
+            .add_device(filtration.clone(), network.clone())
+            .unwrap();
+
+        // Device without an input atmosphere holds no reagents
+        let program = r#"
+lr r0 3 Contents HASH("Oxygen")
+lr r1 3 TotalContents 0
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+        assert_reg(&chip.borrow(), 0, 0.0);
+        assert_reg(&chip.borrow(), 1, 0.0);
+
+        let input = AtmosphericNetwork::new(100.0);
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 4.0, 293.15)
+            .unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::CarbonDioxide, 6.0, 293.15)
+            .unwrap();
+        filtration
+            .borrow_mut()
+            .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+            .unwrap();
+
+        {
+            let mut f_borrow = filtration.borrow_mut();
+            let slot = f_borrow.get_slot_mut(0).unwrap();
+            let mut filter_item = Filter::new(SimulationItemSettings {
+                id: Some(4),
+                ..SimulationItemSettings::default()
+            });
+            filter_item.set_gas_type(GasType::CarbonDioxide);
+            filter_item.set_quantity(10);
+            let filter: Shared<dyn Item> = shared(filter_item);
+            let _ = slot.try_insert(filter);
+        }
+
+        let program = r#"
+define FIL 3
+lr r0 FIL Contents HASH("Oxygen")
+lr r1 FIL Contents HASH("Carbon Dioxide")
+lr r2 FIL Required HASH("Oxygen")
+lr r3 FIL Required HASH("Carbon Dioxide")
+lr r5 FIL TotalContents 0
+lr r6 FIL 0 HASH("Nitrogen")
+lr r7 FIL 0 12345
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        let chip_ref = chip.borrow();
+        assert_reg(&chip_ref, 0, 4.0);
+        assert_reg(&chip_ref, 1, 6.0);
+        assert_reg(&chip_ref, 2, 0.0);
+        assert_reg(&chip_ref, 3, 6.0);
+        assert_reg(&chip_ref, 5, 10.0);
+        assert_reg(&chip_ref, 6, 0.0);
+        assert_reg(&chip_ref, 7, 0.0);
+        drop(chip_ref);
+
+        // Filtration has no recipe to report
+        chip.borrow_mut()
+            .load_program("lr r4 3 Recipe HASH(\"Carbon Dioxide\")")
+            .unwrap();
+        let err = chip.borrow().run(128).unwrap_err();
+        assert!(err.to_string().contains("no recipe"), "{err}");
     }
 
     #[test]
@@ -1918,6 +3621,8 @@ yield
 "#
         );
 
+        // Exceeds the game's program size limits
+        chip.borrow_mut().set_enforce_source_limits(false);
         chip.borrow_mut().load_program(&program).unwrap();
         chip.borrow().run(128).unwrap();
 
@@ -2059,4 +3764,223 @@ yield
         assert_reg(&chip_ref, 10, 555.0);
         assert_reg(&chip_ref, 11, 666.0);
     }
+
+    #[test]
+    fn test_clr_and_clrd_clear_another_housing_stack() {
+        let (chip, housing, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let housing2 = ICHousing::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        let chip2 = shared(ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(4),
+            ..SimulationItemSettings::default()
+        }));
+        housing2.borrow().set_chip(chip2.clone()).unwrap();
+        let empty_housing = ICHousing::new(SimulationDeviceSettings {
+            id: Some(5),
+            ..SimulationDeviceSettings::default()
+        });
+        let sensor = DaylightSensor::new(SimulationDeviceSettings {
+            id: Some(6),
+            ..SimulationDeviceSettings::default()
+        });
+        for device in [
+            housing2.clone() as Shared<dyn Device>,
+            empty_housing.clone(),
+            sensor.clone(),
+        ] {
+            network
+                .borrow_mut()
+                .add_device(device, network.clone())
+                .unwrap();
+        }
+        housing.borrow().set_device_pin(0, Some(3));
+        housing.borrow().set_device_pin(1, Some(5));
+        housing.borrow().set_device_pin(2, Some(6));
+
+        // The other chip fills its own stack, then both clears are observed through `get`
+        chip2
+            .borrow_mut()
+            .load_program("push 7\npush 8\nput db 200 9\nyield")
+            .unwrap();
+        chip2.borrow().run(128).unwrap();
+
+        let program = r#"
+get r0 d0 1
+clr d0
+get r1 d0 1
+get r2 d0 200
+put d0 200 5
+clrd 3
+get r3 d0 200
+getd r4 3 0
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+        {
+            let chip_ref = chip.borrow();
+            assert_reg(&chip_ref, 0, 8.0);
+            for reg in 1..=4 {
+                assert_reg(&chip_ref, reg, 0.0);
+            }
+        }
+        assert_eq!(chip2.borrow().read_stack(200).unwrap(), 0.0);
+
+        // Devices without a stack, or hosts without a chip, fail at the clearing line
+        for (source, expected) in [
+            ("clr d2", "has no stack memory"),
+            ("clrd 6", "has no stack memory"),
+            ("clr d1", "No chip installed"),
+            ("clrd 99", "not found"),
+        ] {
+            chip.borrow_mut()
+                .load_program(&format!("move r0 0\n{source}"))
+                .unwrap();
+            let err = chip.borrow().run(128).unwrap_err();
+            match err {
+                SimulationError::RuntimeError { line, message } => {
+                    assert_eq!(line, 1, "{source}");
+                    assert!(message.contains(expected), "{source}: {message}");
+                }
+                other => panic!("{source}: unexpected error {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_put_by_id_is_read_back_by_target_chip() {
+        let (chip_a, housing_a, network) = ItemIntegratedCircuit10::new_with_network();
+
+        let housing_b = ICHousing::new(SimulationDeviceSettings {
+            id: Some(3),
+            ..SimulationDeviceSettings::default()
+        });
+        let chip_b = shared(ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(4),
+            ..SimulationItemSettings::default()
+        }));
+        housing_b.borrow().set_chip(chip_b.clone()).unwrap();
+        let sensor = DaylightSensor::new(SimulationDeviceSettings {
+            id: Some(5),
+            ..SimulationDeviceSettings::default()
+        });
+        for device in [housing_b.clone() as Shared<dyn Device>, sensor.clone()] {
+            network
+                .borrow_mut()
+                .add_device(device, network.clone())
+                .unwrap();
+        }
+        housing_a.borrow().set_device_pin(0, Some(3));
+        housing_b.borrow().set_device_pin(0, Some(1));
+
+        // Chip A writes into chip B's stack by ID and by pin, then reads one slot back
+        let writer = r#"
+putd 3 4 42
+put d0 5 43
+getd r0 3 4
+get r1 d0 5
+yield
+"#;
+        // Chip B sees the values on its own stack and can send one back to chip A
+        let reader = r#"
+move sp 5
+peek r0
+get r1 db 5
+pop r2
+put d0 0 r0
+yield
+"#;
+        chip_a.borrow_mut().load_program(writer).unwrap();
+        chip_a.borrow().run(128).unwrap();
+        chip_b.borrow_mut().load_program(reader).unwrap();
+        chip_b.borrow().run(128).unwrap();
+
+        assert_reg(&chip_a.borrow(), 0, 42.0);
+        assert_reg(&chip_a.borrow(), 1, 43.0);
+        assert_reg(&chip_b.borrow(), 0, 42.0);
+        assert_reg(&chip_b.borrow(), 1, 43.0);
+        assert_reg(&chip_b.borrow(), 2, 42.0);
+        assert_eq!(chip_a.borrow().read_stack(0).unwrap(), 42.0);
+
+        // Devices without a stack and out-of-range indices fail at the accessing line
+        for (source, expected) in [
+            ("getd r0 5 0", "no stack memory to read"),
+            ("putd 5 0 1", "no stack memory to write"),
+            ("getd r0 3 512", "out of bounds"),
+            ("putd 3 512 1", "out of bounds"),
+        ] {
+            chip_a
+                .borrow_mut()
+                .load_program(&format!("move r0 0\n{source}"))
+                .unwrap();
+            let err = chip_a.borrow().run(128).unwrap_err();
+            match err {
+                SimulationError::RuntimeError { line, message } => {
+                    assert_eq!(line, 1, "{source}");
+                    assert!(message.contains(expected), "{source}: {message}");
+                }
+                other => panic!("{source}: unexpected error {other:?}"),
+            }
+        }
+    }
+
+    // ==================== Constant Tests ====================
+
+    #[test]
+    fn test_added_constant_is_available_to_programs() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+        chip.borrow_mut().add_constant("MY_CONST", 21.0);
+        assert_eq!(chip.borrow().constants()["MY_CONST"], 21.0);
+
+        let program = r#"
+define DOUBLE MY_CONST * 2
+move r0 MY_CONST
+move r1 DOUBLE
+add r2 MY_CONST 1
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        assert_reg(&chip.borrow(), 0, 21.0);
+        assert_reg(&chip.borrow(), 1, 42.0);
+        assert_reg(&chip.borrow(), 2, 22.0);
+
+        // Without the constant the define expression fails to load
+        let (other, _, _) = ItemIntegratedCircuit10::new_with_network();
+        assert!(matches!(
+            other.borrow_mut().load_program(program),
+            Err(SimulationError::IC10ParseError { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_builtin_constants_resolve() {
+        let (chip, _, _) = ItemIntegratedCircuit10::new_with_network();
+        let constants = chip.borrow().constants();
+        for name in [
+            "nan", "pinf", "ninf", "pi", "tau", "deg2rad", "rad2deg", "epsilon",
+        ] {
+            assert!(constants.contains_key(name), "{name}");
+        }
+
+        let program = r#"
+move r0 pi
+move r1 deg2rad
+mul r2 180 deg2rad
+define HALF_TURN pi / 2
+move r3 HALF_TURN
+yield
+"#;
+        chip.borrow_mut().load_program(program).unwrap();
+        chip.borrow().run(128).unwrap();
+
+        assert_reg(&chip.borrow(), 0, std::f64::consts::PI);
+        assert_reg(&chip.borrow(), 1, std::f64::consts::PI / 180.0);
+        assert_reg(&chip.borrow(), 2, std::f64::consts::PI);
+        assert_reg(&chip.borrow(), 3, std::f64::consts::FRAC_PI_2);
+    }
 }
@@ -9,3 +9,4 @@ pub mod mode_tests;
 pub mod network_tests;
 pub mod parser_tests;
 pub mod simulation_manager;
+pub mod types_tests;
@@ -2,8 +2,8 @@ use crate::{
     Filter, Item,
     atmospherics::GasType,
     devices::{
-        AirConditioner, AtmosphericDevice, Device, DeviceAtmosphericNetworkType, Filtration,
-        LogicType, SimulationDeviceSettings,
+        AirConditioner, Airlock, AtmosphericDevice, Device, DeviceAtmosphericNetworkType,
+        Filtration, HeatExchanger, LogicSlotType, LogicType, SimulationDeviceSettings, VolumePump,
     },
     items::{FilterSize, SimulationItemSettings},
     networks::AtmosphericNetwork,
@@ -22,7 +22,10 @@ fn filtration_respects_mode() {
     let waste = AtmosphericNetwork::new(10.0);
 
     // add some oxygen to input so filtration has something to do
-    input.borrow_mut().add_gas(GasType::Oxygen, 10.0, 300.0);
+    input
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 300.0)
+        .unwrap();
 
     // attach networks
     {
@@ -41,7 +44,10 @@ fn filtration_respects_mode() {
         ..SimulationDeviceSettings::default()
     });
     let input_rc = AtmosphericNetwork::new(10.0);
-    input_rc.borrow_mut().add_gas(GasType::Oxygen, 10.0, 300.0);
+    input_rc
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 300.0)
+        .unwrap();
     let filtered_rc = AtmosphericNetwork::new(10.0);
     let waste_rc = AtmosphericNetwork::new(10.0);
 
@@ -92,8 +98,14 @@ fn filtration_respects_mode() {
 
         let input3 = AtmosphericNetwork::new(10.0);
         // Add both steam (gas) and liquid water
-        input3.borrow_mut().add_gas(GasType::Steam, 50.0, 300.0);
-        input3.borrow_mut().add_gas(GasType::Water, 50.0, 300.0);
+        input3
+            .borrow_mut()
+            .add_gas(GasType::Steam, 50.0, 300.0)
+            .unwrap();
+        input3
+            .borrow_mut()
+            .add_gas(GasType::Water, 50.0, 300.0)
+            .unwrap();
         let filtered3 = AtmosphericNetwork::new(10.0);
         let waste3 = AtmosphericNetwork::new(10.0);
 
@@ -141,7 +153,10 @@ fn filtration_respects_mode() {
 
     // Reset networks
     let input2 = AtmosphericNetwork::new(10.0);
-    input2.borrow_mut().add_gas(GasType::Oxygen, 10.0, 300.0);
+    input2
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 300.0)
+        .unwrap();
     let input2_rc = input2.clone();
     let filtered2_rc = AtmosphericNetwork::new(10.0);
     let waste2_rc = AtmosphericNetwork::new(10.0);
@@ -201,9 +216,18 @@ fn airconditioner_respects_mode() {
     let waste_rc = AtmosphericNetwork::new(100.0);
 
     // configure networks and set a goal temperature
-    input_rc.borrow_mut().add_gas(GasType::Oxygen, 10.0, 260.0);
-    output_rc.borrow_mut().add_gas(GasType::Oxygen, 10.0, 260.0);
-    waste_rc.borrow_mut().add_gas(GasType::Oxygen, 10.0, 200.0);
+    input_rc
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 260.0)
+        .unwrap();
+    output_rc
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 260.0)
+        .unwrap();
+    waste_rc
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 200.0)
+        .unwrap();
 
     ac.borrow_mut()
         .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input_rc.clone()))
@@ -241,3 +265,333 @@ fn airconditioner_respects_mode() {
         "AC should not process moles when Mode=0.0"
     );
 }
+
+#[test]
+fn filtration_consumes_filter_until_spent() {
+    let filtration = Filtration::new(SimulationDeviceSettings {
+        id: Some(1),
+        ..SimulationDeviceSettings::default()
+    });
+
+    let input = AtmosphericNetwork::new(1000.0);
+    let filtered = AtmosphericNetwork::new(1000.0);
+    let waste = AtmosphericNetwork::new(1000.0);
+    input
+        .borrow_mut()
+        .add_gas(GasType::CarbonDioxide, 500.0, 300.0)
+        .unwrap();
+    input
+        .borrow_mut()
+        .add_gas(GasType::Nitrogen, 500.0, 300.0)
+        .unwrap();
+
+    // Small filter with 1 unit of life is spent after 10 moles filtered
+    {
+        let mut f = filtration.borrow_mut();
+        let slot = f.get_slot_mut(0).unwrap();
+        let mut filter_item = Filter::new(SimulationItemSettings {
+            id: Some(2),
+            ..SimulationItemSettings::default()
+        });
+        filter_item.set_gas_type(GasType::CarbonDioxide);
+        filter_item.set_size(FilterSize::Small);
+        filter_item.set_quantity(1);
+        let filter: Shared<dyn Item> = shared(filter_item);
+        let _ = slot.try_insert(filter);
+
+        f.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+            .unwrap();
+        f.set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(filtered.clone()))
+            .unwrap();
+        f.set_atmospheric_network(DeviceAtmosphericNetworkType::Output2, Some(waste.clone()))
+            .unwrap();
+    }
+    filtration.borrow().write(LogicType::Mode, 1.0).unwrap();
+
+    let mut ticks = 0;
+    while filtration.borrow().update(ticks).unwrap() {
+        ticks += 1;
+        assert!(ticks < 1000, "filter should be exhausted");
+    }
+
+    // Filter is spent and reports no remaining life
+    let quantity = filtration
+        .borrow()
+        .read_slot(0, LogicSlotType::Quantity)
+        .unwrap();
+    assert_eq!(quantity, 0.0);
+    assert!(filtration.borrow().active_filters().is_empty());
+
+    // Roughly the filter's life worth of gas was filtered; the rest stays in the input
+    let filtered_co2 = filtered.borrow().get_moles(GasType::CarbonDioxide);
+    assert!(filtered_co2 >= 10.0, "filtered {filtered_co2}");
+    let remaining_co2 = input.borrow().get_moles(GasType::CarbonDioxide);
+    assert!(remaining_co2 > 0.0);
+
+    // Further updates leave the input untouched
+    let input_moles = input.borrow().total_moles();
+    assert!(!filtration.borrow().update(ticks).unwrap());
+    assert_eq!(input.borrow().total_moles(), input_moles);
+}
+
+#[test]
+fn filtration_routes_each_filter_gas_to_filtered_output() {
+    let filtration = Filtration::new(SimulationDeviceSettings {
+        id: Some(1),
+        ..SimulationDeviceSettings::default()
+    });
+    assert_eq!(filtration.borrow().filter_slot_count(), 2);
+
+    let input = AtmosphericNetwork::new(100.0);
+    let filtered = AtmosphericNetwork::new(100.0);
+    let waste = AtmosphericNetwork::new(100.0);
+    input
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 2.0, 300.0)
+        .unwrap();
+    input
+        .borrow_mut()
+        .add_gas(GasType::CarbonDioxide, 2.0, 300.0)
+        .unwrap();
+    input
+        .borrow_mut()
+        .add_gas(GasType::Nitrogen, 2.0, 300.0)
+        .unwrap();
+
+    {
+        let mut f = filtration.borrow_mut();
+        for (slot_index, gas_type) in [(0, GasType::Oxygen), (1, GasType::CarbonDioxide)] {
+            let mut filter_item = Filter::new(SimulationItemSettings {
+                id: Some(10 + slot_index as i32),
+                ..SimulationItemSettings::default()
+            });
+            filter_item.set_gas_type(gas_type);
+            let filter: Shared<dyn Item> = shared(filter_item);
+            let _ = f.get_slot_mut(slot_index).unwrap().try_insert(filter);
+        }
+
+        f.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+            .unwrap();
+        f.set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(filtered.clone()))
+            .unwrap();
+        f.set_atmospheric_network(DeviceAtmosphericNetworkType::Output2, Some(waste.clone()))
+            .unwrap();
+    }
+    filtration.borrow().write(LogicType::Mode, 1.0).unwrap();
+
+    // A single tick processes both filter types
+    assert!(filtration.borrow().update(0).unwrap());
+
+    assert!(filtered.borrow().get_moles(GasType::Oxygen) > 0.0);
+    assert!(filtered.borrow().get_moles(GasType::CarbonDioxide) > 0.0);
+    assert_eq!(filtered.borrow().get_moles(GasType::Nitrogen), 0.0);
+
+    assert!(waste.borrow().get_moles(GasType::Nitrogen) > 0.0);
+    assert_eq!(waste.borrow().get_moles(GasType::Oxygen), 0.0);
+    assert_eq!(waste.borrow().get_moles(GasType::CarbonDioxide), 0.0);
+}
+
+/// Create a pump connected between two fresh networks of `volume` litres
+fn pump_between(
+    volume: f64,
+) -> (
+    Shared<VolumePump>,
+    Shared<AtmosphericNetwork>,
+    Shared<AtmosphericNetwork>,
+) {
+    let pump = VolumePump::new(SimulationDeviceSettings {
+        id: Some(1),
+        ..SimulationDeviceSettings::default()
+    });
+    let input = AtmosphericNetwork::new(volume);
+    let output = AtmosphericNetwork::new(volume);
+    pump.borrow_mut()
+        .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+        .unwrap();
+    pump.borrow_mut()
+        .set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(output.clone()))
+        .unwrap();
+    pump.borrow().write(LogicType::On, 1.0).unwrap();
+    (pump, input, output)
+}
+
+#[test]
+fn volume_pump_forward_flow() {
+    let (pump, input, output) = pump_between(100.0);
+    input
+        .borrow_mut()
+        .add_gas(GasType::Nitrogen, 10.0, 300.0)
+        .unwrap();
+
+    // Setting is clamped to the pump's max volume per tick
+    pump.borrow().write(LogicType::Setting, 50.0).unwrap();
+    assert_eq!(pump.borrow().read(LogicType::Setting).unwrap(), 10.0);
+
+    assert!(pump.borrow().update(0).unwrap());
+    assert!((input.borrow().total_moles() - 9.0).abs() < 1e-9);
+    assert!((output.borrow().total_moles() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn volume_pump_reverse_flow() {
+    let (pump, input, output) = pump_between(100.0);
+    output
+        .borrow_mut()
+        .add_gas(GasType::Nitrogen, 10.0, 300.0)
+        .unwrap();
+    pump.borrow().write(LogicType::Mode, 1.0).unwrap();
+    pump.borrow().write(LogicType::Setting, 10.0).unwrap();
+
+    assert!(pump.borrow().update(0).unwrap());
+    assert!((output.borrow().total_moles() - 9.0).abs() < 1e-9);
+    assert!((input.borrow().total_moles() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn volume_pump_stops_at_max_output_pressure() {
+    let (pump, input, output) = pump_between(100.0);
+    input
+        .borrow_mut()
+        .add_gas(GasType::Nitrogen, 100.0, 300.0)
+        .unwrap();
+    pump.borrow().write(LogicType::Setting, 10.0).unwrap();
+    // No cutoff unless one is configured
+    assert_eq!(pump.borrow().max_output_pressure(), None);
+    pump.borrow().set_max_output_pressure(Some(200.0));
+
+    let mut ticks = 0;
+    while pump.borrow().update(ticks).unwrap() {
+        ticks += 1;
+        assert!(ticks < 1000, "pump should reach the pressure cutoff");
+    }
+
+    let output_pressure = output.borrow().pressure();
+    assert!((output_pressure - 200.0).abs() < 1e-6, "{output_pressure}");
+    assert!(input.borrow().total_moles() > 0.0);
+
+    // Further updates move nothing
+    let output_moles = output.borrow().total_moles();
+    assert!(!pump.borrow().update(ticks).unwrap());
+    assert_eq!(output.borrow().total_moles(), output_moles);
+}
+
+#[test]
+fn volume_pump_forward_then_reverse_returns_gas() {
+    let (pump, input, output) = pump_between(100.0);
+    input
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 20.0, 300.0)
+        .unwrap();
+    pump.borrow().write(LogicType::Setting, 10.0).unwrap();
+
+    // Default mode is forward
+    assert_eq!(pump.borrow().read(LogicType::Mode).unwrap(), 0.0);
+    for tick in 0..5 {
+        pump.borrow().update(tick).unwrap();
+    }
+    let pumped = output.borrow().total_moles();
+    assert!(pumped > 0.0);
+
+    // Reverse pulls gas back into the input network
+    pump.borrow().write(LogicType::Mode, 1.0).unwrap();
+    for tick in 5..200 {
+        pump.borrow().update(tick).unwrap();
+    }
+    assert!(output.borrow().total_moles() < pumped * 0.01);
+    assert!(input.borrow().total_moles() > 19.9);
+}
+
+#[test]
+fn heat_exchanger_converges_without_moving_moles() {
+    let exchanger = HeatExchanger::new(SimulationDeviceSettings {
+        id: Some(1),
+        ..SimulationDeviceSettings::default()
+    });
+    let hot = AtmosphericNetwork::new(100.0);
+    let cold = AtmosphericNetwork::new(100.0);
+    hot.borrow_mut()
+        .add_gas(GasType::CarbonDioxide, 5.0, 600.0)
+        .unwrap();
+    cold.borrow_mut()
+        .add_gas(GasType::Nitrogen, 20.0, 250.0)
+        .unwrap();
+    exchanger
+        .borrow_mut()
+        .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(hot.clone()))
+        .unwrap();
+    exchanger
+        .borrow_mut()
+        .set_atmospheric_network(DeviceAtmosphericNetworkType::Input2, Some(cold.clone()))
+        .unwrap();
+    exchanger.borrow().write(LogicType::Setting, 20.0).unwrap();
+
+    let hot_capacity = hot.borrow().total_heat_capacity();
+    let cold_capacity = cold.borrow().total_heat_capacity();
+    let expected = (hot_capacity * 600.0 + cold_capacity * 250.0) / (hot_capacity + cold_capacity);
+    let total_energy = hot.borrow().total_energy() + cold.borrow().total_energy();
+
+    assert!(exchanger.borrow().update(0).unwrap());
+    assert!(hot.borrow().temperature() < 600.0);
+    assert!(cold.borrow().temperature() > 250.0);
+
+    for tick in 1..1000 {
+        exchanger.borrow().update(tick).unwrap();
+    }
+
+    assert!((hot.borrow().temperature() - expected).abs() < 1e-6);
+    assert!((cold.borrow().temperature() - expected).abs() < 1e-6);
+    assert_eq!(hot.borrow().total_moles(), 5.0);
+    assert_eq!(cold.borrow().total_moles(), 20.0);
+    assert_eq!(hot.borrow().get_moles(GasType::Nitrogen), 0.0);
+    assert_eq!(cold.borrow().get_moles(GasType::CarbonDioxide), 0.0);
+
+    let total_energy_after = hot.borrow().total_energy() + cold.borrow().total_energy();
+    assert!((total_energy_after - total_energy).abs() < 1e-6 * total_energy);
+}
+
+#[test]
+fn airlock_equalizes_only_while_open() {
+    let airlock = Airlock::new(SimulationDeviceSettings {
+        id: Some(1),
+        ..SimulationDeviceSettings::default()
+    });
+    let inside = AtmosphericNetwork::new(10.0);
+    let outside = AtmosphericNetwork::new(10.0);
+    inside
+        .borrow_mut()
+        .add_gas(GasType::Oxygen, 10.0, 300.0)
+        .unwrap();
+    {
+        let mut a = airlock.borrow_mut();
+        a.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(inside.clone()))
+            .unwrap();
+        a.set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(outside.clone()))
+            .unwrap();
+    }
+
+    // Sealed by default
+    assert_eq!(airlock.borrow().read(LogicType::Open).unwrap(), 0.0);
+    assert!(!airlock.borrow().update(0).unwrap());
+    assert_eq!(outside.borrow().total_moles(), 0.0);
+
+    airlock.borrow().write(LogicType::Open, 1.0).unwrap();
+    assert!(airlock.borrow().update(1).unwrap());
+    assert!((inside.borrow().pressure() - outside.borrow().pressure()).abs() < 1e-6);
+    assert!(!airlock.borrow().update(2).unwrap());
+
+    // Closing again keeps a new pressure difference in place
+    airlock.borrow().write(LogicType::Open, 0.0).unwrap();
+    inside
+        .borrow_mut()
+        .add_gas(GasType::Nitrogen, 4.0, 300.0)
+        .unwrap();
+    assert!(!airlock.borrow().update(3).unwrap());
+    assert_eq!(outside.borrow().get_moles(GasType::Nitrogen), 0.0);
+    assert_eq!(inside.borrow().get_moles(GasType::Nitrogen), 4.0);
+
+    airlock.borrow().write(LogicType::Open, 1.0).unwrap();
+    assert!(airlock.borrow().update(4).unwrap());
+    assert!(outside.borrow().get_moles(GasType::Nitrogen) > 0.0);
+    assert!((inside.borrow().pressure() - outside.borrow().pressure()).abs() < 1e-6);
+    assert!((inside.borrow().total_moles() + outside.borrow().total_moles() - 14.0).abs() < 1e-9);
+}
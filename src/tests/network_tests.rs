@@ -8,6 +8,7 @@ mod tests {
     use crate::types::{OptShared, OptWeakShared, shared};
     use crate::{BatchMode, Device, LogicType, SimulationResult};
     use crate::{CableNetwork, devices::ICHousing};
+
     use std::cell::Cell;
     use std::sync::OnceLock;
 
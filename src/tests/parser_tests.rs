@@ -1,6 +1,11 @@
 //! Unit tests for the parser
 #[cfg(test)]
 mod tests {
+    use crate::ItemIntegratedCircuit10;
+    use crate::error::SimulationError;
+    use crate::instruction::{Instruction, ParsedInstruction};
+    use crate::items::SimulationItemSettings;
+    use crate::items::item_integrated_circuit_10::Operand;
     use crate::parser::*;
 
     // ==================== pack_ascii6 tests ====================
@@ -68,6 +73,21 @@ mod tests {
         assert_eq!(pack_ascii6("\x7F"), Some(127)); // DEL (last ASCII)
     }
 
+    #[test]
+    fn test_unpack_ascii6_round_trips() {
+        for text in ["A", "DONE", "!@#$%^", "Hello!"] {
+            let packed = pack_ascii6(text).unwrap() as f64;
+            assert_eq!(unpack_ascii6(packed).as_deref(), Some(text));
+        }
+
+        // Fractions, negatives, values past 48 bits and non-ASCII bytes are not text
+        assert_eq!(unpack_ascii6(0.0), None);
+        assert_eq!(unpack_ascii6(65.5), None);
+        assert_eq!(unpack_ascii6(-65.0), None);
+        assert_eq!(unpack_ascii6((1u64 << 48) as f64), None);
+        assert_eq!(unpack_ascii6(0x80 as f64), None);
+    }
+
     // ==================== string_to_hash tests ====================
     // Uses CRC32 (ISO HDLC) algorithm, matching Unity's Animator.StringToHash
 
@@ -124,6 +144,24 @@ mod tests {
         let _ = result;
     }
 
+    #[test]
+    fn test_string_to_hash_named_device_prefix() {
+        // CRC32 hash for "Named Device Prefix 0", as printed by the demo IC program
+        assert_eq!(string_to_hash("Named Device Prefix 0"), 807201538);
+    }
+
+    // ==================== hash_to_known_name tests ====================
+
+    #[test]
+    fn test_hash_to_known_name_uses_prefab_registry() {
+        use crate::devices::HeatExchanger;
+
+        assert_eq!(
+            hash_to_known_name(HeatExchanger::PREFAB_HASH),
+            Some("Heat Exchanger")
+        );
+    }
+
     // ==================== parse_binary_str tests ====================
 
     #[test]
@@ -253,6 +291,63 @@ mod tests {
         assert!(result.contains("1680529357"));
     }
 
+    #[test]
+    fn test_preprocess_str_and_hash_edge_cases() {
+        // Empty strings: HASH("") is the CRC32 of nothing, STR("") cannot be packed
+        assert_eq!(preprocess(r#"move r0 HASH("")"#).unwrap(), "move r0 0");
+        assert!(
+            preprocess(r#"move r0 STR("")"#)
+                .unwrap()
+                .contains("<ERR:InvalidStringNull>")
+        );
+        assert!(
+            preprocess(r#"move r0 STR("日本語")"#)
+                .unwrap()
+                .contains("<ERR:InvalidStringNonAscii>")
+        );
+
+        // Escaped quotes and backslashes are part of the string
+        assert_eq!(
+            preprocess(r#"move r0 HASH("a\"b")"#).unwrap(),
+            format!("move r0 {}", string_to_hash("a\"b"))
+        );
+        assert_eq!(
+            preprocess(r#"move r0 STR("\"\\")"#).unwrap(),
+            format!("move r0 {}", pack_ascii6("\"\\").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_str_and_hash_operands() {
+        let module = compile_module(
+            "define PREFIX HASH(\"Named Device Prefix \")\ns db Setting STR(\"DONE\")\nmove r0 HASH(\"Foo\")",
+        )
+        .unwrap();
+        let instructions = module.instructions();
+
+        assert_eq!(
+            instructions[0].instruction,
+            Instruction::Define {
+                name: "PREFIX".to_string(),
+                value: string_to_hash("Named Device Prefix ") as f64,
+            }
+        );
+        match &instructions[1].instruction {
+            Instruction::S { value, .. } => assert_eq!(
+                *value,
+                Operand::Immediate(pack_ascii6("DONE").unwrap() as f64)
+            ),
+            other => panic!("expected s, got {other:?}"),
+        }
+        assert_eq!(
+            instructions[2].instruction,
+            Instruction::Move {
+                dest: Operand::Register(0),
+                arg: Operand::Immediate(string_to_hash("Foo") as f64),
+            }
+        );
+    }
+
     #[test]
     fn test_preprocess_binary() {
         let input = "move r0 %1010";
@@ -267,6 +362,23 @@ mod tests {
         assert!(result.contains("move r0 255"));
     }
 
+    #[test]
+    fn test_preprocess_literals_beyond_exact_precision() {
+        // 2^53 is the largest magnitude a register holds exactly
+        let result = preprocess("move r0 $20000000000000").unwrap();
+        assert_eq!(result, "move r0 9007199254740992");
+
+        let result = preprocess("move r0 $20000000000001").unwrap();
+        assert!(result.contains("<ERR:LiteralExceedsPrecision>"));
+
+        let bits = format!("move r0 %1{}", "0".repeat(54));
+        assert!(
+            preprocess(&bits)
+                .unwrap()
+                .contains("<ERR:LiteralExceedsPrecision>")
+        );
+    }
+
     #[test]
     fn test_preprocess_comment_removal() {
         let input = "move r0 1 # this is a comment";
@@ -307,4 +419,260 @@ mod tests {
         let result = preprocess(input).unwrap();
         assert_eq!(result, "move r0 1\n\n\n\nmove r1 2");
     }
+
+    // ==================== compile_module tests ====================
+
+    const ELMO_AC_TEST: &str = r#"
+    define AC 1
+    define PUMP 2
+
+    define TARGET_TEMP 293.15
+    define TARGET_INPUT_PRESSURE 111
+    define AC_INPUT_VOLUME 10
+
+    # Initialize
+    sd AC Mode 1
+    sd AC Setting 999
+
+    # Main loop
+    main:
+        yield
+        ld r0 AC TemperatureOutput2
+        push r0
+        ble r0 TARGET_TEMP stop
+
+        # Calculate pump setting for target temperature
+        # V = TARGET_INPUT_PRESSURE * AC_INPUT_VOLUME / AC.PressureOutput2
+        mul r0 TARGET_INPUT_PRESSURE AC_INPUT_VOLUME
+        ld r1 AC PressureOutput2
+        div r0 r0 r1
+
+        # Turn on AC and set pump
+        sd AC Mode 1
+        sd PUMP Setting r0
+        sd PUMP On 1
+        j main
+    stop:
+        sd PUMP On 0
+        sd AC Mode 0"#;
+
+    #[test]
+    fn test_compile_module_elmo_ac_test_loads() {
+        let module = compile_module(ELMO_AC_TEST).expect("program should compile");
+        assert_eq!(module.instructions().len(), ELMO_AC_TEST.lines().count());
+        assert_eq!(module.labels().get("main"), Some(&13));
+        assert_eq!(module.labels().get("stop"), Some(&30));
+
+        let mut chip = ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(1),
+            ..SimulationItemSettings::default()
+        });
+        chip.load_compiled(module);
+        assert_eq!(chip.get_source().as_deref(), Some(ELMO_AC_TEST));
+        assert_eq!(chip.get_pc(), 0);
+    }
+
+    #[test]
+    fn test_compile_module_reports_all_diagnostics() {
+        let source =
+            "move r0 1\nfoo r1\nlabel:\nadd r0 1\nlabel:\nmove r1 STR(\"toolong\")\nalias x d9";
+        let diagnostics = compile_module(source).unwrap_err();
+
+        let lines: Vec<usize> = diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 6]);
+        assert!(matches!(
+            diagnostics[0].error,
+            SimulationError::UnrecognizedInstruction(_)
+        ));
+        assert!(matches!(
+            diagnostics[1].error,
+            SimulationError::IncorrectArgumentCount { .. }
+        ));
+        assert!(diagnostics[2].to_string().contains("Duplicate label"));
+        assert!(
+            diagnostics[3]
+                .to_string()
+                .contains("Device pin out of range")
+        );
+
+        // load_program reports the first diagnostic
+        let mut chip = ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(1),
+            ..SimulationItemSettings::default()
+        });
+        assert_eq!(chip.load_program(source).unwrap_err(), diagnostics[0].error);
+    }
+
+    // ==================== Error JSON tests ====================
+
+    #[test]
+    fn test_parse_error_serializes_to_json_object() {
+        let error = SimulationError::IC10ParseError {
+            line: 4,
+            message: "Unknown instruction 'foo'".to_string(),
+        };
+        let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "ParseError",
+                "line": 4,
+                "message": "Unknown instruction 'foo'",
+            })
+        );
+
+        // Errors without a line report null
+        let error = SimulationError::StackOutOfBounds {
+            index: 600,
+            line: None,
+        };
+        let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+        assert_eq!(json["kind"], "StackOutOfBounds");
+        assert!(json["line"].is_null());
+        assert_eq!(json["message"], error.to_string());
+
+        // Bounds errors attributed to a line report it apart from the message
+        let json: serde_json::Value =
+            serde_json::from_str(&error.at_runtime_line(3).to_json()).unwrap();
+        assert_eq!(json["kind"], "StackOutOfBounds");
+        assert_eq!(json["line"], 3);
+        assert_eq!(
+            json["message"],
+            "Stack index 600 out of bounds (valid range: 0-511)"
+        );
+
+        // A failed program load carries the offending line
+        let mut chip = ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(1),
+            ..SimulationItemSettings::default()
+        });
+        let error = chip
+            .load_program("move r0 1\nmove r1 2\nyield\nmove r2 3\nalias foo r99\n")
+            .unwrap_err();
+        let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+        assert_eq!(json["kind"], "ParseError");
+        assert_eq!(json["line"], 4);
+        assert!(json["message"].as_str().unwrap().contains("r99"));
+    }
+
+    // ==================== Define expression tests ====================
+
+    #[test]
+    fn test_define_evaluates_constant_expressions() {
+        let source = r#"
+define A 2
+define B A * (3 + 1)
+define C B / 4 - -1
+define X 2 * PI
+define Y 1024 / 4
+define E 1e-3 * 2
+define H HASH("Name") + 1
+define INF 1 / 0
+define NOTHING 0 / 0
+move r0 C
+"#;
+        let module = compile_module(source).unwrap();
+        let defines: std::collections::HashMap<&str, f64> = module
+            .instructions()
+            .iter()
+            .filter_map(|parsed| match &parsed.instruction {
+                Instruction::Define { name, value } => Some((name.as_str(), *value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(defines["B"], 8.0);
+        assert_eq!(defines["C"], 3.0);
+        assert_eq!(defines["X"], 2.0 * std::f64::consts::PI);
+        assert_eq!(defines["Y"], 256.0);
+        assert_eq!(defines["E"], 0.002);
+        assert_eq!(defines["H"], string_to_hash("Name") as f64 + 1.0);
+        assert_eq!(defines["INF"], f64::INFINITY);
+        assert!(defines["NOTHING"].is_nan());
+
+        let mut chip = ItemIntegratedCircuit10::new(SimulationItemSettings {
+            id: Some(1),
+            ..SimulationItemSettings::default()
+        });
+        chip.load_program(source).unwrap();
+        chip.run(128).unwrap();
+        assert_eq!(chip.get_register(0).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_define_expression_errors_report_parse_errors() {
+        for (source, expected) in [
+            ("define X 2 * FOO", "unknown identifier 'FOO'"),
+            (
+                "define X LATER + 1\ndefine LATER 1",
+                "unknown identifier 'LATER'",
+            ),
+            ("define X (1 + 2", "missing ')'"),
+            ("define X 1 +", "unexpected end"),
+            ("define X 2 3", "unexpected '3'"),
+            ("define X 2 ^ 3", "unexpected '^'"),
+        ] {
+            let diagnostics = compile_module(&format!("yield\n{source}")).unwrap_err();
+            match &diagnostics[0].error {
+                SimulationError::IC10ParseError { line, message } => {
+                    assert_eq!(*line, 1, "{source}");
+                    assert!(message.contains(expected), "{source}: {message}");
+                }
+                other => panic!("{source}: unexpected error {other:?}"),
+            }
+        }
+    }
+
+    // ==================== parse_program tests ====================
+
+    #[test]
+    fn test_parse_program_collects_every_error() {
+        let (instructions, errors) = parse_program("bogus r0\nadd r0 r1 2\nmove r0");
+
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].instruction, Instruction::Noop);
+        assert_eq!(instructions[0].original_line, "bogus r0");
+        assert_eq!(
+            instructions[1].instruction,
+            Instruction::Add {
+                dest: Operand::Register(0),
+                arg1: Operand::Register(1),
+                arg2: Operand::Immediate(2.0),
+            }
+        );
+        assert_eq!(instructions[1].line_number, 1);
+        assert_eq!(instructions[2].instruction, Instruction::Noop);
+
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert_eq!(errors[0].line(), Some(0));
+        assert!(errors[0].message().contains("bogus"), "{}", errors[0]);
+        assert_eq!(errors[1].line(), Some(2));
+        assert!(errors[1].message().contains("move"), "{}", errors[1]);
+    }
+
+    #[test]
+    fn test_parse_program_without_errors_matches_source_lines() {
+        let (instructions, errors) = parse_program("start:\nyield\nj start");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[1].instruction, Instruction::Yield);
+    }
+
+    #[test]
+    fn test_label_lines_parse_as_labels() {
+        let parsed = ParsedInstruction::parse("  loop:  ", 4).unwrap();
+        assert_eq!(
+            parsed.instruction,
+            Instruction::Label {
+                name: "loop".to_string()
+            }
+        );
+        assert_eq!(parsed.instruction.to_source(), "loop:");
+        assert_eq!(parsed.opcode_span(), None);
+
+        // A commented-out label is just a comment
+        assert_eq!(
+            ParsedInstruction::parse("# loop:", 0).unwrap().instruction,
+            Instruction::Noop
+        );
+    }
 }
@@ -1,18 +1,20 @@
 //! Unit tests for the simulation manager
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
 
     use crate::{
-        Filter, SimulationManager,
+        Filter, LogicType, SimulationManager,
+        atmospherics::{GasType, celsius_to_kelvin},
+        constants::DEFAULT_TICKS_PER_SECOND,
         devices::{
-            AirConditioner, AtmosphericDevice, DaylightSensor, Device,
-            DeviceAtmosphericNetworkType, Filtration, ICHostDevice, ICHousing, LogicMemory,
-            SimulationDeviceSettings, SlotHostDevice, VolumePump,
+            AirConditioner, AtmosphericDevice, Battery, DaylightSensor, Device,
+            DeviceAtmosphericNetworkType, Filtration, GasTank, ICHostDevice, ICHousing,
+            LogicMemory, SimulationDeviceSettings, SlotHostDevice, VolumePump,
         },
-        items::{FilterSize, ItemIntegratedCircuit10, SimulationItemSettings},
+        items::{FilterSize, Item, ItemIntegratedCircuit10, SimulationItemSettings},
         networks::AtmosphericNetwork,
-        types::shared,
+        parser::string_to_hash,
+        types::{Shared, SharedPtr, shared},
     };
 
     #[test]
@@ -20,44 +22,44 @@ mod tests {
         // Start from a clean state
         let mut manager = SimulationManager::new();
 
-        let cn = manager.create_cable_network();
+        let cn = manager.create_cable_network().unwrap();
 
         // Create one of each device
         let ac = AirConditioner::new(SimulationDeviceSettings {
             id: Some(1),
             ..SimulationDeviceSettings::default()
         });
-        let ac_weak = Rc::downgrade(&ac);
+        let ac_weak = SharedPtr::downgrade(&ac);
 
         let fil = Filtration::new(SimulationDeviceSettings {
             id: Some(2),
             ..SimulationDeviceSettings::default()
         });
-        let fil_weak = Rc::downgrade(&fil);
+        let fil_weak = SharedPtr::downgrade(&fil);
 
         let pump = VolumePump::new(SimulationDeviceSettings {
             id: Some(3),
             ..SimulationDeviceSettings::default()
         });
-        let pump_weak = Rc::downgrade(&pump);
+        let pump_weak = SharedPtr::downgrade(&pump);
 
         let housing = ICHousing::new(SimulationDeviceSettings {
             id: Some(4),
             ..SimulationDeviceSettings::default()
         });
-        let housing_weak = Rc::downgrade(&housing);
+        let housing_weak = SharedPtr::downgrade(&housing);
 
         let lm = LogicMemory::new(SimulationDeviceSettings {
             id: Some(5),
             ..SimulationDeviceSettings::default()
         });
-        let lm_weak = Rc::downgrade(&lm);
+        let lm_weak = SharedPtr::downgrade(&lm);
 
         let ds = DaylightSensor::new(SimulationDeviceSettings {
             id: Some(6),
             ..SimulationDeviceSettings::default()
         });
-        let ds_weak = Rc::downgrade(&ds);
+        let ds_weak = SharedPtr::downgrade(&ds);
 
         // Add all devices to the cable network
         cn.borrow_mut().add_device(ac.clone(), cn.clone()).unwrap();
@@ -76,7 +78,7 @@ mod tests {
             id: Some(7),
             ..SimulationItemSettings::default()
         }));
-        let chip_ac_weak = Rc::downgrade(&chip_ac);
+        let chip_ac_weak = SharedPtr::downgrade(&chip_ac);
         ac.borrow().set_chip(chip_ac.clone()).unwrap();
         drop(chip_ac);
 
@@ -84,7 +86,7 @@ mod tests {
             id: Some(8),
             ..SimulationItemSettings::default()
         }));
-        let chip_fil_weak = Rc::downgrade(&chip_fil);
+        let chip_fil_weak = SharedPtr::downgrade(&chip_fil);
         fil.borrow().set_chip(chip_fil.clone()).unwrap();
         drop(chip_fil);
 
@@ -92,7 +94,7 @@ mod tests {
             id: Some(9),
             ..SimulationItemSettings::default()
         }));
-        let chip_housing_weak = Rc::downgrade(&chip_housing);
+        let chip_housing_weak = SharedPtr::downgrade(&chip_housing);
         housing.borrow().set_chip(chip_housing.clone()).unwrap();
         drop(chip_housing);
 
@@ -153,14 +155,14 @@ mod tests {
             .unwrap();
 
         // Keep a weak to one representative atmospheric network
-        let an_rep_weak = Rc::downgrade(&an_ac_in);
+        let an_rep_weak = SharedPtr::downgrade(&an_ac_in);
 
         // Also keep a weak to the AirConditioner's internal network
         let ac_internal = ac
             .borrow()
             .get_atmospheric_network(DeviceAtmosphericNetworkType::Internal)
             .unwrap();
-        let ac_internal_weak = Rc::downgrade(&ac_internal);
+        let ac_internal_weak = SharedPtr::downgrade(&ac_internal);
 
         // Drop local strong refs so only global manager holds references
         drop(ac);
@@ -235,7 +237,7 @@ mod tests {
     fn test_simulation_manager_device_item_enumeration() {
         let mut manager = SimulationManager::new();
 
-        let cn = manager.create_cable_network();
+        let cn = manager.create_cable_network().unwrap();
 
         // Create Filtration device and register it on the network
         let fil = Filtration::new(SimulationDeviceSettings {
@@ -273,17 +275,19 @@ mod tests {
         let an_internal = AtmosphericNetwork::new(50.0);
         let settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(1),
             internal_atmospheric_network: Some(an_internal.clone()),
+            memory_size: None,
         };
         let ac = AirConditioner::new(settings);
         let ac_internal = ac
             .borrow()
             .get_atmospheric_network(DeviceAtmosphericNetworkType::Internal)
             .unwrap();
-        assert!(Rc::ptr_eq(&an_internal, &ac_internal));
+        assert!(SharedPtr::ptr_eq(&an_internal, &ac_internal));
     }
 
     #[test]
@@ -311,7 +315,7 @@ mod tests {
         let found = manager
             .get_atmospheric_network_by_id(id)
             .expect("manager should have registered the network");
-        assert!(Rc::ptr_eq(&found, &internal_net));
+        assert!(SharedPtr::ptr_eq(&found, &internal_net));
         assert_eq!(manager.all_atmospheric_networks().len(), 1);
     }
 
@@ -321,45 +325,57 @@ mod tests {
         // Use negative IDs to avoid clashes with other tests that allocate global IDs concurrently
         let ac_settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(-1100),
             internal_atmospheric_network: None,
+            memory_size: None,
         };
         let fil_settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(-1101),
             internal_atmospheric_network: None,
+            memory_size: None,
         };
         let pump_settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(-1102),
             internal_atmospheric_network: None,
+            memory_size: None,
         };
         let housing_settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(-1103),
             internal_atmospheric_network: None,
+            memory_size: None,
         };
         let ds_settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(-1104),
             internal_atmospheric_network: None,
+            memory_size: None,
         };
         let lm_settings = SimulationDeviceSettings {
             ticks_per_day: Some(2400.0),
+            ticks_per_second: None,
             max_instructions_per_tick: Some(128),
             name: None,
             id: Some(-1105),
             internal_atmospheric_network: None,
+            memory_size: None,
         };
 
         let ac = AirConditioner::new(ac_settings);
@@ -376,4 +392,1821 @@ mod tests {
         assert_eq!(ds.borrow().get_id(), -1104);
         assert_eq!(lm.borrow().get_id(), -1105);
     }
+
+    #[test]
+    fn test_flow_log_records_pump_transfer() {
+        let mut manager = SimulationManager::new();
+        assert!(!manager.is_flow_logging());
+
+        let pump = manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .expect("Device creation failed");
+        let pump_id = pump.borrow().get_id();
+
+        let input = manager.create_atmospheric_network(100.0).unwrap();
+        let output = manager.create_atmospheric_network(100.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 50.0, 300.0)
+            .unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 50.0, 300.0)
+            .unwrap();
+
+        {
+            let mut device = pump.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+                .unwrap();
+            atmo.set_atmospheric_network(
+                DeviceAtmosphericNetworkType::Output,
+                Some(output.clone()),
+            )
+            .unwrap();
+        }
+        pump.borrow().write(LogicType::On, 1.0).unwrap();
+
+        // Disabled by default: nothing is recorded
+        manager.update().unwrap();
+        assert!(manager.flow_log().is_empty());
+
+        manager.set_flow_logging(true);
+        let input_before = input.borrow().get_moles(GasType::Oxygen);
+        let output_before = output.borrow().get_moles(GasType::Oxygen);
+        manager.update().unwrap();
+        let input_delta = input.borrow().get_moles(GasType::Oxygen) - input_before;
+        let output_delta = output.borrow().get_moles(GasType::Oxygen) - output_before;
+        assert!(output_delta > 0.0);
+
+        let logged = |connection| {
+            manager
+                .flow_log()
+                .iter()
+                .find(|e| {
+                    e.device_id == pump_id && e.connection == connection && e.gas == GasType::Oxygen
+                })
+                .map(|e| e.moles)
+                .unwrap()
+        };
+        assert!((logged(DeviceAtmosphericNetworkType::Input) - input_delta).abs() < 1e-9);
+        assert!((logged(DeviceAtmosphericNetworkType::Output) - output_delta).abs() < 1e-9);
+        assert!(
+            manager
+                .flow_log()
+                .iter()
+                .any(|e| e.gas == GasType::Nitrogen)
+        );
+
+        manager.set_flow_logging(false);
+        assert!(manager.flow_log().is_empty());
+    }
+
+    #[test]
+    fn test_network_flow_cap_is_shared_between_pumps() {
+        let mut manager = SimulationManager::new();
+        let input = manager.create_atmospheric_network(1000.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 500.0, 300.0)
+            .unwrap();
+        let outputs = [
+            manager.create_atmospheric_network(1000.0).unwrap(),
+            manager.create_atmospheric_network(1000.0).unwrap(),
+        ];
+        for output in &outputs {
+            manager
+                .build_volume_pump()
+                .input(input.clone())
+                .output(output.clone())
+                .setting(10.0)
+                .on()
+                .build()
+                .unwrap();
+        }
+        let moles = |net: &Shared<AtmosphericNetwork>| net.borrow().total_moles();
+
+        // Uncapped, each pump alone moves more than the cap used below
+        manager.update().unwrap();
+        assert!(outputs.iter().all(|output| moles(output) > 1.0));
+
+        input.borrow_mut().set_max_flow_per_tick(Some(1.0));
+        for _ in 0..3 {
+            let input_before = moles(&input);
+            let before = outputs.each_ref().map(moles);
+            manager.update().unwrap();
+
+            // The pumps split the cap evenly and together extract exactly the cap
+            assert!((input_before - moles(&input) - 1.0).abs() < 1e-9);
+            for (output, before) in outputs.iter().zip(before) {
+                assert!((moles(output) - before - 0.5).abs() < 1e-9);
+            }
+        }
+
+        input.borrow_mut().set_max_flow_per_tick(None);
+        let input_before = moles(&input);
+        manager.update().unwrap();
+        assert!(input_before - moles(&input) > 2.0);
+    }
+
+    #[test]
+    fn test_pump_reports_total_moles_of_both_networks() {
+        let mut manager = SimulationManager::new();
+        let pump = manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .expect("Device creation failed");
+
+        // Reading a side with nothing connected is an error
+        assert!(pump.borrow().read(LogicType::TotalMolesInput).is_err());
+
+        let input = manager.create_atmospheric_network(100.0).unwrap();
+        let output = manager.create_atmospheric_network(100.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 40.0, 300.0)
+            .unwrap();
+        output
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 5.0, 300.0)
+            .unwrap();
+        {
+            let mut device = pump.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+                .unwrap();
+            atmo.set_atmospheric_network(
+                DeviceAtmosphericNetworkType::Output,
+                Some(output.clone()),
+            )
+            .unwrap();
+        }
+        pump.borrow().write(LogicType::On, 1.0).unwrap();
+
+        for _ in 0..3 {
+            let device = pump.borrow();
+            assert_eq!(
+                device.read(LogicType::TotalMolesInput).unwrap(),
+                input.borrow().total_moles()
+            );
+            assert_eq!(
+                device.read(LogicType::TotalMolesOutput).unwrap(),
+                output.borrow().total_moles()
+            );
+            assert_eq!(
+                device
+                    .as_atmospheric_device()
+                    .unwrap()
+                    .network_total_moles(DeviceAtmosphericNetworkType::Input)
+                    .unwrap(),
+                input.borrow().total_moles()
+            );
+            drop(device);
+            manager.update().unwrap();
+        }
+        assert!(output.borrow().total_moles() > 5.0);
+    }
+
+    #[test]
+    fn test_over_pressure_trips_at_max_pressure_and_bursts() {
+        let mut manager = SimulationManager::new();
+        let pump = manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .expect("Device creation failed");
+
+        let input = manager.create_atmospheric_network(1000.0).unwrap();
+        let output = manager.create_atmospheric_network(100.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 500.0, 300.0)
+            .unwrap();
+        output.borrow_mut().set_max_pressure(Some(200.0));
+        {
+            let mut device = pump.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+                .unwrap();
+            atmo.set_atmospheric_network(
+                DeviceAtmosphericNetworkType::Output,
+                Some(output.clone()),
+            )
+            .unwrap();
+        }
+        pump.borrow().write(LogicType::Setting, 10.0).unwrap();
+        pump.borrow().write(LogicType::On, 1.0).unwrap();
+        assert_eq!(pump.borrow().read(LogicType::Error).unwrap(), 0.0);
+
+        // Each tick adds about 125 kPa to the output; the flag trips once it passes 200 kPa
+        let mut tripped_at = None;
+        for tick in 1..=3 {
+            manager.update().unwrap();
+            let pressure = output.borrow().pressure();
+            let over = output.borrow().over_pressure();
+            assert_eq!(over, pressure > 200.0, "tick {tick}: {pressure} kPa");
+            assert_eq!(
+                pump.borrow().read(LogicType::Error).unwrap(),
+                if over { 1.0 } else { 0.0 }
+            );
+            if over && tripped_at.is_none() {
+                tripped_at = Some(tick);
+            }
+        }
+        assert_eq!(tripped_at, Some(2));
+
+        // Without venting nothing is lost; with it, the next tick bursts the network
+        assert!(!output.borrow().has_burst());
+        assert!(output.borrow().total_moles() > 0.0);
+        pump.borrow().write(LogicType::On, 0.0).unwrap();
+        output.borrow_mut().set_vent_on_burst(true);
+        manager.update().unwrap();
+        assert!(output.borrow().has_burst());
+        assert_eq!(output.borrow().total_moles(), 0.0);
+        assert!(output.borrow().over_pressure());
+        assert_eq!(pump.borrow().read(LogicType::Error).unwrap(), 1.0);
+
+        output.borrow_mut().repair();
+        assert!(!output.borrow().over_pressure());
+        assert_eq!(pump.borrow().read(LogicType::Error).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_error_reports_missing_atmospheric_connections() {
+        use DeviceAtmosphericNetworkType::*;
+
+        let mut manager = SimulationManager::new();
+        let networks: Vec<_> = (0..3)
+            .map(|_| manager.create_atmospheric_network(100.0).unwrap())
+            .collect();
+        let connect = |device: &Shared<dyn Device>,
+                       connections: &[DeviceAtmosphericNetworkType]| {
+            let mut device = device.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            for (connection, net) in connections.iter().zip(&networks) {
+                atmo.set_atmospheric_network(*connection, Some(net.clone()))
+                    .unwrap();
+            }
+        };
+
+        for (prefab_hash, connections) in [
+            (AirConditioner::PREFAB_HASH, &[Input, Output, Output2][..]),
+            (Filtration::PREFAB_HASH, &[Input, Output, Output2][..]),
+            (VolumePump::PREFAB_HASH, &[Input, Output][..]),
+        ] {
+            let device = manager.create_device(prefab_hash, None).unwrap();
+            assert_eq!(device.borrow().read(LogicType::Error).unwrap(), 1.0);
+
+            // Every connection but the last (the AC waste, filtration waste, pump output)
+            connect(&device, &connections[..connections.len() - 1]);
+            assert_eq!(device.borrow().read(LogicType::Error).unwrap(), 1.0);
+
+            connect(&device, connections);
+            assert_eq!(device.borrow().read(LogicType::Error).unwrap(), 0.0);
+        }
+
+        // Chips can branch on it
+        let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+        let ac = manager
+            .create_device(AirConditioner::PREFAB_HASH, None)
+            .unwrap();
+        let cn = manager.create_cable_network().unwrap();
+        cn.borrow_mut()
+            .add_device(housing.clone(), cn.clone())
+            .unwrap();
+        cn.borrow_mut().add_device(ac.clone(), cn.clone()).unwrap();
+        let chip = manager.create_chip().unwrap();
+        {
+            let housing = housing.borrow();
+            let host = housing.as_ic_host_device().unwrap();
+            host.set_chip(chip.clone()).unwrap();
+            host.set_device_pin(0, Some(ac.borrow().get_id()));
+        }
+        chip.borrow_mut()
+            .load_program("l r0 d0 Error\nbeqz r0 4\nmove r1 1\nhcf\nmove r1 2")
+            .unwrap();
+        manager.update().unwrap();
+        assert_eq!(chip.borrow().get_register(1).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_combustion_reads_follow_the_recorded_burn() {
+        let mut manager = SimulationManager::new();
+        let input = manager.create_atmospheric_network(100.0).unwrap();
+        let fil = Filtration::new(SimulationDeviceSettings {
+            id: Some(1),
+            ..SimulationDeviceSettings::default()
+        });
+        fil.borrow_mut()
+            .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+            .unwrap();
+        let combustion = || fil.borrow().read(LogicType::CombustionInput).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Volatiles, 20.0, 600.0)
+            .unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 10.0, 600.0)
+            .unwrap();
+
+        // Ticking alone never ignites anything: there is no combustion model
+        manager.update().unwrap();
+        assert_eq!(combustion(), 0.0);
+        assert_eq!(input.borrow().get_moles(GasType::Volatiles), 20.0);
+
+        // The reads follow the recorded burn
+        input.borrow_mut().set_combustion(2.5);
+        assert_eq!(input.borrow().combustion(), 2.5);
+        assert_eq!(combustion(), 1.0);
+
+        input.borrow_mut().set_combustion(0.0);
+        assert_eq!(combustion(), 0.0);
+
+        // Without a network the read reports the missing connection
+        fil.borrow_mut()
+            .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, None)
+            .unwrap();
+        assert!(fil.borrow().read(LogicType::CombustionInput).is_err());
+    }
+
+    #[test]
+    fn test_thermal_link_conducts_heat_without_moving_moles() {
+        let mut manager = SimulationManager::new();
+        let hot = manager.create_atmospheric_network(100.0).unwrap();
+        let cold = manager.create_atmospheric_network(100.0).unwrap();
+        hot.borrow_mut()
+            .add_gas(GasType::Nitrogen, 10.0, 500.0)
+            .unwrap();
+        cold.borrow_mut()
+            .add_gas(GasType::Nitrogen, 10.0, 200.0)
+            .unwrap();
+
+        let hot_id = hot.borrow().get_id().unwrap();
+        let cold_id = cold.borrow().get_id().unwrap();
+        assert!(!manager.thermally_link(hot_id, hot_id, 50.0));
+        assert!(!manager.thermally_link(hot_id, 999, 50.0));
+        assert!(manager.thermally_link(hot_id, cold_id, 50.0));
+
+        let total_energy = hot.borrow().total_energy() + cold.borrow().total_energy();
+
+        manager.update().unwrap();
+        let hot_temp = hot.borrow().temperature();
+        let cold_temp = cold.borrow().temperature();
+        assert!(hot_temp < 500.0 && hot_temp > 350.0);
+        assert!(cold_temp > 200.0 && cold_temp < 350.0);
+
+        for _ in 0..500 {
+            manager.update().unwrap();
+        }
+
+        assert!((hot.borrow().temperature() - 350.0).abs() < 1e-6);
+        assert!((cold.borrow().temperature() - 350.0).abs() < 1e-6);
+        assert_eq!(hot.borrow().total_moles(), 10.0);
+        assert_eq!(cold.borrow().total_moles(), 10.0);
+
+        let total_energy_after = hot.borrow().total_energy() + cold.borrow().total_energy();
+        assert!((total_energy_after - total_energy).abs() < 1e-6 * total_energy);
+
+        // Removing a network drops its links
+        manager.remove_atmospheric_network(cold_id);
+        assert!(manager.thermal_links().is_empty());
+    }
+
+    #[test]
+    fn test_reserved_ids_are_never_allocated_again() {
+        let mut manager = SimulationManager::new();
+        assert!(manager.reserve_id(5));
+        assert!(!manager.reserve_id(5));
+
+        // Requesting a reserved ID for a device is an error, not a panic
+        let err = manager
+            .create_device(
+                VolumePump::PREFAB_HASH,
+                Some(SimulationDeviceSettings {
+                    id: Some(5),
+                    ..SimulationDeviceSettings::default()
+                }),
+            )
+            .expect_err("Reserved ID should be rejected");
+        assert!(
+            err.to_string().contains("ID 5 is already allocated"),
+            "{err}"
+        );
+        assert!(manager.get_device(5).is_none());
+
+        // Allocation skips past every reserved ID without collisions
+        assert!(manager.reserve_id(1_000));
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..10_000 {
+            let id = manager.allocate_next_id().unwrap();
+            assert!(id > 1_000, "{id}");
+            assert!(seen.insert(id), "{id} allocated twice");
+        }
+        assert!(!manager.reserve_id(1_001));
+    }
+
+    #[test]
+    fn test_allocate_next_id_errors_when_exhausted() {
+        let mut manager = SimulationManager::new();
+        assert!(manager.reserve_id(i32::MAX - 1));
+        assert_eq!(manager.allocate_next_id().unwrap(), i32::MAX);
+        assert!(manager.allocate_next_id().is_err());
+        assert!(manager.create_chip().is_err());
+        assert!(
+            manager
+                .create_device(VolumePump::PREFAB_HASH, None)
+                .is_err()
+        );
+
+        // Explicit free IDs can still be reserved
+        assert!(manager.reserve_id(7));
+    }
+
+    #[test]
+    fn test_device_and_network_limits() {
+        let mut manager = SimulationManager::new();
+        assert_eq!(manager.max_devices(), None);
+
+        manager.set_max_devices(Some(2));
+        manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .unwrap();
+        manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .unwrap();
+        assert!(
+            manager
+                .create_device(VolumePump::PREFAB_HASH, None)
+                .is_err()
+        );
+        assert_eq!(manager.all_devices().len(), 2);
+
+        // Removing a device frees a slot
+        let id = manager.all_devices()[0].borrow().get_id();
+        manager.remove_device(id);
+        assert!(manager.create_device(VolumePump::PREFAB_HASH, None).is_ok());
+
+        manager.set_max_networks(Some(2));
+        manager.create_cable_network().unwrap();
+        manager.create_atmospheric_network(10.0).unwrap();
+        assert!(manager.create_cable_network().is_err());
+        assert!(manager.create_atmospheric_network(10.0).is_err());
+
+        manager.set_max_networks(None);
+        assert!(manager.create_cable_network().is_ok());
+    }
+
+    /// Build three networks joined by a ring of pumps and thermal links, creating the pumps and
+    /// links in the given orders, then return each network's state after 10 ticks
+    fn run_pump_ring(pump_order: &[usize], link_order: &[usize]) -> Vec<(f64, f64, f64)> {
+        let mut manager = SimulationManager::new();
+        let networks = [
+            manager.create_atmospheric_network(100.0).unwrap(),
+            manager.create_atmospheric_network(100.0).unwrap(),
+            manager.create_atmospheric_network(50.0).unwrap(),
+        ];
+        networks[0]
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 80.0, 350.0)
+            .unwrap();
+        networks[1]
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 40.0, 250.0)
+            .unwrap();
+        networks[2]
+            .borrow_mut()
+            .add_gas(GasType::CarbonDioxide, 20.0, 300.0)
+            .unwrap();
+
+        // (pump id, input network, output network)
+        let pumps = [(10, 0, 1), (20, 1, 2), (30, 2, 0)];
+        for &i in pump_order {
+            let (id, input, output) = pumps[i];
+            let pump = manager
+                .create_device(
+                    VolumePump::PREFAB_HASH,
+                    Some(SimulationDeviceSettings {
+                        id: Some(id),
+                        ..SimulationDeviceSettings::default()
+                    }),
+                )
+                .unwrap();
+            {
+                let mut device = pump.borrow_mut();
+                let atmo = device.as_atmospheric_device_mut().unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Input,
+                    Some(networks[input].clone()),
+                )
+                .unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Output,
+                    Some(networks[output].clone()),
+                )
+                .unwrap();
+            }
+            pump.borrow().write(LogicType::On, 1.0).unwrap();
+        }
+
+        // (network a, network b, conductivity)
+        let links = [(0, 1, 10.0), (1, 2, 5.0), (0, 2, 20.0)];
+        for &i in link_order {
+            let (a, b, conductivity) = links[i];
+            let a = networks[a].borrow().get_id().unwrap();
+            let b = networks[b].borrow().get_id().unwrap();
+            assert!(manager.thermally_link(a, b, conductivity));
+        }
+
+        for _ in 0..10 {
+            manager.update().unwrap();
+        }
+
+        networks
+            .iter()
+            .map(|net| {
+                let net = net.borrow();
+                (
+                    net.total_moles(),
+                    net.get_moles(GasType::Oxygen),
+                    net.temperature(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_update_order_is_independent_of_creation_order() {
+        let forward = run_pump_ring(&[0, 1, 2], &[0, 1, 2]);
+        let shuffled = run_pump_ring(&[2, 0, 1], &[2, 1, 0]);
+        assert_eq!(forward, shuffled);
+
+        // Sanity check: the pumps actually moved gas around the ring
+        assert!(forward[1].1 > 0.0);
+    }
+
+    #[test]
+    fn test_update_with_report_counts_instructions() {
+        let mut manager = SimulationManager::new();
+        let programs = ["move r0 1\nmove r1 2\nyield\nj 0", "add r0 r0 1\nj 0"];
+
+        let mut housings = Vec::new();
+        for program in programs {
+            let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+            let chip = manager.create_chip().unwrap();
+            chip.borrow_mut().load_program(program).unwrap();
+            housing
+                .borrow()
+                .as_ic_host_device()
+                .unwrap()
+                .set_chip(chip)
+                .unwrap();
+            housings.push(housing);
+        }
+        manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .unwrap();
+
+        for _ in 0..3 {
+            let report = manager.update_with_report().unwrap();
+            let expected: usize = housings
+                .iter()
+                .map(|h| {
+                    h.borrow()
+                        .as_ic_host_device()
+                        .unwrap()
+                        .get_last_executed_instructions()
+                })
+                .sum();
+
+            assert!(expected > 0);
+            assert_eq!(report.instructions_executed, expected);
+            assert_eq!(report.devices_updated, 3);
+            assert_eq!(report.phase_changes, 0);
+        }
+        assert_eq!(manager.ticks, 3);
+    }
+
+    #[test]
+    fn test_global_instruction_budget_is_shared_between_chips() {
+        let mut manager = SimulationManager::new();
+        manager.set_default_instruction_limit(Some(80));
+
+        let add_housing = |manager: &mut SimulationManager, program: &str| {
+            let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+            let chip = manager.create_chip().unwrap();
+            chip.borrow_mut().load_program(program).unwrap();
+            housing
+                .borrow()
+                .as_ic_host_device()
+                .unwrap()
+                .set_chip(chip)
+                .unwrap();
+            housing
+        };
+        // The idle host comes first, so whatever it leaves of its share rolls over
+        let idle = add_housing(&mut manager, "yield\nj 0");
+        let busy = [
+            add_housing(&mut manager, "add r0 r0 1\nj 0"),
+            add_housing(&mut manager, "add r0 r0 1\nj 0"),
+        ];
+
+        let executed = |housing: &Shared<dyn Device>| {
+            housing
+                .borrow()
+                .as_ic_host_device()
+                .unwrap()
+                .get_last_executed_instructions()
+        };
+        let ticks_run = |housing: &Shared<dyn Device>| {
+            housing
+                .borrow()
+                .as_ic_host_device()
+                .unwrap()
+                .chip_slot()
+                .borrow()
+                .get_chip()
+                .unwrap()
+                .ticks_run()
+        };
+
+        // New housings take the manager's default limit
+        for housing in busy.iter().chain([&idle]) {
+            assert_eq!(
+                housing
+                    .borrow()
+                    .as_ic_host_device()
+                    .unwrap()
+                    .max_instructions_per_tick(),
+                80
+            );
+        }
+
+        // Without a budget each chip runs to its own limit
+        let report = manager.update_with_report().unwrap();
+        assert_eq!(report.instructions_executed, 160 + executed(&idle));
+
+        // A budget below the combined demand is split, and the idle host's unused share
+        // passes on to the busy ones after it
+        manager.set_global_instruction_budget(Some(100));
+        for _ in 0..3 {
+            let report = manager.update_with_report().unwrap();
+            let idle_executed = executed(&idle);
+            assert!((1..=2).contains(&idle_executed));
+            assert_eq!(report.instructions_executed, 100);
+            assert_eq!(executed(&busy[0]), (100 - idle_executed).div_ceil(2));
+            assert_eq!(executed(&busy[0]) + executed(&busy[1]), 100 - idle_executed);
+            assert!(executed(&busy[1]) > 100usize.div_ceil(3));
+        }
+
+        // A used-up budget skips every chip without advancing its ticks
+        manager.set_global_instruction_budget(Some(0));
+        let before = [&idle, &busy[0], &busy[1]].map(ticks_run);
+        let report = manager.update_with_report().unwrap();
+        assert_eq!(report.instructions_executed, 0);
+        assert_eq!([&idle, &busy[0], &busy[1]].map(ticks_run), before);
+
+        // Never more than a host's own limit, even with budget to spare
+        manager.set_global_instruction_budget(Some(1000));
+        let report = manager.update_with_report().unwrap();
+        assert_eq!(executed(&busy[0]), 80);
+        assert_eq!(executed(&busy[1]), 80);
+        assert_eq!(report.instructions_executed, 160 + executed(&idle));
+    }
+
+    #[test]
+    fn test_registered_reagents_reach_new_chips() {
+        let mut manager = SimulationManager::new();
+        let before = manager.create_chip().unwrap();
+        let hash = manager.register_reagent("TestReagentManager", 515151);
+        let after = manager.create_chip().unwrap();
+
+        assert_eq!(manager.reagents().prefab_hash(hash), Some(515151));
+        assert_eq!(after.borrow().reagents().prefab_hash(hash), Some(515151));
+        assert_eq!(before.borrow().reagents().prefab_hash(hash), None);
+        assert_eq!(SimulationManager::new().reagents().prefab_hash(hash), None);
+
+        // Built-in reagents are always known
+        assert_eq!(
+            before
+                .borrow()
+                .reagents()
+                .prefab_hash(string_to_hash("Iron")),
+            Some(string_to_hash("ItemIronIngot"))
+        );
+    }
+
+    #[test]
+    fn test_register_name_reverse_lookup() {
+        let mut manager = SimulationManager::new();
+        let name = "Named Device Prefix 0";
+        let hash = manager.register_name(name);
+        assert_eq!(hash, string_to_hash(name));
+        assert_eq!(manager.hash_to_known_name(hash), Some(name));
+
+        // Registering again keeps the same entry
+        assert_eq!(manager.register_name(name), hash);
+        assert_eq!(manager.hash_to_known_name(hash), Some(name));
+
+        // Prefab display names are known without registering them
+        assert_eq!(
+            manager.hash_to_known_name(ICHousing::PREFAB_HASH),
+            Some("IC Housing")
+        );
+
+        // Names are registered per manager
+        assert_eq!(SimulationManager::new().hash_to_known_name(hash), None);
+        assert_eq!(
+            manager.hash_to_known_name(string_to_hash("Never Registered Name")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_name_hash_write_readdresses_device_for_lbn() {
+        let mut manager = SimulationManager::new();
+        let cn = manager.create_cable_network().unwrap();
+        let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+        cn.borrow_mut()
+            .add_device(housing.clone(), cn.clone())
+            .unwrap();
+        let old_hash = housing.borrow().get_name_hash();
+        let chip = manager.create_chip().unwrap();
+        chip.borrow_mut()
+            .load_program(&format!(
+                "define Housing {}\n\
+                 define Renamed 123456789\n\
+                 s db NameHash Renamed\n\
+                 lbn r0 Housing Renamed NameHash Average\n\
+                 lbn r1 Housing Renamed On Sum\n\
+                 lbn r2 Housing {old_hash} On Sum\n\
+                 yield\n\
+                 sbn Housing Renamed NameHash HASH(\"Front Door\")\n\
+                 lbn r3 Housing HASH(\"Front Door\") On Sum\n\
+                 lbn r4 Housing Renamed On Sum\n\
+                 hcf",
+                ICHousing::PREFAB_HASH
+            ))
+            .unwrap();
+        housing
+            .borrow()
+            .as_ic_host_device()
+            .unwrap()
+            .set_chip(chip)
+            .unwrap();
+
+        let register = |index| {
+            let host = housing.borrow();
+            host.as_ic_host_device()
+                .unwrap()
+                .get_register(index)
+                .unwrap()
+        };
+
+        // The chip readdresses its own housing while running inside it
+        manager.update().unwrap();
+        assert_eq!(register(0), 123456789.0);
+        assert_eq!(register(1), 1.0);
+        assert_eq!(register(2), 0.0);
+        assert_eq!(housing.borrow().get_name_hash(), 123456789);
+        assert_eq!(
+            housing.borrow().read(LogicType::NameHash).unwrap(),
+            123456789.0
+        );
+
+        // Batch writes move the device too, and the name itself is left alone
+        manager.update().unwrap();
+        assert_eq!(register(3), 1.0);
+        assert_eq!(register(4), 0.0);
+        assert_eq!(housing.borrow().get_name(), "IC Housing");
+
+        // Renaming drops the written hash in favour of the new name's
+        housing.borrow_mut().rename("Front Door");
+        assert_eq!(
+            housing.borrow().get_name_hash(),
+            string_to_hash("Front Door")
+        );
+        assert_eq!(
+            cn.borrow()
+                .get_devices_by_name(string_to_hash("Front Door"))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_host_name_hash_write_keeps_network_index_current() {
+        let mut manager = SimulationManager::new();
+        let cn = manager.create_cable_network().unwrap();
+        let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+        cn.borrow_mut()
+            .add_device(housing.clone(), cn.clone())
+            .unwrap();
+        let id = housing.borrow().get_id();
+        let old_hash = housing.borrow().get_name_hash();
+        let chip = manager.create_chip().unwrap();
+        chip.borrow_mut()
+            .load_program(&format!(
+                "lbn r0 {} 123456789 On Sum\n\
+                 lbn r1 {0} {old_hash} On Sum\n\
+                 hcf",
+                ICHousing::PREFAB_HASH
+            ))
+            .unwrap();
+        housing
+            .borrow()
+            .as_ic_host_device()
+            .unwrap()
+            .set_chip(chip)
+            .unwrap();
+
+        // Written from the host, outside any chip instruction
+        housing
+            .borrow()
+            .write(LogicType::NameHash, 123456789.0)
+            .unwrap();
+        assert_eq!(cn.borrow().get_devices_by_name(123456789), vec![id]);
+        assert_eq!(cn.borrow().count_devices_by_name(old_hash), 0);
+
+        manager.update().unwrap();
+        let register = |index| {
+            let host = housing.borrow();
+            host.as_ic_host_device()
+                .unwrap()
+                .get_register(index)
+                .unwrap()
+        };
+        assert_eq!(register(0), 1.0);
+        assert_eq!(register(1), 0.0);
+
+        // Removing the device clears it from the bucket of the written hash
+        assert!(cn.borrow_mut().remove_device(id).is_some());
+        assert_eq!(cn.borrow().count_devices_by_name(123456789), 0);
+        assert_eq!(cn.borrow().count_devices_by_name(old_hash), 0);
+    }
+
+    #[test]
+    fn test_tick_rate_reaches_new_devices_and_chips() {
+        let mut manager = SimulationManager::new();
+        let battery_rate = |device: &Shared<dyn Device>| {
+            device
+                .borrow()
+                .downcast_ref::<Battery>()
+                .unwrap()
+                .ticks_per_second()
+        };
+
+        let before = manager.create_device(Battery::PREFAB_HASH, None).unwrap();
+        assert_eq!(battery_rate(&before), DEFAULT_TICKS_PER_SECOND);
+
+        manager.set_ticks_per_second(Some(5.0));
+        let battery = manager.create_device(Battery::PREFAB_HASH, None).unwrap();
+        assert_eq!(battery_rate(&battery), 5.0);
+        assert_eq!(
+            manager.create_chip().unwrap().borrow().ticks_per_second(),
+            5.0
+        );
+
+        // A rate in the device's own settings wins
+        let own = manager
+            .create_device(
+                Battery::PREFAB_HASH,
+                Some(SimulationDeviceSettings {
+                    ticks_per_second: Some(10.0),
+                    ..SimulationDeviceSettings::default()
+                }),
+            )
+            .unwrap();
+        assert_eq!(battery_rate(&own), 10.0);
+
+        // Devices created earlier keep their rate
+        assert_eq!(battery_rate(&before), DEFAULT_TICKS_PER_SECOND);
+    }
+
+    #[test]
+    fn test_run_until_halt() {
+        let mut manager = SimulationManager::new();
+
+        let mut chips = Vec::new();
+        let mut housings = Vec::new();
+        for program in [
+            "j 0",
+            "move r0 1\nyield\nadd r0 r0 1\nyield",
+            "move r0 1\nsleep 1\nadd r0 r0 1",
+        ] {
+            let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+            let chip = manager.create_chip().unwrap();
+            chip.borrow_mut().load_program(program).unwrap();
+            let chip_id = chip.borrow().get_id();
+            housing
+                .borrow()
+                .as_ic_host_device()
+                .unwrap()
+                .set_chip(chip)
+                .unwrap();
+            housings.push(housing);
+            chips.push(chip_id);
+        }
+
+        // Ticks spent asleep are run through rather than ending the run
+        let (executed, halted) = manager.run_until_halt(chips[2], 10_000).unwrap();
+        assert!(halted);
+        assert_eq!(executed, 3);
+
+        // An infinite loop runs until the cap and reports that it did not halt
+        let (executed, halted) = manager.run_until_halt(chips[0], 10_000).unwrap();
+        assert!(!halted);
+        assert!(executed >= 10_000);
+
+        // A terminating program halts once it runs past its last line
+        let (executed, halted) = manager.run_until_halt(chips[1], 10_000).unwrap();
+        assert!(halted);
+        assert!(executed < 10_000);
+
+        assert!(manager.run_until_halt(-1, 100).is_err());
+
+        // A busy device is reported instead of panicking
+        let guard = housings[0].borrow_mut();
+        let err = manager.run_until_halt(chips[0], 100).unwrap_err();
+        drop(guard);
+        assert!(err.message().contains("busy"), "{err}");
+    }
+
+    #[test]
+    fn test_downcast_device_to_concrete_type() {
+        let mut manager = SimulationManager::new();
+        let device = manager
+            .create_device(AirConditioner::PREFAB_HASH, None)
+            .unwrap();
+
+        assert!(device.borrow().is::<AirConditioner>());
+        assert!(device.borrow().downcast_ref::<VolumePump>().is_none());
+
+        device
+            .borrow_mut()
+            .downcast_mut::<AirConditioner>()
+            .unwrap()
+            .rename("Cooler");
+
+        let device = device.borrow();
+        let ac = device.downcast_ref::<AirConditioner>().unwrap();
+        assert_eq!(ac.get_id(), device.get_id());
+        assert_eq!(ac.get_name(), "Cooler");
+        assert_eq!(ac.energy_moved_last_tick(), 0.0);
+    }
+
+    #[test]
+    fn test_network_counts_track_creation_and_removal() {
+        let mut manager = SimulationManager::new();
+        assert_eq!(manager.cable_network_count(), 0);
+        assert_eq!(manager.atmospheric_network_count(), 0);
+
+        let cable_a = manager.create_cable_network().unwrap();
+        let cable_b = manager.create_cable_network().unwrap();
+        let atmo = manager.create_atmospheric_network(100.0).unwrap();
+        assert_eq!(manager.cable_network_count(), 2);
+        assert_eq!(manager.atmospheric_network_count(), 1);
+
+        // Devices with an internal network add to the atmospheric count
+        let ac = manager
+            .create_device(AirConditioner::PREFAB_HASH, None)
+            .unwrap();
+        assert_eq!(manager.atmospheric_network_count(), 2);
+
+        manager.remove_cable_network(cable_a.borrow().get_id().unwrap());
+        assert_eq!(manager.cable_network_count(), 1);
+        manager.remove_cable_network(cable_b.borrow().get_id().unwrap());
+        assert_eq!(manager.cable_network_count(), 0);
+
+        manager.remove_atmospheric_network(atmo.borrow().get_id().unwrap());
+        assert_eq!(manager.atmospheric_network_count(), 1);
+        manager.remove_device(ac.borrow().get_id());
+        assert_eq!(manager.atmospheric_network_count(), 0);
+
+        // Removing an unknown network leaves the counts unchanged
+        assert!(manager.remove_cable_network(999).is_none());
+        assert_eq!(manager.cable_network_count(), 0);
+    }
+
+    #[test]
+    fn test_filtration_conserves_moles() {
+        let mut manager = SimulationManager::new();
+        let cn = manager.create_cable_network().unwrap();
+
+        let input = manager.create_atmospheric_network(10.0).unwrap();
+        let filtered = manager.create_atmospheric_network(20.0).unwrap();
+        let waste = manager.create_atmospheric_network(10.0).unwrap();
+
+        let fil = Filtration::new(SimulationDeviceSettings {
+            id: Some(1),
+            ..SimulationDeviceSettings::default()
+        });
+        {
+            let mut f = Filter::new(SimulationItemSettings {
+                id: Some(2),
+                ..SimulationItemSettings::default()
+            });
+            f.set_gas_type(GasType::Oxygen);
+            f.set_size(FilterSize::Small);
+            f.set_quantity(100);
+
+            let mut fil_mut = fil.borrow_mut();
+            fil_mut.try_insert_item(0, shared(f)).unwrap();
+            for (connection, network) in [
+                (DeviceAtmosphericNetworkType::Input, &input),
+                (DeviceAtmosphericNetworkType::Output, &filtered),
+                (DeviceAtmosphericNetworkType::Output2, &waste),
+            ] {
+                fil_mut
+                    .set_atmospheric_network(connection, Some(network.clone()))
+                    .unwrap();
+            }
+            fil_mut.write(LogicType::Mode, 1.0).unwrap();
+        }
+        cn.borrow_mut().add_device(fil.clone(), cn.clone()).unwrap();
+
+        // Trace gases below the siphon ratio and the per-gas cleanup thresholds
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 63.0, 273.15)
+            .unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::CarbonDioxide, 4.0, 273.15)
+            .unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Nitrogen, 0.00002, 273.15)
+            .unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Water, 0.0005, 273.15)
+            .unwrap();
+
+        let total = || {
+            input.borrow().total_moles()
+                + filtered.borrow().total_moles()
+                + waste.borrow().total_moles()
+        };
+        let initial = total();
+
+        let mut ticks = 0;
+        while !input.borrow().is_empty() && ticks < 1000 {
+            fil.borrow().update(ticks).unwrap();
+            assert!(
+                (total() - initial).abs() < 1e-9,
+                "tick {ticks}: {} moles before, {} after",
+                initial,
+                total()
+            );
+            ticks += 1;
+        }
+        assert!(input.borrow().is_empty(), "filtration did not drain input");
+        assert_eq!(filtered.borrow().get_moles(GasType::CarbonDioxide), 0.0);
+        assert!((waste.borrow().get_moles(GasType::Nitrogen) - 0.00002).abs() < 1e-12);
+    }
+
+    /// Build a pump scenario with a logic memory; the derived `Clone` shares devices and
+    /// networks, so an independent copy is made by building the scenario again
+    fn pump_scenario() -> SimulationManager {
+        let mut manager = SimulationManager::new();
+        let pump = manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .expect("Device creation failed");
+        let input = manager.create_atmospheric_network(100.0).unwrap();
+        let output = manager.create_atmospheric_network(100.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 50.0, 300.0)
+            .unwrap();
+        {
+            let mut device = pump.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input))
+                .unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Output, Some(output))
+                .unwrap();
+        }
+        pump.borrow().write(LogicType::On, 1.0).unwrap();
+        manager
+            .create_device(LogicMemory::PREFAB_HASH, None)
+            .expect("Device creation failed");
+        manager
+    }
+
+    #[test]
+    fn test_diff_reports_diverged_setting() {
+        let mut a = pump_scenario();
+        let mut b = pump_scenario();
+        assert!(a.diff(&b).is_empty());
+
+        for _ in 0..3 {
+            a.update().unwrap();
+            b.update().unwrap();
+        }
+        assert!(a.diff(&b).is_empty());
+
+        let memory_id = a.all_devices()[1].borrow().get_id();
+        b.get_device(memory_id)
+            .unwrap()
+            .borrow()
+            .write(LogicType::Setting, 42.0)
+            .unwrap();
+
+        let diff = a.diff(&b);
+        assert_eq!(diff.len(), 1, "unexpected differences: {diff:?}");
+        assert!(diff[0].contains(&format!("#{memory_id}")));
+        assert!(diff[0].contains("Setting"));
+        assert_eq!(b.diff(&a).len(), 1);
+    }
+
+    /// The `ac_device_test` scenario from the demo binary, wired by hand or through the builder
+    fn ac_scenario(use_builder: bool) -> (SimulationManager, Shared<AtmosphericNetwork>) {
+        let mut manager = SimulationManager::new();
+        let input = manager.create_atmospheric_network(120.0).unwrap();
+        let waste = manager.create_atmospheric_network(60.0).unwrap();
+
+        if use_builder {
+            let ac = manager
+                .build_air_conditioner()
+                .input(input.clone())
+                .output(input.clone())
+                .waste(waste.clone())
+                .setting(celsius_to_kelvin(20.0))
+                .mode(1.0)
+                .on()
+                .build()
+                .unwrap();
+            assert!(ac.borrow().get_network().is_some());
+        } else {
+            let cable = manager.create_cable_network().unwrap();
+            let ac = manager
+                .create_device(AirConditioner::PREFAB_HASH, None)
+                .unwrap();
+            {
+                let mut device = ac.borrow_mut();
+                let atmo = device.as_atmospheric_device_mut().unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Input,
+                    Some(input.clone()),
+                )
+                .unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Output,
+                    Some(input.clone()),
+                )
+                .unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Output2,
+                    Some(waste.clone()),
+                )
+                .unwrap();
+            }
+            ac.borrow()
+                .write(LogicType::Setting, celsius_to_kelvin(20.0))
+                .unwrap();
+            ac.borrow().write(LogicType::Mode, 1.0).unwrap();
+            ac.borrow().write(LogicType::On, 1.0).unwrap();
+            cable
+                .borrow_mut()
+                .add_device(ac.clone(), cable.clone())
+                .unwrap();
+        }
+
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 100.0, celsius_to_kelvin(15.0))
+            .unwrap();
+        waste
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 100.0, celsius_to_kelvin(15.0))
+            .unwrap();
+
+        (manager, input)
+    }
+
+    #[test]
+    fn test_builder_matches_manual_wiring() {
+        let (mut manual, _) = ac_scenario(false);
+        let (mut built, input) = ac_scenario(true);
+        assert!(manual.diff(&built).is_empty());
+
+        for _ in 0..38 {
+            manual.update().unwrap();
+            built.update().unwrap();
+        }
+
+        let diff = manual.diff(&built);
+        assert!(diff.is_empty(), "unexpected differences: {diff:?}");
+        assert!(input.borrow().temperature() > celsius_to_kelvin(15.0) + 1.0);
+
+        // A failed build leaves nothing behind
+        let devices = built.all_devices().len();
+        let cables = built.cable_network_count();
+        let net = built.create_atmospheric_network(10.0).unwrap();
+        assert!(
+            built
+                .build_air_conditioner()
+                .connect(DeviceAtmosphericNetworkType::Input2, net)
+                .build()
+                .is_err()
+        );
+        assert_eq!(built.all_devices().len(), devices);
+        assert_eq!(built.cable_network_count(), cables);
+
+        // So does a cable network that refuses the device, and its ID stays free
+        let cable = built.create_cable_network().unwrap();
+        let stranger = LogicMemory::new(SimulationDeviceSettings {
+            id: Some(900),
+            ..SimulationDeviceSettings::default()
+        });
+        cable
+            .borrow_mut()
+            .add_device(stranger, cable.clone())
+            .unwrap();
+        assert!(
+            built
+                .build_device(LogicMemory::new)
+                .id(900)
+                .cable_network(cable)
+                .build()
+                .is_err()
+        );
+        assert_eq!(built.all_devices().len(), devices);
+        assert!(built.build_device(LogicMemory::new).id(900).build().is_ok());
+    }
+
+    #[test]
+    fn test_step_and_snapshot_exports_state_as_json() {
+        let (mut manager, input) = ac_scenario(true);
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.ticks, 0);
+
+        let json = manager.step_and_snapshot().unwrap();
+        let state: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(state["ticks"], 1);
+        assert_eq!(manager.snapshot().ticks, 1);
+
+        let device = &state["devices"][0];
+        assert_eq!(device["prefab_hash"], AirConditioner::PREFAB_HASH);
+        assert_eq!(device["logic"]["Mode"], 1.0);
+        assert_eq!(
+            device["logic"]["Setting"].as_f64().unwrap(),
+            celsius_to_kelvin(20.0)
+        );
+        assert_eq!(state["cable_networks"][0]["device_ids"][0], device["id"]);
+
+        let input_id = input.borrow().get_id().unwrap();
+        let network = state["atmospheric_networks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|n| n["id"] == input_id)
+            .unwrap();
+        assert_eq!(
+            network["temperature"].as_f64().unwrap(),
+            input.borrow().temperature()
+        );
+        assert_eq!(
+            network["moles"]["Oxygen"].as_f64().unwrap(),
+            input.borrow().get_moles(GasType::Oxygen)
+        );
+        assert!(network["moles"].get("Nitrogen").is_none());
+
+        // Snapshotting does not advance the simulation
+        assert_eq!(manager.snapshot_json(), json);
+    }
+
+    #[test]
+    fn test_list_prefabs_reports_display_names_and_supported_types() {
+        use crate::devices::device_factory::list_prefabs;
+
+        let prefabs = list_prefabs();
+
+        let pump = prefabs
+            .iter()
+            .find(|p| p.prefab_hash == VolumePump::PREFAB_HASH)
+            .expect("Pump should be listed");
+        assert_eq!(pump.display_name, VolumePump::display_name_static());
+        let mut pump_types = pump.logic_types.clone();
+        pump_types.sort_by_key(|lt| *lt as i32);
+        let mut expected = vec![
+            LogicType::ReferenceId,
+            LogicType::PrefabHash,
+            LogicType::NameHash,
+            LogicType::Error,
+            LogicType::Ratio,
+            LogicType::On,
+            LogicType::Mode,
+            LogicType::Setting,
+            LogicType::TotalMolesInput,
+            LogicType::TotalMolesOutput,
+        ];
+        expected.sort_by_key(|lt| *lt as i32);
+        assert_eq!(pump_types, expected);
+        assert!(pump.slot_logic_types.is_empty());
+
+        let ac = prefabs
+            .iter()
+            .find(|p| p.prefab_hash == AirConditioner::PREFAB_HASH)
+            .expect("Air conditioner should be listed");
+        assert_eq!(ac.display_name, AirConditioner::display_name_static());
+        let mut ac_types = AirConditioner::properties().supported_types();
+        ac_types.push(LogicType::StackSize);
+        assert_eq!(ac.logic_types, ac_types);
+        for lt in [
+            LogicType::On,
+            LogicType::Mode,
+            LogicType::Setting,
+            LogicType::TotalMolesInput,
+            LogicType::TemperatureOutput,
+        ] {
+            assert!(ac.logic_types.contains(&lt), "AC should support {lt:?}");
+        }
+        assert!(!ac.logic_types.contains(&LogicType::Horizontal));
+        assert!(ac.slot_logic_types.is_empty());
+
+        // Listing is sorted by display name
+        assert!(
+            prefabs
+                .windows(2)
+                .all(|w| w[0].display_name <= w[1].display_name)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_reads_are_errors_on_every_device() {
+        use crate::devices::device_factory::{create_device, list_prefabs};
+
+        let all_types: Vec<LogicType> = (0..1024)
+            .filter_map(|v| LogicType::from_value(v as f64))
+            .collect();
+
+        for prefab in list_prefabs() {
+            let device = create_device(
+                prefab.prefab_hash,
+                SimulationDeviceSettings {
+                    id: Some(1),
+                    ..SimulationDeviceSettings::default()
+                },
+            )
+            .unwrap();
+            let device = device.borrow();
+
+            let unsupported: Vec<LogicType> = all_types
+                .iter()
+                .copied()
+                .filter(|lt| !device.can_read(*lt))
+                .collect();
+            assert!(!unsupported.is_empty(), "{}", prefab.display_name);
+
+            for lt in unsupported {
+                let err = device.read(lt).unwrap_err();
+                assert_eq!(
+                    err.kind(),
+                    "RuntimeError",
+                    "{}: {lt:?}",
+                    prefab.display_name
+                );
+            }
+        }
+
+        // A memory has no input pressure to read
+        let memory = LogicMemory::new(SimulationDeviceSettings {
+            id: Some(2),
+            ..SimulationDeviceSettings::default()
+        });
+        assert!(!memory.borrow().can_read(LogicType::PressureInput));
+        assert!(memory.borrow().read(LogicType::PressureInput).is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_manager_ticks_on_another_thread() {
+        let mut manager = SimulationManager::new();
+        let input = manager.create_atmospheric_network(1000.0).unwrap();
+        let output = manager.create_atmospheric_network(1000.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 100.0, 300.0)
+            .unwrap();
+        let pump = manager
+            .create_device(VolumePump::PREFAB_HASH, None)
+            .unwrap();
+        {
+            let mut device = pump.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input.clone()))
+                .unwrap();
+            atmo.set_atmospheric_network(
+                DeviceAtmosphericNetworkType::Output,
+                Some(output.clone()),
+            )
+            .unwrap();
+        }
+
+        // The chip switches the pump on from its first tick
+        let housing = manager.create_device(ICHousing::PREFAB_HASH, None).unwrap();
+        let cn = manager.create_cable_network().unwrap();
+        cn.borrow_mut()
+            .add_device(housing.clone(), cn.clone())
+            .unwrap();
+        cn.borrow_mut()
+            .add_device(pump.clone(), cn.clone())
+            .unwrap();
+        let chip = manager.create_chip().unwrap();
+        {
+            let housing = housing.borrow();
+            let host = housing.as_ic_host_device().unwrap();
+            host.set_chip(chip.clone()).unwrap();
+            host.set_device_pin(0, Some(pump.borrow().get_id()));
+        }
+        chip.borrow_mut()
+            .load_program("s d0 On 1\nadd r0 r0 1\nyield\nj 1")
+            .unwrap();
+
+        let mut manager = std::thread::spawn(move || {
+            for _ in 0..5 {
+                manager.update().unwrap();
+            }
+            manager
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 5.0);
+
+        // The manager keeps working after moving back
+        manager.update().unwrap();
+        assert_eq!(chip.borrow().get_register(0).unwrap(), 6.0);
+        assert!(output.borrow().total_moles() > 0.0);
+        assert!(
+            (input.borrow().total_moles() + output.borrow().total_moles() - 100.0).abs() < 1e-9
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_updates_match_sequential() {
+        fn connect(
+            manager: &mut SimulationManager,
+            input: &Shared<AtmosphericNetwork>,
+            output: &Shared<AtmosphericNetwork>,
+        ) {
+            let pump = manager
+                .create_device(VolumePump::PREFAB_HASH, None)
+                .unwrap();
+            {
+                let mut device = pump.borrow_mut();
+                let atmo = device.as_atmospheric_device_mut().unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Input,
+                    Some(input.clone()),
+                )
+                .unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Output,
+                    Some(output.clone()),
+                )
+                .unwrap();
+            }
+            pump.borrow().write(LogicType::Setting, 10.0).unwrap();
+            pump.borrow().write(LogicType::On, 1.0).unwrap();
+        }
+
+        fn build(parallel: bool) -> SimulationManager {
+            let mut manager = SimulationManager::new();
+            manager.set_parallel_updates(parallel);
+            // Split work across threads even on single-core machines and below the threshold
+            manager.force_parallel_workers(3);
+            manager.set_flow_logging(true);
+
+            // Isolated pairs, each with its own pump
+            for i in 1..=4 {
+                let input = manager.create_atmospheric_network(100.0).unwrap();
+                let output = manager.create_atmospheric_network(50.0).unwrap();
+                input
+                    .borrow_mut()
+                    .add_gas(GasType::Oxygen, 20.0 * i as f64, 250.0 + 10.0 * i as f64)
+                    .unwrap();
+                connect(&mut manager, &input, &output);
+            }
+
+            // A loop of pumps through shared networks, where update order matters
+            let a = manager.create_atmospheric_network(100.0).unwrap();
+            let b = manager.create_atmospheric_network(40.0).unwrap();
+            let c = manager.create_atmospheric_network(60.0).unwrap();
+            a.borrow_mut()
+                .add_gas(GasType::Nitrogen, 80.0, 300.0)
+                .unwrap();
+            c.borrow_mut()
+                .add_gas(GasType::CarbonDioxide, 30.0, 400.0)
+                .unwrap();
+            connect(&mut manager, &a, &b);
+            connect(&mut manager, &b, &c);
+            connect(&mut manager, &c, &a);
+
+            // Heat conducts from the loop into the first isolated pair
+            let loop_id = a.borrow().get_id().unwrap();
+            assert!(manager.thermally_link(1, loop_id, 20.0));
+            manager
+        }
+
+        let mut sequential = build(false);
+        let mut parallel = build(true);
+        assert!(!sequential.is_parallel_updates());
+        assert!(parallel.is_parallel_updates());
+
+        for tick in 1..=10 {
+            assert_eq!(
+                sequential.update().unwrap(),
+                parallel.update().unwrap(),
+                "tick {tick}"
+            );
+            assert_eq!(sequential.flow_log(), parallel.flow_log(), "tick {tick}");
+            assert_eq!(sequential.snapshot(), parallel.snapshot(), "tick {tick}");
+        }
+        assert!(!parallel.flow_log().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_parallel_update_errors_leave_other_groups_updated() {
+        fn build(parallel: bool) -> (SimulationManager, Shared<AtmosphericNetwork>) {
+            let mut manager = SimulationManager::new();
+            manager.set_parallel_updates(parallel);
+            manager.force_parallel_workers(2);
+
+            // The lower-ID pump is switched on without an output network and fails
+            let broken = manager
+                .create_device(VolumePump::PREFAB_HASH, None)
+                .unwrap();
+            let input = manager.create_atmospheric_network(100.0).unwrap();
+            broken
+                .borrow_mut()
+                .as_atmospheric_device_mut()
+                .unwrap()
+                .set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input))
+                .unwrap();
+            broken.borrow().write(LogicType::On, 1.0).unwrap();
+
+            // The higher-ID pump shares nothing with it
+            let pump = manager
+                .create_device(VolumePump::PREFAB_HASH, None)
+                .unwrap();
+            let input = manager.create_atmospheric_network(100.0).unwrap();
+            let output = manager.create_atmospheric_network(100.0).unwrap();
+            input
+                .borrow_mut()
+                .add_gas(GasType::Oxygen, 20.0, 300.0)
+                .unwrap();
+            {
+                let mut device = pump.borrow_mut();
+                let atmo = device.as_atmospheric_device_mut().unwrap();
+                atmo.set_atmospheric_network(DeviceAtmosphericNetworkType::Input, Some(input))
+                    .unwrap();
+                atmo.set_atmospheric_network(
+                    DeviceAtmosphericNetworkType::Output,
+                    Some(output.clone()),
+                )
+                .unwrap();
+            }
+            pump.borrow().write(LogicType::Setting, 10.0).unwrap();
+            pump.borrow().write(LogicType::On, 1.0).unwrap();
+            (manager, output)
+        }
+
+        // Both paths report the failing device's error
+        let (mut sequential, sequential_output) = build(false);
+        let (mut parallel, parallel_output) = build(true);
+        let sequential_error = sequential.update().unwrap_err();
+        let parallel_error = parallel.update().unwrap_err();
+        assert_eq!(sequential_error.to_string(), parallel_error.to_string());
+        assert!(
+            parallel_error
+                .to_string()
+                .contains("no output atmospheric network"),
+            "{parallel_error}"
+        );
+
+        // Sequentially the tick stops before the later pump; in parallel its group has
+        // already updated by the time the error is returned
+        assert_eq!(sequential_output.borrow().total_moles(), 0.0);
+        assert!(parallel_output.borrow().total_moles() > 0.0);
+    }
+
+    #[test]
+    fn test_gas_tank_buffer_fills_and_drains() {
+        use DeviceAtmosphericNetworkType::*;
+
+        let mut manager = SimulationManager::new();
+        let tank = manager.create_device(GasTank::PREFAB_HASH, None).unwrap();
+        let input = manager.create_atmospheric_network(100.0).unwrap();
+        let output = manager.create_atmospheric_network(100.0).unwrap();
+        input
+            .borrow_mut()
+            .add_gas(GasType::Oxygen, 50.0, 300.0)
+            .unwrap();
+        {
+            let mut device = tank.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            atmo.set_atmospheric_network(Input, Some(input.clone()))
+                .unwrap();
+            atmo.set_atmospheric_network(Output, Some(output.clone()))
+                .unwrap();
+            assert!(atmo.set_atmospheric_network(Internal, None).is_err());
+        }
+
+        // The buffer is the Internal connection and the manager tracks it
+        let internal = tank
+            .borrow()
+            .as_atmospheric_device()
+            .unwrap()
+            .get_atmospheric_network(Internal)
+            .unwrap();
+        let internal_id = internal.borrow().get_id().unwrap();
+        assert!(SharedPtr::ptr_eq(
+            &manager.get_atmospheric_network_by_id(internal_id).unwrap(),
+            &internal
+        ));
+        let read = |logic_type| tank.borrow().read(logic_type).unwrap();
+        assert_eq!(read(LogicType::Error), 0.0);
+
+        // The tank starts off and moves nothing until switched on
+        assert_eq!(read(LogicType::On), 0.0);
+        manager.update().unwrap();
+        assert_eq!(read(LogicType::TotalMoles), 0.0);
+        tank.borrow().write(LogicType::On, 1.0).unwrap();
+
+        // With the default Setting of 0 the buffer only fills
+        let mut buffered = 0.0;
+        for tick in 1..=5 {
+            manager.update().unwrap();
+            let moles = read(LogicType::TotalMoles);
+            assert!(moles > buffered, "tick {tick}: {moles}");
+            buffered = moles;
+            assert_eq!(read(LogicType::Pressure), internal.borrow().pressure());
+            assert_eq!(
+                read(LogicType::Temperature),
+                internal.borrow().temperature()
+            );
+            assert_eq!(output.borrow().total_moles(), 0.0);
+        }
+        assert!((read(LogicType::TotalMolesInput) + buffered - 50.0).abs() < 1e-9);
+
+        // Once the input is cut off and a release pressure is set, the buffer drains
+        input
+            .borrow_mut()
+            .remove_gas(GasType::Oxygen, f64::INFINITY);
+        tank.borrow().write(LogicType::Setting, 500.0).unwrap();
+        let staged = buffered;
+        for tick in 1..=5 {
+            manager.update().unwrap();
+            let moles = read(LogicType::TotalMoles);
+            assert!(moles < buffered, "tick {tick}: {moles}");
+            buffered = moles;
+            assert!((read(LogicType::TotalMolesOutput) + buffered - staged).abs() < 1e-9);
+        }
+        assert!(read(LogicType::PressureOutput) > 0.0);
+        assert!(read(LogicType::PressureOutput) <= 500.0);
+    }
+
+    #[test]
+    fn test_atmospheric_connections_are_validated_per_device() {
+        use crate::devices::{
+            ActiveVent, Airlock, HeatExchanger, Hydroponics, PassiveVent,
+            device_factory::create_device,
+        };
+        use DeviceAtmosphericNetworkType::*;
+
+        let cases: &[(i32, &str, &[DeviceAtmosphericNetworkType])] = &[
+            (VolumePump::PREFAB_HASH, "VolumePump", &[Input, Output]),
+            (
+                AirConditioner::PREFAB_HASH,
+                "AirConditioner",
+                &[Input, Output, Output2],
+            ),
+            (
+                Filtration::PREFAB_HASH,
+                "Filtration",
+                &[Input, Output, Output2],
+            ),
+            (PassiveVent::PREFAB_HASH, "PassiveVent", &[Input, Output]),
+            (ActiveVent::PREFAB_HASH, "ActiveVent", &[Input, Output]),
+            (
+                HeatExchanger::PREFAB_HASH,
+                "HeatExchanger",
+                &[Input, Input2],
+            ),
+            (Airlock::PREFAB_HASH, "Airlock", &[Input, Output]),
+            (Hydroponics::PREFAB_HASH, "Hydroponics", &[Input]),
+            (GasTank::PREFAB_HASH, "GasTank", &[Input, Output]),
+        ];
+
+        for &(prefab_hash, name, valid) in cases {
+            let device = create_device(
+                prefab_hash,
+                SimulationDeviceSettings {
+                    id: Some(1),
+                    ..SimulationDeviceSettings::default()
+                },
+            )
+            .unwrap();
+            let mut device = device.borrow_mut();
+            let atmo = device.as_atmospheric_device_mut().unwrap();
+            assert_eq!(atmo.supported_atmospheric_connections(), valid, "{name}");
+
+            for connection in DeviceAtmosphericNetworkType::all() {
+                let net = AtmosphericNetwork::new(100.0);
+                let result = atmo.set_atmospheric_network(connection, Some(net.clone()));
+                if valid.contains(&connection) {
+                    assert!(result.is_ok(), "{name} {connection}: {result:?}");
+                    assert!(SharedPtr::ptr_eq(
+                        &atmo.get_atmospheric_network(connection).unwrap(),
+                        &net
+                    ));
+                } else {
+                    let message = result
+                        .expect_err(&format!("{name} {connection}"))
+                        .to_string();
+                    assert!(
+                        message.contains(&format!(
+                            "{name} does not support atmospheric connection {connection}"
+                        )),
+                        "{message}"
+                    );
+                    assert!(message.contains("valid connections: "), "{message}");
+                }
+            }
+        }
+
+        // Every atmospheric device is covered above
+        for prefab in crate::devices::device_factory::list_prefabs() {
+            let device = create_device(
+                prefab.prefab_hash,
+                SimulationDeviceSettings {
+                    id: Some(1),
+                    ..SimulationDeviceSettings::default()
+                },
+            )
+            .unwrap();
+            if device.borrow().as_atmospheric_device().is_some() {
+                assert!(
+                    cases.iter().any(|case| case.0 == prefab.prefab_hash),
+                    "{} is not covered",
+                    prefab.display_name
+                );
+            }
+        }
+
+        // A pump has no Output2
+        let pump = VolumePump::new(SimulationDeviceSettings {
+            id: Some(2),
+            ..SimulationDeviceSettings::default()
+        });
+        let error = pump
+            .borrow_mut()
+            .set_atmospheric_network(Output2, Some(AtmosphericNetwork::new(100.0)))
+            .unwrap_err();
+        assert!(
+            error.to_string().contains(
+                "VolumePump does not support atmospheric connection Output2; valid connections: Input, Output"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_device_pins_wire_a_housing_to_a_pump() {
+        let mut manager = SimulationManager::new();
+        let housing = manager.build_device(ICHousing::new).build().unwrap();
+        let cn = housing.borrow().get_network().unwrap();
+        let pump = manager
+            .build_device(VolumePump::new)
+            .cable_network(cn)
+            .build()
+            .unwrap();
+        let pump_id = pump.borrow().get_id();
+
+        // Pins are wired directly on the housing, no alias needed
+        {
+            let housing = housing.borrow();
+            assert_eq!(housing.get_device_pin(0), None);
+            housing.set_device_pin(0, Some(pump_id));
+            assert_eq!(housing.get_device_pin(0), Some(pump_id));
+
+            let pins = housing.chip_slot().borrow().device_pin_count();
+            housing.set_device_pin(pins, Some(pump_id));
+            assert_eq!(housing.get_device_pin(pins), None);
+        }
+
+        let chip = manager.create_chip().unwrap();
+        chip.borrow_mut().load_program("s d0 On 1").unwrap();
+        housing.borrow().set_chip(chip).unwrap();
+        assert_eq!(pump.borrow().read(LogicType::On).unwrap(), 0.0);
+
+        manager.update().unwrap();
+        assert_eq!(pump.borrow().read(LogicType::On).unwrap(), 1.0);
+
+        // Pins can be cleared again
+        housing.borrow().set_device_pin(0, None);
+        assert_eq!(housing.borrow().get_device_pin(0), None);
+    }
 }
@@ -0,0 +1,211 @@
+//! Unit tests for shared reference types
+//!
+//! The `parallel` tests exercise the unsafe `SyncRefCell` and are small enough to run under
+//! miri: `cargo +nightly miri test --features parallel types_tests`
+#[cfg(test)]
+mod tests {
+    use crate::types::shared;
+
+    #[test]
+    #[cfg(all(debug_assertions, not(feature = "parallel")))]
+    fn test_borrow_conflict_reports_call_sites() {
+        let value = shared(42_i32);
+        let _held = value.borrow_mut();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _conflict = value.borrow();
+        }));
+        let payload = result.expect_err("conflicting borrow should panic");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("panic message should be a String");
+
+        assert!(message.contains("already mutably borrowed"), "{message}");
+        assert!(message.contains("i32"), "{message}");
+        // Both the conflicting borrow and the outstanding mutable borrow are reported
+        assert_eq!(
+            message.matches("src/tests/types_tests.rs").count(),
+            2,
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_non_conflicting_borrows_succeed() {
+        let value = shared(vec![1, 2, 3]);
+        value.borrow_mut().push(4);
+        let first = value.borrow();
+        let second = value.borrow();
+        assert_eq!(first.len(), 4);
+        assert_eq!(second.len(), 4);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_borrows_on_other_threads_fail_instead_of_waiting() {
+        use crate::types::BorrowConflict;
+
+        let value = shared(0_i32);
+        let held = value.borrow_mut();
+        assert_eq!(value.try_borrow().err(), Some(BorrowConflict::SameThread));
+
+        let other = value.clone();
+        let result = std::thread::spawn(move || {
+            assert_eq!(other.try_borrow().err(), Some(BorrowConflict::OtherThread));
+            assert!(other.try_borrow_mut().is_err());
+            *other.borrow_mut() += 1;
+        })
+        .join();
+        let payload = result.expect_err("borrowing on another thread should panic");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("panic message should be a String");
+        assert!(message.contains("borrowed on another thread"), "{message}");
+
+        // Once released, the value can be borrowed from any thread
+        drop(held);
+        let other = value.clone();
+        std::thread::spawn(move || *other.borrow_mut() += 1)
+            .join()
+            .unwrap();
+        assert_eq!(*value.borrow(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_same_thread_conflicts_panic() {
+        let value = shared(0_i32);
+        let _reader = value.borrow();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _writer = value.borrow_mut();
+        }));
+        let payload = result.expect_err("conflicting borrow should panic");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("panic message should be a String");
+        assert!(message.contains("already borrowed"), "{message}");
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_mapped_guards_release_their_borrow() {
+        use crate::types::{SharedRef, SharedRefMut};
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let value = shared((1_i32, 2_i32));
+        {
+            let first = SharedRef::map(value.borrow(), |pair| &pair.0);
+            assert_eq!(*first, 1);
+            assert!(value.try_borrow_mut().is_err());
+        }
+        *SharedRefMut::map(value.borrow_mut(), |pair| &mut pair.1) = 3;
+        assert_eq!(*value.borrow(), (1, 3));
+
+        // A panicking projection releases the original borrow
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            SharedRef::map(value.borrow(), |_| -> &i32 { panic!("projection failed") });
+        }));
+        assert!(result.is_err());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            SharedRefMut::map(value.borrow_mut(), |_| -> &mut i32 {
+                panic!("projection failed")
+            });
+        }));
+        assert!(result.is_err());
+        assert!(value.try_borrow_mut().is_ok());
+    }
+
+    /// Iterations per thread in the concurrent tests, kept low under miri
+    #[cfg(feature = "parallel")]
+    const CONTENDED_ROUNDS: usize = if cfg!(miri) { 20 } else { 2000 };
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_concurrent_borrows_never_overlap() {
+        use std::cell::Cell;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // `Cell` is `Send` but not `Sync`, like the per-field cells devices keep
+        let value = shared((Cell::new(0_usize), Vec::new()));
+        let successes = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for id in 0..4 {
+                let value = value.clone();
+                let successes = &successes;
+                scope.spawn(move || {
+                    for round in 0..CONTENDED_ROUNDS {
+                        if round % 2 == 0 {
+                            if let Ok(mut guard) = value.try_borrow_mut() {
+                                // Non-atomic read-modify-write: a data race here is a
+                                // failure under miri and a lost update otherwise
+                                let count = guard.0.get();
+                                guard.0.set(count + 1);
+                                guard.1.push(id);
+                                successes.fetch_add(1, Ordering::Relaxed);
+                            }
+                        } else if let Ok(first) = value.try_borrow() {
+                            // Shared borrows on one thread stack, and no writer can sneak in
+                            let second = value.try_borrow().expect("same-thread reads stack");
+                            assert_eq!(first.0.get(), first.1.len());
+                            assert_eq!(second.0.get(), first.0.get());
+                        }
+                    }
+                });
+            }
+        });
+
+        let guard = value.borrow();
+        assert_eq!(guard.0.get(), successes.load(Ordering::Relaxed));
+        assert_eq!(guard.1.len(), guard.0.get());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_ownership_hands_over_between_threads() {
+        use crate::types::{BorrowConflict, SharedRefMut};
+        use std::sync::mpsc;
+
+        let rounds = CONTENDED_ROUNDS.min(100);
+        let value = shared(vec![0_u32]);
+        let (to_worker, worker_rx) = mpsc::channel::<bool>();
+        let (to_main, main_rx) = mpsc::channel::<()>();
+
+        std::thread::scope(|scope| {
+            let worker_value = value.clone();
+            scope.spawn(move || {
+                while let Ok(held_by_main) = worker_rx.recv() {
+                    if held_by_main {
+                        let conflict = worker_value.try_borrow().err();
+                        assert_eq!(conflict, Some(BorrowConflict::OtherThread));
+                    } else {
+                        let mut last = SharedRefMut::map(worker_value.borrow_mut(), |values| {
+                            values.last_mut().unwrap()
+                        });
+                        *last += 1;
+                        drop(last);
+                        worker_value.borrow_mut().push(0);
+                    }
+                    to_main.send(()).unwrap();
+                }
+            });
+
+            for round in 0..rounds {
+                {
+                    let mut guard = value.borrow_mut();
+                    assert_eq!(guard.len(), round + 1);
+                    *guard.last_mut().unwrap() += 1;
+                    to_worker.send(true).unwrap();
+                    main_rx.recv().unwrap();
+                }
+                to_worker.send(false).unwrap();
+                main_rx.recv().unwrap();
+            }
+            drop(to_worker);
+        });
+
+        let values = value.borrow();
+        assert_eq!(values.len(), rounds + 1);
+        assert!(values[..rounds].iter().all(|&count| count == 2));
+    }
+}
@@ -1,21 +1,502 @@
 //! Shared reference types for interior mutability
+//!
+//! By default shared values live in `Rc<RefCell<T>>`. The `parallel` feature switches them
+//! to `Arc<SyncRefCell<T>>`, making a `SimulationManager` `Send` so it can be moved to and
+//! ticked on another thread, without changing the API: values are still accessed through
+//! `borrow()`/`borrow_mut()`, which return `SharedRef`/`SharedRefMut` guards. A value may be
+//! borrowed by one thread at a time; borrowing it while another thread holds it panics.
 
+#[cfg(all(not(feature = "parallel"), not(debug_assertions)))]
 use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+
+/// Reference-counted pointer behind `Shared`: `Rc`, or `Arc` with the `parallel` feature
+#[cfg(not(feature = "parallel"))]
+pub type SharedPtr<T> = std::rc::Rc<T>;
+
+/// Reference-counted pointer behind `Shared`: `Rc`, or `Arc` with the `parallel` feature
+#[cfg(feature = "parallel")]
+pub type SharedPtr<T> = std::sync::Arc<T>;
+
+/// Weak counterpart of `SharedPtr`
+#[cfg(not(feature = "parallel"))]
+pub type WeakPtr<T> = std::rc::Weak<T>;
+
+/// Weak counterpart of `SharedPtr`
+#[cfg(feature = "parallel")]
+pub type WeakPtr<T> = std::sync::Weak<T>;
+
+/// Cell used by `Shared`. Release builds use a plain `RefCell`.
+#[cfg(all(not(feature = "parallel"), not(debug_assertions)))]
+pub type SharedCell<T> = RefCell<T>;
+
+/// Cell used by `Shared`. Debug builds use `DebugRefCell` for borrow-conflict diagnostics.
+#[cfg(all(not(feature = "parallel"), debug_assertions))]
+pub type SharedCell<T> = DebugRefCell<T>;
+
+/// Cell used by `Shared`. The `parallel` feature uses the thread-safe `SyncRefCell`.
+#[cfg(feature = "parallel")]
+pub type SharedCell<T> = SyncRefCell<T>;
+
+/// Guard returned by `Shared::borrow`
+#[cfg(not(feature = "parallel"))]
+pub type SharedRef<'a, T> = std::cell::Ref<'a, T>;
+
+/// Guard returned by `Shared::borrow`
+#[cfg(feature = "parallel")]
+pub type SharedRef<'a, T> = sync_cell::SyncRef<'a, T>;
+
+/// Guard returned by `Shared::borrow_mut`
+#[cfg(not(feature = "parallel"))]
+pub type SharedRefMut<'a, T> = std::cell::RefMut<'a, T>;
+
+/// Guard returned by `Shared::borrow_mut`
+#[cfg(feature = "parallel")]
+pub type SharedRefMut<'a, T> = sync_cell::SyncRefMut<'a, T>;
 
 /// Shared alias: reference-counted `RefCell` for interior mutability
-pub type Shared<T> = Rc<RefCell<T>>;
+pub type Shared<T> = SharedPtr<SharedCell<T>>;
 
 /// Optional `Shared` reference
 pub type OptShared<T> = Option<Shared<T>>;
 
 /// Weak reference to a `Shared` wrapper
-pub type WeakShared<T> = Weak<RefCell<T>>;
+pub type WeakShared<T> = WeakPtr<SharedCell<T>>;
 
 /// Optional weak shared reference
 pub type OptWeakShared<T> = Option<WeakShared<T>>;
 
 /// Create a new `Shared` wrapper
 pub fn shared<T>(value: T) -> Shared<T> {
-    Rc::new(RefCell::new(value))
+    SharedPtr::new(SharedCell::new(value))
+}
+
+/// Bound for values held behind `Shared` trait objects: `Send` with the `parallel` feature,
+/// no requirement otherwise
+#[cfg(feature = "parallel")]
+pub trait MaybeSend: Send {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// Bound for values held behind `Shared` trait objects: `Send` with the `parallel` feature,
+/// no requirement otherwise
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSend {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+#[cfg(feature = "parallel")]
+pub use sync_cell::{BorrowConflict, SyncRefCell};
+
+#[cfg(all(not(feature = "parallel"), debug_assertions))]
+pub use debug_cell::DebugRefCell;
+
+#[cfg(all(not(feature = "parallel"), debug_assertions))]
+mod debug_cell {
+    use std::cell::{BorrowError, BorrowMutError, Cell, Ref, RefCell, RefMut};
+    use std::fmt::{self, Debug};
+    use std::ops::Deref;
+    use std::panic::Location;
+
+    /// `RefCell` wrapper that records borrow call sites and reports them on conflict.
+    ///
+    /// `borrow` and `borrow_mut` are `#[track_caller]`, so a conflicting borrow panics with
+    /// both the failing call site and the site of the most recent conflicting borrow.
+    pub struct DebugRefCell<T: ?Sized> {
+        last_borrow: Cell<Option<&'static Location<'static>>>,
+        last_borrow_mut: Cell<Option<&'static Location<'static>>>,
+        inner: RefCell<T>,
+    }
+
+    impl<T> DebugRefCell<T> {
+        /// Create a new cell holding `value`
+        pub fn new(value: T) -> Self {
+            Self {
+                last_borrow: Cell::new(None),
+                last_borrow_mut: Cell::new(None),
+                inner: RefCell::new(value),
+            }
+        }
+    }
+
+    impl<T: ?Sized> DebugRefCell<T> {
+        /// Immutably borrow the value, panicking with call-site context on conflict
+        #[track_caller]
+        pub fn borrow(&self) -> Ref<'_, T> {
+            let caller = Location::caller();
+            match self.inner.try_borrow() {
+                Ok(value) => {
+                    self.last_borrow.set(Some(caller));
+                    value
+                }
+                Err(_) => panic!(
+                    "already mutably borrowed: {} borrowed at {caller} while mutably borrowed at {}",
+                    std::any::type_name::<T>(),
+                    describe(self.last_borrow_mut.get()),
+                ),
+            }
+        }
+
+        /// Mutably borrow the value, panicking with call-site context on conflict
+        #[track_caller]
+        pub fn borrow_mut(&self) -> RefMut<'_, T> {
+            let caller = Location::caller();
+            match self.inner.try_borrow_mut() {
+                Ok(value) => {
+                    self.last_borrow_mut.set(Some(caller));
+                    value
+                }
+                Err(_) => panic!(
+                    "already borrowed: {} mutably borrowed at {caller} while borrowed at {} (last mutable borrow at {})",
+                    std::any::type_name::<T>(),
+                    describe(self.last_borrow.get()),
+                    describe(self.last_borrow_mut.get()),
+                ),
+            }
+        }
+
+        /// Immutably borrow the value, returning an error on conflict
+        #[track_caller]
+        pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
+            let value = self.inner.try_borrow()?;
+            self.last_borrow.set(Some(Location::caller()));
+            Ok(value)
+        }
+
+        /// Mutably borrow the value, returning an error on conflict
+        #[track_caller]
+        pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+            let value = self.inner.try_borrow_mut()?;
+            self.last_borrow_mut.set(Some(Location::caller()));
+            Ok(value)
+        }
+    }
+
+    fn describe(location: Option<&'static Location<'static>>) -> String {
+        location.map_or_else(|| "<unknown>".to_string(), |l| l.to_string())
+    }
+
+    impl<T: ?Sized> Deref for DebugRefCell<T> {
+        type Target = RefCell<T>;
+
+        fn deref(&self) -> &Self::Target {
+            &self.inner
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for DebugRefCell<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.inner.fmt(f)
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+mod sync_cell {
+    //! `SyncRefCell`, the `RefCell` behind `Shared` with the `parallel` feature.
+    //!
+    //! `RwLock<T>` is only `Sync` for `T: Send + Sync`, but devices keep per-field `RefCell`s
+    //! and are `Send` only, so `Shared` needs a cell that is `Sync` for `T: Send`, the same
+    //! bound `Mutex<T>` has.
+    //!
+    //! # Soundness
+    //!
+    //! A `BorrowLock` records which thread owns the cell and how it is borrowed. Every guard
+    //! is created after registering its borrow under the lock and unregisters it on drop, so:
+    //!
+    //! - All live guards of a cell were created on the owning thread, and they follow the
+    //!   `RefCell` rules: any number of `SyncRef`s or exactly one `SyncRefMut`.
+    //! - Guards are neither `Send` nor `Sync`, so a `&T`/`&mut T` obtained through them is only
+    //!   used on the owning thread. `T` is therefore never accessed from two threads at once
+    //!   and does not need to be `Sync`; handing the value to another thread between borrows
+    //!   only needs `T: Send`.
+    //! - Ownership changes hands only after the last guard is released under the mutex, which
+    //!   orders every access on the old owner before any access on the new one.
+    //!
+    //! Borrowing a cell owned by another thread panics (`try_borrow*` returns an error), like
+    //! a conflicting borrow on the same thread. Waiting instead would turn a cycle between two
+    //! threads into a silent deadlock; simulation code never borrows the same value from two
+    //! threads at once, so a conflict is a bug worth reporting.
+
+    use std::cell::UnsafeCell;
+    use std::fmt::{self, Debug};
+    use std::marker::PhantomData;
+    use std::ops::{Deref, DerefMut};
+    use std::ptr::NonNull;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+    use std::thread::{self, ThreadId};
+
+    /// Error returned by `SyncRefCell::try_borrow`/`try_borrow_mut` when the value is busy
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BorrowConflict {
+        /// The current thread's own borrows conflict with the request
+        SameThread,
+        /// Another thread holds borrows of the value
+        OtherThread,
+    }
+
+    impl fmt::Display for BorrowConflict {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::SameThread => write!(f, "value is already borrowed"),
+                Self::OtherThread => write!(f, "value is borrowed on another thread"),
+            }
+        }
+    }
+
+    impl std::error::Error for BorrowConflict {}
+
+    /// Which thread holds a `SyncRefCell` and how
+    #[derive(Default)]
+    struct BorrowState {
+        owner: Option<ThreadId>,
+        readers: usize,
+        writing: bool,
+    }
+
+    /// Borrow bookkeeping shared by a `SyncRefCell` and its guards
+    #[derive(Default)]
+    struct BorrowLock {
+        state: Mutex<BorrowState>,
+    }
+
+    impl BorrowLock {
+        fn lock(&self) -> MutexGuard<'_, BorrowState> {
+            self.state.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        /// Register a borrow for the current thread
+        fn acquire(&self, write: bool) -> Result<(), BorrowConflict> {
+            let mut state = self.lock();
+            let me = thread::current().id();
+            match state.owner {
+                Some(owner) if owner != me => Err(BorrowConflict::OtherThread),
+                Some(_) if write || state.writing => Err(BorrowConflict::SameThread),
+                _ => {
+                    state.owner = Some(me);
+                    if write {
+                        state.writing = true;
+                    } else {
+                        state.readers += 1;
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        /// Unregister a borrow, giving up ownership once none are left
+        fn release(&self, write: bool) {
+            let mut state = self.lock();
+            if write {
+                state.writing = false;
+            } else {
+                state.readers -= 1;
+            }
+            if state.readers == 0 && !state.writing {
+                state.owner = None;
+            }
+        }
+    }
+
+    #[track_caller]
+    fn conflict_panic<T: ?Sized>(conflict: BorrowConflict, write: bool) -> ! {
+        match (conflict, write) {
+            (BorrowConflict::OtherThread, _) => {
+                panic!("{}: {conflict}", std::any::type_name::<T>())
+            }
+            (BorrowConflict::SameThread, false) => {
+                panic!("already mutably borrowed: {}", std::any::type_name::<T>())
+            }
+            (BorrowConflict::SameThread, true) => {
+                panic!("already borrowed: {}", std::any::type_name::<T>())
+            }
+        }
+    }
+
+    /// Thread-safe `RefCell`: one thread at a time may hold borrows, following the usual
+    /// `RefCell` rules (many shared borrows or one mutable borrow). Conflicting borrows panic,
+    /// whether they come from the owning thread or another one.
+    pub struct SyncRefCell<T: ?Sized> {
+        lock: BorrowLock,
+        value: UnsafeCell<T>,
+    }
+
+    // SAFETY: only the owning thread can reach the value (see the module docs), so `T: Send`
+    // is enough, as for `Mutex<T>`
+    unsafe impl<T: ?Sized + Send> Send for SyncRefCell<T> {}
+    unsafe impl<T: ?Sized + Send> Sync for SyncRefCell<T> {}
+
+    impl<T> SyncRefCell<T> {
+        /// Create a new cell holding `value`
+        pub fn new(value: T) -> Self {
+            Self {
+                lock: BorrowLock::default(),
+                value: UnsafeCell::new(value),
+            }
+        }
+    }
+
+    impl<T: ?Sized> SyncRefCell<T> {
+        /// Immutably borrow the value, panicking if it is mutably borrowed or borrowed on
+        /// another thread
+        #[track_caller]
+        pub fn borrow(&self) -> SyncRef<'_, T> {
+            self.try_borrow()
+                .unwrap_or_else(|conflict| conflict_panic::<T>(conflict, false))
+        }
+
+        /// Mutably borrow the value, panicking if it is already borrowed
+        #[track_caller]
+        pub fn borrow_mut(&self) -> SyncRefMut<'_, T> {
+            self.try_borrow_mut()
+                .unwrap_or_else(|conflict| conflict_panic::<T>(conflict, true))
+        }
+
+        /// Immutably borrow the value, returning an error instead of panicking
+        pub fn try_borrow(&self) -> Result<SyncRef<'_, T>, BorrowConflict> {
+            self.lock.acquire(false)?;
+            Ok(SyncRef {
+                // SAFETY: `UnsafeCell::get` never returns null
+                value: unsafe { NonNull::new_unchecked(self.value.get()) },
+                lock: &self.lock,
+                _borrow: PhantomData,
+            })
+        }
+
+        /// Mutably borrow the value, returning an error instead of panicking
+        pub fn try_borrow_mut(&self) -> Result<SyncRefMut<'_, T>, BorrowConflict> {
+            self.lock.acquire(true)?;
+            Ok(SyncRefMut {
+                // SAFETY: `UnsafeCell::get` never returns null
+                value: unsafe { NonNull::new_unchecked(self.value.get()) },
+                lock: &self.lock,
+                _borrow: PhantomData,
+            })
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for SyncRefCell<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.try_borrow() {
+                Ok(value) => f
+                    .debug_struct("SyncRefCell")
+                    .field("value", &&*value)
+                    .finish(),
+                Err(_) => f.write_str("SyncRefCell { <borrowed> }"),
+            }
+        }
+    }
+
+    /// Shared borrow of a `SyncRefCell`. Neither `Send` nor `Sync`: the borrow belongs to the
+    /// thread that took it.
+    pub struct SyncRef<'a, T: ?Sized> {
+        value: NonNull<T>,
+        lock: &'a BorrowLock,
+        _borrow: PhantomData<(&'a T, *const ())>,
+    }
+
+    impl<'a, T: ?Sized> SyncRef<'a, T> {
+        /// Make a guard for a component of the borrowed value, like `Ref::map`. If `f`
+        /// panics, `orig` is dropped and the borrow released.
+        pub fn map<U: ?Sized, F: FnOnce(&T) -> &U>(orig: Self, f: F) -> SyncRef<'a, U> {
+            let value = NonNull::from(f(&orig));
+            let lock = orig.lock;
+            // The registered borrow moves to the new guard
+            std::mem::forget(orig);
+            SyncRef {
+                value,
+                lock,
+                _borrow: PhantomData,
+            }
+        }
+    }
+
+    impl<T: ?Sized> Deref for SyncRef<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: the shared borrow is registered until this guard drops
+            unsafe { self.value.as_ref() }
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for SyncRef<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+
+    impl<T: ?Sized + fmt::Display> fmt::Display for SyncRef<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+
+    impl<T: ?Sized> Drop for SyncRef<'_, T> {
+        fn drop(&mut self) {
+            self.lock.release(false);
+        }
+    }
+
+    /// Mutable borrow of a `SyncRefCell`. Neither `Send` nor `Sync`: the borrow belongs to
+    /// the thread that took it.
+    pub struct SyncRefMut<'a, T: ?Sized> {
+        value: NonNull<T>,
+        lock: &'a BorrowLock,
+        _borrow: PhantomData<(&'a mut T, *const ())>,
+    }
+
+    impl<'a, T: ?Sized> SyncRefMut<'a, T> {
+        /// Make a guard for a component of the borrowed value, like `RefMut::map`. If `f`
+        /// panics, `orig` is dropped and the borrow released.
+        pub fn map<U: ?Sized, F: FnOnce(&mut T) -> &mut U>(
+            mut orig: Self,
+            f: F,
+        ) -> SyncRefMut<'a, U> {
+            let value = NonNull::from(f(&mut orig));
+            let lock = orig.lock;
+            // The registered borrow moves to the new guard
+            std::mem::forget(orig);
+            SyncRefMut {
+                value,
+                lock,
+                _borrow: PhantomData,
+            }
+        }
+    }
+
+    impl<T: ?Sized> Deref for SyncRefMut<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: the mutable borrow is registered until this guard drops
+            unsafe { self.value.as_ref() }
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for SyncRefMut<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // SAFETY: the mutable borrow is registered until this guard drops
+            unsafe { self.value.as_mut() }
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for SyncRefMut<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+
+    impl<T: ?Sized + fmt::Display> fmt::Display for SyncRefMut<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+
+    impl<T: ?Sized> Drop for SyncRefMut<'_, T> {
+        fn drop(&mut self) {
+            self.lock.release(true);
+        }
+    }
 }
@@ -13,13 +13,15 @@ use crate::atmospherics::{GasMixture, GasType, MatterState};
 use crate::constants::{REGISTER_COUNT, STACK_SIZE};
 use crate::devices::LogicSlotType;
 use crate::devices::LogicType;
-use crate::devices::{Device, SimulationDeviceSettings};
+use crate::devices::{Device, RejectedItem, SimulationDeviceSettings};
 use crate::devices::{DeviceAtmosphericNetworkType, device_factory};
-use crate::items::{self, ItemIntegratedCircuit10};
+use crate::items::{self, ChipState, ItemIntegratedCircuit10};
 use crate::items::{FilterSize, SimulationItemSettings, item::Item};
 use crate::networks::BatchMode;
 use crate::types::{OptShared, Shared};
-use crate::{AtmosphericNetwork, CableNetwork, SimulationManager, parser};
+use crate::{
+    AtmosphericNetwork, CableNetwork, SimulationError, SimulationManager, conversions, parser,
+};
 use serde::Serialize;
 use serde_wasm_bindgen::to_value;
 
@@ -38,6 +40,13 @@ export interface WasmDevice { read_batch(logic_types: LogicType[]): Array<number
 
 export interface WasmGasTypeInfo { value: number; name: string; symbol: string; isLiquid: boolean; }
 export function get_all_gas_types(): WasmGasTypeInfo[];
+
+export interface DeviceSnapshot { id: number; prefab_hash: number; name: string; logic: Record<string, number | null>; }
+export interface CableNetworkSnapshot { id: number; device_ids: number[]; }
+export interface AtmosphericNetworkSnapshot { id: number; volume: number; pressure: number | null; temperature: number | null; total_moles: number; moles: Record<string, number>; }
+/** Shape of the JSON returned by `WasmSimulationManager.snapshot()` and `step_and_snapshot()` */
+export interface SimulationSnapshot { ticks: number; devices: DeviceSnapshot[]; cable_networks: CableNetworkSnapshot[]; atmospheric_networks: AtmosphericNetworkSnapshot[]; }
+export interface SimulationErrorInfo { kind: string; line: number | null; message: string; }
 "#;
 
 #[wasm_bindgen]
@@ -273,8 +282,10 @@ impl WasmGasMixture {
         self.inner.partial_pressure(gas)
     }
 
-    pub fn add_gas(&mut self, gas: GasType, moles: f64, temperature: f64) {
-        self.inner.add_gas(gas, moles, temperature);
+    pub fn add_gas(&mut self, gas: GasType, moles: f64, temperature: f64) -> Result<(), JsValue> {
+        self.inner
+            .add_gas(gas, moles, temperature)
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))
     }
 
     pub fn remove_gas(&mut self, gas: GasType, moles: f64) -> f64 {
@@ -353,6 +364,11 @@ impl WasmGasMixture {
         self.inner.total_moles_by_state(state)
     }
 
+    pub fn state_ratio(&self, state_value: u32) -> f64 {
+        let state = MatterState::from_value(state_value).unwrap_or(MatterState::None);
+        self.inner.state_ratio(state)
+    }
+
     pub fn total_volume_liquids(&self) -> f64 {
         self.inner.total_volume_liquids()
     }
@@ -668,12 +684,28 @@ impl WasmDevice {
         }
     }
 
+    /// Check whether an item may be inserted into a slot on this device, with the reason
+    /// it would be rejected as the error (e.g. a slot restricted to other item types)
+    pub fn check_insert_item(&self, index: usize, item: &WasmItem) -> Result<(), JsValue> {
+        let shared_item = item
+            .inner
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("Invalid or already-consumed item"))?;
+        let dev = self.inner.borrow();
+        let slot_host = dev
+            .as_slot_host_device()
+            .ok_or_else(|| JsValue::from_str("Device does not support slots"))?;
+        slot_host
+            .check_insert(index, &*shared_item.borrow())
+            .map_err(|e| JsValue::from_str(&e.message()))
+    }
+
     /// Insert an item into a slot on this device. The item is consumed on success.
     /// Accepts a `WasmItem` which owns the shared `Item` instance.
     ///
     /// Returns Ok(None) on successful insertion, or Ok(Some(WasmItem)) if the item
     /// could not be inserted (leftover returned to the caller). Errors indicate
-    /// invalid/consumed inputs.
+    /// invalid/consumed inputs. Use `check_insert_item` to learn why an item is rejected.
     pub fn insert_item_into_slot(
         &self,
         index: usize,
@@ -691,14 +723,20 @@ impl WasmDevice {
                 slot_host.try_insert_item(index, shared_item)
             } else {
                 // Device doesn't support slots; return the shared item as Err so we can hand it back to JS
-                Err(shared_item)
+                Err(RejectedItem {
+                    item: shared_item,
+                    reason: SimulationError::RuntimeError {
+                        message: "Device does not support slots".to_string(),
+                        line: 0,
+                    },
+                })
             }
         };
 
         match result {
             Ok(()) => Ok(None),
-            Err(leftover) => Ok(Some(WasmItem {
-                inner: Some(leftover),
+            Err(rejected) => Ok(Some(WasmItem {
+                inner: Some(rejected.item),
             })),
         }
     }
@@ -783,6 +821,12 @@ impl WasmDevice {
         }
     }
 
+    /// Get the number of values on the hosted chip's stack (0 if not an IC host or no chip)
+    pub fn get_stack_depth(&self) -> usize {
+        let dev = self.inner.borrow();
+        dev.as_ic_host_device().map_or(0, |host| host.stack_depth())
+    }
+
     /// Check whether this IC host device currently has a chip installed
     pub fn has_chip(&self) -> bool {
         let dev = self.inner.borrow();
@@ -915,6 +959,41 @@ impl WasmICChip {
             .map_err(|e| JsValue::from_str(&format!("{e:?}")))
     }
 
+    pub fn set_enforce_source_limits(&self, enforce: bool) {
+        self.inner.borrow_mut().set_enforce_source_limits(enforce);
+    }
+
+    pub fn enforces_source_limits(&self) -> bool {
+        self.inner.borrow().enforces_source_limits()
+    }
+
+    pub fn set_ticks_per_second(&self, ticks_per_second: f64) {
+        self.inner
+            .borrow_mut()
+            .set_ticks_per_second(ticks_per_second);
+    }
+
+    pub fn ticks_per_second(&self) -> f64 {
+        self.inner.borrow().ticks_per_second()
+    }
+
+    pub fn set_strict_logic(&self, strict: bool) {
+        self.inner.borrow_mut().set_strict_logic(strict);
+    }
+
+    pub fn is_strict_logic(&self) -> bool {
+        self.inner.borrow().is_strict_logic()
+    }
+
+    pub fn set_hcf_errors(&self, enabled: bool) {
+        self.inner.borrow_mut().set_hcf_errors(enabled);
+    }
+
+    /// Whether `hcf` raises a runtime error instead of halting silently
+    pub fn hcf_errors(&self) -> bool {
+        self.inner.borrow().hcf_errors()
+    }
+
     pub fn step(&self) -> Result<bool, JsValue> {
         self.inner
             .borrow()
@@ -937,6 +1016,11 @@ impl WasmICChip {
         self.inner.borrow().clear_registers();
     }
 
+    /// Restart the loaded program from scratch, keeping the program, aliases and defines
+    pub fn reset(&self) {
+        self.inner.borrow().reset();
+    }
+
     pub fn get_pc(&self) -> usize {
         self.inner.borrow().get_pc()
     }
@@ -945,6 +1029,25 @@ impl WasmICChip {
         self.inner.borrow().is_halted()
     }
 
+    /// Chip state name: "Running", "Yielded", "Sleeping" or "Halted"
+    pub fn state(&self) -> String {
+        match self.inner.borrow().state() {
+            ChipState::Running => "Running",
+            ChipState::Yielded => "Yielded",
+            ChipState::Sleeping { .. } => "Sleeping",
+            ChipState::Halted => "Halted",
+        }
+        .to_string()
+    }
+
+    /// Tick count at which a sleeping chip executes again, if it is asleep
+    pub fn sleep_until_tick(&self) -> Option<u64> {
+        match self.inner.borrow().state() {
+            ChipState::Sleeping { until_tick } => Some(until_tick),
+            _ => None,
+        }
+    }
+
     pub fn get_memory(&self, index: usize) -> Result<f64, JsValue> {
         self.inner
             .borrow()
@@ -970,6 +1073,10 @@ impl WasmICChip {
         self.inner.borrow().insert_define(name, value);
     }
 
+    pub fn add_constant(&self, name: &str, value: f64) {
+        self.inner.borrow_mut().add_constant(name, value);
+    }
+
     pub fn insert_alias(&self, name: &str, device_ref_id: i32) {
         self.inner
             .borrow()
@@ -1032,8 +1139,10 @@ impl WasmAtmosphericNetwork {
     }
 
     pub fn add_gas(&self, gas: GasType, moles: f64, temperature: f64) -> Result<(), JsValue> {
-        self.inner.borrow_mut().add_gas(gas, moles, temperature);
-        Ok(())
+        self.inner
+            .borrow_mut()
+            .add_gas(gas, moles, temperature)
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))
     }
 
     pub fn remove_gas(&self, gas: GasType, moles: f64) -> Result<f64, JsValue> {
@@ -1051,6 +1160,34 @@ impl WasmAtmosphericNetwork {
         self.inner.borrow_mut().clear();
     }
 
+    /// Cap the moles devices may transfer through this network per tick (`None` removes it)
+    pub fn set_max_flow_per_tick(&self, max_flow: Option<f64>) {
+        self.inner.borrow_mut().set_max_flow_per_tick(max_flow);
+    }
+
+    /// Get the per-tick flow cap in moles, if any
+    pub fn max_flow_per_tick(&self) -> Option<f64> {
+        self.inner.borrow().max_flow_per_tick()
+    }
+
+    /// Set the pressure (kPa) above which the network is over pressure (`None` removes it)
+    pub fn set_max_pressure(&self, max_pressure: Option<f64>) {
+        self.inner.borrow_mut().set_max_pressure(max_pressure);
+    }
+
+    pub fn over_pressure(&self) -> bool {
+        self.inner.borrow().over_pressure()
+    }
+
+    /// Choose whether the network loses its contents when it goes over pressure
+    pub fn set_vent_on_burst(&self, vent: bool) {
+        self.inner.borrow_mut().set_vent_on_burst(vent);
+    }
+
+    pub fn repair(&self) {
+        self.inner.borrow_mut().repair();
+    }
+
     /// Return a string representation
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string_js(&self) -> String {
@@ -1154,6 +1291,11 @@ impl WasmAtmosphericNetwork {
         self.inner.borrow().total_moles_by_state(state)
     }
 
+    pub fn state_ratio(&self, state_value: u32) -> f64 {
+        let state = MatterState::from_value(state_value).unwrap_or(MatterState::None);
+        self.inner.borrow().state_ratio(state)
+    }
+
     pub fn total_volume_liquids(&self) -> f64 {
         self.inner.borrow().total_volume_liquids()
     }
@@ -1210,11 +1352,41 @@ pub fn pack_ascii6(text: &str) -> Option<i64> {
     parser::pack_ascii6(text)
 }
 
+#[wasm_bindgen]
+pub fn unpack_ascii6(value: f64) -> Option<String> {
+    parser::unpack_ascii6(value)
+}
+
 #[wasm_bindgen]
 pub fn string_to_hash(text: &str) -> i32 {
     parser::string_to_hash(text)
 }
 
+#[wasm_bindgen]
+pub fn hash_to_known_name(hash: i32) -> Option<String> {
+    parser::hash_to_known_name(hash).map(str::to_string)
+}
+
+#[wasm_bindgen]
+pub fn moles_to_grams(g: GasType, moles: f64) -> f64 {
+    conversions::moles_to_grams(g, moles)
+}
+
+#[wasm_bindgen]
+pub fn grams_to_moles(g: GasType, grams: f64) -> f64 {
+    conversions::grams_to_moles(g, grams)
+}
+
+#[wasm_bindgen]
+pub fn energy_for_temperature_change(g: GasType, moles: f64, delta_k: f64) -> f64 {
+    conversions::energy_for_temperature_change(g, moles, delta_k)
+}
+
+#[wasm_bindgen]
+pub fn temperature_change_for_energy(g: GasType, moles: f64, joules: f64) -> f64 {
+    conversions::temperature_change_for_energy(g, moles, joules)
+}
+
 #[wasm_bindgen]
 pub fn gas_type_symbol(g: GasType) -> String {
     g.symbol().to_string()
@@ -1265,6 +1437,44 @@ impl WasmSimulationManager {
             .map_err(|e| JsValue::from_str(&format!("{e}")))
     }
 
+    /// Run one tick and return the full simulation state as a JSON string, so a UI can render
+    /// each frame from a single call
+    pub fn step_and_snapshot(&mut self) -> Result<String, JsValue> {
+        self.inner
+            .step_and_snapshot()
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    /// Return the full simulation state as a JSON string without advancing the simulation
+    pub fn snapshot(&self) -> String {
+        self.inner.snapshot_json()
+    }
+
+    /// Load IC10 source onto the chip installed in the device with reference ID `id`.
+    /// Errors are `{ kind, line, message }` objects, e.g. `kind: "ParseError"` with the
+    /// offending line for source that fails to parse.
+    pub fn load_program(&self, id: i32, source: &str) -> Result<(), JsValue> {
+        let error_to_js =
+            |e: SimulationError| to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()));
+        let runtime_error = |message: String| SimulationError::RuntimeError { line: 0, message };
+
+        let device = self
+            .inner
+            .get_device(id)
+            .ok_or_else(|| error_to_js(runtime_error(format!("Device {id} not found"))))?;
+        let device = device.borrow();
+        let host = device.as_ic_host_device().ok_or_else(|| {
+            error_to_js(runtime_error(format!(
+                "Device {id} does not support IC hosting"
+            )))
+        })?;
+        if let Some(mut chip) = host.chip_slot().borrow().get_chip_mut() {
+            chip.load_program(source).map_err(error_to_js)
+        } else {
+            Err(error_to_js(runtime_error("No chip installed".to_string())))
+        }
+    }
+
     /// Get a string representation
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string_js(&self) -> String {
@@ -1274,15 +1484,14 @@ impl WasmSimulationManager {
     /// Create a device by prefab hash via this `SimulationManager` and track it.
     /// Returns a `WasmDevice` wrapper for the created instance.
     pub fn create_device(&mut self, prefab_hash: i32) -> Result<WasmDevice, JsValue> {
-        match self.inner.create_device(prefab_hash, None) {
-            Some(d) => Ok(WasmDevice { inner: d }),
-            None => Err(JsValue::from_str(
-                "Unsupported prefab hash for device creation",
-            )),
-        }
+        self.inner
+            .create_device(prefab_hash, None)
+            .map(|d| WasmDevice { inner: d })
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
     }
 
     /// Create a device with explicit simulation settings via this `SimulationManager` and track it.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_device_with_settings(
         &mut self,
         prefab_hash: i32,
@@ -1291,21 +1500,23 @@ impl WasmSimulationManager {
         internal_atmospheric_network: Option<WasmAtmosphericNetwork>,
         ticks_per_day: Option<f64>,
         max_instructions_per_tick: Option<usize>,
+        memory_size: Option<usize>,
+        ticks_per_second: Option<f64>,
     ) -> Result<WasmDevice, JsValue> {
         let settings = SimulationDeviceSettings {
             id,
             name,
             internal_atmospheric_network: internal_atmospheric_network.map(|n| n.inner.clone()),
             ticks_per_day,
+            ticks_per_second,
             max_instructions_per_tick,
+            memory_size,
         };
 
-        match self.inner.create_device(prefab_hash, Some(settings)) {
-            Some(d) => Ok(WasmDevice { inner: d }),
-            None => Err(JsValue::from_str(
-                "Unsupported prefab hash for device creation",
-            )),
-        }
+        self.inner
+            .create_device(prefab_hash, Some(settings))
+            .map(|d| WasmDevice { inner: d })
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
     }
 
     /// Create an item by `prefab_hash` via this `SimulationManager` and track it.
@@ -1346,9 +1557,12 @@ impl WasmSimulationManager {
     }
 
     /// Create an IC10 chip item via this `SimulationManager`.
-    pub fn create_chip(&mut self) -> WasmICChip {
-        let chip = self.inner.create_chip();
-        WasmICChip { inner: chip }
+    pub fn create_chip(&mut self) -> Result<WasmICChip, JsValue> {
+        let chip = self
+            .inner
+            .create_chip()
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+        Ok(WasmICChip { inner: chip })
     }
 
     /// Return all devices created by this manager as `WasmDevice` wrappers
@@ -1369,15 +1583,22 @@ impl WasmSimulationManager {
     }
 
     /// Create a cable network and register it with this simulation manager
-    pub fn create_cable_network(&mut self) -> WasmCableNetwork {
-        let net = self.inner.create_cable_network();
-        WasmCableNetwork { inner: net }
+    pub fn create_cable_network(&mut self) -> Result<WasmCableNetwork, JsValue> {
+        self.inner
+            .create_cable_network()
+            .map(|net| WasmCableNetwork { inner: net })
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
     }
 
     /// Create an atmospheric network and register it with this simulation manager
-    pub fn create_atmospheric_network(&mut self, volume: f64) -> WasmAtmosphericNetwork {
-        let net = self.inner.create_atmospheric_network(volume);
-        WasmAtmosphericNetwork { inner: net }
+    pub fn create_atmospheric_network(
+        &mut self,
+        volume: f64,
+    ) -> Result<WasmAtmosphericNetwork, JsValue> {
+        self.inner
+            .create_atmospheric_network(volume)
+            .map(|net| WasmAtmosphericNetwork { inner: net })
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
     }
 
     /// Get all cable networks
@@ -1432,10 +1653,35 @@ impl WasmSimulationManager {
             .collect()
     }
 
+    /// Number of cable networks managed by the simulation
+    pub fn cable_network_count(&self) -> usize {
+        self.inner.cable_network_count()
+    }
+
+    /// Number of atmospheric networks managed by the simulation
+    pub fn atmospheric_network_count(&self) -> usize {
+        self.inner.atmospheric_network_count()
+    }
+
     /// Get the current simulation tick
     pub fn current_tick(&self) -> u64 {
         self.inner.ticks
     }
+
+    /// Describe every difference between this simulation's state and `other`
+    pub fn diff(&self, other: &WasmSimulationManager) -> Vec<String> {
+        self.inner.diff(&other.inner)
+    }
+
+    /// Register `name` for reverse lookups with `hash_to_known_name`, returning its hash
+    pub fn register_name(&mut self, name: &str) -> i32 {
+        self.inner.register_name(name)
+    }
+
+    /// Look up a registered name or prefab display name for `hash`
+    pub fn hash_to_known_name(&self, hash: i32) -> Option<String> {
+        self.inner.hash_to_known_name(hash).map(str::to_string)
+    }
 }
 
 /// Return a list of registered device prefab hashes